@@ -4,9 +4,18 @@ use alloc::format;
 use alloc::string::String;
 
 use crate::parse::{Category, ConstValType, Enum, Generic, Struct, Type};
+#[cfg(feature = "nanoserde")]
+use crate::shared::attrs_de_bin_checked;
 #[cfg(feature = "generated_setters")]
 use crate::shared::{attrs_all_setters, attrs_setter};
-use crate::shared::{attrs_collection_type, attrs_expose, attrs_recurse, attrs_skip};
+use crate::shared::{
+    attrs_arbitrary, attrs_auto, attrs_collection_type, attrs_diff_as, attrs_encoded_size,
+    attrs_eq_with, attrs_expose, attrs_float_epsilon, attrs_getter, attrs_getter_mut, attrs_merge,
+    attrs_recurse, attrs_remote, attrs_skip, attrs_verify_base, attrs_with,
+    resolve_auto_strategies,
+};
+#[cfg(feature = "serde")]
+use crate::shared::{attrs_serde, rename_field, serde_arg, serde_args_without};
 use proc_macro::TokenStream;
 
 fn get_used_lifetimes(ty: &Type) -> Vec<String> {
@@ -49,7 +58,7 @@ const BOUNDS: &[&str] = &[
     #[cfg(feature = "debug_diffs")]
     "core::fmt::Debug",
     #[cfg(feature = "nanoserde")]
-    "nanoserde::DeBin",
+    "structdiff::checked::DeBinChecked",
     #[cfg(feature = "nanoserde")]
     "nanoserde::SerBin",
     #[cfg(feature = "serde")]
@@ -69,15 +78,364 @@ const REF_BOUNDS: &[&str] = &[
     "serde::Serialize",
 ];
 
+/// Expressions which reach a field from generated code: `read` and `write`
+/// for `self`, and `updated` for the value being compared against. In a
+/// `remote` impl `self` is the `__self` argument, and fields with a getter are
+/// accessed through it.
+struct FieldAccess {
+    read: String,
+    write: String,
+    updated: String,
+}
+
+fn field_access(struct_: &Struct, field: &crate::parse::Field) -> FieldAccess {
+    let field_name = field.field_name.as_ref().unwrap();
+    if attrs_remote(&struct_.attributes).is_none() {
+        return FieldAccess {
+            read: format!("self.{field_name}"),
+            write: format!("self.{field_name}"),
+            updated: format!("updated.{field_name}"),
+        };
+    }
+
+    match attrs_getter(&field.attributes) {
+        Some(getter) => {
+            let getter_mut = attrs_getter_mut(&field.attributes).unwrap_or_else(|| {
+                panic!("Field `{field_name}` needs a `getter_mut` for diffs to be applied to it")
+            });
+            FieldAccess {
+                read: format!("(*{getter}(__self))"),
+                write: format!("(*{getter_mut}(__self))"),
+                updated: format!("(*{getter}(updated))"),
+            }
+        }
+        None => FieldAccess {
+            read: format!("__self.{field_name}"),
+            write: format!("__self.{field_name}"),
+            updated: format!("updated.{field_name}"),
+        },
+    }
+}
+
+/// Expression which is true when a replaced field differs between `lhs` and
+/// `rhs`, using the `eq_with` or `float_epsilon` comparison if one is given.
+fn field_changed(attributes: &[crate::parse::Attribute], lhs: &str, rhs: &str) -> String {
+    match (attrs_eq_with(attributes), attrs_float_epsilon(attributes)) {
+        (Some(_), Some(_)) => panic!("`eq_with` and `float_epsilon` cannot be used together"),
+        (Some(eq_with), None) => format!("!{}(&{}, &{})", eq_with, lhs, rhs),
+        (None, Some(epsilon)) => format!(
+            "!((({} - {}) as f64).abs() <= {} as f64)",
+            lhs, rhs, epsilon
+        ),
+        (None, None) => format!("{} != {}", lhs, rhs),
+    }
+}
+
+/// Expression for the optional diff of a collection field computed by `diff`.
+/// For fields with `collection_strategy = "auto"`, the diff built by
+/// `replacement` is used instead when it is estimated to be smaller.
+fn auto_diff(attributes: &[crate::parse::Attribute], diff: String, replacement: String) -> String {
+    match attrs_auto(attributes) {
+        true => format!("structdiff::size::smaller({diff}, || {replacement})"),
+        false => diff,
+    }
+}
+
+/// Statement pushing the diffs of a collection field to `diffs`, each built from
+/// a change list by `wrap`. `diff` builds the whole change list at once. In the
+/// bodies shared by `diff_ref` and `diff_into` (`streamed`), `diff_into` instead
+/// streams the changes by calling `stream` with `stream_args` and a sink, so a
+/// long change list is pushed as several diffs. Fields with
+/// `collection_strategy = "auto"` are always diffed whole, since the replacement
+/// is only chosen by comparing against the complete diff.
+fn push_collection_diffs(
+    attributes: &[crate::parse::Attribute],
+    streamed: bool,
+    diff: &str,
+    stream: &str,
+    stream_args: &str,
+    wrap: &str,
+) -> String {
+    let whole = format!("if let Some(list_diffs) = {diff} {{ diffs.push({wrap}); }};");
+    match streamed && !attrs_auto(attributes) {
+        true => format!(
+            "if __chunk_changes {{ {stream}({stream_args}, &mut |list_diffs| diffs.push({wrap})); }} else {whole}"
+        ),
+        false => whole,
+    }
+}
+
+/// Expression which is true when a field would produce a diff, using the same
+/// comparison as its diff but without generating one.
+fn field_has_diff(struct_: &Struct, field: &crate::parse::Field) -> String {
+    use crate::shared::{CollectionStrategy, MapStrategy};
+
+    let attributes = &field.attributes;
+    let FieldAccess {
+        read: lhs,
+        updated: rhs,
+        ..
+    } = field_access(struct_, field);
+
+    if let Some(module) = attrs_with(attributes) {
+        return format!("{module}::diff_ref(&{lhs}, &{rhs}).is_some()");
+    }
+    if let Some(mirror) = attrs_remote(attributes) {
+        return format!(
+            "<{} as structdiff::StructDiffRemote<{}>>::has_diff(&{lhs}, &{rhs})",
+            mirror,
+            field.ty.full()
+        );
+    }
+    if attrs_diff_as(attributes).is_some() {
+        return field_changed(attributes, &lhs, &rhs);
+    }
+
+    let key_only = |strategy: &MapStrategy| matches!(strategy, MapStrategy::KeyOnly);
+    match (
+        attrs_recurse(attributes),
+        attrs_collection_type(attributes),
+        field.ty.base() == "Option",
+    ) {
+        (false, None, _) => field_changed(attributes, &lhs, &rhs),
+        // recursed fields are diffed whenever they are unequal
+        (true, None, _) => format!("{lhs} != {rhs}"),
+        (true, Some(CollectionStrategy::UnorderedMapLikeHash(strategy)), false) => format!(
+            "structdiff::collections::unordered_map_like_recursive::has_changes({lhs}.iter(), {rhs}.iter(), {})",
+            key_only(&strategy)
+        ),
+        (false, Some(CollectionStrategy::OrderedArrayLike), false) => {
+            format!("structdiff::collections::ordered_array_like::has_changes(&{rhs}, &{lhs})")
+        }
+        (false, Some(CollectionStrategy::UnorderedArrayLikeHash), false) => format!(
+            "structdiff::collections::unordered_array_like::has_changes({lhs}.iter(), {rhs}.iter())"
+        ),
+        (false, Some(CollectionStrategy::UnorderedMapLikeHash(strategy)), false) => format!(
+            "structdiff::collections::unordered_map_like::has_changes({lhs}.iter(), {rhs}.iter(), {})",
+            key_only(&strategy)
+        ),
+        (false, Some(CollectionStrategy::Text(_)), false) => format!(
+            "core::convert::AsRef::<str>::as_ref(&{lhs}) != core::convert::AsRef::<str>::as_ref(&{rhs})"
+        ),
+        (false, Some(CollectionStrategy::BinaryDelta), false) => format!(
+            "core::convert::AsRef::<[u8]>::as_ref(&{lhs}) != core::convert::AsRef::<[u8]>::as_ref(&{rhs})"
+        ),
+        // rejected while generating the diff
+        _ => unreachable!(),
+    }
+}
+
+/// Mutators which edit part of a collection field and return the diff of the
+/// edit directly, instead of comparing the whole field. Recursed fields get a
+/// `modify_{field}_with_diff` which runs an arbitrary edit, so it clones the
+/// field and diffs the result against the clone.
+#[cfg(feature = "generated_setters")]
+fn field_mutators(field: &crate::parse::Field) -> String {
+    use crate::shared::CollectionStrategy;
+
+    let attributes = &field.attributes;
+    if attrs_with(attributes).is_some() || attrs_remote(attributes).is_some() {
+        return String::new();
+    }
+    let field_name = field.field_name.as_ref().unwrap();
+    let diff = "<Self as structdiff::StructDiff>::Diff";
+    let wrapped = |index: usize| {
+        field
+            .ty
+            .wraps
+            .as_ref()
+            .and_then(|wraps| wraps.get(index))
+            .unwrap_or_else(|| panic!("Field `{field_name}` must be a generic collection"))
+            .full()
+    };
+    let map_mutators = |module: &str, diff_type: &str| {
+        let (key, value) = (wrapped(0), wrapped(1));
+        format!(
+            "
+/// Mutator generated by StructDiff. Use to insert an entry into the {field_name} field and generate a diff if necessary
+pub fn insert_{field_name}_with_diff(&mut self, key: {key}, value: {value}) -> Option<{diff}> {{
+	let previous = self.{field_name}.insert(key.clone(), value.clone());
+	structdiff::collections::{module}::{diff_type}::from_insert(key, value, previous).map({diff}::{field_name})
+}}
+/// Mutator generated by StructDiff. Use to remove an entry from the {field_name} field and generate a diff if it was present
+pub fn remove_{field_name}_with_diff(&mut self, key: &{key}) -> Option<({value}, {diff})> {{
+	let removed = self.{field_name}.remove(key)?;
+	Some((removed, {diff}::{field_name}(structdiff::collections::{module}::{diff_type}::from_remove(key.clone()))))
+}}"
+        )
+    };
+
+    match (
+        attrs_recurse(attributes),
+        attrs_collection_type(attributes),
+        field.ty.base() == "Option",
+    ) {
+        (false, Some(CollectionStrategy::OrderedArrayLike), false) => {
+            let item = wrapped(0);
+            format!(
+                "
+/// Mutator generated by StructDiff. Use to append to the {field_name} field and generate its diff
+pub fn push_{field_name}_with_diff(&mut self, value: {item}) -> {diff} {{
+	{diff}::{field_name}(structdiff::collections::ordered_array_like::push_with_diff(&mut self.{field_name}, value))
+}}
+/// Mutator generated by StructDiff. Use to insert into the {field_name} field and generate its diff
+pub fn insert_{field_name}_with_diff(&mut self, index: usize, value: {item}) -> {diff} {{
+	{diff}::{field_name}(structdiff::collections::ordered_array_like::insert_with_diff(&mut self.{field_name}, index, value))
+}}
+/// Mutator generated by StructDiff. Use to remove from the {field_name} field and generate its diff
+pub fn remove_{field_name}_with_diff(&mut self, index: usize) -> ({item}, {diff}) {{
+	let (removed, diff) = structdiff::collections::ordered_array_like::remove_with_diff(&mut self.{field_name}, index);
+	(removed, {diff}::{field_name}(diff))
+}}"
+            )
+        }
+        (false, Some(CollectionStrategy::UnorderedMapLikeHash(_)), false) => {
+            map_mutators("unordered_map_like", "UnorderedMapLikeDiff")
+        }
+        (true, Some(CollectionStrategy::UnorderedMapLikeHash(_)), false) => map_mutators(
+            "unordered_map_like_recursive",
+            "UnorderedMapLikeRecursiveDiffOwned",
+        ),
+        (true, None, false) => format!(
+            "
+/// Mutator generated by StructDiff. Use to modify the {field_name} field in place and generate a diff if necessary.
+/// The field is cloned before `modify` runs and diffed against the result, so this costs as much as diffing the whole field
+pub fn modify_{field_name}_with_diff(&mut self, modify: impl FnOnce(&mut {ty})) -> Option<{diff}> {{
+	let previous = self.{field_name}.clone();
+	modify(&mut self.{field_name});
+	let diffs = structdiff::StructDiff::diff(&previous, &self.{field_name});
+	(!diffs.is_empty()).then(|| {diff}::{field_name}(diffs))
+}}",
+            ty = field.ty.full()
+        ),
+        (true, None, true) => format!(
+            "
+/// Mutator generated by StructDiff. Use to modify the contents of the {field_name} field in place, if it is `Some`, and generate a diff if necessary.
+/// The contents are cloned before `modify` runs and diffed against the result, so this costs as much as diffing the whole field
+pub fn modify_{field_name}_with_diff(&mut self, modify: impl FnOnce(&mut {ty})) -> Option<{diff}> {{
+	let inner = self.{field_name}.as_mut()?;
+	let previous = inner.clone();
+	modify(inner);
+	let diffs = structdiff::StructDiff::diff(&previous, inner);
+	(!diffs.is_empty()).then(|| {diff}::{field_name}(Some(diffs)))
+}}",
+            ty = wrapped(0)
+        ),
+        _ => String::new(),
+    }
+}
+
+/// The payload type of a field's variant in the borrowed diff enum, and the
+/// expression applying it, for fields which can borrow from the input buffer.
+/// All other fields keep their owned payload.
+#[cfg(any(feature = "serde", feature = "nanoserde"))]
+fn borrowed_payload(field: &crate::parse::Field, index: usize) -> Option<(&'static str, String)> {
+    let attributes = &field.attributes;
+    if attrs_with(attributes).is_some()
+        || attrs_diff_as(attributes).is_some()
+        || attrs_remote(attributes).is_some()
+        || attrs_recurse(attributes)
+    {
+        return None;
+    }
+
+    // a custom deserializer produces an owned value anyway
+    #[cfg(feature = "serde")]
+    if attrs_serde(attributes).iter().any(|serde_attr| {
+        serde_arg(serde_attr, "with").is_some()
+            || serde_arg(serde_attr, "deserialize_with").is_some()
+    }) {
+        return None;
+    }
+
+    let field_name = field.field_name.as_ref().unwrap();
+    match (attrs_collection_type(attributes), field.ty.full().as_str()) {
+        (Some(crate::shared::CollectionStrategy::Text(_)), _) => Some((
+            "structdiff::collections::text::TextDiffBorrowed<'__diff_de>",
+            format!("structdiff::collections::text::apply_borrowed_in_place(__{index}, &mut self.{field_name})"),
+        )),
+        (Some(crate::shared::CollectionStrategy::BinaryDelta), _) => Some((
+            "structdiff::collections::binary_delta::BinaryDeltaBorrowed<'__diff_de>",
+            format!("structdiff::collections::binary_delta::apply_borrowed_in_place(__{index}, &mut self.{field_name})"),
+        )),
+        (None, "String" | "std::string::String" | "alloc::string::String") => Some((
+            "structdiff::borrowed::BorrowedStr<'__diff_de>",
+            format!("__{index}.assign_to(&mut self.{field_name})"),
+        )),
+        (None, "Vec<u8>" | "std::vec::Vec<u8>" | "alloc::vec::Vec<u8>") => Some((
+            "structdiff::borrowed::BorrowedBytes<'__diff_de>",
+            format!("__{index}.assign_to(&mut self.{field_name})"),
+        )),
+        _ => None,
+    }
+}
+
+/// The expression reading a diff payload of type `ty` from `offset` and
+/// `bytes`. Diff types read their contents checked themselves, and values
+/// carried whole, such as the replacement for a field, are read with
+/// `DeBinChecked`.
+#[cfg(feature = "nanoserde")]
+fn checked_de_bin(ty: &str) -> String {
+    let ty = ty.trim();
+    // aliases for the diffs of recursed fields
+    if ty.starts_with("__") && ty.ends_with("StructDiffVec") {
+        return String::from("structdiff::checked::de_bin_vec(offset, bytes)");
+    }
+    if let Some(contents) = ty
+        .strip_prefix("Option<")
+        .and_then(|ty| ty.strip_suffix('>'))
+    {
+        if contents.starts_with("__") && contents.ends_with("StructDiffVec") {
+            return format!(
+                "structdiff::checked::de_bin_option_with(offset, bytes, |offset, bytes| {})",
+                checked_de_bin(contents)
+            );
+        }
+    }
+    if ty.starts_with("structdiff::collections::") || ty.ends_with("::Diff") {
+        return String::from("nanoserde::DeBin::de_bin(offset, bytes)");
+    }
+    String::from("structdiff::checked::DeBinChecked::de_bin_checked(offset, bytes)")
+}
+
+/// A newtype variant of a generated diff enum
+struct DiffVariant {
+    /// Attributes forwarded to the variant, such as serde renames
+    attrs: String,
+    name: String,
+    payload: String,
+}
+
+impl DiffVariant {
+    fn render(&self) -> String {
+        format!("{}\n {}({}),", self.attrs, self.name, self.payload)
+    }
+}
+
+/// Record a variant of the owned diff enum, with the attributes gathered for it
+fn push_variant(
+    variants: &mut Vec<DiffVariant>,
+    attrs: &mut String,
+    name: String,
+    payload: String,
+) {
+    variants.push(DiffVariant {
+        attrs: std::mem::take(attrs),
+        name,
+        payload,
+    });
+}
+
 pub(crate) fn derive_struct_diff_struct(struct_: &Struct) -> TokenStream {
+    let struct_ = &resolve_auto_strategies(struct_);
+
+    // `DeBin` is implemented below, with bounds-checked reads
     let owned_derives: String = [
         #[cfg(feature = "debug_diffs")]
         "core::fmt::Debug",
         "Clone",
         #[cfg(feature = "nanoserde")]
         "nanoserde::SerBin",
-        #[cfg(feature = "nanoserde")]
-        "nanoserde::DeBin",
         #[cfg(feature = "serde")]
         "serde::Serialize",
         #[cfg(feature = "serde")]
@@ -96,17 +454,22 @@ pub(crate) fn derive_struct_diff_struct(struct_: &Struct) -> TokenStream {
     ]
     .join(", ");
 
-    let mut diff_enum_body = String::new();
+    let mut diff_variants: Vec<DiffVariant> = Vec::new();
     let mut diff_body = String::new();
     let mut diff_ref_enum_body = String::new();
     let mut diff_ref_body = String::new();
     let mut apply_single_body = String::new();
+    let mut apply_single_ref_body = String::new();
+    let mut apply_single_limited_body = String::new();
     let mut owned_type_aliases = String::new();
     let mut ref_type_aliases = String::new();
     let mut used_generics: Vec<&Generic> = Vec::new();
     let mut ref_into_owned_body = String::new();
     #[cfg(feature = "generated_setters")]
     let mut setters_body = String::new();
+    // where each field's variants start in `diff_variants`, for building the borrowed enum
+    #[cfg(any(feature = "serde", feature = "nanoserde"))]
+    let mut field_variants_start: Vec<(usize, &crate::parse::Field, usize)> = Vec::new();
 
     let exposed = attrs_expose(&struct_.attributes);
 
@@ -129,6 +492,11 @@ pub(crate) fn derive_struct_diff_struct(struct_: &Struct) -> TokenStream {
     #[cfg(feature = "generated_setters")]
     let all_setters = attrs_all_setters(&struct_.attributes);
 
+    #[cfg(feature = "serde")]
+    let rename_all = attrs_serde(&struct_.attributes)
+        .into_iter()
+        .find_map(|serde_attr| serde_arg(serde_attr, "rename_all"));
+
     struct_
         .fields
         .iter()
@@ -136,6 +504,208 @@ pub(crate) fn derive_struct_diff_struct(struct_: &Struct) -> TokenStream {
         .enumerate()
         .for_each(|(index, field)| {
             let field_name = field.field_name.as_ref().unwrap();
+            let FieldAccess {
+                read: self_field,
+                write: self_field_mut,
+                updated: updated_field,
+            } = field_access(struct_, field);
+
+            #[cfg(any(feature = "serde", feature = "nanoserde"))]
+            field_variants_start.push((index, field, diff_variants.len()));
+            // attributes for the next variant pushed to `diff_variants`
+            let mut variant_attrs = String::new();
+
+            // serde attributes are forwarded to the variant(s) for this field,
+            // and `rename_all` is applied here since variants are named after fields
+            #[cfg(feature = "serde")]
+            let serde_name = {
+                let field_serde_attrs = attrs_serde(&field.attributes);
+                for serde_attr in field_serde_attrs.iter() {
+                    l!(variant_attrs, "\n#[serde({})]", serde_attr);
+                    l!(diff_ref_enum_body, "\n#[serde({})]", serde_attr);
+                }
+                match field_serde_attrs.iter().find_map(|serde_attr| serde_arg(serde_attr, "rename")) {
+                    Some(rename) => Some(String::from(rename)),
+                    None => rename_all.map(|rule| {
+                        let renamed = rename_field(field_name, rule);
+                        l!(variant_attrs, "\n#[serde(rename = \"{}\")]", renamed);
+                        l!(diff_ref_enum_body, "\n#[serde(rename = \"{}\")]", renamed);
+                        renamed
+                    }),
+                }
+            };
+            if (attrs_eq_with(&field.attributes).is_some() || attrs_float_epsilon(&field.attributes).is_some())
+                && (attrs_recurse(&field.attributes) || attrs_collection_type(&field.attributes).is_some() || attrs_with(&field.attributes).is_some() || attrs_remote(&field.attributes).is_some())
+            {
+                panic!("`eq_with` and `float_epsilon` can only be used on fields which are replaced whole (field `{field_name}`)");
+            }
+            if attrs_float_epsilon(&field.attributes).is_some() && !matches!(field.ty.full().as_str(), "f32" | "f64") {
+                panic!("`float_epsilon` can only be used on `f32` and `f64` fields, found `{}` (field `{field_name}`)", field.ty.full());
+            }
+
+            if let Some(module) = attrs_with(&field.attributes) { // The module supplies the diff type and the diff/apply functions
+                push_variant(&mut diff_variants, &mut variant_attrs, field_name.clone(), format!("{}::Diff", module));
+                l!(diff_ref_enum_body, " {}({}::DiffRef<'__diff_target>),", field_name, module);
+
+                l!(
+                    apply_single_body,
+                    "Self::Diff::{}(__{}) => {}::apply(&mut {}, __{}),",
+                    field_name,
+                    index,
+                    module,
+                    self_field_mut,
+                    index
+                );
+
+                l!(
+                    apply_single_ref_body,
+                    "Self::DiffRef::{}(__{}) => {}::apply(&mut {}, __{}.into()),",
+                    field_name,
+                    index,
+                    module,
+                    self_field_mut,
+                    index
+                );
+
+                l!(
+                    diff_body,
+                    "if let Some(inner) = {}::diff(&{}, &{}) {{diffs.push(Self::Diff::{}(inner))}};",
+                    module,
+                    self_field,
+                    updated_field,
+                    field_name
+                );
+
+                l!(
+                    diff_ref_body,
+                    "if let Some(inner) = {}::diff_ref(&{}, &{}) {{diffs.push(Self::DiffRef::{}(inner))}};",
+                    module,
+                    self_field,
+                    updated_field,
+                    field_name
+                );
+
+                l!(
+                    ref_into_owned_body,
+                    "\t {}Ref::{}(v) => {}::{}(v.into()),",
+                    enum_name,
+                    field_name,
+                    enum_name,
+                    field_name
+                );
+
+                #[cfg(feature = "generated_setters")]
+                match (all_setters, attrs_setter(&field.attributes)) {
+                    (_, (_, true, _)) => (),
+                    (true, (_, false, name_override)) | (false, (true, false, name_override)) => {
+                        let setter_name = match name_override {
+                            Some(name_override) => String::from(name_override),
+                            None => format!("set_{}_with_diff", field_name),
+                        };
+                        l!(setters_body, "\n/// Setter generated by StructDiff. Use to set the {} field and generate a diff if necessary", setter_name);
+                        l!(setters_body, "\npub fn {}(&mut self, value: {}) -> Option<<Self as structdiff::StructDiff>::Diff> {{", setter_name, field.ty.full());
+                        l!(setters_body, "\n\tlet diff = {}::diff(&self.{}, &value);", module, field_name);
+                        l!(setters_body, "\n\tself.{} = value;", field_name);
+                        l!(setters_body, "\n\tdiff.map(<Self as structdiff::StructDiff>::Diff::{})", field_name);
+                        l!(setters_body, "\n}");
+                    },
+                    _ => ()
+                };
+
+                return;
+            }
+
+            if let Some(proxy) = attrs_diff_as(&field.attributes) { // Store the field as a proxy type in the diff, converting with From/Into
+                if attrs_recurse(&field.attributes) || attrs_collection_type(&field.attributes).is_some() || attrs_with(&field.attributes).is_some() || attrs_remote(&field.attributes).is_some() {
+                    panic!("`diff_as` can only be used on fields which are replaced whole (field `{field_name}`)");
+                }
+
+                // optional fields convert their contents, so that the proxy
+                // type doesn't need to know about Option
+                let is_option = field.ty.base() == "Option";
+                let typename = format!("__{field_name}StructDiffProxy");
+                match is_option {
+                    true => l!(owned_type_aliases, "///Generated aliases from StructDiff\n type {} = Option<{}>;", typename, proxy),
+                    false => l!(owned_type_aliases, "///Generated aliases from StructDiff\n type {} = {};", typename, proxy),
+                }
+                let (to_proxy, from_proxy) = match is_option {
+                    true => (".map(Into::into)", ".map(Into::into)"),
+                    false => (".into()", ".into()"),
+                };
+                let changed = field_changed(&field.attributes, &self_field, &updated_field);
+
+                push_variant(&mut diff_variants, &mut variant_attrs, field_name.clone(), typename.to_string());
+                l!(diff_ref_enum_body, " {}({}),", field_name, typename);
+
+                l!(
+                    apply_single_body,
+                    "Self::Diff::{}(__{}) => {} = __{}{},",
+                    field_name,
+                    index,
+                    self_field_mut,
+                    index,
+                    from_proxy
+                );
+
+                l!(
+                    apply_single_ref_body,
+                    "Self::DiffRef::{}(__{}) => {} = __{}{},",
+                    field_name,
+                    index,
+                    self_field_mut,
+                    index,
+                    from_proxy
+                );
+
+                l!(
+                    diff_body,
+                    "if {} {{diffs.push(Self::Diff::{}({}.clone(){}))}};",
+                    changed,
+                    field_name,
+                    updated_field,
+                    to_proxy
+                );
+
+                l!(
+                    diff_ref_body,
+                    "if {} {{diffs.push(Self::DiffRef::{}({}.clone(){}))}};",
+                    changed,
+                    field_name,
+                    updated_field,
+                    to_proxy
+                );
+
+                l!(
+                    ref_into_owned_body,
+                    "\t {}Ref::{}(v) => {}::{}(v),",
+                    enum_name,
+                    field_name,
+                    enum_name,
+                    field_name
+                );
+
+                #[cfg(feature = "generated_setters")]
+                match (all_setters, attrs_setter(&field.attributes)) {
+                    (_, (_, true, _)) => (),
+                    (true, (_, false, name_override)) | (false, (true, false, name_override)) => {
+                        let setter_name = match name_override {
+                            Some(name_override) => String::from(name_override),
+                            None => format!("set_{}_with_diff", field_name),
+                        };
+                        l!(setters_body, "\n/// Setter generated by StructDiff. Use to set the {} field and generate a diff if necessary", setter_name);
+                        l!(setters_body, "\npub fn {}(&mut self, value: {}) -> Option<<Self as structdiff::StructDiff>::Diff> {{", setter_name, field.ty.full());
+                        l!(setters_body, "\n\tif !({}) {{return None}};", field_changed(&field.attributes, &format!("self.{field_name}"), "value"));
+                        l!(setters_body, "\n\tlet diff = <Self as structdiff::StructDiff>::Diff::{}(value.clone(){});", field_name, to_proxy);
+                        l!(setters_body, "\n\tself.{} = value;", field_name);
+                        l!(setters_body, "\n\tSome(diff)");
+                        l!(setters_body, "\n}");
+                    },
+                    _ => ()
+                };
+
+                return;
+            }
+
             used_generics.extend(struct_.generics.iter().filter(|x| x.full() == field.ty.ident.path(&field.ty, false)));
 
             let to_add = struct_.generics.iter().filter(|x| field.ty.wraps().iter().any(|wrapped_type| x.full() == wrapped_type));
@@ -152,38 +722,121 @@ pub(crate) fn derive_struct_diff_struct(struct_: &Struct) -> TokenStream {
                 }
             }
 
+            if let Some(mirror) = attrs_remote(&field.attributes) { // Diff a foreign type through the StructDiffRemote impl of its mirror
+                let remote_impl = format!("<{} as structdiff::StructDiffRemote<{}>>", mirror, field.ty.full());
+                let typename = format!("__{field_name}StructDiffVec");
+                l!(owned_type_aliases, "///Generated aliases from StructDiff\n type {} = Vec<{}::Diff>;", typename, remote_impl);
+                let typename_ref = format!("__{field_name}StructDiffRefVec<'__diff_target>");
+                l!(ref_type_aliases, "///Generated aliases from StructDiff\n type {} = Vec<{}::DiffRef<'__diff_target>>;", typename_ref, remote_impl);
+
+                push_variant(&mut diff_variants, &mut variant_attrs, field_name.clone(), typename.to_string());
+                l!(diff_ref_enum_body, " {}({}),", field_name, typename_ref);
+
+                l!(
+                    apply_single_body,
+                    "Self::Diff::{}(__{}) => {}::apply_mut(&mut {}, __{}),",
+                    field_name,
+                    index,
+                    remote_impl,
+                    self_field_mut,
+                    index
+                );
+
+                l!(
+                    apply_single_ref_body,
+                    "Self::DiffRef::{}(__{}) => for __diff in __{} {{ {}::apply_single_ref(&mut {}, __diff) }},",
+                    field_name,
+                    index,
+                    index,
+                    remote_impl,
+                    self_field_mut);
+
+                l!(
+                    diff_body,
+                    "{{ let inner = {}::diff(&{}, &{}); if !inner.is_empty() {{diffs.push(Self::Diff::{}(inner))}} }};",
+                    remote_impl,
+                    self_field,
+                    updated_field,
+                    field_name
+                );
+
+                l!(
+                    diff_ref_body,
+                    "{{ let inner = {}::diff_ref(&{}, &{}); if !inner.is_empty() {{diffs.push(Self::DiffRef::{}(inner))}} }};",
+                    remote_impl,
+                    self_field,
+                    updated_field,
+                    field_name
+                );
+
+                l!(
+                    ref_into_owned_body,
+                    "\t {}Ref::{}(v) => {}::{}(v.into_iter().map(Into::into).collect()),",
+                    enum_name,
+                    field_name,
+                    enum_name,
+                    field_name
+                );
+
+                #[cfg(feature = "generated_setters")]
+                match (all_setters, attrs_setter(&field.attributes)) {
+                    (_, (_, true, _)) => (),
+                    (true, (_, false, name_override)) | (false, (true, false, name_override)) => {
+                        let setter_name = match name_override {
+                            Some(name_override) => String::from(name_override),
+                            None => format!("set_{}_with_diff", field_name),
+                        };
+                        l!(setters_body, "\n/// Setter generated by StructDiff. Use to set the {} field and generate a diff if necessary", setter_name);
+                        l!(setters_body, "\npub fn {}(&mut self, value: {}) -> Option<<Self as structdiff::StructDiff>::Diff> {{", setter_name, field.ty.full());
+                        l!(setters_body, "\n\tlet diffs = {}::diff(&self.{}, &value);", remote_impl, field_name);
+                        l!(setters_body, "\n\tself.{} = value;", field_name);
+                        l!(setters_body, "\n\t(!diffs.is_empty()).then(|| <Self as structdiff::StructDiff>::Diff::{}(diffs))", field_name);
+                        l!(setters_body, "\n}");
+                    },
+                    _ => ()
+                };
+
+                return;
+            }
+
             match (attrs_recurse(&field.attributes), attrs_collection_type(&field.attributes), field.ty.base() == "Option") {
 
                 (false, None, false) => {  // The default case
-                    l!(diff_enum_body, " {}({}),", field_name, field.ty.full());
+                    let changed = field_changed(&field.attributes, &self_field, &updated_field);
+                    push_variant(&mut diff_variants, &mut variant_attrs, field_name.clone(), field.ty.full().to_string());
                     l!(diff_ref_enum_body, " {}(&'__diff_target {}),", field_name, field.ty.full());
 
                     l!(
                         apply_single_body,
-                        "Self::Diff::{}(__{}) => self.{} = __{},",
+                        "Self::Diff::{}(__{}) => {} = __{},",
                         field_name,
                         index,
+                        self_field_mut,
+                        index
+                    );
+
+                    l!(
+                        apply_single_ref_body,
+                        "Self::DiffRef::{}(__{}) => {}.clone_from(__{}),",
                         field_name,
+                        index,
+                        self_field_mut,
                         index
                     );
 
                     l!(
                         diff_body,
-                        "if self.{} != updated.{} {{diffs.push(Self::Diff::{}(updated.{}.clone()))}};",
-                        field_name,
-                        field_name,
+                        "if {} {{diffs.push(Self::Diff::{}({}.clone()))}};",
+                        changed,
                         field_name,
-                        field_name
-                    );
+                        updated_field);
 
                     l!(
                         diff_ref_body,
-                        "if self.{} != updated.{} {{diffs.push(Self::DiffRef::{}(&updated.{}))}};",
-                        field_name,
-                        field_name,
+                        "if {} {{diffs.push(Self::DiffRef::{}(&{}))}};",
+                        changed,
                         field_name,
-                        field_name
-                    );
+                        updated_field);
 
                     l!(
                         ref_into_owned_body,
@@ -201,7 +854,7 @@ pub(crate) fn derive_struct_diff_struct(struct_: &Struct) -> TokenStream {
                         (true, (_, false, Some(name_override))) | (false, (true, false, Some(name_override))) => {
                             l!(setters_body, "\n/// Setter generated by StructDiff. Use to set the {} field and generate a diff if necessary", name_override);
                             l!(setters_body, "\npub fn {}(&mut self, value: {}) -> Option<<Self as structdiff::StructDiff>::Diff> {{", name_override, field.ty.full());
-                            l!(setters_body, "\n\tif self.{} == value {{return None}};", field_name);
+                            l!(setters_body, "\n\tif !({}) {{return None}};", field_changed(&field.attributes, &format!("self.{field_name}"), "value"));
                             l!(setters_body, "\n\tlet diff = <Self as structdiff::StructDiff>::Diff::{}(value.clone());", field_name);
                             l!(setters_body, "\n\tself.{} = value;", field_name);
                             l!(setters_body, "\n\treturn Some(diff)");
@@ -210,7 +863,7 @@ pub(crate) fn derive_struct_diff_struct(struct_: &Struct) -> TokenStream {
                         (true, (_, false, None)) | (false, (true, false, None)) => {
                             l!(setters_body, "\n/// Setter generated by StructDiff. Use to set the {} field and generate a diff if necessary", field_name);
                             l!(setters_body, "\npub fn set_{}_with_diff(&mut self, value: {}) -> Option<<Self as structdiff::StructDiff>::Diff> {{", field_name, field.ty.full());
-                            l!(setters_body, "\n\tif self.{} == value {{return None}};", field_name);
+                            l!(setters_body, "\n\tif !({}) {{return None}};", field_changed(&field.attributes, &format!("self.{field_name}"), "value"));
                             l!(setters_body, "\n\tlet diff = <Self as structdiff::StructDiff>::Diff::{}(value.clone());", field_name);
                             l!(setters_body, "\n\tself.{} = value;", field_name);
                             l!(setters_body, "\n\treturn Some(diff)");
@@ -220,36 +873,42 @@ pub(crate) fn derive_struct_diff_struct(struct_: &Struct) -> TokenStream {
                     };
                 },
                 (false, None, true) => {  // The default case, but with an option
+                    let changed = field_changed(&field.attributes, &self_field, &updated_field);
 
-                    l!(diff_enum_body, " {}({}),", field_name, field.ty.full());
+                    push_variant(&mut diff_variants, &mut variant_attrs, field_name.clone(), field.ty.full().to_string());
                     l!(diff_ref_enum_body, " {}(&'__diff_target {}),", field_name, field.ty.full());
 
                     l!(
                         apply_single_body,
-                        "Self::Diff::{}(__{}) => self.{} = __{},",
+                        "Self::Diff::{}(__{}) => {} = __{},",
                         field_name,
                         index,
+                        self_field_mut,
+                        index
+                    );
+
+                    l!(
+                        apply_single_ref_body,
+                        "Self::DiffRef::{}(__{}) => {}.clone_from(__{}),",
                         field_name,
+                        index,
+                        self_field_mut,
                         index
                     );
 
                     l!(
                         diff_body,
-                        "if self.{} != updated.{} {{diffs.push(Self::Diff::{}(updated.{}.clone()))}};",
-                        field_name,
+                        "if {} {{diffs.push(Self::Diff::{}({}.clone()))}};",
+                        changed,
                         field_name,
-                        field_name,
-                        field_name
-                    );
+                        updated_field);
 
                     l!(
                         diff_ref_body,
-                        "if self.{} != updated.{} {{diffs.push(Self::DiffRef::{}(&updated.{}))}};",
-                        field_name,
+                        "if {} {{diffs.push(Self::DiffRef::{}(&{}))}};",
+                        changed,
                         field_name,
-                        field_name,
-                        field_name
-                    );
+                        updated_field);
 
                     l!(
                         ref_into_owned_body,
@@ -266,7 +925,7 @@ pub(crate) fn derive_struct_diff_struct(struct_: &Struct) -> TokenStream {
                         (true, (_, false, Some(name_override))) | (false, (true, false, Some(name_override))) => {
                             l!(setters_body, "\n/// Setter generated by StructDiff. Use to set the {} field and generate a diff if necessary", name_override);
                             l!(setters_body, "\npub fn {}(&mut self, value: {}) -> Option<<Self as structdiff::StructDiff>::Diff> {{", name_override, field.ty.full());
-                            l!(setters_body, "\n\tif self.{} == value {{return None}};", field_name);
+                            l!(setters_body, "\n\tif !({}) {{return None}};", field_changed(&field.attributes, &format!("self.{field_name}"), "value"));
                             l!(setters_body, "\n\tlet diff = <Self as structdiff::StructDiff>::Diff::{}(value.clone());", field_name);
                             l!(setters_body, "\n\tself.{} = value;", field_name);
                             l!(setters_body, "\n\treturn Some(diff)");
@@ -275,7 +934,7 @@ pub(crate) fn derive_struct_diff_struct(struct_: &Struct) -> TokenStream {
                         (true, (_, false, None)) | (false, (true, false, None)) => {
                             l!(setters_body, "\n/// Setter generated by StructDiff. Use to set the {} field and generate a diff if necessary", field_name);
                             l!(setters_body, "\npub fn set_{}_with_diff(&mut self, value: {}) -> Option<<Self as structdiff::StructDiff>::Diff> {{", field_name, field.ty.full());
-                            l!(setters_body, "\n\tif self.{} == value {{return None}};", field_name);
+                            l!(setters_body, "\n\tif !({}) {{return None}};", field_changed(&field.attributes, &format!("self.{field_name}"), "value"));
                             l!(setters_body, "\n\tlet diff = <Self as structdiff::StructDiff>::Diff::{}(value.clone());", field_name);
                             l!(setters_body, "\n\tself.{} = value;", field_name);
                             l!(setters_body, "\n\treturn Some(diff)");
@@ -290,38 +949,54 @@ pub(crate) fn derive_struct_diff_struct(struct_: &Struct) -> TokenStream {
                     let typename_ref = format!("__{field_name}StructDiffRefVec<'__diff_target>");
                     l!(ref_type_aliases, "///Generated aliases from StructDiff\n type {} = Vec<<{} as structdiff::StructDiff>::DiffRef<'__diff_target>>;", typename_ref, field.ty.full());
 
-                    l!(diff_enum_body, " {}({}),", field_name, typename);
+                    push_variant(&mut diff_variants, &mut variant_attrs, field_name.clone(), typename.to_string());
                     l!(diff_ref_enum_body, " {}({}),", field_name, typename_ref);
 
                     l!(
                         apply_single_body,
-                        "Self::Diff::{}(__{}) => self.{} = self.{}.apply_ref(__{}),",
+                        "Self::Diff::{}(__{}) => {} = {}.apply_ref(__{}),",
                         field_name,
                         index,
+                        self_field_mut,
+                        self_field_mut,
+                        index
+                    );
+
+                    l!(
+                        apply_single_ref_body,
+                        "Self::DiffRef::{}(__{}) => {}.apply_ref_diffs(__{}),",
                         field_name,
+                        index,
+                        self_field_mut,
+                        index
+                    );
+
+                    l!(
+                        apply_single_limited_body,
+                        "Self::Diff::{}(__{}) => budget.apply_nested(&mut {}, __{})?,",
                         field_name,
+                        index,
+                        self_field_mut,
                         index
                     );
 
                     l!(
                         diff_body,
-                        "if &self.{} != &updated.{} {{diffs.push(Self::Diff::{}(self.{}.diff(&updated.{})))}};",
-                        field_name,
-                        field_name,
-                        field_name,
+                        "if &{} != &{} {{diffs.push(Self::Diff::{}({}.diff(&{})))}};",
+                        self_field,
+                        updated_field,
                         field_name,
-                        field_name
-                    );
+                        self_field,
+                        updated_field);
 
                     l!(
                         diff_ref_body,
-                        "if &self.{} != &updated.{} {{diffs.push(Self::DiffRef::{}(self.{}.diff_ref(&updated.{})))}};",
-                        field_name,
-                        field_name,
+                        "if &{} != &{} {{diffs.push(Self::DiffRef::{}({}.diff_ref(&{})))}};",
+                        self_field,
+                        updated_field,
                         field_name,
-                        field_name,
-                        field_name
-                    );
+                        self_field,
+                        updated_field);
 
                     l!(
                         ref_into_owned_body,
@@ -371,29 +1046,37 @@ pub(crate) fn derive_struct_diff_struct(struct_: &Struct) -> TokenStream {
                         field.ty.wraps.as_ref().expect("Option must wrap a type").first().expect("Option must wrap a type").full()
                     );
 
-                    l!(diff_enum_body, " {}(Option<{}>),", field_name, typename);
-                    l!(diff_enum_body, " {}_full({}),", field_name, field.ty.wraps.as_ref().expect("Option must wrap a type").first().expect("Option must wrap a type").full());
+                    push_variant(&mut diff_variants, &mut variant_attrs, field_name.clone(), format!("Option<{}>", typename));
+                    #[cfg(feature = "serde")]
+                    if let Some(serde_name) = &serde_name {
+                        l!(variant_attrs, "\n#[serde(rename = \"{}_full\")]", serde_name);
+                    }
+                    push_variant(&mut diff_variants, &mut variant_attrs, format!("{field_name}_full"), field.ty.wraps.as_ref().expect("Option must wrap a type").first().expect("Option must wrap a type").full().to_string());
 
                     l!(diff_ref_enum_body, " {}(Option<{}>),", field_name, ref_typename);
+                    #[cfg(feature = "serde")]
+                    if let Some(serde_name) = &serde_name {
+                        l!(diff_ref_enum_body, "\n#[serde(rename = \"{}_full\")]", serde_name);
+                    }
                     l!(diff_ref_enum_body, " {}_full(&'__diff_target {}),", field_name, field.ty.wraps.as_ref().expect("Option must wrap a type").first().expect("Option must wrap a type").full());
 
                     let apply_single_body_partial = format!(
-                        "Self::Diff::{field_name}(Some(__{index})) => if let Some(ref mut inner) = self.{field_name} {{ 
+                        "Self::Diff::{field_name}(Some(__{index})) => if let Some(ref mut inner) = {self_field_mut} {{ 
                             inner.apply_mut(__{index});
                         }},"
                     );
 
 
                     let apply_single_body_full = format!(
-                        "Self::Diff::{field_name}_full(__{index}) => self.{field_name} = Some(__{index}),"
+                        "Self::Diff::{field_name}_full(__{index}) => {self_field_mut} = Some(__{index}),"
                     );
 
                     let apply_single_body_none = format!(
-                        "Self::Diff::{field_name}(None) => self.{field_name} = None,"
+                        "Self::Diff::{field_name}(None) => {self_field_mut} = None,"
                     );
 
                     let diff_body_fragment = format!(
-                        "match (&self.{field_name}, &updated.{field_name}) {{
+                        "match (&{self_field}, &{updated_field}) {{
                             (Some(val1), Some(val2)) if &val1 != &val2 => diffs.push(Self::Diff::{field_name}(Some(val1.diff(&val2)))),
                             (Some(val1), None) => diffs.push(Self::Diff::{field_name}(None)),
                             (None, Some(val2)) => diffs.push(Self::Diff::{field_name}_full(val2.clone())),
@@ -402,7 +1085,7 @@ pub(crate) fn derive_struct_diff_struct(struct_: &Struct) -> TokenStream {
                     );
 
                     let diff_body_fragment_ref = format!(
-                        "match (&self.{field_name}, &updated.{field_name}) {{
+                        "match (&{self_field}, &{updated_field}) {{
                             (Some(val1), Some(val2)) if &val1 != &val2 => diffs.push(Self::DiffRef::{field_name}(Some(val1.diff_ref(&val2)))),
                             (Some(val1), None) => diffs.push(Self::DiffRef::{field_name}(None)),
                             (None, Some(val2)) => diffs.push(Self::DiffRef::{field_name}_full(&val2)),
@@ -466,6 +1149,28 @@ pub(crate) fn derive_struct_diff_struct(struct_: &Struct) -> TokenStream {
                     l!(apply_single_body, "{}", apply_single_body_partial);
                     l!(apply_single_body, "{}", apply_single_body_full);
                     l!(apply_single_body, "{}", apply_single_body_none);
+                    l!(
+                        apply_single_limited_body,
+                        "Self::Diff::{}(Some(__{})) => if let Some(ref mut inner) = {} {{
+                            budget.apply_nested(inner, __{})?;
+                        }},",
+                        field_name,
+                        index,
+                        self_field_mut,
+                        index
+                    );
+                    l!(
+                        apply_single_ref_body,
+                        "{}",
+                        format!("Self::DiffRef::{field_name}(Some(__{index})) => if let Some(ref mut inner) = {self_field_mut} {{
+                            inner.apply_ref_diffs(__{index});
+                        }},
+                        Self::DiffRef::{field_name}_full(__{index}) => match {self_field_mut} {{
+                            Some(ref mut inner) => inner.clone_from(__{index}),
+                            None => {self_field_mut} = Some(__{index}.clone()),
+                        }},
+                        Self::DiffRef::{field_name}(None) => {self_field_mut} = None,")
+                    );
                     l!(diff_body, "{}", diff_body_fragment);
 
                     l!(diff_ref_body, "{}", diff_body_fragment_ref);
@@ -473,41 +1178,65 @@ pub(crate) fn derive_struct_diff_struct(struct_: &Struct) -> TokenStream {
                 (true, Some(strat), false) => match strat {
                     crate::shared::CollectionStrategy::OrderedArrayLike => { panic!("Recursion inside of array-like collections is not yet supported"); },
                     crate::shared::CollectionStrategy::UnorderedArrayLikeHash => { panic!("Recursion inside of array-like collections is not yet supported"); },
+                    crate::shared::CollectionStrategy::Text(_) => { panic!("Recursion is not supported for the text collection strategy"); },
+                    crate::shared::CollectionStrategy::BinaryDelta => { panic!("Recursion is not supported for the binary_delta collection strategy"); },
                     crate::shared::CollectionStrategy::UnorderedMapLikeHash(crate::shared::MapStrategy::KeyAndValue) => {
                         let generic_names = field.ty.wraps.as_ref().map(|x| x.iter().map(|y| y.full().clone()).collect::<Vec<_>>()).expect("Missing types for map creation").join(",");
-                        l!(diff_enum_body, " {}(structdiff::collections::unordered_map_like_recursive::UnorderedMapLikeRecursiveDiffOwned<{}>),", field_name, generic_names);
+                        push_variant(&mut diff_variants, &mut variant_attrs, field_name.clone(), format!("structdiff::collections::unordered_map_like_recursive::UnorderedMapLikeRecursiveDiffOwned<{}>", generic_names));
                         l!(diff_ref_enum_body, " {}(structdiff::collections::unordered_map_like_recursive::UnorderedMapLikeRecursiveDiffRef<'__diff_target, {}>),", field_name, generic_names);
 
                         l!(
                             apply_single_body,
-                            "Self::Diff::{}(__{}) => self.{} = structdiff::collections::unordered_map_like_recursive::apply_unordered_hashdiffs(std::mem::take(&mut self.{}).into_iter(), __{}).collect(),",
+                            "Self::Diff::{}(__{}) => structdiff::collections::unordered_map_like_recursive::apply_unordered_hashdiffs_in_place(&mut {}, __{}),",
                             field_name,
                             index,
+                            self_field_mut,
+                            index
+                        );
+
+                        l!(
+                            apply_single_ref_body,
+                            "Self::DiffRef::{}(__{}) => structdiff::collections::unordered_map_like_recursive::apply_unordered_hashdiffs_in_place_ref(&mut {}, __{}),",
                             field_name,
+                            index,
+                            self_field_mut,
+                            index
+                        );
+
+                        l!(
+                            apply_single_limited_body,
+                            "Self::Diff::{}(__{}) => structdiff::collections::unordered_map_like_recursive::apply_unordered_hashdiffs_limited(&mut {}, __{}, budget)?,",
                             field_name,
+                            index,
+                            self_field_mut,
                             index
                         );
 
+                        let diff_call = format!("structdiff::collections::unordered_map_like_recursive::unordered_hashcmp({}.iter(), {}.iter(), false)", self_field, updated_field);
+                        let stream_args = format!("{}.iter(), {}.iter(), false", self_field, updated_field);
+
                         l!(
                             diff_body,
-                            "if let Some(list_diffs) = structdiff::collections::unordered_map_like_recursive::unordered_hashcmp(self.{}.iter(), updated.{}.iter(), false) {{
-                                diffs.push(Self::Diff::{}(list_diffs.into()));
-                            }};"
-                            ,
-                            field_name,
-                            field_name,
-                            field_name
+                            &push_collection_diffs(
+                                &field.attributes,
+                                false,
+                                &diff_call,
+                                "structdiff::collections::unordered_map_like_recursive::unordered_hashcmp_into",
+                                &stream_args,
+                                &format!("Self::Diff::{}(core::convert::Into::into(list_diffs))", field_name),
+                            )
                         );
 
                         l!(
                             diff_ref_body,
-                            "if let Some(list_diffs) = structdiff::collections::unordered_map_like_recursive::unordered_hashcmp(self.{}.iter(), updated.{}.iter(), false) {{
-                                diffs.push(Self::DiffRef::{}(list_diffs));
-                            }};"
-                            ,
-                            field_name,
-                            field_name,
-                            field_name
+                            &push_collection_diffs(
+                                &field.attributes,
+                                true,
+                                &diff_call,
+                                "structdiff::collections::unordered_map_like_recursive::unordered_hashcmp_into",
+                                &stream_args,
+                                &format!("Self::DiffRef::{}(list_diffs)", field_name),
+                            )
                         );
 
                         l!(
@@ -521,39 +1250,61 @@ pub(crate) fn derive_struct_diff_struct(struct_: &Struct) -> TokenStream {
                     },
                     crate::shared::CollectionStrategy::UnorderedMapLikeHash(crate::shared::MapStrategy::KeyOnly) => {
                         let generic_names = field.ty.wraps.as_ref().map(|x| x.iter().map(|y| y.full()).collect::<Vec<_>>()).expect("Missing types for map creation").join(",");
-                        l!(diff_enum_body, " {}(structdiff::collections::unordered_map_like_recursive::UnorderedMapLikeRecursiveDiffOwned<{}>),", field_name, generic_names);
+                        push_variant(&mut diff_variants, &mut variant_attrs, field_name.clone(), format!("structdiff::collections::unordered_map_like_recursive::UnorderedMapLikeRecursiveDiffOwned<{}>", generic_names));
                         l!(diff_ref_enum_body, " {}(structdiff::collections::unordered_map_like_recursive::UnorderedMapLikeRecursiveDiffRef<'__diff_target, {}>),", field_name, generic_names);
 
                         l!(
                             apply_single_body,
-                            "Self::Diff::{}(__{}) => self.{} = structdiff::collections::unordered_map_like_recursive::apply_unordered_hashdiffs(std::mem::take(&mut self.{}).into_iter(), __{}).collect(),",
+                            "Self::Diff::{}(__{}) => structdiff::collections::unordered_map_like_recursive::apply_unordered_hashdiffs_in_place(&mut {}, __{}),",
                             field_name,
                             index,
+                            self_field_mut,
+                            index
+                        );
+
+                        l!(
+                            apply_single_ref_body,
+                            "Self::DiffRef::{}(__{}) => structdiff::collections::unordered_map_like_recursive::apply_unordered_hashdiffs_in_place_ref(&mut {}, __{}),",
                             field_name,
+                            index,
+                            self_field_mut,
+                            index
+                        );
+
+                        l!(
+                            apply_single_limited_body,
+                            "Self::Diff::{}(__{}) => structdiff::collections::unordered_map_like_recursive::apply_unordered_hashdiffs_limited(&mut {}, __{}, budget)?,",
                             field_name,
+                            index,
+                            self_field_mut,
                             index
                         );
 
+                        let diff_call = format!("structdiff::collections::unordered_map_like_recursive::unordered_hashcmp({}.iter(), {}.iter(), true)", self_field, updated_field);
+                        let stream_args = format!("{}.iter(), {}.iter(), true", self_field, updated_field);
+
                         l!(
                             diff_body,
-                            "if let Some(list_diffs) = structdiff::collections::unordered_map_like_recursive::unordered_hashcmp(self.{}.iter(), updated.{}.iter(), true) {{
-                                diffs.push(Self::Diff::{}(list_diffs.into()));
-                            }};"
-                            ,
-                            field_name,
-                            field_name,
-                            field_name
+                            &push_collection_diffs(
+                                &field.attributes,
+                                false,
+                                &diff_call,
+                                "structdiff::collections::unordered_map_like_recursive::unordered_hashcmp_into",
+                                &stream_args,
+                                &format!("Self::Diff::{}(core::convert::Into::into(list_diffs))", field_name),
+                            )
                         );
 
                         l!(
                             diff_ref_body,
-                            "if let Some(list_diffs) = structdiff::collections::unordered_map_like_recursive::unordered_hashcmp(self.{}.iter(), updated.{}.iter(), true) {{
-                                diffs.push(Self::DiffRef::{}(list_diffs));
-                            }};"
-                            ,
-                            field_name,
-                            field_name,
-                            field_name
+                            &push_collection_diffs(
+                                &field.attributes,
+                                true,
+                                &diff_call,
+                                "structdiff::collections::unordered_map_like_recursive::unordered_hashcmp_into",
+                                &stream_args,
+                                &format!("Self::DiffRef::{}(list_diffs)", field_name),
+                            )
                         );
 
                         l!(
@@ -595,39 +1346,63 @@ pub(crate) fn derive_struct_diff_struct(struct_: &Struct) -> TokenStream {
                 (false, Some(strat), false) => match strat {
                     crate::shared::CollectionStrategy::OrderedArrayLike => {
                         let generic_ref_names = field.ty.wraps.as_ref().map(|x| x.iter().map(|y| y.full()).collect::<Vec<_>>()).expect("Missing types for map creation").join(", &'__diff_target ");
-                        l!(diff_enum_body, " {}(structdiff::collections::ordered_array_like::OrderedArrayLikeDiffOwned<{}>),", field_name, field.ty.wraps.as_ref().expect("Using collection strategy on a non-collection")[0].full());
+                        push_variant(&mut diff_variants, &mut variant_attrs, field_name.clone(), format!("structdiff::collections::ordered_array_like::OrderedArrayLikeDiffOwned<{}>", field.ty.wraps.as_ref().expect("Using collection strategy on a non-collection")[0].full()));
                         l!(diff_ref_enum_body, " {}(structdiff::collections::ordered_array_like::OrderedArrayLikeDiffRef<'__diff_target, {}>),", field_name, generic_ref_names);
 
                         l!(
                             apply_single_body,
-                            "Self::Diff::{}(__{}) => self.{} = structdiff::collections::ordered_array_like::apply(__{}, std::mem::take(&mut self.{})).collect(),",
+                            "Self::Diff::{}(__{}) => structdiff::collections::ordered_array_like::apply_in_place(__{}, &mut {}),",
+                            field_name,
+                            index,
+                            index,
+                            self_field_mut);
+
+                        l!(
+                            apply_single_ref_body,
+                            "Self::DiffRef::{}(__{}) => structdiff::collections::ordered_array_like::apply_ref_in_place(__{}, &mut {}),",
                             field_name,
                             index,
+                            index,
+                            self_field_mut);
+
+                        l!(
+                            apply_single_limited_body,
+                            "Self::Diff::{}(__{}) => structdiff::collections::ordered_array_like::apply_in_place_limited(__{}, &mut {}, budget)?,",
                             field_name,
                             index,
-                            field_name
+                            index,
+                            self_field_mut);
+
+                        let diff_call = auto_diff(
+                            &field.attributes,
+                            format!("structdiff::collections::ordered_array_like::hirschberg(&{}, &{})", updated_field, self_field),
+                            format!("structdiff::collections::ordered_array_like::replacement(&{}, &{})", updated_field, self_field),
                         );
 
+                        let stream_args = format!("&{}, &{}", updated_field, self_field);
+
                         l!(
                             diff_body,
-                            "if let Some(list_diffs) = structdiff::collections::ordered_array_like::hirschberg(&updated.{}, &self.{}) {{
-                                diffs.push(Self::Diff::{}(list_diffs.into()));
-                            }};"
-                            ,
-                            field_name,
-                            field_name,
-                            field_name
+                            &push_collection_diffs(
+                                &field.attributes,
+                                false,
+                                &diff_call,
+                                "structdiff::collections::ordered_array_like::hirschberg_into",
+                                &stream_args,
+                                &format!("Self::Diff::{}(core::convert::Into::into(list_diffs))", field_name),
+                            )
                         );
 
                         l!(
                             diff_ref_body,
-                            "if let Some(list_diffs) = structdiff::collections::ordered_array_like::hirschberg(&updated.{}, &self.{}) {{
-                                diffs.push(Self::DiffRef::{}(list_diffs));
-                            }};"
-                            ,
-                            field_name,
-                            field_name,
-                            field_name
+                            &push_collection_diffs(
+                                &field.attributes,
+                                true,
+                                &diff_call,
+                                "structdiff::collections::ordered_array_like::hirschberg_into",
+                                &stream_args,
+                                &format!("Self::DiffRef::{}(list_diffs)", field_name),
+                            )
                         );
 
                         l!(
@@ -661,40 +1436,57 @@ pub(crate) fn derive_struct_diff_struct(struct_: &Struct) -> TokenStream {
                             _ => ()
                         };
                     }
-                    crate::shared::CollectionStrategy::UnorderedArrayLikeHash => {
-                        let generic_ref_names = field.ty.wraps.as_ref().map(|x| x.iter().map(|y| y.full()).collect::<Vec<_>>()).expect("Missing types for map creation").join(", &'__diff_target ");
-                        l!(diff_enum_body, " {}(structdiff::collections::unordered_array_like::UnorderedArrayLikeDiff<{}>),", field_name, field.ty.wraps.as_ref().expect("Using collection strategy on a non-collection")[0].full());
-                        l!(diff_ref_enum_body, " {}(structdiff::collections::unordered_array_like::UnorderedArrayLikeDiff<&'__diff_target {}>),", field_name, generic_ref_names);
+                    crate::shared::CollectionStrategy::Text(granularity) => {
+                        push_variant(&mut diff_variants, &mut variant_attrs, field_name.clone(), String::from("structdiff::collections::text::TextDiffOwned"));
+                        l!(diff_ref_enum_body, " {}(structdiff::collections::text::TextDiffRef<'__diff_target>),", field_name);
 
                         l!(
                             apply_single_body,
-                            "Self::Diff::{}(__{}) => self.{} = structdiff::collections::unordered_array_like::apply_unordered_hashdiffs(std::mem::take(&mut self.{}).into_iter(), __{}).collect(),",
+                            "Self::Diff::{}(__{}) => structdiff::collections::text::apply_in_place(__{}, &mut {}),",
                             field_name,
                             index,
+                            index,
+                            self_field_mut);
+
+                        l!(
+                            apply_single_ref_body,
+                            "Self::DiffRef::{}(__{}) => structdiff::collections::text::apply_ref_in_place(__{}, &mut {}),",
                             field_name,
+                            index,
+                            index,
+                            self_field_mut);
+
+                        l!(
+                            apply_single_limited_body,
+                            "Self::Diff::{}(__{}) => structdiff::collections::text::apply_in_place_limited(__{}, &mut {}, budget)?,",
                             field_name,
-                            index
+                            index,
+                            index,
+                            self_field_mut);
+
+                        let diff_call = auto_diff(
+                            &field.attributes,
+                            format!("structdiff::collections::text::diff_text(core::convert::AsRef::<str>::as_ref(&{}), core::convert::AsRef::<str>::as_ref(&{}), {})", updated_field, self_field, granularity.path()),
+                            format!("structdiff::collections::text::replacement(core::convert::AsRef::<str>::as_ref(&{}), core::convert::AsRef::<str>::as_ref(&{}))", updated_field, self_field),
                         );
 
                         l!(
                             diff_body,
-                            "if let Some(list_diffs) = structdiff::collections::unordered_array_like::unordered_hashcmp(self.{}.iter(), updated.{}.iter()) {{
-                                diffs.push(Self::Diff::{}(list_diffs.into()));
+                            "if let Some(text_diffs) = {} {{
+                                diffs.push(Self::Diff::{}(text_diffs.into()));
                             }};"
                             ,
-                            field_name,
-                            field_name,
+                            diff_call,
                             field_name
                         );
 
                         l!(
                             diff_ref_body,
-                            "if let Some(list_diffs) = structdiff::collections::unordered_array_like::unordered_hashcmp(self.{}.iter(), updated.{}.iter()) {{
-                                diffs.push(Self::DiffRef::{}(list_diffs));
+                            "if let Some(text_diffs) = {} {{
+                                diffs.push(Self::DiffRef::{}(text_diffs));
                             }};"
                             ,
-                            field_name,
-                            field_name,
+                            diff_call,
                             field_name
                         );
 
@@ -713,7 +1505,7 @@ pub(crate) fn derive_struct_diff_struct(struct_: &Struct) -> TokenStream {
                             (true, (_, false, Some(name_override))) | (false, (true, false, Some(name_override))) => {
                                 l!(setters_body, "\n/// Setter generated by StructDiff. Use to set the {} field and generate a diff if necessary", name_override);
                                 l!(setters_body, "\npub fn {}(&mut self, value: {}) -> Option<<Self as structdiff::StructDiff>::Diff> {{", name_override, field.ty.full());
-                                l!(setters_body, "\n\tlet ret = structdiff::collections::unordered_array_like::unordered_hashcmp(self.{}.iter(), value.iter()).map(|x| <Self as structdiff::StructDiff>::Diff::{}(x.into()));", field_name, field_name);
+                                l!(setters_body, "\n\tlet ret = structdiff::collections::text::diff_text(core::convert::AsRef::<str>::as_ref(&value), core::convert::AsRef::<str>::as_ref(&self.{}), {}).map(|x| <Self as structdiff::StructDiff>::Diff::{}(x.into()));", field_name, granularity.path(), field_name);
                                 l!(setters_body, "\n\tself.{} = value;", field_name);
                                 l!(setters_body, "\n\tret");
                                 l!(setters_body, "\n}");
@@ -721,51 +1513,258 @@ pub(crate) fn derive_struct_diff_struct(struct_: &Struct) -> TokenStream {
                             (true, (_, false, None)) | (false, (true, false, None)) => {
                                 l!(setters_body, "\n/// Setter generated by StructDiff. Use to set the {} field and generate a diff if necessary", field_name);
                                 l!(setters_body, "\npub fn set_{}_with_diff(&mut self, value: {}) -> Option<<Self as structdiff::StructDiff>::Diff> {{", field_name, field.ty.full());
-                                l!(setters_body, "\n\tlet ret = structdiff::collections::unordered_array_like::unordered_hashcmp(self.{}.iter(), value.iter()).map(|x| <Self as structdiff::StructDiff>::Diff::{}(x.into()));", field_name, field_name);
+                                l!(setters_body, "\n\tlet ret = structdiff::collections::text::diff_text(core::convert::AsRef::<str>::as_ref(&value), core::convert::AsRef::<str>::as_ref(&self.{}), {}).map(|x| <Self as structdiff::StructDiff>::Diff::{}(x.into()));", field_name, granularity.path(), field_name);
                                 l!(setters_body, "\n\tself.{} = value;", field_name);
                                 l!(setters_body, "\n\tret");
                                 l!(setters_body, "\n}");
                             },
                             _ => ()
                         };
-                    },
-                    crate::shared::CollectionStrategy::UnorderedMapLikeHash(map_strat) => match map_strat {
-                        crate::shared::MapStrategy::KeyOnly => {
-                            let generic_names = field.ty.wraps.as_ref().map(|x| x.iter().map(|y| y.full()).collect::<Vec<_>>()).expect("Missing types for map creation").join(",");
-                            let generic_ref_names = field.ty.wraps.as_ref().map(|x| x.iter().map(|y| y.full()).collect::<Vec<_>>()).expect("Missing types for map creation").join(", &'__diff_target ");
-                            l!(diff_enum_body, " {}(structdiff::collections::unordered_map_like::UnorderedMapLikeDiff<{}>),", field_name, generic_names);
-                            l!(diff_ref_enum_body, " {}(structdiff::collections::unordered_map_like::UnorderedMapLikeDiff<&'__diff_target {}>),", field_name,generic_ref_names);
+                    }
+                    crate::shared::CollectionStrategy::BinaryDelta => {
+                        push_variant(&mut diff_variants, &mut variant_attrs, field_name.clone(), String::from("structdiff::collections::binary_delta::BinaryDeltaOwned"));
+                        l!(diff_ref_enum_body, " {}(structdiff::collections::binary_delta::BinaryDeltaRef<'__diff_target>),", field_name);
 
-                            l!(
+                        l!(
+                            apply_single_body,
+                            "Self::Diff::{}(__{}) => structdiff::collections::binary_delta::apply_in_place(__{}, &mut {}),",
+                            field_name,
+                            index,
+                            index,
+                            self_field_mut);
+
+                        l!(
+                            apply_single_ref_body,
+                            "Self::DiffRef::{}(__{}) => structdiff::collections::binary_delta::apply_ref_in_place(__{}, &mut {}),",
+                            field_name,
+                            index,
+                            index,
+                            self_field_mut);
+
+                        l!(
+                            apply_single_limited_body,
+                            "Self::Diff::{}(__{}) => structdiff::collections::binary_delta::apply_in_place_limited(__{}, &mut {}, budget)?,",
+                            field_name,
+                            index,
+                            index,
+                            self_field_mut);
+
+                        let diff_call = auto_diff(
+                            &field.attributes,
+                            format!("structdiff::collections::binary_delta::binary_delta(core::convert::AsRef::<[u8]>::as_ref(&{}), core::convert::AsRef::<[u8]>::as_ref(&{}))", updated_field, self_field),
+                            format!("structdiff::collections::binary_delta::replacement(core::convert::AsRef::<[u8]>::as_ref(&{}))", updated_field),
+                        );
+
+                        l!(
+                            diff_body,
+                            "if let Some(delta) = {} {{
+                                diffs.push(Self::Diff::{}(delta.into()));
+                            }};"
+                            ,
+                            diff_call,
+                            field_name
+                        );
+
+                        l!(
+                            diff_ref_body,
+                            "if let Some(delta) = {} {{
+                                diffs.push(Self::DiffRef::{}(delta));
+                            }};"
+                            ,
+                            diff_call,
+                            field_name
+                        );
+
+                        l!(
+                            ref_into_owned_body,
+                            "\t {}Ref::{}(v) => {}::{}(v.into()),",
+                            enum_name,
+                            field_name,
+                            enum_name,
+                            field_name
+                        );
+
+                        #[cfg(feature = "generated_setters")]
+                        match (all_setters, attrs_setter(&field.attributes)) {
+                            (_, (_, true, _)) => (),
+                            (true, (_, false, Some(name_override))) | (false, (true, false, Some(name_override))) => {
+                                l!(setters_body, "\n/// Setter generated by StructDiff. Use to set the {} field and generate a diff if necessary", name_override);
+                                l!(setters_body, "\npub fn {}(&mut self, value: {}) -> Option<<Self as structdiff::StructDiff>::Diff> {{", name_override, field.ty.full());
+                                l!(setters_body, "\n\tlet ret = structdiff::collections::binary_delta::binary_delta(core::convert::AsRef::<[u8]>::as_ref(&value), core::convert::AsRef::<[u8]>::as_ref(&self.{})).map(|x| <Self as structdiff::StructDiff>::Diff::{}(x.into()));", field_name, field_name);
+                                l!(setters_body, "\n\tself.{} = value;", field_name);
+                                l!(setters_body, "\n\tret");
+                                l!(setters_body, "\n}");
+                            },
+                            (true, (_, false, None)) | (false, (true, false, None)) => {
+                                l!(setters_body, "\n/// Setter generated by StructDiff. Use to set the {} field and generate a diff if necessary", field_name);
+                                l!(setters_body, "\npub fn set_{}_with_diff(&mut self, value: {}) -> Option<<Self as structdiff::StructDiff>::Diff> {{", field_name, field.ty.full());
+                                l!(setters_body, "\n\tlet ret = structdiff::collections::binary_delta::binary_delta(core::convert::AsRef::<[u8]>::as_ref(&value), core::convert::AsRef::<[u8]>::as_ref(&self.{})).map(|x| <Self as structdiff::StructDiff>::Diff::{}(x.into()));", field_name, field_name);
+                                l!(setters_body, "\n\tself.{} = value;", field_name);
+                                l!(setters_body, "\n\tret");
+                                l!(setters_body, "\n}");
+                            },
+                            _ => ()
+                        };
+                    }
+                    crate::shared::CollectionStrategy::UnorderedArrayLikeHash => {
+                        let generic_ref_names = field.ty.wraps.as_ref().map(|x| x.iter().map(|y| y.full()).collect::<Vec<_>>()).expect("Missing types for map creation").join(", &'__diff_target ");
+                        push_variant(&mut diff_variants, &mut variant_attrs, field_name.clone(), format!("structdiff::collections::unordered_array_like::UnorderedArrayLikeDiff<{}>", field.ty.wraps.as_ref().expect("Using collection strategy on a non-collection")[0].full()));
+                        l!(diff_ref_enum_body, " {}(structdiff::collections::unordered_array_like::UnorderedArrayLikeDiff<&'__diff_target {}>),", field_name, generic_ref_names);
+
+                        l!(
+                            apply_single_body,
+                            "Self::Diff::{}(__{}) => structdiff::collections::unordered_array_like::apply_unordered_hashdiffs_in_place(&mut {}, __{}),",
+                            field_name,
+                            index,
+                            self_field_mut,
+                            index
+                        );
+
+                        l!(
+                            apply_single_ref_body,
+                            "Self::DiffRef::{}(__{}) => structdiff::collections::unordered_array_like::apply_unordered_hashdiffs_in_place_ref(&mut {}, __{}),",
+                            field_name,
+                            index,
+                            self_field_mut,
+                            index
+                        );
+
+                        l!(
+                            apply_single_limited_body,
+                            "Self::Diff::{}(__{}) => structdiff::collections::unordered_array_like::apply_unordered_hashdiffs_in_place_limited(&mut {}, __{}, budget)?,",
+                            field_name,
+                            index,
+                            self_field_mut,
+                            index
+                        );
+
+                        let diff_call = auto_diff(
+                            &field.attributes,
+                            format!("structdiff::collections::unordered_array_like::unordered_hashcmp({}.iter(), {}.iter())", self_field, updated_field),
+                            format!("structdiff::collections::unordered_array_like::replacement({}.iter())", updated_field),
+                        );
+
+                        let stream_args = format!("{}.iter(), {}.iter()", self_field, updated_field);
+
+                        l!(
+                            diff_body,
+                            &push_collection_diffs(
+                                &field.attributes,
+                                false,
+                                &diff_call,
+                                "structdiff::collections::unordered_array_like::unordered_hashcmp_into",
+                                &stream_args,
+                                &format!("Self::Diff::{}(core::convert::Into::into(list_diffs))", field_name),
+                            )
+                        );
+
+                        l!(
+                            diff_ref_body,
+                            &push_collection_diffs(
+                                &field.attributes,
+                                true,
+                                &diff_call,
+                                "structdiff::collections::unordered_array_like::unordered_hashcmp_into",
+                                &stream_args,
+                                &format!("Self::DiffRef::{}(list_diffs)", field_name),
+                            )
+                        );
+
+                        l!(
+                            ref_into_owned_body,
+                            "\t {}Ref::{}(v) => {}::{}(v.into()),",
+                            enum_name,
+                            field_name,
+                            enum_name,
+                            field_name
+                        );
+
+                        #[cfg(feature = "generated_setters")]
+                        match (all_setters, attrs_setter(&field.attributes)) {
+                            (_, (_, true, _)) => (),
+                            (true, (_, false, Some(name_override))) | (false, (true, false, Some(name_override))) => {
+                                l!(setters_body, "\n/// Setter generated by StructDiff. Use to set the {} field and generate a diff if necessary", name_override);
+                                l!(setters_body, "\npub fn {}(&mut self, value: {}) -> Option<<Self as structdiff::StructDiff>::Diff> {{", name_override, field.ty.full());
+                                l!(setters_body, "\n\tlet ret = structdiff::collections::unordered_array_like::unordered_hashcmp(self.{}.iter(), value.iter()).map(|x| <Self as structdiff::StructDiff>::Diff::{}(x.into()));", field_name, field_name);
+                                l!(setters_body, "\n\tself.{} = value;", field_name);
+                                l!(setters_body, "\n\tret");
+                                l!(setters_body, "\n}");
+                            },
+                            (true, (_, false, None)) | (false, (true, false, None)) => {
+                                l!(setters_body, "\n/// Setter generated by StructDiff. Use to set the {} field and generate a diff if necessary", field_name);
+                                l!(setters_body, "\npub fn set_{}_with_diff(&mut self, value: {}) -> Option<<Self as structdiff::StructDiff>::Diff> {{", field_name, field.ty.full());
+                                l!(setters_body, "\n\tlet ret = structdiff::collections::unordered_array_like::unordered_hashcmp(self.{}.iter(), value.iter()).map(|x| <Self as structdiff::StructDiff>::Diff::{}(x.into()));", field_name, field_name);
+                                l!(setters_body, "\n\tself.{} = value;", field_name);
+                                l!(setters_body, "\n\tret");
+                                l!(setters_body, "\n}");
+                            },
+                            _ => ()
+                        };
+                    },
+                    crate::shared::CollectionStrategy::UnorderedMapLikeHash(map_strat) => match map_strat {
+                        crate::shared::MapStrategy::KeyOnly => {
+                            let generic_names = field.ty.wraps.as_ref().map(|x| x.iter().map(|y| y.full()).collect::<Vec<_>>()).expect("Missing types for map creation").join(",");
+                            let generic_ref_names = field.ty.wraps.as_ref().map(|x| x.iter().map(|y| y.full()).collect::<Vec<_>>()).expect("Missing types for map creation").join(", &'__diff_target ");
+                            push_variant(&mut diff_variants, &mut variant_attrs, field_name.clone(), format!("structdiff::collections::unordered_map_like::UnorderedMapLikeDiff<{}>", generic_names));
+                            l!(diff_ref_enum_body, " {}(structdiff::collections::unordered_map_like::UnorderedMapLikeDiff<&'__diff_target {}>),", field_name,generic_ref_names);
+
+                            l!(
                                 apply_single_body,
-                                "Self::Diff::{}(__{}) => self.{} = structdiff::collections::unordered_map_like::apply_unordered_hashdiffs(std::mem::take(&mut self.{}).into_iter(), __{}).collect(),",
+                                "Self::Diff::{}(__{}) => structdiff::collections::unordered_map_like::apply_unordered_hashdiffs_in_place(&mut {}, __{}),",
                                 field_name,
                                 index,
+                                self_field_mut,
+                                index
+                            );
+
+                            l!(
+                                apply_single_ref_body,
+                                "Self::DiffRef::{}(__{}) => structdiff::collections::unordered_map_like::apply_unordered_hashdiffs_in_place_ref(&mut {}, __{}),",
                                 field_name,
+                                index,
+                                self_field_mut,
+                                index
+                            );
+
+                            l!(
+                                apply_single_limited_body,
+                                "Self::Diff::{}(__{}) => structdiff::collections::unordered_map_like::apply_unordered_hashdiffs_in_place_limited(&mut {}, __{}, budget)?,",
                                 field_name,
+                                index,
+                                self_field_mut,
                                 index
                             );
 
+                            let diff_call = auto_diff(
+                                &field.attributes,
+                                format!("structdiff::collections::unordered_map_like::unordered_hashcmp({}.iter(), {}.iter(), true)", self_field, updated_field),
+                                format!("structdiff::collections::unordered_map_like::replacement({}.iter())", updated_field),
+                            );
+
+                            let stream_args = format!("{}.iter(), {}.iter(), true", self_field, updated_field);
+
                             l!(
                                 diff_body,
-                                "if let Some(list_diffs) = structdiff::collections::unordered_map_like::unordered_hashcmp(self.{}.iter(), updated.{}.iter(), true) {{
-                                    diffs.push(Self::Diff::{}(list_diffs.into()));
-                                }};"
-                                ,
-                                field_name,
-                                field_name,
-                                field_name
+                                &push_collection_diffs(
+                                    &field.attributes,
+                                    false,
+                                    &diff_call,
+                                    "structdiff::collections::unordered_map_like::unordered_hashcmp_into",
+                                    &stream_args,
+                                    &format!("Self::Diff::{}(core::convert::Into::into(list_diffs))", field_name),
+                                )
                             );
 
                             l!(
                                 diff_ref_body,
-                                "if let Some(list_diffs) = structdiff::collections::unordered_map_like::unordered_hashcmp(self.{}.iter(), updated.{}.iter(), true) {{
-                                    diffs.push(Self::DiffRef::{}(list_diffs));
-                                }};"
-                                ,
-                                field_name,
-                                field_name,
-                                field_name
+                                &push_collection_diffs(
+                                    &field.attributes,
+                                    true,
+                                    &diff_call,
+                                    "structdiff::collections::unordered_map_like::unordered_hashcmp_into",
+                                    &stream_args,
+                                    &format!("Self::DiffRef::{}(list_diffs)", field_name),
+                                )
                             );
 
                             l!(
@@ -802,39 +1801,66 @@ pub(crate) fn derive_struct_diff_struct(struct_: &Struct) -> TokenStream {
                         crate::shared::MapStrategy::KeyAndValue => {
                             let generic_names = field.ty.wraps.as_ref().map(|x| x.iter().map(|y| y.full()).collect::<Vec<_>>()).expect("Missing types for map creation").join(",");
                             let generic_ref_names = field.ty.wraps.as_ref().map(|x| x.iter().map(|y| y.full()).collect::<Vec<_>>()).expect("Missing types for map creation").join(", &'__diff_target ");
-                            l!(diff_enum_body, " {}(structdiff::collections::unordered_map_like::UnorderedMapLikeDiff<{}>),", field_name, generic_names);
+                            push_variant(&mut diff_variants, &mut variant_attrs, field_name.clone(), format!("structdiff::collections::unordered_map_like::UnorderedMapLikeDiff<{}>", generic_names));
                             l!(diff_ref_enum_body, " {}(structdiff::collections::unordered_map_like::UnorderedMapLikeDiff<&'__diff_target {}>),", field_name,generic_ref_names);
 
                             l!(
                                 apply_single_body,
-                                "Self::Diff::{}(__{}) => self.{} = structdiff::collections::unordered_map_like::apply_unordered_hashdiffs(std::mem::take(&mut self.{}).into_iter(), __{}).collect(),",
+                                "Self::Diff::{}(__{}) => structdiff::collections::unordered_map_like::apply_unordered_hashdiffs_in_place(&mut {}, __{}),",
                                 field_name,
                                 index,
+                                self_field_mut,
+                                index
+                            );
+
+                            l!(
+                                apply_single_ref_body,
+                                "Self::DiffRef::{}(__{}) => structdiff::collections::unordered_map_like::apply_unordered_hashdiffs_in_place_ref(&mut {}, __{}),",
                                 field_name,
+                                index,
+                                self_field_mut,
+                                index
+                            );
+
+                            l!(
+                                apply_single_limited_body,
+                                "Self::Diff::{}(__{}) => structdiff::collections::unordered_map_like::apply_unordered_hashdiffs_in_place_limited(&mut {}, __{}, budget)?,",
                                 field_name,
+                                index,
+                                self_field_mut,
                                 index
                             );
 
+                            let diff_call = auto_diff(
+                                &field.attributes,
+                                format!("structdiff::collections::unordered_map_like::unordered_hashcmp({}.iter(), {}.iter(), false)", self_field, updated_field),
+                                format!("structdiff::collections::unordered_map_like::replacement({}.iter())", updated_field),
+                            );
+
+                            let stream_args = format!("{}.iter(), {}.iter(), false", self_field, updated_field);
+
                             l!(
                                 diff_body,
-                                "if let Some(list_diffs) = structdiff::collections::unordered_map_like::unordered_hashcmp(self.{}.iter(), updated.{}.iter(), false) {{
-                                    diffs.push(Self::Diff::{}(list_diffs.into()));
-                                }};"
-                                ,
-                                field_name,
-                                field_name,
-                                field_name
+                                &push_collection_diffs(
+                                    &field.attributes,
+                                    false,
+                                    &diff_call,
+                                    "structdiff::collections::unordered_map_like::unordered_hashcmp_into",
+                                    &stream_args,
+                                    &format!("Self::Diff::{}(core::convert::Into::into(list_diffs))", field_name),
+                                )
                             );
 
                             l!(
                                 diff_ref_body,
-                                "if let Some(list_diffs) = structdiff::collections::unordered_map_like::unordered_hashcmp(self.{}.iter(), updated.{}.iter(), false) {{
-                                    diffs.push(Self::DiffRef::{}(list_diffs));
-                                }};"
-                                ,
-                                field_name,
-                                field_name,
-                                field_name
+                                &push_collection_diffs(
+                                    &field.attributes,
+                                    true,
+                                    &diff_call,
+                                    "structdiff::collections::unordered_map_like::unordered_hashcmp_into",
+                                    &stream_args,
+                                    &format!("Self::DiffRef::{}(list_diffs)", field_name),
+                                )
                             );
 
                             l!(
@@ -922,73 +1948,810 @@ pub(crate) fn derive_struct_diff_struct(struct_: &Struct) -> TokenStream {
         let end = "\")]";
         [start, &mid, end].join("")
     };
-    #[cfg(not(feature = "serde"))]
-    let serde_bound = "";
+    #[cfg(not(feature = "serde"))]
+    let serde_bound = "";
+
+    #[cfg(feature = "serde")]
+    let serde_attrs = attrs_serde(&struct_.attributes)
+        .iter()
+        .map(|serde_attr| serde_args_without(serde_attr, "rename_all"))
+        .filter(|serde_attr| !serde_attr.is_empty())
+        .map(|serde_attr| format!("\n#[serde({})]", serde_attr))
+        .collect::<String>();
+    #[cfg(not(feature = "serde"))]
+    let serde_attrs = "";
+
+    // the borrowed enum has the same variants and encoding as the owned enum,
+    // but string and byte payloads borrow from the buffer being deserialized
+    #[cfg(any(feature = "serde", feature = "nanoserde"))]
+    let (borrowed_variants, apply_single_borrowed_body) = {
+        let mut borrowed_variants: Vec<DiffVariant> = Vec::new();
+        let mut apply_single_borrowed_body = String::new();
+        for (i, (index, field, start)) in field_variants_start.iter().enumerate() {
+            let field_name = field.field_name.as_ref().unwrap();
+            let end = field_variants_start
+                .get(i + 1)
+                .map_or(diff_variants.len(), |(_, _, next)| *next);
+            let borrowed = borrowed_payload(field, *index);
+            if let Some((_, apply)) = &borrowed {
+                l!(
+                    apply_single_borrowed_body,
+                    "Self::DiffBorrowed::{}(__{}) => {},",
+                    field_name,
+                    index,
+                    apply
+                );
+            }
+
+            for variant in &diff_variants[*start..end] {
+                #[allow(unused_mut)]
+                let mut attrs = variant.attrs.clone();
+                let payload = match &borrowed {
+                    Some((payload, _)) if &variant.name == field_name => {
+                        #[cfg(feature = "serde")]
+                        l!(attrs, "\n#[serde(borrow)]");
+                        payload.to_string()
+                    }
+                    _ => variant.payload.clone(),
+                };
+                borrowed_variants.push(DiffVariant {
+                    attrs,
+                    name: variant.name.clone(),
+                    payload,
+                });
+            }
+        }
+
+        l!(
+            apply_single_borrowed_body,
+            "#[allow(unreachable_patterns)]\ndiff => structdiff::StructDiff::apply_single(self, diff.into()),"
+        );
+
+        (borrowed_variants, apply_single_borrowed_body)
+    };
+    #[cfg(any(feature = "serde", feature = "nanoserde"))]
+    let diff_borrowed_enum_body = {
+        let mut diff_borrowed_enum_body = borrowed_variants
+            .iter()
+            .map(DiffVariant::render)
+            .collect::<String>();
+        if !borrowed_variants
+            .iter()
+            .any(|variant| variant.payload.contains("'__diff_de"))
+        {
+            #[cfg(feature = "serde")]
+            l!(diff_borrowed_enum_body, "\n#[serde(skip)]");
+            l!(
+                diff_borrowed_enum_body,
+                "\n#[doc(hidden)]\n#[allow(dead_code)]\n __StructDiffLifetime(core::marker::PhantomData<&'__diff_de ()>),"
+            );
+        }
+        diff_borrowed_enum_body
+    };
+
+    // fields diffed through `with` or `diff_as` store owned values in the ref
+    // enum, so mark the lifetime as used if no other field does
+    if !diff_ref_enum_body.contains("'__diff_target") {
+        l!(
+            diff_ref_enum_body,
+            "\n#[doc(hidden)]\n#[allow(dead_code)]\n __StructDiffLifetime(&'__diff_target u8),"
+        );
+        l!(
+            ref_into_owned_body,
+            "\t {}Ref::__StructDiffLifetime(_) => unreachable!(),",
+            enum_name
+        );
+        l!(
+            apply_single_ref_body,
+            "Self::DiffRef::__StructDiffLifetime(_) => unreachable!(),"
+        );
+    }
+
+    #[cfg(feature = "generated_setters")]
+    for field in struct_
+        .fields
+        .iter()
+        .filter(|field| !attrs_skip(&field.attributes))
+    {
+        match (all_setters, attrs_setter(&field.attributes)) {
+            (_, (_, true, _)) => (),
+            (true, _) | (false, (true, false, _)) => setters_body.push_str(&field_mutators(field)),
+            _ => (),
+        }
+    }
+
+    let changed_exprs: Vec<String> = struct_
+        .fields
+        .iter()
+        .filter(|field| !attrs_skip(&field.attributes))
+        .map(|field| field_has_diff(struct_, field))
+        .collect();
+    let has_diff_body = match changed_exprs.is_empty() {
+        true => String::from("false"),
+        false => changed_exprs
+            .iter()
+            .map(|changed| format!("({changed})"))
+            .collect::<Vec<_>>()
+            .join("\n|| "),
+    };
+    let changed_fields_body = changed_exprs
+        .iter()
+        .enumerate()
+        .map(|(index, changed)| format!("if {changed} {{ fields.insert({index}) }};"))
+        .collect::<String>();
+    let field_names = struct_
+        .fields
+        .iter()
+        .filter(|field| !attrs_skip(&field.attributes))
+        .map(|field| format!("\"{}\"", field.field_name.as_ref().unwrap()))
+        .collect::<Vec<_>>()
+        .join(", ");
+
+    // mirror definitions for a remote type implement StructDiffRemote for it
+    // instead of StructDiff, and cannot add setters to the remote type
+    let remote = attrs_remote(&struct_.attributes);
+    if remote.is_some() {
+        // the mirror is never constructed, so read its fields once to keep
+        // the dead_code lint quiet
+        let fields_read = struct_
+            .fields
+            .iter()
+            .filter_map(|field| field.field_name.as_ref())
+            .map(|field_name| format!("let _ = &__mirror.{};", field_name))
+            .collect::<String>();
+        diff_body = format!(
+            "let _ = |__mirror: &Self| {{ {} }};\n{}",
+            fields_read, diff_body
+        );
+    }
+
+    let setters = {
+        #[cfg(feature = "generated_setters")]
+        {
+            if setters_body.is_empty() || remote.is_some() {
+                String::new()
+            } else {
+                format!(
+                    "impl<{}> {}<{}> where 
+                    {}
+                    {{
+                        {}
+                    }}
+                    ",
+                    struct_
+                        .generics
+                        .iter()
+                        .filter(|gen_type| !matches!(gen_type, Generic::WhereBounded { .. }))
+                        .map(Generic::ident_with_const)
+                        .collect::<Vec<_>>()
+                        .join(", "),
+                    struct_
+                        .name
+                        .clone()
+                        .unwrap_or_else(|| String::from("Anonymous")),
+                    struct_
+                        .generics
+                        .iter()
+                        .filter(|gen_type| !matches!(gen_type, Generic::WhereBounded { .. }))
+                        .map(Generic::ident_only)
+                        .collect::<Vec<_>>()
+                        .join(", "),
+                    struct_
+                        .generics
+                        .iter()
+                        .filter(|gen_type| !matches!(
+                            gen_type,
+                            Generic::Const { .. } | Generic::WhereBounded { .. }
+                        ))
+                        .map(|gen_type| Generic::full_with_const(
+                            gen_type,
+                            get_used_generic_bounds(),
+                            &[],
+                            true
+                        ))
+                        .collect::<Vec<_>>()
+                        .into_iter()
+                        .chain(
+                            struct_
+                                .generics
+                                .iter()
+                                .filter(|gen_type| matches!(gen_type, Generic::WhereBounded { .. }))
+                                .map(|gen_type| Generic::full_with_const(gen_type, &[], &[], true))
+                                .collect::<Vec<_>>()
+                                .into_iter()
+                        )
+                        .collect::<Vec<_>>()
+                        .join(",\n"),
+                    setters_body
+                )
+            }
+        }
+
+        #[cfg(not(feature = "generated_setters"))]
+        ""
+    };
+
+    let owned_enum_where_bounds = used_generics
+        .iter()
+        .filter(|gen_type| {
+            !matches!(
+                gen_type,
+                Generic::WhereBounded { .. } | Generic::Const { .. }
+            )
+        })
+        .filter(|g| Generic::has_where_bounds(g, false, true))
+        .map(|gen_type| Generic::full_with_const(gen_type, get_used_generic_bounds(), &[], true))
+        .collect::<Vec<_>>()
+        .join(",\n");
+    let impl_generics = format!(
+        "<{}>",
+        struct_
+            .generics
+            .iter()
+            .filter(|gen_type| !matches!(gen_type, Generic::WhereBounded { .. }))
+            .map(Generic::ident_with_const)
+            .collect::<Vec<_>>()
+            .join(", ")
+    );
+    let struct_generics = format!(
+        "<{}>",
+        struct_
+            .generics
+            .iter()
+            .filter(|gen_type| !matches!(gen_type, Generic::WhereBounded { .. }))
+            .map(Generic::ident_only)
+            .collect::<Vec<_>>()
+            .join(", ")
+    );
+    let struct_where_bounds = struct_
+        .generics
+        .iter()
+        .filter(|gen_type| {
+            !matches!(
+                gen_type,
+                Generic::Const { .. } | Generic::WhereBounded { .. }
+            )
+        })
+        .filter(|g| Generic::has_where_bounds(g, false, true))
+        .map(|gen_type| Generic::full_with_const(gen_type, get_used_generic_bounds(), &[], true))
+        .collect::<Vec<_>>()
+        .into_iter()
+        .chain(
+            struct_
+                .generics
+                .iter()
+                .filter(|gen_type| matches!(gen_type, Generic::WhereBounded { .. }))
+                .map(|gen_type| Generic::full_with_const(gen_type, &[], &[], true))
+                .collect::<Vec<_>>(),
+        )
+        .collect::<Vec<_>>()
+        .join(",\n");
+    let owned_enum_impl_generics = format!(
+        "<{}>",
+        used_generics
+            .iter()
+            .filter(|gen_type| !matches!(gen_type, Generic::WhereBounded { .. }))
+            .map(Generic::ident_only)
+            .collect::<Vec<_>>()
+            .join(", ")
+    );
+
+    // remote mirrors only implement StructDiffRemote, so they have no borrowed diff
+    #[cfg(any(feature = "serde", feature = "nanoserde"))]
+    let (borrowed_enum_def, borrowed_impls) = match remote {
+        Some(_) => (String::new(), String::new()),
+        None => {
+            let borrowed_derives: String = [
+                #[cfg(feature = "debug_diffs")]
+                "core::fmt::Debug",
+                "Clone",
+                #[cfg(feature = "serde")]
+                "serde::Deserialize",
+            ]
+            .join(", ");
+
+            let borrowed_enum_def_generics = std::iter::once(String::from("'__diff_de"))
+                .chain(
+                    used_generics
+                        .iter()
+                        .filter(|gen_type| !matches!(gen_type, Generic::WhereBounded { .. }))
+                        .map(Generic::ident_with_const),
+                )
+                .collect::<Vec<_>>()
+                .join(", ");
+            let borrowed_enum_impl_generics = std::iter::once(String::from("'__diff_de"))
+                .chain(
+                    used_generics
+                        .iter()
+                        .filter(|gen_type| !matches!(gen_type, Generic::WhereBounded { .. }))
+                        .map(|gen_type| gen_type.ident_only()),
+                )
+                .collect::<Vec<_>>()
+                .join(", ");
+
+            let mut borrowed_into_owned_body = borrowed_variants
+                .iter()
+                .map(|variant| {
+                    format!(
+                        "{}Borrowed::{}(v) => {}::{}(v.into()),",
+                        enum_name, variant.name, enum_name, variant.name
+                    )
+                })
+                .collect::<String>();
+            if diff_borrowed_enum_body.contains("__StructDiffLifetime") {
+                l!(
+                    borrowed_into_owned_body,
+                    "{}Borrowed::__StructDiffLifetime(_) => unreachable!(),",
+                    enum_name
+                );
+            }
+
+            #[allow(unused_mut)]
+            let mut borrowed_impls = format!(
+                "impl<{borrowed_enum_def_generics}> Into<{enum_name}{owned_enum_impl_generics}> for {enum_name}Borrowed<{borrowed_enum_impl_generics}>
+                where
+                {owned_enum_where_bounds}
+                {{
+                    fn into(self) -> {enum_name}{owned_enum_impl_generics} {{
+                        match self {{
+                            {borrowed_into_owned_body}
+                        }}
+                    }}
+                }}
+
+                impl{impl_generics} structdiff::StructDiffBorrowed for {struct_name}{struct_generics}
+                where
+                {struct_where_bounds}
+                {{
+                    type DiffBorrowed<'__diff_de> = {enum_name}Borrowed<{borrowed_enum_impl_generics}>;
+
+                    #[inline(always)]
+                    fn apply_single_borrowed(&mut self, diff: Self::DiffBorrowed<'_>) {{
+                        match diff {{
+                            {apply_single_borrowed_body}
+                        }}
+                    }}
+                }}
+                ",
+                struct_name = struct_.name.as_ref().unwrap(),
+            );
+
+            // nanoserde has no borrowed deserialization, so read the variant
+            // index written by the owned enum's `SerBin` by hand, and the
+            // owned payloads as its `DeBin` does
+            #[cfg(feature = "nanoserde")]
+            {
+                let variants = borrowed_variants
+                    .iter()
+                    .enumerate()
+                    .map(|(index, variant)| {
+                        format!(
+                            "{}u16 => Ok(Self::{}({}?)),",
+                            index,
+                            variant.name,
+                            match variant.payload.contains("'__diff_de") {
+                                true => String::from(
+                                    "structdiff::borrowed::DeBinBorrowed::de_bin_borrowed(offset, bytes)",
+                                ),
+                                false => checked_de_bin(&variant.payload),
+                            }
+                        )
+                    })
+                    .collect::<String>();
+                l!(
+                    borrowed_impls,
+                    "impl<{}> structdiff::borrowed::DeBinBorrowed<'__diff_de> for {}Borrowed<{}>
+                    where
+                    {}
+                    {{
+                        fn de_bin_borrowed(offset: &mut usize, bytes: &'__diff_de [u8]) -> core::result::Result<Self, nanoserde::DeBinErr> {{
+                            let id: u16 = nanoserde::DeBin::de_bin(offset, bytes)?;
+                            match id {{
+                                {}
+                                _ => Err(nanoserde::DeBinErr {{ o: *offset - 2, l: 2, s: bytes.len() }}),
+                            }}
+                        }}
+                    }}",
+                    borrowed_enum_def_generics,
+                    enum_name,
+                    borrowed_enum_impl_generics,
+                    owned_enum_where_bounds,
+                    variants
+                );
+            }
+
+            let borrowed_enum_def = format!(
+                "#[allow(non_camel_case_types)]
+                /// Generated type from StructDiff
+                #[derive({borrowed_derives})]{serde_bound}{serde_attrs}
+                pub enum {enum_name}Borrowed<{borrowed_enum_def_generics}>
+                where
+                {owned_enum_where_bounds}
+                {{
+                    {diff_borrowed_enum_body}
+                }}"
+            );
+
+            (borrowed_enum_def, borrowed_impls)
+        }
+    };
+    #[cfg(not(any(feature = "serde", feature = "nanoserde")))]
+    let (borrowed_enum_def, borrowed_impls) = ("", "");
+
+    // the same encoding as nanoserde's derive, but strings and collections
+    // are read without trusting their length prefix
+    #[cfg(feature = "nanoserde")]
+    let owned_de_bin_impl = {
+        let variants = diff_variants
+            .iter()
+            .enumerate()
+            .map(|(index, variant)| {
+                format!(
+                    "{}u16 => Ok(Self::{}({}?)),",
+                    index,
+                    variant.name,
+                    checked_de_bin(&variant.payload)
+                )
+            })
+            .collect::<String>();
+        let owned_enum_def_generics = used_generics
+            .iter()
+            .filter(|gen_type| !matches!(gen_type, Generic::WhereBounded { .. }))
+            .map(Generic::ident_with_const)
+            .collect::<Vec<_>>()
+            .join(", ");
+        format!(
+            "impl<{owned_enum_def_generics}> nanoserde::DeBin for {enum_name}{owned_enum_impl_generics}
+            where
+            {owned_enum_where_bounds}
+            {{
+                fn de_bin(offset: &mut usize, bytes: &[u8]) -> core::result::Result<Self, nanoserde::DeBinErr> {{
+                    let id: u16 = nanoserde::DeBin::de_bin(offset, bytes)?;
+                    match id {{
+                        {variants}
+                        _ => Err(nanoserde::DeBinErr {{ o: *offset - 2, l: 2, s: bytes.len() }}),
+                    }}
+                }}
+            }}"
+        )
+    };
+    #[cfg(not(feature = "nanoserde"))]
+    let owned_de_bin_impl = "";
+
+    // fields without limited arms are applied as usual, and when there are
+    // none at all the default implementation does the same
+    let apply_single_limited_impl = match (apply_single_limited_body.is_empty(), remote) {
+        (false, None) => format!(
+            "fn apply_single_limited(&mut self, diff: Self::Diff, budget: &mut structdiff::limits::ApplyBudget) -> Result<(), structdiff::limits::LimitExceeded> {{
+                match diff {{
+                    {apply_single_limited_body}
+                    diff => self.apply_single(diff),
+                }}
+                Ok(())
+            }}"
+        ),
+        _ => String::new(),
+    };
+
+    // remote mirrors have no fields of their own to report
+    let fields_impl = match remote {
+        Some(_) => String::new(),
+        None => format!(
+            "impl{impl_generics} structdiff::StructDiffFields for {struct_name}{struct_generics}
+            where
+            {struct_where_bounds}
+            {{
+                const FIELDS: &'static [&'static str] = &[{field_names}];
+
+                fn changed_fields(&self, updated: &Self) -> structdiff::FieldSet {{
+                    let mut fields = structdiff::FieldSet::new(<Self as structdiff::StructDiffFields>::FIELDS);
+                    {changed_fields_body}
+                    fields
+                }}
+            }}",
+            struct_name = struct_.name.as_ref().unwrap(),
+        ),
+    };
+
+    // diffs are fingerprinted by the fields they modify, so every variant is
+    // mapped back to the index of its field
+    let verified_impl = match (attrs_verify_base(&struct_.attributes), remote) {
+        (false, _) => String::new(),
+        (true, Some(_)) => panic!("`verify_base` is not supported on `remote` mirrors"),
+        (true, None) => {
+            let fields: Vec<&crate::parse::Field> = struct_
+                .fields
+                .iter()
+                .filter(|field| !attrs_skip(&field.attributes))
+                .collect();
+            let mut field_index_arms = String::new();
+            let mut fingerprint_body = String::new();
+            for (index, field) in fields.iter().enumerate() {
+                let field_name = field.field_name.as_ref().unwrap();
+                l!(
+                    field_index_arms,
+                    "Self::Diff::{}(..) => {},",
+                    field_name,
+                    index
+                );
+                if attrs_with(&field.attributes).is_none()
+                    && attrs_remote(&field.attributes).is_none()
+                    && attrs_recurse(&field.attributes)
+                    && attrs_collection_type(&field.attributes).is_none()
+                    && field.ty.base() == "Option"
+                {
+                    l!(
+                        field_index_arms,
+                        "Self::Diff::{}_full(..) => {},",
+                        field_name,
+                        index
+                    );
+                }
+                l!(
+                    fingerprint_body,
+                    "if touched.contains_index({}) {{ core::hash::Hash::hash(&{}u64, &mut hasher); core::hash::Hash::hash(&self.{}, &mut hasher); }}",
+                    index,
+                    index,
+                    field_name
+                );
+            }
+            let collect_touched = match fields.is_empty() {
+                true => String::from("let _ = diffs;"),
+                false => format!(
+                    "for diff in diffs {{ touched.insert(match diff {{ {field_index_arms} }}); }}"
+                ),
+            };
+            let where_bounds = std::iter::once(struct_where_bounds.clone())
+                .filter(|bounds| !bounds.is_empty())
+                .chain(
+                    fields
+                        .iter()
+                        .map(|field| format!("{}: core::hash::Hash", field.ty.full())),
+                )
+                .collect::<Vec<_>>()
+                .join(",\n");
+            format!(
+                "impl{impl_generics} structdiff::StructDiffVerified for {struct_name}{struct_generics}
+                where
+                {where_bounds}
+                {{
+                    fn base_fingerprint(&self, diffs: &[Self::Diff]) -> u64 {{
+                        #[allow(unused_mut)]
+                        let mut touched = structdiff::FieldSet::new(<Self as structdiff::StructDiffFields>::FIELDS);
+                        {collect_touched}
+                        #[allow(unused_mut)]
+                        let mut hasher = structdiff::verify::StableHasher::default();
+                        {fingerprint_body}
+                        core::hash::Hasher::finish(&hasher)
+                    }}
+                }}",
+                struct_name = struct_.name.as_ref().unwrap(),
+            )
+        }
+    };
+
+    // skipped fields keep their own value, since they have no merge rule
+    let merge_impl = match (attrs_merge(&struct_.attributes), remote) {
+        (false, _) => String::new(),
+        (true, Some(_)) => panic!("`merge` is not supported on `remote` mirrors"),
+        (true, None) => {
+            let fields: Vec<&crate::parse::Field> = struct_
+                .fields
+                .iter()
+                .filter(|field| !attrs_skip(&field.attributes))
+                .collect();
+            let merge_body = fields
+                .iter()
+                .map(|field| {
+                    let field_name = field.field_name.as_ref().unwrap();
+                    format!("structdiff::collections::crdt::Merge::merge(&mut self.{field_name}, other.{field_name});")
+                })
+                .collect::<String>();
+            let where_bounds = std::iter::once(struct_where_bounds.clone())
+                .filter(|bounds| !bounds.is_empty())
+                .chain(fields.iter().map(|field| {
+                    format!("{}: structdiff::collections::crdt::Merge", field.ty.full())
+                }))
+                .collect::<Vec<_>>()
+                .join(",\n");
+            format!(
+                "impl{impl_generics} structdiff::collections::crdt::Merge for {struct_name}{struct_generics}
+                where
+                {where_bounds}
+                {{
+                    #[allow(unused_variables)]
+                    fn merge(&mut self, other: Self) {{
+                        {merge_body}
+                    }}
+                }}",
+                struct_name = struct_.name.as_ref().unwrap(),
+            )
+        }
+    };
+
+    // every field is counted, since skipped fields are still encoded with
+    // the struct, and each diff is counted with the tag of its variant
+    let encoded_size_impl = match (attrs_encoded_size(&struct_.attributes), remote) {
+        (false, _) => String::new(),
+        (true, Some(_)) => panic!("`encoded_size` is not supported on `remote` mirrors"),
+        (true, None) => {
+            let fields_size = struct_
+                .fields
+                .iter()
+                .map(|field| {
+                    format!(
+                        " + structdiff::size::EncodedSize::encoded_size(&self.{})",
+                        field.field_name.as_ref().unwrap()
+                    )
+                })
+                .collect::<String>();
+            let struct_bounds = std::iter::once(struct_where_bounds.clone())
+                .filter(|bounds| !bounds.is_empty())
+                .chain(
+                    struct_
+                        .fields
+                        .iter()
+                        .map(|field| format!("{}: structdiff::size::EncodedSize", field.ty.full())),
+                )
+                .collect::<Vec<_>>()
+                .join(",\n");
+            let variant_arms = diff_variants
+                .iter()
+                .map(|variant| {
+                    format!(
+                        "Self::{}(diff) => structdiff::size::EncodedSize::encoded_size(diff),",
+                        variant.name
+                    )
+                })
+                .collect::<String>();
+            let enum_bounds =
+                std::iter::once(owned_enum_where_bounds.clone())
+                    .filter(|bounds| !bounds.is_empty())
+                    .chain(diff_variants.iter().map(|variant| {
+                        format!("{}: structdiff::size::EncodedSize", variant.payload)
+                    }))
+                    .collect::<Vec<_>>()
+                    .join(",\n");
+            let owned_enum_def_generics = used_generics
+                .iter()
+                .filter(|gen_type| !matches!(gen_type, Generic::WhereBounded { .. }))
+                .map(Generic::ident_with_const)
+                .collect::<Vec<_>>()
+                .join(", ");
+            let enum_size = match diff_variants.is_empty() {
+                true => String::from("0"),
+                false => format!("structdiff::size::DIFF_TAG + match self {{ {variant_arms} }}"),
+            };
+            format!(
+                "impl{impl_generics} structdiff::size::EncodedSize for {struct_name}{struct_generics}
+                where
+                {struct_bounds}
+                {{
+                    fn encoded_size(&self) -> usize {{
+                        0{fields_size}
+                    }}
+                }}
+
+                impl<{owned_enum_def_generics}> structdiff::size::EncodedSize for {enum_name}{owned_enum_impl_generics}
+                where
+                {enum_bounds}
+                {{
+                    fn encoded_size(&self) -> usize {{
+                        {enum_size}
+                    }}
+                }}",
+                struct_name = struct_.name.as_ref().unwrap(),
+            )
+        }
+    };
 
-    let setters = {
-        #[cfg(feature = "generated_setters")]
-        {
-            if setters_body.is_empty() {
-                String::new()
-            } else {
-                format!(
-                    "impl<{}> {}<{}> where 
-                    {}
-                    {{
-                        {}
+    // every field is read, in the order nanoserde's derive writes them,
+    // since skipped fields are still encoded with the struct
+    #[cfg(feature = "nanoserde")]
+    let de_bin_checked_impl = match (attrs_de_bin_checked(&struct_.attributes), remote) {
+        (false, _) => String::new(),
+        (true, Some(_)) => panic!("`de_bin_checked` is not supported on `remote` mirrors"),
+        (true, None) => {
+            let fields_body = struct_
+                .fields
+                .iter()
+                .map(|field| {
+                    format!(
+                        "{}: structdiff::checked::DeBinChecked::de_bin_checked(offset, bytes)?,",
+                        field.field_name.as_ref().unwrap()
+                    )
+                })
+                .collect::<String>();
+            let where_bounds =
+                std::iter::once(struct_where_bounds.clone())
+                    .filter(|bounds| !bounds.is_empty())
+                    .chain(struct_.fields.iter().map(|field| {
+                        format!("{}: structdiff::checked::DeBinChecked", field.ty.full())
+                    }))
+                    .collect::<Vec<_>>()
+                    .join(",\n");
+            format!(
+                "impl{impl_generics} structdiff::checked::DeBinChecked for {struct_name}{struct_generics}
+                where
+                {where_bounds}
+                {{
+                    fn de_bin_checked(offset: &mut usize, bytes: &[u8]) -> core::result::Result<Self, nanoserde::DeBinErr> {{
+                        core::result::Result::Ok(Self {{
+                            {fields_body}
+                        }})
                     }}
-                    ",
+                }}",
+                struct_name = struct_.name.as_ref().unwrap(),
+            )
+        }
+    };
+    #[cfg(not(feature = "nanoserde"))]
+    let de_bin_checked_impl = "";
+
+    // skipped fields are left at their default, as they are never diffed
+    let arbitrary_impl = match (attrs_arbitrary(&struct_.attributes), remote) {
+        (false, _) => String::new(),
+        (true, _) if cfg!(not(feature = "testing")) => {
+            panic!("`arbitrary` requires the `testing` feature of structdiff")
+        }
+        (true, Some(_)) => panic!("`arbitrary` is not supported on `remote` mirrors"),
+        (true, None) => {
+            let fields_body = struct_
+                .fields
+                .iter()
+                .map(|field| {
+                    let field_name = field.field_name.as_ref().unwrap();
+                    match attrs_skip(&field.attributes) {
+                        true => format!("{field_name}: core::default::Default::default(),"),
+                        false => {
+                            format!("{field_name}: structdiff::testing::Arbitrary::arbitrary(u)?,")
+                        }
+                    }
+                })
+                .collect::<String>();
+            let where_bounds = std::iter::once(struct_where_bounds.clone())
+                .filter(|bounds| !bounds.is_empty())
+                .chain(
                     struct_
-                        .generics
+                        .fields
                         .iter()
-                        .filter(|gen_type| !matches!(gen_type, Generic::WhereBounded { .. }))
-                        .map(Generic::ident_with_const)
-                        .collect::<Vec<_>>()
-                        .join(", "),
-                    struct_
-                        .name
-                        .clone()
-                        .unwrap_or_else(|| String::from("Anonymous")),
+                        .map(|field| match attrs_skip(&field.attributes) {
+                            true => format!("{}: core::default::Default", field.ty.full()),
+                            false => format!(
+                                "{}: structdiff::testing::Arbitrary<'__arbitrary>",
+                                field.ty.full()
+                            ),
+                        }),
+                )
+                .collect::<Vec<_>>()
+                .join(",\n");
+            let arbitrary_impl_generics = std::iter::once(String::from("'__arbitrary"))
+                .chain(
                     struct_
                         .generics
                         .iter()
                         .filter(|gen_type| !matches!(gen_type, Generic::WhereBounded { .. }))
-                        .map(Generic::ident_only)
-                        .collect::<Vec<_>>()
-                        .join(", "),
-                    struct_
-                        .generics
-                        .iter()
-                        .filter(|gen_type| !matches!(
-                            gen_type,
-                            Generic::Const { .. } | Generic::WhereBounded { .. }
-                        ))
-                        .map(|gen_type| Generic::full_with_const(
-                            gen_type,
-                            get_used_generic_bounds(),
-                            &[],
-                            true
-                        ))
-                        .collect::<Vec<_>>()
-                        .into_iter()
-                        .chain(
-                            struct_
-                                .generics
-                                .iter()
-                                .filter(|gen_type| matches!(gen_type, Generic::WhereBounded { .. }))
-                                .map(|gen_type| Generic::full_with_const(gen_type, &[], &[], true))
-                                .collect::<Vec<_>>()
-                                .into_iter()
-                        )
-                        .collect::<Vec<_>>()
-                        .join(",\n"),
-                    setters_body
+                        .map(Generic::ident_with_const),
                 )
-            }
+                .collect::<Vec<_>>()
+                .join(", ");
+            format!(
+                "impl<{arbitrary_impl_generics}> structdiff::testing::Arbitrary<'__arbitrary> for {struct_name}{struct_generics}
+                where
+                {where_bounds}
+                {{
+                    #[allow(unused_variables)]
+                    fn arbitrary(
+                        u: &mut structdiff::testing::Unstructured<'__arbitrary>,
+                    ) -> structdiff::testing::arbitrary::Result<Self> {{
+                        core::result::Result::Ok(Self {{
+                            {fields_body}
+                        }})
+                    }}
+                }}",
+                struct_name = struct_.name.as_ref().unwrap(),
+            )
         }
-
-        #[cfg(not(feature = "generated_setters"))]
-        ""
     };
 
     let const_start = "#[allow(non_camel_case_types)]\nconst _: () = {";
@@ -1000,7 +2763,7 @@ pub(crate) fn derive_struct_diff_struct(struct_: &Struct) -> TokenStream {
             {nanoserde_hack}
             #[allow(non_camel_case_types)]
             /// Generated type from StructDiff
-            #[derive({owned_derives})]{serde_bounds}
+            #[derive({owned_derives})]{serde_bounds}{serde_attrs}
             pub enum {enum_name}{owned_enum_def_generics} 
             where
             {owned_enum_where_bounds}
@@ -1010,13 +2773,15 @@ pub(crate) fn derive_struct_diff_struct(struct_: &Struct) -> TokenStream {
 
             #[allow(non_camel_case_types)]
             /// Generated type from StructDiff
-            #[derive({ref_derives})]
+            #[derive({ref_derives})]{serde_attrs}
             pub enum {enum_name}Ref{ref_enum_def_generics} 
             where
             {ref_enum_where_bounds}
             {{
                 {diff_ref_enum_body}
             }}
+
+            {borrowed_enum_def}
         {exposed_const_start}
 
             
@@ -1032,35 +2797,75 @@ pub(crate) fn derive_struct_diff_struct(struct_: &Struct) -> TokenStream {
                 }}
             }}
             
-            impl{impl_generics} structdiff::StructDiff for {struct_name}{struct_generics} 
+            impl{impl_generics} {trait_path} for {struct_name}{struct_generics} 
             where 
             {struct_where_bounds}
             {{
                 type Diff = {enum_name}{owned_enum_impl_generics};
                 type DiffRef<'__diff_target> = {enum_name}Ref{ref_enum_impl_generics} where
-                    {diff_ref_type_where_bounds};
+                    {remote_target_bound}{diff_ref_type_where_bounds};
 
-                fn diff(&self, updated: &Self) -> Vec<Self::Diff> {{
+                fn diff({diff_args}) -> Vec<Self::Diff> {{
                     let mut diffs = vec![];
                     {diff_body}
                     diffs
                 }}
 
-                fn diff_ref<'__diff_target>(&'__diff_target self, updated: &'__diff_target Self) -> Vec<Self::DiffRef<'__diff_target>> {{
+                fn diff_ref<'__diff_target>({diff_ref_args}) -> Vec<Self::DiffRef<'__diff_target>> {{
                     let mut diffs = vec![];
+                    let __chunk_changes = false;
                     {diff_ref_body}
                     diffs
                 }}
 
+                fn diff_into<'__diff_target>({diff_ref_args}, diffs: &mut impl structdiff::DiffSink<Self::DiffRef<'__diff_target>>) {{
+                    #[allow(unused_imports)]
+                    use structdiff::DiffSink as _;
+                    let __chunk_changes = true;
+                    {diff_ref_body}
+                }}
+
+                fn has_diff({diff_args}) -> bool {{
+                    {has_diff_body}
+                }}
+
 
                 #[inline(always)]
-                fn apply_single(&mut self, diff: Self::Diff) {{
+                fn apply_single({apply_single_args}, diff: Self::Diff) {{
                     match diff {{
                         {apply_single_body}
                     }}
                 }}
+
+                #[inline(always)]
+                fn apply_single_ref<'__diff_target>({apply_single_args}, diff: Self::DiffRef<'__diff_target>)
+                where
+                    {apply_single_ref_bound}: '__diff_target
+                {{
+                    match diff {{
+                        {apply_single_ref_body}
+                    }}
+                }}
+
+                {apply_single_limited_impl}
             }}
 
+            {owned_de_bin_impl}
+
+            {borrowed_impls}
+
+            {fields_impl}
+
+            {verified_impl}
+
+            {merge_impl}
+
+            {encoded_size_impl}
+
+            {de_bin_checked_impl}
+
+            {arbitrary_impl}
+
             {setters}
         }};",
         non_exposed_const_start = if exposed.is_some() { "" } else { const_start },
@@ -1071,13 +2876,36 @@ pub(crate) fn derive_struct_diff_struct(struct_: &Struct) -> TokenStream {
         owned_derives = owned_derives,
         ref_derives = ref_derives,
         struct_name = struct_.name.as_ref().unwrap(),
+        trait_path = match remote {
+            Some(remote) => format!("structdiff::StructDiffRemote<{remote}>"),
+            None => String::from("structdiff::StructDiff"),
+        },
+        remote_target_bound = match remote {
+            Some(remote) => format!("{remote}: '__diff_target,\n"),
+            None => String::new(),
+        },
+        diff_args = match remote {
+            Some(remote) => format!("__self: &{remote}, updated: &{remote}"),
+            None => String::from("&self, updated: &Self"),
+        },
+        diff_ref_args = match remote {
+            Some(remote) => format!("__self: &'__diff_target {remote}, updated: &'__diff_target {remote}"),
+            None => String::from("&'__diff_target self, updated: &'__diff_target Self"),
+        },
+        apply_single_args = match remote {
+            Some(remote) => format!("__self: &mut {remote}"),
+            None => String::from("&mut self"),
+        },
         diff_body = diff_body,
         diff_ref_body = diff_ref_body,
         enum_name = enum_name,
-        enum_body = diff_enum_body,
+        enum_body = diff_variants.iter().map(DiffVariant::render).collect::<String>(),
         diff_ref_enum_body = diff_ref_enum_body,
         ref_into_owned_body = ref_into_owned_body,
         apply_single_body = apply_single_body,
+        apply_single_ref_body = apply_single_ref_body,
+        apply_single_ref_bound = remote.unwrap_or("Self"),
+        apply_single_limited_impl = apply_single_limited_impl,
         owned_enum_def_generics = format_args!(
             "<{}>",
             used_generics
@@ -1097,19 +2925,6 @@ pub(crate) fn derive_struct_diff_struct(struct_: &Struct) -> TokenStream {
                 .collect::<Vec<_>>()
                 .join(", ")
         ),
-        owned_enum_where_bounds = format_args!(
-            "{}",
-            used_generics
-                .iter()
-                .filter(|gen_type| !matches!(
-                    gen_type,
-                    Generic::WhereBounded { .. } | Generic::Const { .. }
-                ))
-                .filter(|g| Generic::has_where_bounds(g, false, true))
-                .map(|gen_type| Generic::full_with_const(gen_type, get_used_generic_bounds(), &[], true))
-                .collect::<Vec<_>>()
-                .join(",\n")
-        ),
         ref_enum_where_bounds =format_args!(
             "{}",
             used_generics
@@ -1151,50 +2966,6 @@ pub(crate) fn derive_struct_diff_struct(struct_: &Struct) -> TokenStream {
                 .collect::<Vec<_>>()
                 .join(",\n")
         ),
-        impl_generics = format_args!(
-            "<{}>",
-            struct_
-                .generics
-                .iter()
-                .filter(|gen_type| !matches!(gen_type, Generic::WhereBounded { .. }))
-                .map(Generic::ident_with_const)
-                .collect::<Vec<_>>()
-                .join(", ")
-        ),
-        struct_generics = format_args!(
-            "<{}>",
-            struct_
-                .generics
-                .iter()
-                .filter(|gen_type| !matches!(gen_type, Generic::WhereBounded { .. }))
-                .map(Generic::ident_only)
-                .collect::<Vec<_>>()
-                .join(", ")
-        ),
-        struct_where_bounds = format_args!(
-            "{}",
-            struct_
-                .generics
-                .iter()
-                .filter(|gen_type| !matches!(gen_type, Generic::Const { .. } | Generic::WhereBounded { .. }))
-                .filter(|g| Generic::has_where_bounds(g, false, true))
-                .map(|gen_type| Generic::full_with_const(gen_type, get_used_generic_bounds(), &[], true))
-                .collect::<Vec<_>>().into_iter().chain(struct_
-                    .generics
-                    .iter()
-                    .filter(|gen_type| matches!(gen_type, Generic::WhereBounded { .. }))
-                    .map(|gen_type| Generic::full_with_const(gen_type, &[], &[], true)).collect::<Vec<_>>().into_iter()).collect::<Vec<_>>()
-                .join(",\n")
-        ),
-        owned_enum_impl_generics = format_args!(
-            "<{}>",
-            used_generics
-                .iter()
-                .filter(|gen_type| !matches!(gen_type, Generic::WhereBounded { .. }))
-                .map(Generic::ident_only)
-                .collect::<Vec<_>>()
-                .join(", ")
-        ),
         ref_enum_impl_generics = format_args!(
             "<{}>",
             std::iter::once(String::from("'__diff_target")).chain(
@@ -1205,21 +2976,33 @@ pub(crate) fn derive_struct_diff_struct(struct_: &Struct) -> TokenStream {
                 .collect::<Vec<_>>()
                 .join(", ")
         ),
-        serde_bounds = serde_bound
+        serde_bounds = serde_bound,
+        serde_attrs = serde_attrs
     )
     .parse()
     .unwrap()
 }
 
 pub(crate) fn derive_struct_diff_enum(enum_: &Enum) -> TokenStream {
+    if attrs_remote(&enum_.attributes).is_some() {
+        panic!("`remote` is only supported on structs");
+    }
+    if attrs_verify_base(&enum_.attributes) {
+        panic!("`verify_base` is only supported on structs");
+    }
+    if attrs_merge(&enum_.attributes) {
+        panic!("`merge` is only supported on structs");
+    }
+    if attrs_arbitrary(&enum_.attributes) {
+        panic!("`arbitrary` is only supported on structs");
+    }
+
     let owned_derives: String = [
         #[cfg(feature = "debug_diffs")]
         "core::fmt::Debug",
         "Clone",
         #[cfg(feature = "nanoserde")]
         "nanoserde::SerBin",
-        #[cfg(feature = "nanoserde")]
-        "nanoserde::DeBin",
         #[cfg(feature = "serde")]
         "serde::Serialize",
         #[cfg(feature = "serde")]
@@ -1435,12 +3218,171 @@ pub(crate) fn derive_struct_diff_enum(enum_: &Enum) -> TokenStream {
     #[cfg(not(feature = "serde"))]
     let serde_bound = "";
 
+    #[cfg(feature = "serde")]
+    let serde_attrs = attrs_serde(&enum_.attributes)
+        .iter()
+        .map(|serde_attr| format!("\n#[serde({})]", serde_attr))
+        .collect::<String>();
+    #[cfg(not(feature = "serde"))]
+    let serde_attrs = "";
+
+    let owned_enum_def_generics = format!(
+        "<{}>",
+        enum_
+            .generics
+            .iter()
+            .filter(|gen_type| !matches!(gen_type, Generic::WhereBounded { .. }))
+            .map(Generic::ident_with_const)
+            .collect::<Vec<_>>()
+            .join(", ")
+    );
+    let enum_where_bounds = enum_
+        .generics
+        .iter()
+        .filter(|gen_type| !matches!(gen_type, Generic::Const { .. }))
+        .filter(|g| Generic::has_where_bounds(g, false, true))
+        .map(|gen_type| Generic::full_with_const(gen_type, get_used_generic_bounds(), &[], true))
+        .collect::<Vec<_>>()
+        .join(",\n");
+    let impl_generics = format!(
+        "<{}>",
+        enum_
+            .generics
+            .iter()
+            .filter(|gen_type| !matches!(gen_type, Generic::WhereBounded { .. }))
+            .map(Generic::ident_with_const)
+            .collect::<Vec<_>>()
+            .join(", ")
+    );
+    let struct_generics = format!(
+        "<{}>",
+        enum_
+            .generics
+            .iter()
+            .filter(|gen_type| !matches!(gen_type, Generic::WhereBounded { .. }))
+            .map(Generic::ident_only)
+            .collect::<Vec<_>>()
+            .join(", ")
+    );
+    let struct_where_bounds = enum_
+        .generics
+        .iter()
+        .filter(|gen_type| {
+            !matches!(
+                gen_type,
+                Generic::Const { .. } | Generic::WhereBounded { .. }
+            )
+        })
+        .map(|gen_type| Generic::full_with_const(gen_type, get_used_generic_bounds(), &[], true))
+        .collect::<Vec<_>>()
+        .into_iter()
+        .chain(
+            enum_
+                .generics
+                .iter()
+                .filter(|gen_type| matches!(gen_type, Generic::WhereBounded { .. }))
+                .map(|gen_type| Generic::full_with_const(gen_type, &[], &[], true))
+                .collect::<Vec<_>>(),
+        )
+        .collect::<Vec<_>>()
+        .join(",\n");
+    let enum_impl_generics = format!(
+        "<{}>",
+        enum_
+            .generics
+            .iter()
+            .filter(|gen_type| !matches!(gen_type, Generic::WhereBounded { .. }))
+            .map(Generic::ident_only)
+            .collect::<Vec<_>>()
+            .join(", ")
+    );
+
+    // the enum is read the way nanoserde's derive writes it, with the
+    // contents of its variants read checked, and the diff reads it that way
+    #[cfg(feature = "nanoserde")]
+    let nanoserde_impls = {
+        let read = "structdiff::checked::DeBinChecked::de_bin_checked(offset, bytes)?";
+        let variants = enum_
+            .variants
+            .iter()
+            .enumerate()
+            .map(|(index, variant)| {
+                let variant_name = variant.field_name.as_ref().unwrap();
+                match &variant.ty.ident {
+                    Category::Tuple { contents } => format!(
+                        "{index}u16 => Self::{variant_name}({}),",
+                        contents
+                            .iter()
+                            .map(|_| format!("{read},"))
+                            .collect::<String>()
+                    ),
+                    Category::AnonymousStruct { contents } => format!(
+                        "{index}u16 => Self::{variant_name} {{ {} }},",
+                        contents
+                            .fields
+                            .iter()
+                            .map(|field| format!("{}: {read},", field.field_name.as_ref().unwrap()))
+                            .collect::<String>()
+                    ),
+                    _ => format!("{index}u16 => Self::{variant_name},"),
+                }
+            })
+            .collect::<String>();
+        let where_bounds = std::iter::once(struct_where_bounds.clone())
+            .filter(|bounds| !bounds.is_empty())
+            .chain(
+                enum_
+                    .variants
+                    .iter()
+                    .flat_map(|variant| match &variant.ty.ident {
+                        Category::Tuple { contents } => contents.iter().collect(),
+                        Category::AnonymousStruct { contents } => {
+                            contents.fields.iter().map(|field| &field.ty).collect()
+                        }
+                        _ => Vec::new(),
+                    })
+                    .map(|ty| format!("{}: structdiff::checked::DeBinChecked", ty.full())),
+            )
+            .collect::<Vec<_>>()
+            .join(",\n");
+        format!(
+            "impl{impl_generics} structdiff::checked::DeBinChecked for {struct_name}{struct_generics}
+            where
+            {where_bounds}
+            {{
+                fn de_bin_checked(offset: &mut usize, bytes: &[u8]) -> core::result::Result<Self, nanoserde::DeBinErr> {{
+                    let id: u16 = nanoserde::DeBin::de_bin(offset, bytes)?;
+                    core::result::Result::Ok(match id {{
+                        {variants}
+                        _ => return Err(nanoserde::DeBinErr {{ o: *offset - 2, l: 2, s: bytes.len() }}),
+                    }})
+                }}
+            }}
+
+            impl{owned_enum_def_generics} nanoserde::DeBin for {enum_name}{enum_impl_generics}
+            where
+            {enum_where_bounds}
+            {{
+                fn de_bin(offset: &mut usize, bytes: &[u8]) -> core::result::Result<Self, nanoserde::DeBinErr> {{
+                    let id: u16 = nanoserde::DeBin::de_bin(offset, bytes)?;
+                    match id {{
+                        0u16 => Ok(Self::Replace({read})),
+                        _ => Err(nanoserde::DeBinErr {{ o: *offset - 2, l: 2, s: bytes.len() }}),
+                    }}
+                }}
+            }}",
+            struct_name = enum_.name,
+        )
+    };
+    #[cfg(not(feature = "nanoserde"))]
+    let nanoserde_impls = "";
+
     let const_start = "#[allow(non_camel_case_types)]\nconst _: () = {";
 
     format!(
         "{non_exposed_const_start}
             /// Generated type from StructDiff
-            #[derive({owned_derives})]{serde_bounds}
+            #[derive({owned_derives})]{serde_bounds}{serde_attrs}
             #[allow(non_camel_case_types)]
             pub enum {enum_name}{owned_enum_def_generics} 
             where
@@ -1451,7 +3393,7 @@ pub(crate) fn derive_struct_diff_enum(enum_: &Enum) -> TokenStream {
 
             #[allow(non_camel_case_types)]
             /// Generated type from StructDiff
-            #[derive({ref_derives})]
+            #[derive({ref_derives})]{serde_attrs}
             pub enum {enum_name}Ref{ref_enum_def_generics} 
             where
             {ref_enum_where_bounds}
@@ -1462,6 +3404,7 @@ pub(crate) fn derive_struct_diff_enum(enum_: &Enum) -> TokenStream {
 
             {type_aliases}
             {nanoserde_hack}
+            {nanoserde_impls}
 
             impl{ref_enum_def_generics} Into<{enum_name}{enum_impl_generics}> for {enum_name}Ref{ref_enum_impl_generics}
             where
@@ -1502,6 +3445,10 @@ pub(crate) fn derive_struct_diff_enum(enum_: &Enum) -> TokenStream {
                     }}
                 }}
 
+                fn has_diff(&self, updated: &Self) -> bool {{
+                    self != updated
+                }}
+
                 #[inline(always)]
                 fn apply_single(&mut self, diff: Self::Diff) {{
                     match diff {{
@@ -1510,6 +3457,16 @@ pub(crate) fn derive_struct_diff_enum(enum_: &Enum) -> TokenStream {
                         }}
                     }}
                 }}
+
+                #[inline(always)]
+                fn apply_single_ref<'__diff_target>(&mut self, diff: Self::DiffRef<'__diff_target>)
+                where
+                    Self: '__diff_target
+                {{
+                    match diff {{
+                        Self::DiffRef::Replace(diff) => self.clone_from(diff),
+                    }}
+                }}
             }}
         }};",
         non_exposed_const_start = if exposed.is_some() { "" } else { const_start },
@@ -1524,16 +3481,6 @@ pub(crate) fn derive_struct_diff_enum(enum_: &Enum) -> TokenStream {
         ref_into_owned_body = ref_into_owned_body,
         enum_name = enum_name,
         apply_single_body = apply_single_body,
-        owned_enum_def_generics = format_args!(
-            "<{}>",
-            enum_
-                .generics
-                .iter()
-                .filter(|gen_type| !matches!(gen_type, Generic::WhereBounded { .. }))
-                .map(Generic::ident_with_const)
-                .collect::<Vec<_>>()
-                .join(", ")
-        ),
         ref_enum_def_generics = format_args!(
             "<{}>",
             std::iter::once(String::from("'__diff_target")).chain(
@@ -1545,20 +3492,6 @@ pub(crate) fn derive_struct_diff_enum(enum_: &Enum) -> TokenStream {
                 .collect::<Vec<_>>()
                 .join(", ")
         ),
-        enum_where_bounds = format_args!(
-            "{}",
-            enum_
-                .generics
-                .iter()
-                .filter(|gen_type| !matches!(
-                    gen_type,
-                     Generic::Const { .. }
-                ))
-                .filter(|g| Generic::has_where_bounds(g, false, true))
-                .map(|gen_type| Generic::full_with_const(gen_type, get_used_generic_bounds(), &[], true))
-                .collect::<Vec<_>>()
-                .join(",\n")
-        ),
         ref_enum_where_bounds = format_args!(
             "{}",
             enum_
@@ -1602,50 +3535,6 @@ pub(crate) fn derive_struct_diff_enum(enum_: &Enum) -> TokenStream {
                 .collect::<Vec<_>>()
                 .join(",\n")
         ),
-        impl_generics = format_args!(
-            "<{}>",
-            enum_
-                .generics
-                .iter()
-                .filter(|gen_type| !matches!(gen_type, Generic::WhereBounded { .. }))
-                .map(Generic::ident_with_const)
-                .collect::<Vec<_>>()
-                .join(", ")
-        ),
-        struct_generics = format_args!(
-            "<{}>",
-            enum_
-                .generics
-                .iter()
-                .filter(|gen_type| !matches!(gen_type, Generic::WhereBounded { .. }))
-                .map(Generic::ident_only)
-                .collect::<Vec<_>>()
-                .join(", ")
-        ),
-        struct_where_bounds = format_args!(
-            "{}",
-            enum_
-                .generics
-                .iter()
-                .filter(|gen_type| !matches!(gen_type, Generic::Const { .. } | Generic::WhereBounded { .. }))
-                .map(|gen_type| Generic::full_with_const(gen_type, get_used_generic_bounds(), &[],true))
-                .collect::<Vec<_>>().into_iter().chain(enum_
-                    .generics
-                    .iter()
-                    .filter(|gen_type| matches!(gen_type, Generic::WhereBounded { .. }))
-                    .map(|gen_type| Generic::full_with_const(gen_type, &[], &[], true)).collect::<Vec<_>>().into_iter()).collect::<Vec<_>>()
-                .join(",\n")
-        ),
-        enum_impl_generics = format_args!(
-            "<{}>",
-            enum_
-                .generics
-                .iter()
-                .filter(|gen_type| !matches!(gen_type, Generic::WhereBounded { .. }))
-                .map(Generic::ident_only)
-                .collect::<Vec<_>>()
-                .join(", ")
-        ),
         ref_enum_impl_generics = format_args!(
             "<{}>",
             std::iter::once(String::from("'__diff_target")).chain(
@@ -1657,7 +3546,8 @@ pub(crate) fn derive_struct_diff_enum(enum_: &Enum) -> TokenStream {
                 .collect::<Vec<_>>()
                 .join(", ")
         ),
-        serde_bounds = serde_bound
+        serde_bounds = serde_bound,
+        serde_attrs = serde_attrs
     )
     .parse()
     .unwrap()
@@ -1085,6 +1085,22 @@ fn next_attribute<T: Iterator<Item = TokenTree>>(
         let attribute_name = next_ident(&mut args_group).expect("Expecting attribute name");
         attr_tokens.push(attribute_name);
 
+        // nested attribute list, like #[structdiff(whatever(a = "b"))], kept as a string
+        if let Some(group) = next_group(&mut args_group) {
+            attr_tokens.push(group.stream().to_string());
+            attrs.push(Attribute {
+                name: name.clone(),
+                tokens: std::mem::take(&mut attr_tokens),
+            });
+            match next_eof(&mut args_group) {
+                Some(_) => break,
+                None => {
+                    next_punct(&mut args_group);
+                    continue;
+                }
+            }
+        }
+
         // single-word attribute, like #[structdiff(whatever)]
         match (
             next_eof(&mut args_group).is_some(),
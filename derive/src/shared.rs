@@ -15,11 +15,33 @@ pub enum MapStrategy {
     KeyAndValue,
 }
 
+#[derive(Debug, Default)]
+pub enum TextGranularity {
+    Bytes,
+    #[default]
+    Chars,
+    Words,
+    Lines,
+}
+
+impl TextGranularity {
+    pub fn path(&self) -> &'static str {
+        match self {
+            TextGranularity::Bytes => "structdiff::collections::text::TextGranularity::Bytes",
+            TextGranularity::Chars => "structdiff::collections::text::TextGranularity::Chars",
+            TextGranularity::Words => "structdiff::collections::text::TextGranularity::Words",
+            TextGranularity::Lines => "structdiff::collections::text::TextGranularity::Lines",
+        }
+    }
+}
+
 #[derive(Debug)]
 pub enum CollectionStrategy {
     OrderedArrayLike,
     UnorderedArrayLikeHash,
     UnorderedMapLikeHash(MapStrategy),
+    Text(TextGranularity),
+    BinaryDelta,
 }
 
 #[cfg(feature = "generated_setters")]
@@ -51,10 +73,39 @@ pub fn attrs_all_setters(attributes: &[crate::parse::Attribute]) -> bool {
         .any(|attr| attr.tokens.len() == 1 && attr.tokens[0] == "setters")
 }
 
+/// CRDT fields diff into deltas through their own `StructDiff` impls, so they
+/// are recursed into
 pub fn attrs_recurse(attributes: &[crate::parse::Attribute]) -> bool {
+    attributes.iter().any(|attr| match attr.tokens.len() {
+        1 => attr.tokens[0] == "recurse",
+        2 => attr.tokens[0] == "collection_strategy" && attr.tokens[1] == "crdt",
+        _ => false,
+    })
+}
+
+pub fn attrs_arbitrary(attributes: &[crate::parse::Attribute]) -> bool {
+    attributes
+        .iter()
+        .any(|attr| attr.tokens.len() == 1 && attr.tokens[0] == "arbitrary")
+}
+
+pub fn attrs_encoded_size(attributes: &[crate::parse::Attribute]) -> bool {
     attributes
         .iter()
-        .any(|attr| attr.tokens.len() == 1 && attr.tokens[0] == "recurse")
+        .any(|attr| attr.tokens.len() == 1 && attr.tokens[0] == "encoded_size")
+}
+
+#[cfg(feature = "nanoserde")]
+pub fn attrs_de_bin_checked(attributes: &[crate::parse::Attribute]) -> bool {
+    attributes
+        .iter()
+        .any(|attr| attr.tokens.len() == 1 && attr.tokens[0] == "de_bin_checked")
+}
+
+pub fn attrs_merge(attributes: &[crate::parse::Attribute]) -> bool {
+    attributes
+        .iter()
+        .any(|attr| attr.tokens.len() == 1 && attr.tokens[0] == "merge")
 }
 
 pub fn attrs_skip(attributes: &[crate::parse::Attribute]) -> bool {
@@ -73,6 +124,10 @@ pub fn attrs_collection_type(attributes: &[crate::parse::Attribute]) -> Option<C
                     let map_compare_type = attrs_map_strategy(attributes).unwrap_or_default();
                     CollectionStrategy::UnorderedMapLikeHash(map_compare_type)
                 }
+                "binary_delta" => CollectionStrategy::BinaryDelta,
+                "text" => {
+                    CollectionStrategy::Text(attrs_text_granularity(attributes).unwrap_or_default())
+                }
                 _ => {
                     return None;
                 }
@@ -84,6 +139,63 @@ pub fn attrs_collection_type(attributes: &[crate::parse::Attribute]) -> Option<C
     })
 }
 
+/// Added to fields with `collection_strategy = "auto"` by
+/// [`resolve_auto_strategies`], which replaces the strategy itself
+const AUTO_MARKER: &str = "__structdiff_auto";
+
+/// Whether the field's strategy was inferred from `collection_strategy = "auto"`,
+/// so that its diff is compared with a replacement of the whole field
+pub fn attrs_auto(attributes: &[crate::parse::Attribute]) -> bool {
+    attributes
+        .iter()
+        .any(|attr| attr.tokens.len() == 1 && attr.tokens[0] == AUTO_MARKER)
+}
+
+/// Replace `collection_strategy = "auto"` on each field with the strategy
+/// suited to its type
+pub fn resolve_auto_strategies(struct_: &crate::parse::Struct) -> crate::parse::Struct {
+    let mut struct_ = struct_.clone();
+    for field in struct_.fields.iter_mut() {
+        let Some(index) = field.attributes.iter().position(|attr| {
+            attr.tokens.len() == 2
+                && attr.tokens[0] == "collection_strategy"
+                && attr.tokens[1] == "auto"
+        }) else {
+            continue;
+        };
+        if attrs_recurse(&field.attributes) {
+            panic!("`collection_strategy = \"auto\"` cannot be combined with `recurse`");
+        }
+
+        let base = field.ty.base();
+        let bytes = field
+            .ty
+            .wraps
+            .as_ref()
+            .is_some_and(|wraps| wraps.len() == 1 && wraps[0].full() == "u8");
+        let strategy = match base.rsplit("::").next().unwrap_or_default() {
+            "Vec" if bytes => "binary_delta",
+            "Vec" | "VecDeque" | "LinkedList" => "ordered_array_like",
+            "HashSet" | "BTreeSet" => "unordered_array_like",
+            "HashMap" | "BTreeMap" => "unordered_map_like",
+            "String" => "text",
+            _ => panic!(
+                "`collection_strategy = \"auto\"` cannot infer a strategy for `{}`, so one must be chosen explicitly",
+                field.ty.full()
+            ),
+        };
+
+        let attr = &mut field.attributes[index];
+        attr.tokens[1] = String::from(strategy);
+        let marker = crate::parse::Attribute {
+            name: attr.name.clone(),
+            tokens: vec![String::from(AUTO_MARKER)],
+        };
+        field.attributes.push(marker);
+    }
+    struct_
+}
+
 pub fn attrs_map_strategy(attributes: &[crate::parse::Attribute]) -> Option<MapStrategy> {
     attributes.iter().find_map(|attr| {
         if attr.tokens.len() == 2 && attr.tokens[0] == "map_equality" {
@@ -101,6 +213,25 @@ pub fn attrs_map_strategy(attributes: &[crate::parse::Attribute]) -> Option<MapS
     })
 }
 
+pub fn attrs_text_granularity(attributes: &[crate::parse::Attribute]) -> Option<TextGranularity> {
+    attributes.iter().find_map(|attr| {
+        if attr.tokens.len() == 2 && attr.tokens[0] == "text_granularity" {
+            let granularity = match attr.tokens[1].as_str() {
+                "bytes" => TextGranularity::Bytes,
+                "chars" => TextGranularity::Chars,
+                "words" => TextGranularity::Words,
+                "lines" => TextGranularity::Lines,
+                _ => {
+                    return None;
+                }
+            };
+            Some(granularity)
+        } else {
+            None
+        }
+    })
+}
+
 pub fn attrs_expose(attributes: &[crate::parse::Attribute]) -> Option<Option<&str>> {
     attributes.iter().find_map(|attr| match attr.tokens.len() {
         1 if attr.tokens[0].starts_with("expose") => Some(None),
@@ -108,3 +239,146 @@ pub fn attrs_expose(attributes: &[crate::parse::Attribute]) -> Option<Option<&st
         _ => None,
     })
 }
+
+pub fn attrs_remote(attributes: &[crate::parse::Attribute]) -> Option<&str> {
+    attributes.iter().find_map(|attr| match attr.tokens.len() {
+        2 if attr.tokens[0] == "remote" => Some(attr.tokens[1].as_str()),
+        _ => None,
+    })
+}
+
+pub fn attrs_verify_base(attributes: &[crate::parse::Attribute]) -> bool {
+    attributes
+        .iter()
+        .any(|attr| attr.tokens.len() == 1 && attr.tokens[0] == "verify_base")
+}
+
+pub fn attrs_getter(attributes: &[crate::parse::Attribute]) -> Option<&str> {
+    attributes.iter().find_map(|attr| match attr.tokens.len() {
+        2 if attr.tokens[0] == "getter" => Some(attr.tokens[1].as_str()),
+        _ => None,
+    })
+}
+
+pub fn attrs_getter_mut(attributes: &[crate::parse::Attribute]) -> Option<&str> {
+    attributes.iter().find_map(|attr| match attr.tokens.len() {
+        2 if attr.tokens[0] == "getter_mut" => Some(attr.tokens[1].as_str()),
+        _ => None,
+    })
+}
+
+pub fn attrs_eq_with(attributes: &[crate::parse::Attribute]) -> Option<&str> {
+    attributes.iter().find_map(|attr| match attr.tokens.len() {
+        2 if attr.tokens[0] == "eq_with" => Some(attr.tokens[1].as_str()),
+        _ => None,
+    })
+}
+
+pub fn attrs_float_epsilon(attributes: &[crate::parse::Attribute]) -> Option<&str> {
+    attributes.iter().find_map(|attr| match attr.tokens.len() {
+        2 if attr.tokens[0] == "float_epsilon" => Some(attr.tokens[1].as_str()),
+        _ => None,
+    })
+}
+
+pub fn attrs_with(attributes: &[crate::parse::Attribute]) -> Option<&str> {
+    attributes.iter().find_map(|attr| match attr.tokens.len() {
+        2 if attr.tokens[0] == "with" => Some(attr.tokens[1].as_str()),
+        _ => None,
+    })
+}
+
+pub fn attrs_diff_as(attributes: &[crate::parse::Attribute]) -> Option<&str> {
+    attributes.iter().find_map(|attr| match attr.tokens.len() {
+        2 if attr.tokens[0] == "diff_as" => Some(attr.tokens[1].as_str()),
+        _ => None,
+    })
+}
+
+#[cfg(feature = "serde")]
+pub fn attrs_serde(attributes: &[crate::parse::Attribute]) -> Vec<&str> {
+    attributes
+        .iter()
+        .filter(|attr| attr.tokens.len() == 2 && attr.tokens[0] == "serde")
+        .map(|attr| attr.tokens[1].as_str())
+        .collect()
+}
+
+#[cfg(feature = "serde")]
+fn split_serde_args(args: &str) -> Vec<&str> {
+    let mut ret = Vec::new();
+    let (mut depth, mut quoted, mut start) = (0usize, false, 0);
+    for (i, c) in args.char_indices() {
+        match c {
+            '"' => quoted = !quoted,
+            '(' if !quoted => depth += 1,
+            ')' if !quoted => depth -= 1,
+            ',' if !quoted && depth == 0 => {
+                ret.push(args[start..i].trim());
+                start = i + 1;
+            }
+            _ => (),
+        }
+    }
+    ret.push(args[start..].trim());
+    ret.retain(|arg| !arg.is_empty());
+    ret
+}
+
+#[cfg(feature = "serde")]
+fn serde_arg_value<'a>(arg: &'a str, key: &str) -> Option<&'a str> {
+    let (name, value) = arg.split_once('=')?;
+    (name.trim() == key).then(|| value.trim().trim_matches('"'))
+}
+
+/// The string value of `key` in the arguments of a `serde(...)` attribute
+#[cfg(feature = "serde")]
+pub fn serde_arg<'a>(args: &'a str, key: &str) -> Option<&'a str> {
+    split_serde_args(args)
+        .into_iter()
+        .find_map(|arg| serde_arg_value(arg, key))
+}
+
+/// The arguments of a `serde(...)` attribute, with `key` removed
+#[cfg(feature = "serde")]
+pub fn serde_args_without(args: &str, key: &str) -> String {
+    split_serde_args(args)
+        .into_iter()
+        .filter(|arg| serde_arg_value(arg, key).is_none())
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// Apply a serde `rename_all` rule to a snake_case field name
+#[cfg(feature = "serde")]
+pub fn rename_field(field_name: &str, rule: &str) -> String {
+    let pascal = || {
+        field_name
+            .split('_')
+            .map(|word| {
+                let mut chars = word.chars();
+                match chars.next() {
+                    Some(first) => first.to_ascii_uppercase().to_string() + chars.as_str(),
+                    None => String::new(),
+                }
+            })
+            .collect::<String>()
+    };
+
+    match rule {
+        "lowercase" | "snake_case" => String::from(field_name),
+        "UPPERCASE" | "SCREAMING_SNAKE_CASE" => field_name.to_ascii_uppercase(),
+        "PascalCase" => pascal(),
+        "camelCase" => {
+            let pascal = pascal();
+            let mut chars = pascal.chars();
+            match chars.next() {
+                Some(first) => first.to_ascii_lowercase().to_string() + chars.as_str(),
+                None => String::new(),
+            }
+        }
+        "kebab-case" => field_name.replace('_', "-"),
+        "SCREAMING-KEBAB-CASE" => field_name.to_ascii_uppercase().replace('_', "-"),
+        _ => panic!("unknown `rename_all` rule \"{rule}\""),
+    }
+}
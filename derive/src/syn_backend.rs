@@ -147,7 +147,10 @@ fn lower_meta(meta: Meta) -> Option<ParsedAttribute> {
                 tokens: vec![path_to_string(&name_value.path), value],
             })
         }
-        Meta::List(_) => None,
+        Meta::List(list) => Some(ParsedAttribute {
+            name: "difference".to_owned(),
+            tokens: vec![path_to_string(&list.path), list.tokens.to_string()],
+        }),
     }
 }
 
@@ -0,0 +1,17 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use structdiff::Difference;
+
+#[derive(Debug, Clone, PartialEq, Difference)]
+struct Target {
+    #[difference(collection_strategy = "binary_delta")]
+    bytes: Vec<u8>,
+}
+
+fuzz_target!(|data: &[u8]| {
+    let target = Target {
+        bytes: (0..256u32).map(|i| (i * 7) as u8).collect(),
+    };
+    structdiff_fuzz::apply(data, &target);
+});
@@ -0,0 +1,26 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use structdiff::collections::crdt::{LwwMap, LwwRegister, OrSet, Rga};
+use structdiff_fuzz::apply_owned;
+
+fuzz_target!(|data: &[u8]| {
+    let mut register = LwwRegister::new(0u8, 0);
+    register.set(1, 1);
+    apply_owned(data, &register);
+
+    let mut set = OrSet::new();
+    set.insert(1u8, 1);
+    set.insert(2, 2);
+    apply_owned(data, &set);
+
+    let mut map = LwwMap::new();
+    map.insert(String::from("a"), String::from("b"), 1);
+    map.insert(String::from("c"), String::from("d"), 2);
+    apply_owned(data, &map);
+
+    let mut list = Rga::new();
+    list.insert(0, 1u8, 1);
+    list.insert(1, 2, 2);
+    apply_owned(data, &list);
+});
@@ -0,0 +1,73 @@
+#![no_main]
+
+use std::collections::{BTreeSet, HashMap};
+
+use libfuzzer_sys::fuzz_target;
+use nanoserde::{DeBin, SerBin};
+use structdiff::Difference;
+
+#[derive(Debug, Clone, PartialEq, Default, Difference, SerBin, DeBin)]
+#[difference(de_bin_checked)]
+struct Inner {
+    a: u32,
+    b: Option<u64>,
+    c: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Default, Difference, SerBin, DeBin)]
+enum Mode {
+    #[default]
+    Off,
+    Named(String),
+    Tagged {
+        tags: Vec<String>,
+    },
+}
+
+/// One field of each kind handled by the derive
+#[derive(Debug, Clone, PartialEq, Difference)]
+struct Target {
+    name: String,
+    label: Option<String>,
+    mode: Mode,
+    bytes: Vec<u8>,
+    values: Vec<u32>,
+    #[difference(recurse)]
+    inner: Inner,
+    #[difference(recurse)]
+    optional: Option<Inner>,
+    #[difference(recurse)]
+    map: HashMap<String, Inner>,
+    #[difference(recurse)]
+    list: Vec<u16>,
+    #[difference(recurse)]
+    set: BTreeSet<String>,
+    #[difference(recurse)]
+    array: [u8; 4],
+    #[difference(recurse)]
+    pair: (u8, u16),
+}
+
+fuzz_target!(|data: &[u8]| {
+    let target = Target {
+        name: String::from("name"),
+        label: Some(String::from("label")),
+        mode: Mode::Named(String::from("mode")),
+        bytes: vec![1, 2, 3],
+        values: vec![4, 5, 6],
+        inner: Inner {
+            a: 1,
+            b: Some(2),
+            c: String::from("inner"),
+        },
+        optional: Some(Inner::default()),
+        map: (0..4)
+            .map(|key| (key.to_string(), Inner::default()))
+            .collect(),
+        list: vec![7, 8, 9],
+        set: (0..4).map(|key| key.to_string()).collect(),
+        array: [1, 2, 3, 4],
+        pair: (5, 6),
+    };
+    structdiff_fuzz::apply(data, &target);
+});
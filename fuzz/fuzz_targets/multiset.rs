@@ -0,0 +1,19 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use structdiff::Difference;
+
+// a single change may insert an item any number of times, which only the
+// apply limits keep in check
+#[derive(Debug, Clone, PartialEq, Difference)]
+struct Target {
+    #[difference(collection_strategy = "unordered_array_like")]
+    values: Vec<u16>,
+}
+
+fuzz_target!(|data: &[u8]| {
+    let target = Target {
+        values: (0..32).chain(0..8).collect(),
+    };
+    structdiff_fuzz::apply_owned(data, &target);
+});
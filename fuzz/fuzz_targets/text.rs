@@ -0,0 +1,17 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use structdiff::Difference;
+
+#[derive(Debug, Clone, PartialEq, Difference)]
+struct Target {
+    #[difference(collection_strategy = "text")]
+    text: String,
+}
+
+fuzz_target!(|data: &[u8]| {
+    let target = Target {
+        text: String::from("the quick\nbrown fox\njumps over\nthe lazy dög"),
+    };
+    structdiff_fuzz::apply(data, &target);
+});
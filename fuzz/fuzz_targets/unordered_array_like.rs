@@ -0,0 +1,19 @@
+#![no_main]
+
+use std::collections::HashSet;
+
+use libfuzzer_sys::fuzz_target;
+use structdiff::Difference;
+
+#[derive(Debug, Clone, PartialEq, Difference)]
+struct Target {
+    #[difference(collection_strategy = "unordered_array_like")]
+    values: HashSet<String>,
+}
+
+fuzz_target!(|data: &[u8]| {
+    let target = Target {
+        values: (0..32).map(|value| value.to_string()).collect(),
+    };
+    structdiff_fuzz::apply(data, &target);
+});
@@ -0,0 +1,19 @@
+#![no_main]
+
+use std::collections::HashMap;
+
+use libfuzzer_sys::fuzz_target;
+use structdiff::Difference;
+
+#[derive(Debug, Clone, PartialEq, Difference)]
+struct Target {
+    #[difference(collection_strategy = "unordered_map_like")]
+    values: HashMap<String, u32>,
+}
+
+fuzz_target!(|data: &[u8]| {
+    let target = Target {
+        values: (0..16).map(|key| (key.to_string(), key * 3)).collect(),
+    };
+    structdiff_fuzz::apply(data, &target);
+});
@@ -0,0 +1,35 @@
+#![no_main]
+
+use std::collections::HashMap;
+
+use libfuzzer_sys::fuzz_target;
+use nanoserde::{DeBin, SerBin};
+use structdiff::Difference;
+
+#[derive(Debug, Clone, PartialEq, Default, Difference, SerBin, DeBin)]
+#[difference(de_bin_checked)]
+struct Inner {
+    a: u32,
+    b: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Difference)]
+struct Target {
+    #[difference(recurse, collection_strategy = "unordered_map_like")]
+    values: HashMap<String, Inner>,
+}
+
+fuzz_target!(|data: &[u8]| {
+    let target = Target {
+        values: (0..8)
+            .map(|key| {
+                let inner = Inner {
+                    a: key * 7,
+                    b: key.to_string(),
+                };
+                (key.to_string(), inner)
+            })
+            .collect(),
+    };
+    structdiff_fuzz::apply(data, &target);
+});
@@ -0,0 +1,30 @@
+//! Shared by the fuzz targets, which each feed arbitrary bytes through the
+//! `DeBin` implementation of a diff type and apply whatever decodes to a
+//! fixed target. Run them with `cargo fuzz run <target>` from this directory.
+//!
+//! Malformed input must be rejected with an error, or applied without
+//! panicking, so any crash is a bug. Owned diffs are applied within the
+//! default [`ApplyLimits`], so an out of memory error is one too.
+
+use structdiff::{limits::ApplyLimits, StructDiff, StructDiffBorrowed};
+
+/// Decode `data` as a list of owned diffs, and apply them to a copy of
+/// `target` within the default limits
+pub fn apply_owned<T: StructDiff + Clone>(data: &[u8], target: &T) {
+    if let Ok(diffs) = structdiff::checked::deserialize_bin::<T::Diff>(data) {
+        let _ = target.clone().apply_limited(diffs, ApplyLimits::new());
+    }
+}
+
+/// Decode `data` as a list of borrowed diffs, and apply them to a copy of `target`
+pub fn apply_borrowed<T: StructDiffBorrowed + Clone>(data: &[u8], target: &T) {
+    if let Ok(diffs) = structdiff::borrowed::deserialize_bin_borrowed::<T::DiffBorrowed<'_>>(data) {
+        target.clone().apply_borrowed(diffs);
+    }
+}
+
+/// Both of the above
+pub fn apply<T: StructDiffBorrowed + Clone>(data: &[u8], target: &T) {
+    apply_owned(data, target);
+    apply_borrowed(data, target);
+}
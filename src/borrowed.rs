@@ -0,0 +1,275 @@
+//! Diff representations which borrow their string and byte payloads from the
+//! buffer they were deserialized from.
+//!
+//! A [`StructDiffBorrowed::DiffBorrowed`](crate::StructDiffBorrowed::DiffBorrowed)
+//! reads the same bytes as the owned [`StructDiff::Diff`](crate::StructDiff::Diff),
+//! so a sender does not need to know how the receiver will deserialize.
+//! `String` and `Vec<u8>` fields, and the splices and literals of `text` and
+//! `binary_delta` fields, are kept as slices of the input buffer until they
+//! are applied. All other fields are deserialized as their owned diffs.
+
+use std::borrow::Cow;
+
+/// A string deserialized from an input buffer, borrowed where the format
+/// allows it (e.g. no escape sequences had to be decoded).
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct BorrowedStr<'de>(Cow<'de, str>);
+
+impl<'de> BorrowedStr<'de> {
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+
+    /// Whether the contents are borrowed from the input buffer
+    pub fn is_borrowed(&self) -> bool {
+        matches!(self.0, Cow::Borrowed(_))
+    }
+
+    /// Overwrite `target` with the contents, reusing its allocation when they
+    /// are borrowed
+    pub fn assign_to(self, target: &mut String) {
+        match self.0 {
+            Cow::Borrowed(value) => {
+                target.clear();
+                target.push_str(value);
+            }
+            Cow::Owned(value) => *target = value,
+        }
+    }
+}
+
+impl<'de> From<&'de str> for BorrowedStr<'de> {
+    fn from(value: &'de str) -> Self {
+        Self(Cow::Borrowed(value))
+    }
+}
+
+impl From<BorrowedStr<'_>> for String {
+    fn from(value: BorrowedStr<'_>) -> Self {
+        value.0.into_owned()
+    }
+}
+
+/// A byte buffer deserialized from an input buffer, borrowed where the
+/// format allows it.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct BorrowedBytes<'de>(Cow<'de, [u8]>);
+
+impl<'de> BorrowedBytes<'de> {
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.0
+    }
+
+    /// Whether the contents are borrowed from the input buffer
+    pub fn is_borrowed(&self) -> bool {
+        matches!(self.0, Cow::Borrowed(_))
+    }
+
+    /// Overwrite `target` with the contents, reusing its allocation when they
+    /// are borrowed
+    pub fn assign_to(self, target: &mut Vec<u8>) {
+        match self.0 {
+            Cow::Borrowed(value) => {
+                target.clear();
+                target.extend_from_slice(value);
+            }
+            Cow::Owned(value) => *target = value,
+        }
+    }
+}
+
+impl<'de> From<&'de [u8]> for BorrowedBytes<'de> {
+    fn from(value: &'de [u8]) -> Self {
+        Self(Cow::Borrowed(value))
+    }
+}
+
+impl From<BorrowedBytes<'_>> for Vec<u8> {
+    fn from(value: BorrowedBytes<'_>) -> Self {
+        value.0.into_owned()
+    }
+}
+
+/// Bound required of every [`StructDiffBorrowed::DiffBorrowed`](crate::StructDiffBorrowed::DiffBorrowed) type.
+///
+/// Implemented for every `Clone` type which also implements `Deserialize<'de>`
+/// with the `serde` feature, and [`DeBinBorrowed<'de>`] with the `nanoserde`
+/// feature.
+pub use __private::DiffBorrowedBound;
+
+#[cfg(all(feature = "serde", feature = "nanoserde"))]
+mod __private {
+    pub trait DiffBorrowedBound<'de>:
+        serde::Deserialize<'de> + super::DeBinBorrowed<'de> + Clone
+    {
+    }
+    impl<'de, T: serde::Deserialize<'de> + super::DeBinBorrowed<'de> + Clone> DiffBorrowedBound<'de>
+        for T
+    {
+    }
+}
+
+#[cfg(all(feature = "serde", not(feature = "nanoserde")))]
+mod __private {
+    pub trait DiffBorrowedBound<'de>: serde::Deserialize<'de> + Clone {}
+    impl<'de, T: serde::Deserialize<'de> + Clone> DiffBorrowedBound<'de> for T {}
+}
+
+#[cfg(all(feature = "nanoserde", not(feature = "serde")))]
+mod __private {
+    pub trait DiffBorrowedBound<'de>: super::DeBinBorrowed<'de> + Clone {}
+    impl<'de, T: super::DeBinBorrowed<'de> + Clone> DiffBorrowedBound<'de> for T {}
+}
+
+#[cfg(feature = "serde")]
+mod serde_impls {
+    use super::*;
+    use serde::de::{Deserialize, Deserializer, Error, SeqAccess, Visitor};
+
+    struct StrVisitor;
+
+    impl<'de> Visitor<'de> for StrVisitor {
+        type Value = BorrowedStr<'de>;
+
+        fn expecting(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
+            formatter.write_str("a string")
+        }
+
+        fn visit_borrowed_str<E: Error>(self, v: &'de str) -> Result<Self::Value, E> {
+            Ok(BorrowedStr(Cow::Borrowed(v)))
+        }
+
+        fn visit_str<E: Error>(self, v: &str) -> Result<Self::Value, E> {
+            Ok(BorrowedStr(Cow::Owned(v.to_owned())))
+        }
+
+        fn visit_string<E: Error>(self, v: String) -> Result<Self::Value, E> {
+            Ok(BorrowedStr(Cow::Owned(v)))
+        }
+    }
+
+    impl<'de: 'a, 'a> Deserialize<'de> for BorrowedStr<'a> {
+        fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+            deserializer.deserialize_str(StrVisitor)
+        }
+    }
+
+    struct BytesVisitor;
+
+    impl<'de> Visitor<'de> for BytesVisitor {
+        type Value = BorrowedBytes<'de>;
+
+        fn expecting(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
+            formatter.write_str("a byte array")
+        }
+
+        fn visit_borrowed_bytes<E: Error>(self, v: &'de [u8]) -> Result<Self::Value, E> {
+            Ok(BorrowedBytes(Cow::Borrowed(v)))
+        }
+
+        fn visit_bytes<E: Error>(self, v: &[u8]) -> Result<Self::Value, E> {
+            Ok(BorrowedBytes(Cow::Owned(v.to_vec())))
+        }
+
+        fn visit_byte_buf<E: Error>(self, v: Vec<u8>) -> Result<Self::Value, E> {
+            Ok(BorrowedBytes(Cow::Owned(v)))
+        }
+
+        // `Vec<u8>` serializes as a sequence, which self-describing formats
+        // hand back element by element
+        fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Self::Value, A::Error> {
+            let mut ret = Vec::with_capacity(seq.size_hint().unwrap_or(0));
+            while let Some(byte) = seq.next_element()? {
+                ret.push(byte);
+            }
+            Ok(BorrowedBytes(Cow::Owned(ret)))
+        }
+    }
+
+    impl<'de: 'a, 'a> Deserialize<'de> for BorrowedBytes<'a> {
+        fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+            deserializer.deserialize_bytes(BytesVisitor)
+        }
+    }
+}
+
+#[cfg(feature = "nanoserde")]
+pub(crate) use nanoserde_impls::de_bin_vec;
+#[cfg(feature = "nanoserde")]
+pub use nanoserde_impls::{deserialize_bin_borrowed, DeBinBorrowed};
+
+#[cfg(feature = "nanoserde")]
+mod nanoserde_impls {
+    use super::*;
+    use nanoserde::{DeBin, DeBinErr};
+
+    /// Binary deserialization which may borrow from the input buffer.
+    ///
+    /// Implemented for every [`DeBin`] type, and for the borrowed diff
+    /// payloads, which read the same encoding as their owned counterparts.
+    pub trait DeBinBorrowed<'de>: Sized {
+        fn de_bin_borrowed(offset: &mut usize, bytes: &'de [u8]) -> Result<Self, DeBinErr>;
+    }
+
+    impl<'de, T: DeBin> DeBinBorrowed<'de> for T {
+        fn de_bin_borrowed(offset: &mut usize, bytes: &'de [u8]) -> Result<Self, DeBinErr> {
+            T::de_bin(offset, bytes)
+        }
+    }
+
+    /// Read a length-prefixed slice, in the encoding of `Vec<u8>` and `String`
+    fn slice<'de>(offset: &mut usize, bytes: &'de [u8]) -> Result<&'de [u8], DeBinErr> {
+        let len = <usize as DeBin>::de_bin(offset, bytes)?;
+        let ret = offset
+            .checked_add(len)
+            .and_then(|end| bytes.get(*offset..end))
+            .ok_or(DeBinErr {
+                o: *offset,
+                l: len,
+                s: bytes.len(),
+            })?;
+        *offset += len;
+        Ok(ret)
+    }
+
+    impl<'de> DeBinBorrowed<'de> for BorrowedStr<'de> {
+        fn de_bin_borrowed(offset: &mut usize, bytes: &'de [u8]) -> Result<Self, DeBinErr> {
+            let start = *offset;
+            let slice = slice(offset, bytes)?;
+            std::str::from_utf8(slice)
+                .map(|value| BorrowedStr(Cow::Borrowed(value)))
+                .map_err(|_| DeBinErr {
+                    o: start,
+                    l: *offset - start,
+                    s: bytes.len(),
+                })
+        }
+    }
+
+    impl<'de> DeBinBorrowed<'de> for BorrowedBytes<'de> {
+        fn de_bin_borrowed(offset: &mut usize, bytes: &'de [u8]) -> Result<Self, DeBinErr> {
+            slice(offset, bytes).map(|value| BorrowedBytes(Cow::Borrowed(value)))
+        }
+    }
+
+    /// Read a length-prefixed list of borrowed values, such as the output of
+    /// `SerBin::serialize_bin` on a `Vec` of diffs.
+    pub fn deserialize_bin_borrowed<'de, T: DeBinBorrowed<'de>>(
+        bytes: &'de [u8],
+    ) -> Result<Vec<T>, DeBinErr> {
+        let mut offset = 0;
+        de_bin_vec(&mut offset, bytes)
+    }
+
+    pub(crate) fn de_bin_vec<'de, T: DeBinBorrowed<'de>>(
+        offset: &mut usize,
+        bytes: &'de [u8],
+    ) -> Result<Vec<T>, DeBinErr> {
+        let len = <usize as DeBin>::de_bin(offset, bytes)?;
+        let mut ret = Vec::with_capacity(crate::checked::capacity(len, *offset, bytes));
+        for _ in 0..len {
+            ret.push(T::de_bin_borrowed(offset, bytes)?);
+        }
+        Ok(ret)
+    }
+}
@@ -0,0 +1,220 @@
+//! Binary deserialization of untrusted diffs.
+//!
+//! `nanoserde` allocates the length prefix of a `Vec` up front and panics
+//! on a `String` which is not UTF-8, so a malformed or hostile buffer can
+//! abort the receiver. The diffs in this crate, and those generated by
+//! `#[derive(Difference)]`, read their collections and strings with the
+//! functions here instead, which return a [`DeBinErr`] for such input.
+//! Use [`deserialize_bin`] in place of `Vec::<T::Diff>::deserialize_bin`
+//! to read the list of diffs itself the same way.
+//!
+//! Values carried by a diff, such as the items inserted into a collection or
+//! the replacement for a field, are read with [`DeBinChecked`]. It reads
+//! strings and collections the same way, and is implemented for the standard
+//! library types nanoserde supports, for enums with `#[derive(Difference)]`,
+//! and for structs with `#[difference(de_bin_checked)]`. Other types can
+//! implement it with no methods, to be read with their own `DeBin`.
+//!
+//! ```
+//! use nanoserde::{DeBin, SerBin};
+//! use structdiff::{Difference, StructDiff};
+//!
+//! #[derive(Debug, PartialEq, Clone, Difference, SerBin, DeBin)]
+//! struct Example {
+//!     name: String,
+//!     #[difference(collection_strategy = "ordered_array_like")]
+//!     values: Vec<u32>,
+//! }
+//!
+//! let first = Example { name: "first".into(), values: vec![1, 2] };
+//! let second = Example { name: "second".into(), values: vec![2, 3] };
+//! let bytes = first.diff(&second).serialize_bin();
+//!
+//! let diffs = structdiff::checked::deserialize_bin(&bytes).unwrap();
+//! assert_eq!(first.clone().apply(diffs), second);
+//!
+//! // a list claiming usize::MAX diffs
+//! let hostile = [0xff; 8];
+//! assert!(structdiff::checked::deserialize_bin::<<Example as StructDiff>::Diff>(&hostile).is_err());
+//! ```
+
+use std::{
+    collections::{BTreeSet, HashMap, HashSet, LinkedList},
+    hash::Hash,
+};
+
+use nanoserde::{DeBin, DeBinErr};
+
+/// Binary deserialization which returns an error for malformed input instead
+/// of panicking or allocating more than the input could hold.
+///
+/// The default method reads the value with its `DeBin` implementation, which
+/// is enough for types without strings or collections, such as fieldless
+/// enums or structs of numbers.
+pub trait DeBinChecked: DeBin {
+    fn de_bin_checked(offset: &mut usize, bytes: &[u8]) -> Result<Self, DeBinErr> {
+        Self::de_bin(offset, bytes)
+    }
+}
+
+/// Read a length-prefixed list of values, such as the output of
+/// `SerBin::serialize_bin` on a `Vec` of diffs, without trusting the length
+pub fn deserialize_bin<T: DeBin>(bytes: &[u8]) -> Result<Vec<T>, DeBinErr> {
+    let mut offset = 0;
+    de_bin_vec(&mut offset, bytes)
+}
+
+/// Read a `Vec`, in the encoding of its `SerBin` implementation
+pub fn de_bin_vec<T: DeBin>(offset: &mut usize, bytes: &[u8]) -> Result<Vec<T>, DeBinErr> {
+    de_bin_vec_with(offset, bytes, T::de_bin)
+}
+
+/// Read a `Vec` in the encoding of its `SerBin` implementation, reading each
+/// element with `read`
+pub fn de_bin_vec_with<T>(
+    offset: &mut usize,
+    bytes: &[u8],
+    mut read: impl FnMut(&mut usize, &[u8]) -> Result<T, DeBinErr>,
+) -> Result<Vec<T>, DeBinErr> {
+    let len = <usize as DeBin>::de_bin(offset, bytes)?;
+    let mut ret = Vec::with_capacity(capacity(len, *offset, bytes));
+    for _ in 0..len {
+        ret.push(read(offset, bytes)?);
+    }
+    Ok(ret)
+}
+
+/// Read an `Option` in the encoding of its `SerBin` implementation, reading
+/// the contents with `read`
+pub fn de_bin_option_with<T>(
+    offset: &mut usize,
+    bytes: &[u8],
+    read: impl FnOnce(&mut usize, &[u8]) -> Result<T, DeBinErr>,
+) -> Result<Option<T>, DeBinErr> {
+    match <u8 as DeBin>::de_bin(offset, bytes)? {
+        1 => read(offset, bytes).map(Some),
+        _ => Ok(None),
+    }
+}
+
+/// Read a `String`, in the encoding of its `SerBin` implementation
+pub fn de_bin_string(offset: &mut usize, bytes: &[u8]) -> Result<String, DeBinErr> {
+    let start = *offset;
+    let len = <usize as DeBin>::de_bin(offset, bytes)?;
+    let slice = offset
+        .checked_add(len)
+        .and_then(|end| bytes.get(*offset..end))
+        .ok_or(DeBinErr {
+            o: *offset,
+            l: len,
+            s: bytes.len(),
+        })?;
+    let ret = std::str::from_utf8(slice).map_err(|_| DeBinErr {
+        o: start,
+        l: *offset + len - start,
+        s: bytes.len(),
+    })?;
+    *offset += len;
+    Ok(ret.to_owned())
+}
+
+macro_rules! default_impls {
+    ($($ty:ty),* $(,)?) => {$(
+        impl DeBinChecked for $ty {}
+    )*};
+}
+
+default_impls!(
+    bool,
+    u8,
+    u16,
+    u32,
+    u64,
+    u128,
+    usize,
+    i8,
+    i16,
+    i32,
+    i64,
+    i128,
+    f32,
+    f64,
+    ()
+);
+
+impl DeBinChecked for String {
+    fn de_bin_checked(offset: &mut usize, bytes: &[u8]) -> Result<Self, DeBinErr> {
+        de_bin_string(offset, bytes)
+    }
+}
+
+impl<T: DeBinChecked> DeBinChecked for Box<T> {
+    fn de_bin_checked(offset: &mut usize, bytes: &[u8]) -> Result<Self, DeBinErr> {
+        T::de_bin_checked(offset, bytes).map(Box::new)
+    }
+}
+
+impl<T: DeBinChecked> DeBinChecked for Option<T> {
+    fn de_bin_checked(offset: &mut usize, bytes: &[u8]) -> Result<Self, DeBinErr> {
+        de_bin_option_with(offset, bytes, T::de_bin_checked)
+    }
+}
+
+impl<T: DeBinChecked> DeBinChecked for Vec<T> {
+    fn de_bin_checked(offset: &mut usize, bytes: &[u8]) -> Result<Self, DeBinErr> {
+        de_bin_vec_with(offset, bytes, T::de_bin_checked)
+    }
+}
+
+impl<T: DeBinChecked> DeBinChecked for LinkedList<T> {
+    fn de_bin_checked(offset: &mut usize, bytes: &[u8]) -> Result<Self, DeBinErr> {
+        de_bin_vec_with(offset, bytes, T::de_bin_checked).map(LinkedList::from_iter)
+    }
+}
+
+impl<T: DeBinChecked + Hash + Eq> DeBinChecked for HashSet<T> {
+    fn de_bin_checked(offset: &mut usize, bytes: &[u8]) -> Result<Self, DeBinErr> {
+        de_bin_vec_with(offset, bytes, T::de_bin_checked).map(HashSet::from_iter)
+    }
+}
+
+impl<T: DeBinChecked + Ord> DeBinChecked for BTreeSet<T> {
+    fn de_bin_checked(offset: &mut usize, bytes: &[u8]) -> Result<Self, DeBinErr> {
+        de_bin_vec_with(offset, bytes, T::de_bin_checked).map(BTreeSet::from_iter)
+    }
+}
+
+impl<K: DeBinChecked + Hash + Eq, V: DeBinChecked> DeBinChecked for HashMap<K, V> {
+    fn de_bin_checked(offset: &mut usize, bytes: &[u8]) -> Result<Self, DeBinErr> {
+        de_bin_vec_with(offset, bytes, <(K, V)>::de_bin_checked).map(HashMap::from_iter)
+    }
+}
+
+impl<T: DeBinChecked, const N: usize> DeBinChecked for [T; N] {
+    fn de_bin_checked(offset: &mut usize, bytes: &[u8]) -> Result<Self, DeBinErr> {
+        let mut ret = Vec::with_capacity(N);
+        for _ in 0..N {
+            ret.push(T::de_bin_checked(offset, bytes)?);
+        }
+        Ok(ret.try_into().unwrap_or_else(|_| unreachable!()))
+    }
+}
+
+macro_rules! tuple_impls {
+    ($(($($name:ident),+)),* $(,)?) => {$(
+        impl<$($name: DeBinChecked),+> DeBinChecked for ($($name,)+) {
+            fn de_bin_checked(offset: &mut usize, bytes: &[u8]) -> Result<Self, DeBinErr> {
+                Ok(($($name::de_bin_checked(offset, bytes)?,)+))
+            }
+        }
+    )*};
+}
+
+tuple_impls!((A, B), (A, B, C), (A, B, C, D));
+
+/// The number of elements to preallocate for a collection with a length
+/// prefix of `len`. Every element this crate encodes takes at least one
+/// byte, so larger lengths are not trusted.
+pub(crate) fn capacity(len: usize, offset: usize, bytes: &[u8]) -> usize {
+    len.min(bytes.len().saturating_sub(offset))
+}
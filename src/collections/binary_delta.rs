@@ -0,0 +1,695 @@
+use std::borrow::Cow;
+
+#[cfg(any(feature = "serde", feature = "nanoserde"))]
+use crate::borrowed::BorrowedBytes;
+use crate::{
+    limits::{ApplyBudget, LimitExceeded},
+    size::{EncodedSize, TAG},
+};
+
+#[cfg(not(feature = "rustc_hash"))]
+type HashMap<K, V> = std::collections::HashMap<K, V>;
+#[cfg(feature = "rustc_hash")]
+type HashMap<K, V> =
+    std::collections::HashMap<K, V, std::hash::BuildHasherDefault<rustc_hash::FxHasher>>;
+
+const MIN_BLOCK_SIZE: usize = 16;
+const MAX_BLOCK_SIZE: usize = 4096;
+
+/// A single instruction for rebuilding the target buffer
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub(crate) enum BinaryDeltaInstructionRef<'src> {
+    /// Copy `len` bytes from `offset` in the original buffer
+    Copy { offset: usize, len: usize },
+    /// Insert literal bytes
+    Insert(&'src [u8]),
+}
+
+/// A single instruction for rebuilding the target buffer
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub(crate) enum BinaryDeltaInstructionOwned {
+    /// Copy `len` bytes from `offset` in the original buffer
+    Copy { offset: usize, len: usize },
+    /// Insert literal bytes
+    Insert(Vec<u8>),
+}
+
+impl<'src> From<BinaryDeltaInstructionRef<'src>> for BinaryDeltaInstructionOwned {
+    fn from(value: BinaryDeltaInstructionRef<'src>) -> Self {
+        match value {
+            BinaryDeltaInstructionRef::Copy { offset, len } => Self::Copy { offset, len },
+            BinaryDeltaInstructionRef::Insert(bytes) => Self::Insert(bytes.to_vec()),
+        }
+    }
+}
+
+/// A single instruction for rebuilding the target buffer
+#[cfg(any(feature = "serde", feature = "nanoserde"))]
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename = "BinaryDeltaInstructionOwned"))]
+pub(crate) enum BinaryDeltaInstructionBorrowed<'src> {
+    /// Copy `len` bytes from `offset` in the original buffer
+    Copy { offset: usize, len: usize },
+    /// Insert literal bytes
+    Insert(#[cfg_attr(feature = "serde", serde(borrow))] BorrowedBytes<'src>),
+}
+
+#[cfg(any(feature = "serde", feature = "nanoserde"))]
+impl<'src> From<BinaryDeltaInstructionBorrowed<'src>> for BinaryDeltaInstructionOwned {
+    fn from(value: BinaryDeltaInstructionBorrowed<'src>) -> Self {
+        match value {
+            BinaryDeltaInstructionBorrowed::Copy { offset, len } => Self::Copy { offset, len },
+            BinaryDeltaInstructionBorrowed::Insert(bytes) => Self::Insert(bytes.into()),
+        }
+    }
+}
+
+/// Copy and insert instructions which rebuild the target buffer from the original,
+/// in the style of rsync/VCDIFF deltas.
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct BinaryDeltaOwned(Vec<BinaryDeltaInstructionOwned>);
+
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct BinaryDeltaRef<'src>(Vec<BinaryDeltaInstructionRef<'src>>);
+
+impl<'src> From<BinaryDeltaRef<'src>> for BinaryDeltaOwned {
+    fn from(value: BinaryDeltaRef<'src>) -> Self {
+        Self(value.0.into_iter().map(Into::into).collect())
+    }
+}
+
+/// A [`BinaryDeltaOwned`] deserialized without copying its literal bytes out
+/// of the input buffer
+#[cfg(any(feature = "serde", feature = "nanoserde"))]
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename = "BinaryDeltaOwned"))]
+pub struct BinaryDeltaBorrowed<'src>(
+    #[cfg_attr(feature = "serde", serde(borrow))] Vec<BinaryDeltaInstructionBorrowed<'src>>,
+);
+
+#[cfg(any(feature = "serde", feature = "nanoserde"))]
+impl<'src> From<BinaryDeltaBorrowed<'src>> for BinaryDeltaOwned {
+    fn from(value: BinaryDeltaBorrowed<'src>) -> Self {
+        Self(value.0.into_iter().map(Into::into).collect())
+    }
+}
+
+impl EncodedSize for BinaryDeltaInstructionRef<'_> {
+    fn encoded_size(&self) -> usize {
+        TAG + match self {
+            Self::Copy { offset, len } => offset.encoded_size() + len.encoded_size(),
+            Self::Insert(bytes) => bytes.encoded_size(),
+        }
+    }
+}
+
+impl EncodedSize for BinaryDeltaInstructionOwned {
+    fn encoded_size(&self) -> usize {
+        TAG + match self {
+            Self::Copy { offset, len } => offset.encoded_size() + len.encoded_size(),
+            Self::Insert(bytes) => bytes.encoded_size(),
+        }
+    }
+}
+
+impl EncodedSize for BinaryDeltaRef<'_> {
+    fn encoded_size(&self) -> usize {
+        self.0.encoded_size()
+    }
+}
+
+impl EncodedSize for BinaryDeltaOwned {
+    fn encoded_size(&self) -> usize {
+        self.0.encoded_size()
+    }
+}
+
+impl BinaryDeltaOwned {
+    /// Number of literal bytes carried by this delta
+    pub fn inserted_len(&self) -> usize {
+        self.0
+            .iter()
+            .map(|instruction| match instruction {
+                BinaryDeltaInstructionOwned::Copy { .. } => 0,
+                BinaryDeltaInstructionOwned::Insert(bytes) => bytes.len(),
+            })
+            .sum()
+    }
+}
+
+/// Adler-32 style checksum over a fixed size window, which can be moved forward
+/// one byte at a time
+struct RollingHash {
+    a: u32,
+    b: u32,
+    window: u32,
+}
+
+impl RollingHash {
+    fn new(window: &[u8]) -> Self {
+        let mut a: u32 = 0;
+        let mut b: u32 = 0;
+        for byte in window {
+            a = a.wrapping_add(*byte as u32);
+            b = b.wrapping_add(a);
+        }
+        Self {
+            a,
+            b,
+            window: window.len() as u32,
+        }
+    }
+
+    #[inline]
+    fn roll(&mut self, outgoing: u8, incoming: u8) {
+        self.a = self
+            .a
+            .wrapping_sub(outgoing as u32)
+            .wrapping_add(incoming as u32);
+        self.b = self
+            .b
+            .wrapping_sub(self.window.wrapping_mul(outgoing as u32))
+            .wrapping_add(self.a);
+    }
+
+    #[inline]
+    fn value(&self) -> u32 {
+        (self.b << 16) | (self.a & 0xffff)
+    }
+}
+
+fn block_size(source_len: usize) -> usize {
+    ((source_len as f64).sqrt() as usize).clamp(MIN_BLOCK_SIZE, MAX_BLOCK_SIZE)
+}
+
+/// A delta which inserts all of `target`, copying nothing from the original
+pub fn replacement<'target>(target: &'target [u8]) -> BinaryDeltaRef<'target> {
+    match target.is_empty() {
+        true => BinaryDeltaRef(Vec::new()),
+        false => BinaryDeltaRef(vec![BinaryDeltaInstructionRef::Insert(target)]),
+    }
+}
+
+/// Generate the instructions needed to turn `source` into `target`, or `None` if they are equal
+pub fn binary_delta<'target>(
+    target: &'target [u8],
+    source: &[u8],
+) -> Option<BinaryDeltaRef<'target>> {
+    if target == source {
+        return None;
+    }
+
+    let block = block_size(source.len());
+    let mut instructions = Vec::new();
+
+    if source.len() < block || target.len() < block {
+        instructions.push(BinaryDeltaInstructionRef::Insert(target));
+        return Some(BinaryDeltaRef(instructions));
+    }
+
+    // index every full block of the source by its weak hash
+    let mut blocks: HashMap<u32, Vec<usize>> = HashMap::default();
+    for offset in (0..=source.len() - block).step_by(block) {
+        blocks
+            .entry(RollingHash::new(&source[offset..offset + block]).value())
+            .or_default()
+            .push(offset);
+    }
+
+    let mut literal_start = 0;
+    let mut idx = 0;
+    let mut hash = RollingHash::new(&target[..block]);
+    while idx + block <= target.len() {
+        let found = blocks.get(&hash.value()).and_then(|offsets| {
+            offsets
+                .iter()
+                .copied()
+                .find(|offset| source[*offset..*offset + block] == target[idx..idx + block])
+        });
+
+        let Some(offset) = found else {
+            if idx + block == target.len() {
+                break;
+            }
+            hash.roll(target[idx], target[idx + block]);
+            idx += 1;
+            continue;
+        };
+
+        // grow the match in both directions past the block boundaries
+        let mut len = block;
+        while idx + len < target.len()
+            && offset + len < source.len()
+            && target[idx + len] == source[offset + len]
+        {
+            len += 1;
+        }
+        let mut back = 0;
+        while idx - back > literal_start
+            && offset - back > 0
+            && target[idx - back - 1] == source[offset - back - 1]
+        {
+            back += 1;
+        }
+
+        if literal_start < idx - back {
+            instructions.push(BinaryDeltaInstructionRef::Insert(
+                &target[literal_start..idx - back],
+            ));
+        }
+        match instructions.last_mut() {
+            Some(BinaryDeltaInstructionRef::Copy {
+                offset: prev_offset,
+                len: prev_len,
+            }) if *prev_offset + *prev_len == offset - back => *prev_len += len + back,
+            _ => instructions.push(BinaryDeltaInstructionRef::Copy {
+                offset: offset - back,
+                len: len + back,
+            }),
+        }
+
+        idx += len;
+        literal_start = idx;
+        if idx + block <= target.len() {
+            hash = RollingHash::new(&target[idx..idx + block]);
+        }
+    }
+
+    if literal_start < target.len() {
+        instructions.push(BinaryDeltaInstructionRef::Insert(&target[literal_start..]));
+    }
+
+    Some(BinaryDeltaRef(instructions))
+}
+
+impl BinaryDeltaInstructionOwned {
+    fn borrowed(&self) -> BinaryDeltaInstructionRef<'_> {
+        match self {
+            Self::Copy { offset, len } => BinaryDeltaInstructionRef::Copy {
+                offset: *offset,
+                len: *len,
+            },
+            Self::Insert(bytes) => BinaryDeltaInstructionRef::Insert(bytes),
+        }
+    }
+}
+
+/// Copies outside of `existing` can only come from a delta generated against
+/// a different buffer, so they are truncated to the bytes that exist
+fn copied(existing: &[u8], offset: usize, len: usize) -> &[u8] {
+    let start = offset.min(existing.len());
+    &existing[start..start + len.min(existing.len() - start)]
+}
+
+/// The length of the buffer produced by applying `instructions` to `existing`
+fn applied_len<'a>(
+    instructions: impl Iterator<Item = BinaryDeltaInstructionRef<'a>>,
+    existing: &[u8],
+) -> usize {
+    instructions
+        .map(|instruction| match instruction {
+            BinaryDeltaInstructionRef::Copy { offset, len } => copied(existing, offset, len).len(),
+            BinaryDeltaInstructionRef::Insert(bytes) => bytes.len(),
+        })
+        .fold(0, usize::saturating_add)
+}
+
+fn apply_to_slice<'a>(
+    instructions: impl Iterator<Item = BinaryDeltaInstructionRef<'a>> + Clone,
+    existing: &[u8],
+) -> Vec<u8> {
+    let mut ret = Vec::with_capacity(applied_len(instructions.clone(), existing));
+    for instruction in instructions {
+        match instruction {
+            BinaryDeltaInstructionRef::Copy { offset, len } => {
+                ret.extend_from_slice(copied(existing, offset, len))
+            }
+            BinaryDeltaInstructionRef::Insert(bytes) => ret.extend_from_slice(bytes),
+        }
+    }
+    ret
+}
+
+/// Byte buffers which a [`BinaryDeltaOwned`] can be applied to
+pub trait BinaryLike {
+    fn apply_binary_delta(&mut self, changes: BinaryDeltaOwned);
+
+    /// Apply a [`BinaryDeltaRef`] without first copying its literal bytes
+    /// into an owned delta
+    fn apply_binary_delta_ref(&mut self, changes: BinaryDeltaRef<'_>) {
+        self.apply_binary_delta(changes.into())
+    }
+}
+
+pub fn apply_in_place<B: BinaryLike>(changes: impl Into<BinaryDeltaOwned>, existing: &mut B) {
+    existing.apply_binary_delta(changes.into())
+}
+
+/// Apply `changes` as [`apply_in_place`] does, after charging the bytes by
+/// which they grow `existing` to `budget`. Copies can repeat any part of
+/// the buffer, so a short delta may still produce a large one.
+pub fn apply_in_place_limited<B: BinaryLike + AsRef<[u8]>>(
+    changes: impl Into<BinaryDeltaOwned>,
+    existing: &mut B,
+    budget: &mut ApplyBudget,
+) -> Result<(), LimitExceeded> {
+    let changes = changes.into();
+    let existing_bytes = existing.as_ref();
+    let len = applied_len(
+        changes.0.iter().map(BinaryDeltaInstructionOwned::borrowed),
+        existing_bytes,
+    );
+    budget.grow(len.saturating_sub(existing_bytes.len()))?;
+    existing.apply_binary_delta(changes);
+    Ok(())
+}
+
+pub fn apply_ref_in_place<B: BinaryLike>(changes: BinaryDeltaRef<'_>, existing: &mut B) {
+    existing.apply_binary_delta_ref(changes)
+}
+
+#[cfg(any(feature = "serde", feature = "nanoserde"))]
+pub fn apply_borrowed_in_place<B: BinaryLike>(changes: BinaryDeltaBorrowed<'_>, existing: &mut B) {
+    let instructions = changes
+        .0
+        .iter()
+        .map(|instruction| match instruction {
+            BinaryDeltaInstructionBorrowed::Copy { offset, len } => {
+                BinaryDeltaInstructionRef::Copy {
+                    offset: *offset,
+                    len: *len,
+                }
+            }
+            BinaryDeltaInstructionBorrowed::Insert(bytes) => {
+                BinaryDeltaInstructionRef::Insert(bytes.as_bytes())
+            }
+        })
+        .collect();
+    existing.apply_binary_delta_ref(BinaryDeltaRef(instructions))
+}
+
+impl BinaryLike for Vec<u8> {
+    fn apply_binary_delta(&mut self, changes: BinaryDeltaOwned) {
+        *self = apply_to_slice(
+            changes.0.iter().map(BinaryDeltaInstructionOwned::borrowed),
+            self,
+        );
+    }
+
+    fn apply_binary_delta_ref(&mut self, changes: BinaryDeltaRef<'_>) {
+        *self = apply_to_slice(changes.0.into_iter(), self);
+    }
+}
+
+impl BinaryLike for Box<[u8]> {
+    fn apply_binary_delta(&mut self, changes: BinaryDeltaOwned) {
+        *self = apply_to_slice(
+            changes.0.iter().map(BinaryDeltaInstructionOwned::borrowed),
+            self,
+        )
+        .into_boxed_slice();
+    }
+
+    fn apply_binary_delta_ref(&mut self, changes: BinaryDeltaRef<'_>) {
+        *self = apply_to_slice(changes.0.into_iter(), self).into_boxed_slice();
+    }
+}
+
+impl BinaryLike for Cow<'_, [u8]> {
+    fn apply_binary_delta(&mut self, changes: BinaryDeltaOwned) {
+        *self = Cow::Owned(apply_to_slice(
+            changes.0.iter().map(BinaryDeltaInstructionOwned::borrowed),
+            self,
+        ));
+    }
+
+    fn apply_binary_delta_ref(&mut self, changes: BinaryDeltaRef<'_>) {
+        *self = Cow::Owned(apply_to_slice(changes.0.into_iter(), self));
+    }
+}
+
+#[cfg(feature = "nanoserde")]
+mod nanoserde_impls {
+    use super::*;
+    use crate::borrowed::DeBinBorrowed;
+    use nanoserde::{DeBin, SerBin};
+
+    impl BinaryDeltaInstructionOwned {
+        #[inline]
+        fn nanoserde_discriminant(&self) -> u8 {
+            match self {
+                BinaryDeltaInstructionOwned::Copy { .. } => 0,
+                BinaryDeltaInstructionOwned::Insert(_) => 1,
+            }
+        }
+    }
+
+    impl BinaryDeltaInstructionRef<'_> {
+        #[inline]
+        fn nanoserde_discriminant(&self) -> u8 {
+            match self {
+                BinaryDeltaInstructionRef::Copy { .. } => 0,
+                BinaryDeltaInstructionRef::Insert(_) => 1,
+            }
+        }
+    }
+
+    impl SerBin for BinaryDeltaInstructionOwned {
+        fn ser_bin(&self, output: &mut Vec<u8>) {
+            self.nanoserde_discriminant().ser_bin(output);
+            match self {
+                BinaryDeltaInstructionOwned::Copy { offset, len } => {
+                    offset.ser_bin(output);
+                    len.ser_bin(output);
+                }
+                BinaryDeltaInstructionOwned::Insert(bytes) => bytes.ser_bin(output),
+            }
+        }
+    }
+
+    impl SerBin for BinaryDeltaInstructionRef<'_> {
+        fn ser_bin(&self, output: &mut Vec<u8>) {
+            self.nanoserde_discriminant().ser_bin(output);
+            match self {
+                BinaryDeltaInstructionRef::Copy { offset, len } => {
+                    offset.ser_bin(output);
+                    len.ser_bin(output);
+                }
+                BinaryDeltaInstructionRef::Insert(bytes) => {
+                    // matches the encoding of `Vec<u8>`
+                    bytes.len().ser_bin(output);
+                    output.extend_from_slice(bytes);
+                }
+            }
+        }
+    }
+
+    impl DeBin for BinaryDeltaInstructionOwned {
+        fn de_bin(offset: &mut usize, bytes: &[u8]) -> Result<Self, nanoserde::DeBinErr> {
+            match <u8 as DeBin>::de_bin(offset, bytes)? {
+                0 => Ok(BinaryDeltaInstructionOwned::Copy {
+                    offset: <usize as DeBin>::de_bin(offset, bytes)?,
+                    len: <usize as DeBin>::de_bin(offset, bytes)?,
+                }),
+                1 => Ok(BinaryDeltaInstructionOwned::Insert(
+                    crate::checked::de_bin_vec(offset, bytes)?,
+                )),
+                _ => Err(nanoserde::DeBinErr {
+                    o: *offset - 1,
+                    l: 1,
+                    s: 1,
+                }),
+            }
+        }
+    }
+
+    impl SerBin for BinaryDeltaRef<'_> {
+        fn ser_bin(&self, output: &mut Vec<u8>) {
+            self.0.ser_bin(output);
+        }
+    }
+
+    impl SerBin for BinaryDeltaOwned {
+        fn ser_bin(&self, output: &mut Vec<u8>) {
+            self.0.ser_bin(output);
+        }
+    }
+
+    impl DeBin for BinaryDeltaOwned {
+        fn de_bin(offset: &mut usize, bytes: &[u8]) -> Result<Self, nanoserde::DeBinErr> {
+            let ret = crate::checked::de_bin_vec(offset, bytes)?;
+            Ok(Self(ret))
+        }
+    }
+
+    impl<'de> DeBinBorrowed<'de> for BinaryDeltaInstructionBorrowed<'de> {
+        fn de_bin_borrowed(
+            offset: &mut usize,
+            bytes: &'de [u8],
+        ) -> Result<Self, nanoserde::DeBinErr> {
+            match <u8 as DeBin>::de_bin(offset, bytes)? {
+                0 => Ok(BinaryDeltaInstructionBorrowed::Copy {
+                    offset: <usize as DeBin>::de_bin(offset, bytes)?,
+                    len: <usize as DeBin>::de_bin(offset, bytes)?,
+                }),
+                1 => Ok(BinaryDeltaInstructionBorrowed::Insert(
+                    BorrowedBytes::de_bin_borrowed(offset, bytes)?,
+                )),
+                _ => Err(nanoserde::DeBinErr {
+                    o: *offset - 1,
+                    l: 1,
+                    s: 1,
+                }),
+            }
+        }
+    }
+
+    impl<'de> DeBinBorrowed<'de> for BinaryDeltaBorrowed<'de> {
+        fn de_bin_borrowed(
+            offset: &mut usize,
+            bytes: &'de [u8],
+        ) -> Result<Self, nanoserde::DeBinErr> {
+            let ret = crate::borrowed::de_bin_vec(offset, bytes)?;
+            Ok(Self(ret))
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use nanorand::{Rng, WyRand};
+
+    use super::{apply_in_place, binary_delta, BinaryDeltaOwned, RollingHash};
+    use crate as structdiff;
+    use structdiff::{Difference, StructDiff};
+
+    fn random_bytes(rng: &mut WyRand, len: usize) -> Vec<u8> {
+        (0..len).map(|_| rng.generate()).collect()
+    }
+
+    fn roundtrip(source: &[u8], target: &[u8]) -> BinaryDeltaOwned {
+        let delta: BinaryDeltaOwned = binary_delta(target, source)
+            .expect("Buffers should differ")
+            .into();
+        let mut applied = source.to_vec();
+        apply_in_place(delta.clone(), &mut applied);
+        assert_eq!(applied, target);
+        delta
+    }
+
+    #[test]
+    fn test_rolling_hash() {
+        let mut rng = WyRand::new();
+        let bytes = random_bytes(&mut rng, 256);
+        let mut rolling = RollingHash::new(&bytes[..32]);
+        for start in 1..bytes.len() - 32 {
+            rolling.roll(bytes[start - 1], bytes[start + 31]);
+            assert_eq!(
+                rolling.value(),
+                RollingHash::new(&bytes[start..start + 32]).value()
+            );
+        }
+    }
+
+    #[test]
+    fn test_identical() {
+        assert!(binary_delta(&[1, 2, 3], &[1, 2, 3]).is_none());
+    }
+
+    #[test]
+    fn test_small_edits() {
+        let mut rng = WyRand::new();
+        let source = random_bytes(&mut rng, 64 * 1024);
+
+        // overwrite a few bytes in the middle, insert at the front, and truncate the end
+        let mut target = source.clone();
+        target[30_000..30_010].copy_from_slice(&[0; 10]);
+        target.splice(0..0, [1, 2, 3, 4, 5]);
+        target.truncate(60_000);
+
+        let delta = roundtrip(&source, &target);
+        assert!(
+            delta.inserted_len() < 1024,
+            "delta carried {} literal bytes",
+            delta.inserted_len()
+        );
+    }
+
+    #[test]
+    fn test_random_buffers() {
+        let mut rng = WyRand::new();
+        for _ in 0..100 {
+            let source_len = rng.generate_range(0_usize..2048);
+            let source = random_bytes(&mut rng, source_len);
+
+            // build the target from shuffled chunks of the source and some new bytes
+            let mut target = Vec::new();
+            for _ in 0..rng.generate_range(0_usize..8) {
+                match rng.generate::<bool>() {
+                    true if !source.is_empty() => {
+                        let start = rng.generate_range(0..source.len());
+                        let end = rng.generate_range(start..=source.len());
+                        target.extend_from_slice(&source[start..end]);
+                    }
+                    _ => {
+                        let len = rng.generate_range(0_usize..64);
+                        target.extend(random_bytes(&mut rng, len));
+                    }
+                }
+            }
+
+            if source != target {
+                roundtrip(&source, &target);
+            }
+        }
+    }
+
+    #[test]
+    fn test_collection_strategy() {
+        #[derive(Debug, PartialEq, Clone, Difference, Default)]
+        #[difference(setters)]
+        struct Blob {
+            #[difference(collection_strategy = "binary_delta")]
+            data: Vec<u8>,
+            #[difference(collection_strategy = "binary_delta")]
+            thumbnail: Box<[u8]>,
+        }
+
+        let mut rng = WyRand::new();
+        let data = random_bytes(&mut rng, 4096);
+        let first = Blob {
+            data: data.clone(),
+            thumbnail: random_bytes(&mut rng, 8).into_boxed_slice(),
+        };
+
+        let mut second = first.clone();
+        second.data[2048] ^= 0xff;
+        second.thumbnail = random_bytes(&mut rng, 12).into_boxed_slice();
+
+        let diffs = first.diff(&second);
+        assert_eq!(diffs.len(), 2);
+        assert_eq!(first.clone().apply(diffs), second);
+
+        let ref_diffs = first.diff_ref(&second);
+        assert_eq!(
+            first
+                .clone()
+                .apply(ref_diffs.into_iter().map(Into::into).collect()),
+            second
+        );
+
+        #[cfg(feature = "generated_setters")]
+        {
+            let mut third = first.clone();
+            let diff = third.set_data_with_diff(second.data.clone());
+            assert!(diff.is_some());
+            assert_eq!(third.data, second.data);
+        }
+    }
+}
@@ -0,0 +1,971 @@
+//! Conflict-free replicated data types, for fields which are edited on
+//! several replicas at once and merged without a central server.
+//!
+//! Each type is diffed into a delta: the part of the updated state which the
+//! original does not have yet. Applying a delta merges it, so deltas from
+//! any number of replicas can be applied in any order, any number of times,
+//! and every replica which has applied the same deltas holds the same value.
+//! Unlike other diffs, `a.apply(a.diff(&b))` is the merge of `a` and `b`,
+//! which is `b` only when `b` was derived from `a`.
+//!
+//! Use them in a derived struct with `#[difference(collection_strategy = "crdt")]`,
+//! and mark the struct `#[difference(merge)]` to implement [`Merge`] for it
+//! when every field is mergeable.
+//!
+//! Edits are stamped with the id of the replica making them. Ids must be
+//! unique among the replicas editing a value, since edits from two replicas
+//! with the same id may be lost.
+
+use std::collections::{BTreeSet, HashMap};
+use std::hash::Hash;
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    limits::{ApplyBudget, LimitExceeded},
+    DiffValueBound, StructDiff,
+};
+
+/// Identifies the replica which made an edit
+pub type ReplicaId = u64;
+
+/// A unique stamp for one edit: a Lamport counter, with the replica id to
+/// break ties between concurrent edits
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Dot {
+    pub counter: u64,
+    pub replica: ReplicaId,
+}
+
+/// Combine the state of another replica into this one.
+///
+/// Merging is commutative, associative and idempotent.
+pub trait Merge {
+    fn merge(&mut self, other: Self);
+}
+
+/// A value where the latest write wins. Concurrent writes are ordered by
+/// replica id.
+#[derive(Clone, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct LwwRegister<T> {
+    value: T,
+    stamp: Dot,
+}
+
+impl<T> LwwRegister<T> {
+    /// A register holding `value`, created by `replica`, which any write
+    /// replaces. Registers created with different values on different
+    /// replicas merge like concurrent writes, ordered by replica id.
+    pub fn new(value: T, replica: ReplicaId) -> Self {
+        Self {
+            value,
+            stamp: Dot {
+                counter: 0,
+                replica,
+            },
+        }
+    }
+
+    pub fn get(&self) -> &T {
+        &self.value
+    }
+
+    /// The stamp of the write which set the current value
+    pub fn stamp(&self) -> Dot {
+        self.stamp
+    }
+
+    pub fn set(&mut self, value: T, replica: ReplicaId) {
+        self.stamp = Dot {
+            counter: self.stamp.counter + 1,
+            replica,
+        };
+        self.value = value;
+    }
+
+    pub fn into_inner(self) -> T {
+        self.value
+    }
+}
+
+impl<T> Merge for LwwRegister<T> {
+    fn merge(&mut self, other: Self) {
+        if other.stamp > self.stamp {
+            *self = other;
+        }
+    }
+}
+
+impl<T: Clone + DiffValueBound> StructDiff for LwwRegister<T> {
+    type Diff = Self;
+    type DiffRef<'target>
+        = Self
+    where
+        Self: 'target;
+
+    fn diff(&self, updated: &Self) -> Vec<Self::Diff> {
+        match updated.stamp != self.stamp {
+            true => vec![updated.clone()],
+            false => Vec::new(),
+        }
+    }
+
+    fn diff_ref<'target>(&'target self, updated: &'target Self) -> Vec<Self::DiffRef<'target>> {
+        self.diff(updated)
+    }
+
+    fn has_diff(&self, updated: &Self) -> bool {
+        updated.stamp != self.stamp
+    }
+
+    fn apply_single(&mut self, diff: Self::Diff) {
+        self.merge(diff)
+    }
+}
+
+/// An observed-remove set: removing an element only removes the insertions
+/// of it which the removing replica has seen, so a concurrent insertion wins.
+///
+/// Removed insertions are remembered, so that they are not restored by
+/// delayed deltas.
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(
+    feature = "serde",
+    serde(bound(
+        serialize = "T: Serialize + Hash + Eq",
+        deserialize = "T: Deserialize<'de> + Hash + Eq"
+    ))
+)]
+pub struct OrSet<T> {
+    /// the insertions of each element which have not been removed
+    entries: HashMap<T, BTreeSet<Dot>>,
+    removed: BTreeSet<Dot>,
+    clock: u64,
+}
+
+impl<T> Default for OrSet<T> {
+    fn default() -> Self {
+        Self {
+            entries: HashMap::new(),
+            removed: BTreeSet::new(),
+            clock: 0,
+        }
+    }
+}
+
+impl<T: Hash + Eq> PartialEq for OrSet<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.entries == other.entries && self.removed == other.removed && self.clock == other.clock
+    }
+}
+
+impl<T: Hash + Eq> OrSet<T> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn insert(&mut self, value: T, replica: ReplicaId) {
+        self.clock += 1;
+        self.entries.entry(value).or_default().insert(Dot {
+            counter: self.clock,
+            replica,
+        });
+    }
+
+    /// Remove `value`, returning whether it was present
+    pub fn remove(&mut self, value: &T) -> bool {
+        match self.entries.remove(value) {
+            Some(dots) => {
+                self.removed.extend(dots);
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub fn contains(&self, value: &T) -> bool {
+        self.entries.contains_key(value)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &T> {
+        self.entries.keys()
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+}
+
+impl<T: Hash + Eq> Merge for OrSet<T> {
+    fn merge(&mut self, other: Self) {
+        self.clock = self.clock.max(other.clock);
+        for (value, dots) in other.entries {
+            let live = dots
+                .into_iter()
+                .filter(|dot| !self.removed.contains(dot))
+                .collect::<Vec<_>>();
+            if !live.is_empty() {
+                self.entries.entry(value).or_default().extend(live);
+            }
+        }
+        if !other.removed.is_empty() {
+            self.entries.retain(|_, dots| {
+                dots.retain(|dot| !other.removed.contains(dot));
+                !dots.is_empty()
+            });
+            self.removed.extend(other.removed);
+        }
+    }
+}
+
+impl<T: Hash + Eq + Clone + DiffValueBound> StructDiff for OrSet<T> {
+    type Diff = Self;
+    type DiffRef<'target>
+        = Self
+    where
+        Self: 'target;
+
+    fn diff(&self, updated: &Self) -> Vec<Self::Diff> {
+        let entries = updated
+            .entries
+            .iter()
+            .filter_map(|(value, dots)| {
+                let known = self.entries.get(value);
+                let new = dots
+                    .iter()
+                    .filter(|dot| {
+                        !known.is_some_and(|known| known.contains(dot))
+                            && !self.removed.contains(dot)
+                    })
+                    .copied()
+                    .collect::<BTreeSet<_>>();
+                (!new.is_empty()).then(|| (value.clone(), new))
+            })
+            .collect::<HashMap<_, _>>();
+        let removed = updated
+            .removed
+            .difference(&self.removed)
+            .copied()
+            .collect::<BTreeSet<_>>();
+
+        match entries.is_empty() && removed.is_empty() {
+            true => Vec::new(),
+            false => vec![Self {
+                entries,
+                removed,
+                clock: updated.clock,
+            }],
+        }
+    }
+
+    fn diff_ref<'target>(&'target self, updated: &'target Self) -> Vec<Self::DiffRef<'target>> {
+        self.diff(updated)
+    }
+
+    fn has_diff(&self, updated: &Self) -> bool {
+        !updated.removed.is_subset(&self.removed)
+            || updated.entries.iter().any(|(value, dots)| {
+                let known = self.entries.get(value);
+                dots.iter().any(|dot| {
+                    !known.is_some_and(|known| known.contains(dot)) && !self.removed.contains(dot)
+                })
+            })
+    }
+
+    fn apply_single(&mut self, diff: Self::Diff) {
+        self.merge(diff)
+    }
+
+    /// Charges each dot and tombstone in the delta
+    fn apply_single_limited(
+        &mut self,
+        diff: Self::Diff,
+        budget: &mut ApplyBudget,
+    ) -> Result<(), LimitExceeded> {
+        let dots = diff.entries.values().fold(diff.removed.len(), |sum, dots| {
+            sum.saturating_add(dots.len())
+        });
+        budget.grow(dots)?;
+        self.merge(diff);
+        Ok(())
+    }
+}
+
+/// A map where the latest write to each key wins. Removed keys are kept as
+/// tombstones, so that a delayed older write does not restore them.
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(
+    feature = "serde",
+    serde(bound(
+        serialize = "K: Serialize + Hash + Eq, V: Serialize",
+        deserialize = "K: Deserialize<'de> + Hash + Eq, V: Deserialize<'de>"
+    ))
+)]
+pub struct LwwMap<K, V> {
+    entries: HashMap<K, LwwRegister<Option<V>>>,
+    clock: u64,
+}
+
+impl<K, V> Default for LwwMap<K, V> {
+    fn default() -> Self {
+        Self {
+            entries: HashMap::new(),
+            clock: 0,
+        }
+    }
+}
+
+impl<K: Hash + Eq, V: PartialEq> PartialEq for LwwMap<K, V> {
+    fn eq(&self, other: &Self) -> bool {
+        self.entries == other.entries && self.clock == other.clock
+    }
+}
+
+impl<K: Hash + Eq, V> LwwMap<K, V> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn write(&mut self, key: K, value: Option<V>, replica: ReplicaId) {
+        self.clock += 1;
+        let stamp = Dot {
+            counter: self.clock,
+            replica,
+        };
+        self.entries.insert(key, LwwRegister { value, stamp });
+    }
+
+    pub fn insert(&mut self, key: K, value: V, replica: ReplicaId) {
+        self.write(key, Some(value), replica)
+    }
+
+    /// Remove `key`, returning whether it was present
+    pub fn remove(&mut self, key: K, replica: ReplicaId) -> bool {
+        let present = self.contains_key(&key);
+        if present {
+            self.write(key, None, replica);
+        }
+        present
+    }
+
+    pub fn get(&self, key: &K) -> Option<&V> {
+        self.entries.get(key)?.value.as_ref()
+    }
+
+    pub fn contains_key(&self, key: &K) -> bool {
+        self.get(key).is_some()
+    }
+
+    /// The entries which have not been removed
+    pub fn iter(&self) -> impl Iterator<Item = (&K, &V)> {
+        self.entries
+            .iter()
+            .filter_map(|(key, register)| Some((key, register.value.as_ref()?)))
+    }
+
+    pub fn len(&self) -> usize {
+        self.iter().count()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.iter().next().is_none()
+    }
+}
+
+impl<K: Hash + Eq, V> Merge for LwwMap<K, V> {
+    fn merge(&mut self, other: Self) {
+        self.clock = self.clock.max(other.clock);
+        for (key, register) in other.entries {
+            match self.entries.get_mut(&key) {
+                Some(existing) => existing.merge(register),
+                None => {
+                    self.entries.insert(key, register);
+                }
+            }
+        }
+    }
+}
+
+impl<K, V> StructDiff for LwwMap<K, V>
+where
+    K: Hash + Eq + Clone + DiffValueBound,
+    V: Clone + DiffValueBound,
+{
+    type Diff = Self;
+    type DiffRef<'target>
+        = Self
+    where
+        Self: 'target;
+
+    fn diff(&self, updated: &Self) -> Vec<Self::Diff> {
+        let entries = updated
+            .entries
+            .iter()
+            .filter(|(key, register)| {
+                self.entries
+                    .get(key)
+                    .is_none_or(|existing| existing.stamp != register.stamp)
+            })
+            .map(|(key, register)| (key.clone(), register.clone()))
+            .collect::<HashMap<_, _>>();
+
+        match entries.is_empty() {
+            true => Vec::new(),
+            false => vec![Self {
+                entries,
+                clock: updated.clock,
+            }],
+        }
+    }
+
+    fn diff_ref<'target>(&'target self, updated: &'target Self) -> Vec<Self::DiffRef<'target>> {
+        self.diff(updated)
+    }
+
+    fn has_diff(&self, updated: &Self) -> bool {
+        updated.entries.iter().any(|(key, register)| {
+            self.entries
+                .get(key)
+                .is_none_or(|existing| existing.stamp != register.stamp)
+        })
+    }
+
+    fn apply_single(&mut self, diff: Self::Diff) {
+        self.merge(diff)
+    }
+
+    /// Charges each entry in the delta
+    fn apply_single_limited(
+        &mut self,
+        diff: Self::Diff,
+        budget: &mut ApplyBudget,
+    ) -> Result<(), LimitExceeded> {
+        budget.grow(diff.entries.len())?;
+        self.merge(diff);
+        Ok(())
+    }
+}
+
+/// One insertion into an [`Rga`]
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+struct RgaNode<T> {
+    id: Dot,
+    /// the element this one was inserted after, or `None` for the start
+    origin: Option<Dot>,
+    value: T,
+    deleted: bool,
+}
+
+/// A replicated growable array: a sequence where concurrent insertions at
+/// the same position are ordered by their stamps, and the relative order of
+/// existing elements never changes.
+///
+/// Removed elements are kept as tombstones, since later insertions may be
+/// positioned relative to them. Inserting or merging an element takes time
+/// linear in the length of the sequence, including tombstones.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Rga<T> {
+    /// every element, including tombstones, in sequence order
+    nodes: Vec<RgaNode<T>>,
+    /// elements received before the element they were inserted after,
+    /// ordered by id
+    pending: Vec<RgaNode<T>>,
+    clock: u64,
+}
+
+impl<T> Default for Rga<T> {
+    fn default() -> Self {
+        Self {
+            nodes: Vec::new(),
+            pending: Vec::new(),
+            clock: 0,
+        }
+    }
+}
+
+impl<T> Rga<T> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Index into `nodes` of the element at `index` among the elements
+    /// which have not been removed
+    fn node_index(&self, index: usize) -> Option<usize> {
+        self.nodes
+            .iter()
+            .enumerate()
+            .filter(|(_, node)| !node.deleted)
+            .nth(index)
+            .map(|(node_index, _)| node_index)
+    }
+
+    /// Insert `value` at `index`, shifting later elements along.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `index > len`.
+    pub fn insert(&mut self, index: usize, value: T, replica: ReplicaId) {
+        let origin = match index {
+            0 => None,
+            index => {
+                let node_index = self
+                    .node_index(index - 1)
+                    .expect("insertion index is out of bounds");
+                Some(self.nodes[node_index].id)
+            }
+        };
+        self.clock += 1;
+        let node = RgaNode {
+            id: Dot {
+                counter: self.clock,
+                replica,
+            },
+            origin,
+            value,
+            deleted: false,
+        };
+        // the new element has the greatest id, so it always has a position
+        let _ = self.integrate(node);
+    }
+
+    pub fn push(&mut self, value: T, replica: ReplicaId) {
+        self.insert(self.len(), value, replica)
+    }
+
+    /// Remove the element at `index`, shifting later elements back.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `index >= len`.
+    pub fn remove(&mut self, index: usize) {
+        let node_index = self
+            .node_index(index)
+            .expect("removal index is out of bounds");
+        self.nodes[node_index].deleted = true;
+    }
+
+    pub fn get(&self, index: usize) -> Option<&T> {
+        Some(&self.nodes[self.node_index(index)?].value)
+    }
+
+    /// The elements which have not been removed, in order
+    pub fn iter(&self) -> impl Iterator<Item = &T> {
+        self.nodes
+            .iter()
+            .filter(|node| !node.deleted)
+            .map(|node| &node.value)
+    }
+
+    pub fn len(&self) -> usize {
+        self.iter().count()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.iter().next().is_none()
+    }
+
+    /// Place `node` after its origin, and before any later insertions
+    /// after the same origin, or return it if its origin is unknown
+    fn integrate(&mut self, node: RgaNode<T>) -> Result<(), RgaNode<T>> {
+        let mut position = match node.origin {
+            None => 0,
+            Some(origin) => match self.nodes.iter().position(|other| other.id == origin) {
+                Some(origin_index) => origin_index + 1,
+                None => return Err(node),
+            },
+        };
+        // elements inserted after the origin later than this one, or after
+        // those, always have a greater id
+        while position < self.nodes.len() && self.nodes[position].id > node.id {
+            position += 1;
+        }
+        self.clock = self.clock.max(node.id.counter);
+        self.nodes.insert(position, node);
+        Ok(())
+    }
+}
+
+impl<T> Merge for Rga<T> {
+    fn merge(&mut self, other: Self) {
+        self.clock = self.clock.max(other.clock);
+
+        let known: HashMap<Dot, usize> = self
+            .nodes
+            .iter()
+            .enumerate()
+            .map(|(index, node)| (node.id, index))
+            .collect();
+        let mut new = std::mem::take(&mut self.pending);
+        for node in other.nodes.into_iter().chain(other.pending) {
+            match known.get(&node.id) {
+                Some(index) => self.nodes[*index].deleted |= node.deleted,
+                None => match new.iter_mut().find(|pending| pending.id == node.id) {
+                    Some(pending) => pending.deleted |= node.deleted,
+                    None => new.push(node),
+                },
+            }
+        }
+
+        // an element's id is always greater than its origin's, so ascending
+        // order integrates origins first
+        new.sort_by_key(|node| node.id);
+        for node in new {
+            if let Err(node) = self.integrate(node) {
+                self.pending.push(node);
+            }
+        }
+    }
+}
+
+impl<T: Clone + DiffValueBound> StructDiff for Rga<T> {
+    type Diff = Self;
+    type DiffRef<'target>
+        = Self
+    where
+        Self: 'target;
+
+    fn diff(&self, updated: &Self) -> Vec<Self::Diff> {
+        let known: HashMap<Dot, bool> = self
+            .nodes
+            .iter()
+            .chain(&self.pending)
+            .map(|node| (node.id, node.deleted))
+            .collect();
+        let nodes = updated
+            .nodes
+            .iter()
+            .chain(&updated.pending)
+            .filter(|node| match known.get(&node.id) {
+                Some(deleted) => node.deleted && !deleted,
+                None => true,
+            })
+            .cloned()
+            .collect::<Vec<_>>();
+
+        match nodes.is_empty() {
+            true => Vec::new(),
+            false => vec![Self {
+                nodes,
+                pending: Vec::new(),
+                clock: updated.clock,
+            }],
+        }
+    }
+
+    fn diff_ref<'target>(&'target self, updated: &'target Self) -> Vec<Self::DiffRef<'target>> {
+        self.diff(updated)
+    }
+
+    fn apply_single(&mut self, diff: Self::Diff) {
+        self.merge(diff)
+    }
+
+    /// Charges each node in the delta
+    fn apply_single_limited(
+        &mut self,
+        diff: Self::Diff,
+        budget: &mut ApplyBudget,
+    ) -> Result<(), LimitExceeded> {
+        budget.grow(diff.nodes.len().saturating_add(diff.pending.len()))?;
+        self.merge(diff);
+        Ok(())
+    }
+}
+
+#[cfg(feature = "nanoserde")]
+mod nanoserde_impls {
+    use super::*;
+    use crate::checked::DeBinChecked;
+    use nanoserde::{DeBin, DeBinErr, SerBin};
+
+    impl SerBin for Dot {
+        fn ser_bin(&self, output: &mut Vec<u8>) {
+            self.counter.ser_bin(output);
+            self.replica.ser_bin(output);
+        }
+    }
+
+    impl DeBin for Dot {
+        fn de_bin(offset: &mut usize, bytes: &[u8]) -> Result<Self, DeBinErr> {
+            Ok(Self {
+                counter: DeBin::de_bin(offset, bytes)?,
+                replica: DeBin::de_bin(offset, bytes)?,
+            })
+        }
+    }
+
+    impl<T: SerBin> SerBin for LwwRegister<T> {
+        fn ser_bin(&self, output: &mut Vec<u8>) {
+            self.value.ser_bin(output);
+            self.stamp.ser_bin(output);
+        }
+    }
+
+    impl<T: DeBinChecked> DeBin for LwwRegister<T> {
+        fn de_bin(offset: &mut usize, bytes: &[u8]) -> Result<Self, DeBinErr> {
+            Ok(Self {
+                value: T::de_bin_checked(offset, bytes)?,
+                stamp: DeBin::de_bin(offset, bytes)?,
+            })
+        }
+    }
+
+    impl<T: SerBin> SerBin for OrSet<T> {
+        fn ser_bin(&self, output: &mut Vec<u8>) {
+            self.entries.len().ser_bin(output);
+            for (value, dots) in self.entries.iter() {
+                value.ser_bin(output);
+                dots.ser_bin(output);
+            }
+            self.removed.ser_bin(output);
+            self.clock.ser_bin(output);
+        }
+    }
+
+    impl<T: DeBinChecked + Hash + Eq> DeBin for OrSet<T> {
+        fn de_bin(offset: &mut usize, bytes: &[u8]) -> Result<Self, DeBinErr> {
+            let len: usize = DeBin::de_bin(offset, bytes)?;
+            let mut entries = HashMap::new();
+            for _ in 0..len {
+                let value = T::de_bin_checked(offset, bytes)?;
+                entries.insert(value, DeBin::de_bin(offset, bytes)?);
+            }
+            Ok(Self {
+                entries,
+                removed: DeBin::de_bin(offset, bytes)?,
+                clock: DeBin::de_bin(offset, bytes)?,
+            })
+        }
+    }
+
+    impl<K: SerBin, V: SerBin> SerBin for LwwMap<K, V> {
+        fn ser_bin(&self, output: &mut Vec<u8>) {
+            self.entries.len().ser_bin(output);
+            for (key, register) in self.entries.iter() {
+                key.ser_bin(output);
+                register.ser_bin(output);
+            }
+            self.clock.ser_bin(output);
+        }
+    }
+
+    impl<K: DeBinChecked + Hash + Eq, V: DeBinChecked> DeBin for LwwMap<K, V> {
+        fn de_bin(offset: &mut usize, bytes: &[u8]) -> Result<Self, DeBinErr> {
+            let len: usize = DeBin::de_bin(offset, bytes)?;
+            let mut entries = HashMap::new();
+            for _ in 0..len {
+                let key = K::de_bin_checked(offset, bytes)?;
+                entries.insert(key, DeBin::de_bin(offset, bytes)?);
+            }
+            Ok(Self {
+                entries,
+                clock: DeBin::de_bin(offset, bytes)?,
+            })
+        }
+    }
+
+    impl<T: SerBin> SerBin for RgaNode<T> {
+        fn ser_bin(&self, output: &mut Vec<u8>) {
+            self.id.ser_bin(output);
+            self.origin.ser_bin(output);
+            self.value.ser_bin(output);
+            self.deleted.ser_bin(output);
+        }
+    }
+
+    impl<T: DeBinChecked> DeBin for RgaNode<T> {
+        fn de_bin(offset: &mut usize, bytes: &[u8]) -> Result<Self, DeBinErr> {
+            Ok(Self {
+                id: DeBin::de_bin(offset, bytes)?,
+                origin: DeBin::de_bin(offset, bytes)?,
+                value: T::de_bin_checked(offset, bytes)?,
+                deleted: DeBin::de_bin(offset, bytes)?,
+            })
+        }
+    }
+
+    impl<T: SerBin> SerBin for Rga<T> {
+        fn ser_bin(&self, output: &mut Vec<u8>) {
+            self.nodes.ser_bin(output);
+            self.pending.ser_bin(output);
+            self.clock.ser_bin(output);
+        }
+    }
+
+    impl<T: DeBinChecked> DeBin for Rga<T> {
+        fn de_bin(offset: &mut usize, bytes: &[u8]) -> Result<Self, DeBinErr> {
+            Ok(Self {
+                nodes: crate::checked::de_bin_vec(offset, bytes)?,
+                pending: crate::checked::de_bin_vec(offset, bytes)?,
+                clock: DeBin::de_bin(offset, bytes)?,
+            })
+        }
+    }
+
+    // the `DeBin` implementations above already read their values checked
+    impl<T: DeBinChecked> DeBinChecked for LwwRegister<T> {}
+    impl<T: DeBinChecked + Hash + Eq> DeBinChecked for OrSet<T> {}
+    impl<K: DeBinChecked + Hash + Eq, V: DeBinChecked> DeBinChecked for LwwMap<K, V> {}
+    impl<T: DeBinChecked> DeBinChecked for Rga<T> {}
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Apply every delta to `base` in each of the given orders, and check
+    /// that all orders converge
+    fn converge<T: StructDiff + Clone + PartialEq + std::fmt::Debug>(
+        base: &T,
+        deltas: &[Vec<T::Diff>],
+        orders: &[&[usize]],
+    ) -> T {
+        let results = orders
+            .iter()
+            .map(|order| {
+                let mut state = base.clone();
+                for index in order.iter() {
+                    state.apply_mut(deltas[*index].clone());
+                }
+                state
+            })
+            .collect::<Vec<_>>();
+        for result in results.iter() {
+            assert_eq!(result, &results[0]);
+        }
+        results[0].clone()
+    }
+
+    #[test]
+    fn test_lww_register() {
+        let base = LwwRegister::new(0u32, 0);
+        let mut first = base.clone();
+        first.set(1, 1);
+        let mut second = base.clone();
+        second.set(2, 2);
+        let deltas = [base.diff(&first), base.diff(&second)];
+
+        let merged = converge(&base, &deltas, &[&[0, 1], &[1, 0], &[1, 0, 1, 0]]);
+        // concurrent writes are ordered by replica id
+        assert_eq!(*merged.get(), 2);
+
+        // a write made after seeing another wins regardless of replica id
+        let mut later = merged.clone();
+        later.set(3, 1);
+        assert_eq!(*merged.apply(base.diff(&later)).get(), 3);
+        assert!(later.diff(&later).is_empty());
+    }
+
+    #[test]
+    fn test_lww_register_initial_values() {
+        // replicas which create the register independently still converge
+        let first = LwwRegister::new(String::from("first"), 1);
+        let second = LwwRegister::new(String::from("second"), 2);
+
+        let mut on_first = first.clone();
+        on_first.merge(second.clone());
+        let on_second = second.clone().apply(second.diff(&first));
+        assert_eq!(on_first, on_second);
+        assert_eq!(on_first.get(), "second");
+        assert!(first.has_diff(&second));
+    }
+
+    #[test]
+    fn test_or_set() {
+        let mut base = OrSet::new();
+        base.insert(String::from("a"), 0);
+        base.insert(String::from("b"), 0);
+
+        let mut first = base.clone();
+        first.remove(&String::from("a"));
+        first.insert(String::from("c"), 1);
+        let mut second = base.clone();
+        second.insert(String::from("a"), 2);
+        second.remove(&String::from("b"));
+
+        let deltas = [base.diff(&first), base.diff(&second)];
+        let merged = converge(&base, &deltas, &[&[0, 1], &[1, 0], &[0, 0, 1, 1]]);
+        let mut values = merged.iter().cloned().collect::<Vec<_>>();
+        values.sort();
+        // the concurrent insertion of "a" survives the removal
+        assert_eq!(values, ["a", "c"]);
+        assert!(!merged.has_diff(&first) && !merged.has_diff(&second));
+        assert!(first.has_diff(&merged));
+        assert!(!first.clone().apply(first.diff(&merged)).has_diff(&merged));
+    }
+
+    #[test]
+    fn test_lww_map() {
+        let mut base = LwwMap::new();
+        base.insert(1u32, String::from("one"), 0);
+        base.insert(2, String::from("two"), 0);
+
+        let mut first = base.clone();
+        first.remove(1, 1);
+        first.insert(2, String::from("first"), 1);
+        let mut second = base.clone();
+        second.insert(3, String::from("three"), 2);
+        // concurrent with the write from `first`, and ordered by replica id
+        second.insert(2, String::from("second"), 2);
+
+        let deltas = [base.diff(&first), base.diff(&second)];
+        let merged = converge(&base, &deltas, &[&[0, 1], &[1, 0], &[1, 1, 0]]);
+        assert_eq!(merged.get(&1), None);
+        assert_eq!(merged.get(&2).map(String::as_str), Some("second"));
+        assert_eq!(merged.get(&3).map(String::as_str), Some("three"));
+        assert_eq!(merged.len(), 2);
+    }
+
+    #[test]
+    fn test_rga() {
+        let mut base = Rga::new();
+        for value in "ace".bytes() {
+            base.push(value, 0);
+        }
+
+        let mut first = base.clone();
+        first.insert(1, b'b', 1);
+        first.remove(2);
+        let mut second = base.clone();
+        second.insert(1, b'x', 2);
+        second.insert(2, b'y', 2);
+        second.push(b'f', 2);
+
+        let deltas = [base.diff(&first), base.diff(&second)];
+        let merged = converge(&base, &deltas, &[&[0, 1], &[1, 0], &[0, 1, 0]]);
+        // concurrent insertions at the same position are not interleaved
+        assert_eq!(merged.iter().copied().collect::<Vec<_>>(), b"axybef");
+
+        // deltas which arrive before the elements they follow are held back
+        let mut third = merged.clone();
+        third.insert(0, b'1', 3);
+        let first_delta = merged.diff(&third);
+        third.insert(1, b'2', 3);
+        let second_delta = third.diff(&{
+            let mut fourth = third.clone();
+            fourth.insert(2, b'3', 3);
+            fourth
+        });
+        let mut out_of_order = merged.clone();
+        out_of_order.apply_mut(second_delta);
+        assert_eq!(out_of_order.len(), merged.len());
+        out_of_order.apply_mut(merged.diff(&third));
+        out_of_order.apply_mut(first_delta);
+        assert_eq!(
+            out_of_order.iter().copied().collect::<Vec<_>>(),
+            b"123axybef"
+        );
+        assert_eq!(out_of_order.get(2), Some(&b'3'));
+    }
+}
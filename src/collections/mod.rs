@@ -1,7 +1,49 @@
-pub(crate) mod rope;
+pub mod rope;
 
 pub mod unordered_array_like;
 pub mod unordered_map_like;
 pub mod unordered_map_like_recursive;
 
 pub mod ordered_array_like;
+
+pub mod text;
+
+pub mod binary_delta;
+
+pub mod crdt;
+
+/// The most changes a collection field puts into one diff when `diff_into`
+/// streams its changes into a [`DiffSink`](crate::DiffSink). Longer change lists
+/// are split across several diffs of the same field, which apply in order.
+/// `diff` and `diff_ref` are not chunked, and produce one diff per field.
+pub const CHANGES_PER_DIFF: usize = 1024;
+
+/// Gathers changes into lists of at most [`CHANGES_PER_DIFF`], handing each
+/// full list to `flush`
+pub(crate) struct Chunks<C, F: FnMut(Vec<C>)> {
+    chunk: Vec<C>,
+    flush: F,
+}
+
+impl<C, F: FnMut(Vec<C>)> Chunks<C, F> {
+    pub(crate) fn new(flush: F) -> Self {
+        Self {
+            chunk: Vec::new(),
+            flush,
+        }
+    }
+
+    pub(crate) fn push(&mut self, change: C) {
+        self.chunk.push(change);
+        if self.chunk.len() == CHANGES_PER_DIFF {
+            (self.flush)(std::mem::take(&mut self.chunk));
+        }
+    }
+
+    /// Hand over the last, partial list, if there is one
+    pub(crate) fn finish(mut self) {
+        if !self.chunk.is_empty() {
+            (self.flush)(self.chunk);
+        }
+    }
+}
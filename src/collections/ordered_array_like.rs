@@ -1,6 +1,14 @@
-use std::fmt::Debug;
-
-use super::rope::Rope;
+use std::{
+    collections::{LinkedList, VecDeque},
+    fmt::Debug,
+};
+
+use super::{rope::Rope, Chunks};
+use crate::{
+    limits::{ApplyBudget, LimitExceeded},
+    size::{EncodedSize, TAG},
+    DiffSink,
+};
 
 const LEVENSHTEIN_CUTOFF: usize = 8;
 const DELETE_COST: usize = 1;
@@ -13,27 +21,79 @@ pub fn hirschberg<'src, 'target: 'src, T: Clone + PartialEq + 'target>(
 ) -> Option<OrderedArrayLikeDiffRef<'target, T>> {
     let target = target.into_iter().collect::<Vec<_>>();
     let source = source.into_iter().collect::<Vec<_>>();
+    let indices = Indices {
+        target_start: 0,
+        target_end: target.len(),
+        source_start: 0,
+        source_end: source.len(),
+    };
 
-    match hirschberg_impl(
-        &target,
-        &source,
-        Indices {
-            target_start: 0,
-            target_end: target.len(),
-            source_start: 0,
-            source_end: source.len(),
-        },
-    )
-    .collect::<Vec<_>>()
-    {
-        empty if empty.is_empty() => None,
-        mut nonempty => {
-            nonempty.reverse();
-            Some(OrderedArrayLikeDiffRef(nonempty))
-        }
+    let mut changes = Vec::new();
+    let mut coalesce = Coalesce::new();
+    hirschberg_walk(&target, &source, indices, &mut |change| {
+        coalesce.push(change, &mut |change| changes.push(change))
+    });
+    coalesce.finish(&mut |change| changes.push(change));
+
+    match changes.is_empty() {
+        true => None,
+        false => Some(OrderedArrayLikeDiffRef(changes)),
     }
 }
 
+/// Generate the same changes as [`hirschberg`], pushing them to `sink` as they
+/// are found, in diffs of at most [`CHANGES_PER_DIFF`](super::CHANGES_PER_DIFF) changes. Nothing is
+/// pushed if the sequences are equal.
+pub fn hirschberg_into<'src, 'target: 'src, T: Clone + PartialEq + 'target>(
+    target: impl IntoIterator<Item = &'target T>,
+    source: impl IntoIterator<Item = &'src T>,
+    sink: &mut impl DiffSink<OrderedArrayLikeDiffRef<'target, T>>,
+) {
+    let target = target.into_iter().collect::<Vec<_>>();
+    let source = source.into_iter().collect::<Vec<_>>();
+    let indices = Indices {
+        target_start: 0,
+        target_end: target.len(),
+        source_start: 0,
+        source_end: source.len(),
+    };
+
+    let mut chunks = Chunks::new(|changes| sink.push(OrderedArrayLikeDiffRef(changes)));
+    let mut coalesce = Coalesce::new();
+    hirschberg_walk(&target, &source, indices, &mut |change| {
+        coalesce.push(change, &mut |change| chunks.push(change))
+    });
+    coalesce.finish(&mut |change| chunks.push(change));
+    chunks.finish();
+}
+
+/// A diff which removes every item of `source` and inserts every item of
+/// `target` in their place
+pub fn replacement<'src, 'target: 'src, T: 'target>(
+    target: impl IntoIterator<Item = &'target T>,
+    source: impl IntoIterator<Item = &'src T>,
+) -> OrderedArrayLikeDiffRef<'target, T> {
+    let target = target.into_iter().collect::<Vec<_>>();
+    let mut changes = Vec::with_capacity(2);
+    match source.into_iter().count() {
+        0 => (),
+        1 => changes.push(OrderedArrayLikeChangeRef::Delete(0, None)),
+        len => changes.push(OrderedArrayLikeChangeRef::Delete(0, Some(len - 1))),
+    }
+    if !target.is_empty() {
+        changes.push(OrderedArrayLikeChangeRef::InsertMany(target, 0));
+    }
+    OrderedArrayLikeDiffRef(changes)
+}
+
+/// Whether the two sequences differ, without generating a diff
+pub fn has_changes<'src, 'target, T: PartialEq + 'src + 'target>(
+    target: impl IntoIterator<Item = &'target T>,
+    source: impl IntoIterator<Item = &'src T>,
+) -> bool {
+    !target.into_iter().eq(source)
+}
+
 pub fn levenshtein<'src, 'target: 'src, T: Clone + PartialEq + 'target>(
     target: impl IntoIterator<Item = &'target T>,
     source: impl IntoIterator<Item = &'src T>,
@@ -54,11 +114,11 @@ pub fn levenshtein<'src, 'target: 'src, T: Clone + PartialEq + 'target>(
     .collect::<Vec<_>>()
     {
         empty if empty.is_empty() => None,
-        nonempty => Some(OrderedArrayLikeDiffRef(nonempty)),
+        nonempty => Some(OrderedArrayLikeDiffRef(coalesce(nonempty))),
     }
 }
 
-#[derive(Clone, Copy, Debug)]
+#[derive(Clone, Debug)]
 #[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub(crate) enum OrderedArrayLikeChangeRef<'a, T> {
     Replace(&'a T, usize),
@@ -67,6 +127,10 @@ pub(crate) enum OrderedArrayLikeChangeRef<'a, T> {
     Delete(usize, Option<usize>),
     #[allow(unused)]
     Swap(usize, usize),
+    /// consecutive values replacing the items starting at the index
+    ReplaceRange(Vec<&'a T>, usize),
+    /// consecutive values inserted starting at the index
+    InsertMany(Vec<&'a T>, usize),
 }
 
 #[derive(Debug, Clone)]
@@ -77,6 +141,10 @@ pub(crate) enum OrderedArrayLikeChangeOwned<T> {
     /// (start, optional end) range for deletion
     Delete(usize, Option<usize>),
     Swap(usize, usize),
+    /// consecutive values replacing the items starting at the index
+    ReplaceRange(Vec<T>, usize),
+    /// consecutive values inserted starting at the index
+    InsertMany(Vec<T>, usize),
 }
 
 impl<'a, T: Clone> From<OrderedArrayLikeChangeRef<'a, T>> for OrderedArrayLikeChangeOwned<T> {
@@ -84,12 +152,162 @@ impl<'a, T: Clone> From<OrderedArrayLikeChangeRef<'a, T>> for OrderedArrayLikeCh
         match value {
             OrderedArrayLikeChangeRef::Replace(val, idx) => Self::Replace(val.to_owned(), idx),
             OrderedArrayLikeChangeRef::Insert(val, idx) => Self::Insert(val.to_owned(), idx),
+            OrderedArrayLikeChangeRef::ReplaceRange(vals, idx) => {
+                Self::ReplaceRange(vals.into_iter().cloned().collect(), idx)
+            }
+            OrderedArrayLikeChangeRef::InsertMany(vals, idx) => {
+                Self::InsertMany(vals.into_iter().cloned().collect(), idx)
+            }
             OrderedArrayLikeChangeRef::Delete(idx, range) => Self::Delete(idx, range),
             OrderedArrayLikeChangeRef::Swap(l, r) => Self::Swap(l, r),
         }
     }
 }
 
+/// A run of single-item changes which is being merged into one range change
+enum Run<'a, T> {
+    Replace(VecDeque<&'a T>, usize),
+    Insert(VecDeque<&'a T>, usize),
+    /// inclusive range of deleted indices
+    Delete(usize, usize),
+}
+
+impl<'a, T> Run<'a, T> {
+    fn new(
+        change: OrderedArrayLikeChangeRef<'a, T>,
+    ) -> Result<Self, OrderedArrayLikeChangeRef<'a, T>> {
+        match change {
+            OrderedArrayLikeChangeRef::Replace(val, idx) => {
+                Ok(Run::Replace(VecDeque::from([val]), idx))
+            }
+            OrderedArrayLikeChangeRef::Insert(val, idx) => {
+                Ok(Run::Insert(VecDeque::from([val]), idx))
+            }
+            OrderedArrayLikeChangeRef::Delete(start, end) => {
+                Ok(Run::Delete(start, end.unwrap_or(start)))
+            }
+            other => Err(other),
+        }
+    }
+
+    /// Try to extend the run with the next change to be applied, handing it back if
+    /// the two can't be merged
+    fn extend(
+        &mut self,
+        change: OrderedArrayLikeChangeRef<'a, T>,
+    ) -> Option<OrderedArrayLikeChangeRef<'a, T>> {
+        match (self, change) {
+            (Run::Replace(vals, start), OrderedArrayLikeChangeRef::Replace(val, idx)) => {
+                if (*start..*start + vals.len()).contains(&idx) {
+                    vals[idx - *start] = val;
+                } else if idx == *start + vals.len() {
+                    vals.push_back(val);
+                } else if idx + 1 == *start {
+                    vals.push_front(val);
+                    *start = idx;
+                } else {
+                    return Some(OrderedArrayLikeChangeRef::Replace(val, idx));
+                }
+            }
+            (Run::Insert(vals, start), OrderedArrayLikeChangeRef::Insert(val, idx)) => {
+                // the run occupies `start..start + len` once inserted, so any insertion
+                // from its start up to its end lands inside of it
+                if (*start..=*start + vals.len()).contains(&idx) {
+                    vals.insert(idx - *start, val);
+                } else {
+                    return Some(OrderedArrayLikeChangeRef::Insert(val, idx));
+                }
+            }
+            (Run::Delete(start, end), OrderedArrayLikeChangeRef::Delete(del_start, del_end)) => {
+                let del_end = del_end.unwrap_or(del_start);
+                // the items after the run have already shifted down into its place, so
+                // any deletion touching `start` extends the run
+                if del_start <= *start && *start <= del_end + 1 {
+                    let count = (*end - *start + 1) + (del_end - del_start + 1);
+                    *start = del_start;
+                    *end = del_start + count - 1;
+                } else {
+                    return Some(OrderedArrayLikeChangeRef::Delete(del_start, Some(del_end)));
+                }
+            }
+            (_, change) => return Some(change),
+        }
+        None
+    }
+
+    fn finish(self) -> OrderedArrayLikeChangeRef<'a, T> {
+        match self {
+            Run::Replace(mut vals, idx) if vals.len() == 1 => {
+                OrderedArrayLikeChangeRef::Replace(vals.pop_front().unwrap(), idx)
+            }
+            Run::Replace(vals, idx) => OrderedArrayLikeChangeRef::ReplaceRange(vals.into(), idx),
+            Run::Insert(mut vals, idx) if vals.len() == 1 => {
+                OrderedArrayLikeChangeRef::Insert(vals.pop_front().unwrap(), idx)
+            }
+            Run::Insert(vals, idx) => OrderedArrayLikeChangeRef::InsertMany(vals.into(), idx),
+            Run::Delete(start, end) if start == end => {
+                OrderedArrayLikeChangeRef::Delete(start, None)
+            }
+            Run::Delete(start, end) => OrderedArrayLikeChangeRef::Delete(start, Some(end)),
+        }
+    }
+}
+
+/// Merge runs of adjacent single-item changes into range changes, without changing
+/// the result of applying them in order
+fn coalesce<'a, T>(
+    changes: Vec<OrderedArrayLikeChangeRef<'a, T>>,
+) -> Vec<OrderedArrayLikeChangeRef<'a, T>> {
+    let mut ret = Vec::with_capacity(changes.len());
+    let mut coalesce = Coalesce::new();
+    for change in changes {
+        coalesce.push(change, &mut |change| ret.push(change));
+    }
+    coalesce.finish(&mut |change| ret.push(change));
+    ret
+}
+
+/// Merges runs of adjacent single-item changes as [`coalesce`] does, for changes
+/// which are pushed one at a time in the order they are applied
+struct Coalesce<'a, T> {
+    run: Option<Run<'a, T>>,
+}
+
+impl<'a, T> Coalesce<'a, T> {
+    fn new() -> Self {
+        Self { run: None }
+    }
+
+    /// Add the next change, handing any change which can no longer be merged to `emit`
+    fn push(
+        &mut self,
+        change: OrderedArrayLikeChangeRef<'a, T>,
+        emit: &mut impl FnMut(OrderedArrayLikeChangeRef<'a, T>),
+    ) {
+        let change = match self.run.as_mut() {
+            Some(current) => match current.extend(change) {
+                None => return,
+                Some(unmerged) => {
+                    emit(self.run.take().unwrap().finish());
+                    unmerged
+                }
+            },
+            None => change,
+        };
+
+        match Run::new(change) {
+            Ok(new_run) => self.run = Some(new_run),
+            Err(change) => emit(change),
+        }
+    }
+
+    fn finish(self, emit: &mut impl FnMut(OrderedArrayLikeChangeRef<'a, T>)) {
+        if let Some(run) = self.run {
+            emit(run.finish());
+        }
+    }
+}
+
 #[derive(Clone, Copy, Debug)]
 enum ChangeInternal {
     NoOp(usize),
@@ -109,18 +327,91 @@ impl ChangeInternal {
     }
 }
 
+// Positions that fall outside the container can only come from a diff
+// generated against a different base, so they are clamped or skipped rather
+// than panicking
 impl<T> OrderedArrayLikeChangeOwned<T> {
     fn apply(self, container: &mut Rope<T>) {
+        let len = container.len();
         match self {
-            OrderedArrayLikeChangeOwned::Replace(val, loc) => container[loc] = val,
-            OrderedArrayLikeChangeOwned::Insert(val, loc) => container.insert(loc, val),
+            OrderedArrayLikeChangeOwned::Replace(val, loc) => {
+                if let Some(slot) = container.get_mut(loc) {
+                    *slot = val
+                }
+            }
+            OrderedArrayLikeChangeOwned::Insert(val, loc) => container.insert(loc.min(len), val),
+            OrderedArrayLikeChangeOwned::ReplaceRange(vals, loc) => {
+                for (offset, val) in vals.into_iter().enumerate() {
+                    match container.get_mut(loc + offset) {
+                        Some(slot) => *slot = val,
+                        None => break,
+                    }
+                }
+            }
+            OrderedArrayLikeChangeOwned::InsertMany(vals, loc) => {
+                let mut tail = container.split_off(loc.min(len));
+                container.extend(vals);
+                container.append(&mut tail);
+            }
             OrderedArrayLikeChangeOwned::Delete(loc, None) => {
-                container.remove(loc);
+                if loc < len {
+                    container.remove(loc);
+                }
             }
             OrderedArrayLikeChangeOwned::Delete(l, Some(r)) => {
-                container.drain(l..=r);
+                if l <= r && l < len {
+                    container.drain(l..=r.min(len - 1));
+                }
+            }
+            OrderedArrayLikeChangeOwned::Swap(l, r) => {
+                if l < len && r < len {
+                    container.swap(l, r)
+                }
+            }
+        }
+    }
+}
+
+impl<T: Clone> OrderedArrayLikeChangeRef<'_, T> {
+    fn apply(self, container: &mut Rope<T>) {
+        let len = container.len();
+        match self {
+            OrderedArrayLikeChangeRef::Replace(val, loc) => {
+                if let Some(slot) = container.get_mut(loc) {
+                    slot.clone_from(val)
+                }
+            }
+            OrderedArrayLikeChangeRef::Insert(val, loc) => {
+                container.insert(loc.min(len), val.clone())
+            }
+            OrderedArrayLikeChangeRef::ReplaceRange(vals, loc) => {
+                for (offset, val) in vals.into_iter().enumerate() {
+                    match container.get_mut(loc + offset) {
+                        Some(slot) => slot.clone_from(val),
+                        None => break,
+                    }
+                }
+            }
+            OrderedArrayLikeChangeRef::InsertMany(vals, loc) => {
+                let mut tail = container.split_off(loc.min(len));
+                container.extend(vals.into_iter().cloned());
+                container.append(&mut tail);
+            }
+            OrderedArrayLikeChangeRef::Delete(loc, None) => {
+                if loc < len {
+                    container.remove(loc);
+                }
+            }
+            OrderedArrayLikeChangeRef::Delete(l, Some(r)) => {
+                if l <= r && l < len {
+                    container.drain(l..=r.min(len - 1));
+                }
+            }
+            OrderedArrayLikeChangeRef::Swap(l, r) => {
+                if l < len && r < len {
+                    container.swap(l, r)
+                }
             }
-            OrderedArrayLikeChangeOwned::Swap(l, r) => container.swap(l, r),
         }
     }
 }
@@ -147,6 +438,48 @@ impl<'src, T: Clone> From<OrderedArrayLikeDiffRef<'src, T>> for OrderedArrayLike
     }
 }
 
+impl<T: EncodedSize> EncodedSize for OrderedArrayLikeChangeRef<'_, T> {
+    fn encoded_size(&self) -> usize {
+        TAG + match self {
+            Self::Replace(val, idx) | Self::Insert(val, idx) => {
+                val.encoded_size() + idx.encoded_size()
+            }
+            Self::ReplaceRange(vals, idx) | Self::InsertMany(vals, idx) => {
+                vals.encoded_size() + idx.encoded_size()
+            }
+            Self::Delete(idx, end) => idx.encoded_size() + end.encoded_size(),
+            Self::Swap(l, r) => l.encoded_size() + r.encoded_size(),
+        }
+    }
+}
+
+impl<T: EncodedSize> EncodedSize for OrderedArrayLikeChangeOwned<T> {
+    fn encoded_size(&self) -> usize {
+        TAG + match self {
+            Self::Replace(val, idx) | Self::Insert(val, idx) => {
+                val.encoded_size() + idx.encoded_size()
+            }
+            Self::ReplaceRange(vals, idx) | Self::InsertMany(vals, idx) => {
+                vals.encoded_size() + idx.encoded_size()
+            }
+            Self::Delete(idx, end) => idx.encoded_size() + end.encoded_size(),
+            Self::Swap(l, r) => l.encoded_size() + r.encoded_size(),
+        }
+    }
+}
+
+impl<T: EncodedSize> EncodedSize for OrderedArrayLikeDiffRef<'_, T> {
+    fn encoded_size(&self) -> usize {
+        self.0.encoded_size()
+    }
+}
+
+impl<T: EncodedSize> EncodedSize for OrderedArrayLikeDiffOwned<T> {
+    fn encoded_size(&self) -> usize {
+        self.0.encoded_size()
+    }
+}
+
 #[cfg(unused)]
 fn print_table(table: &Vec<Vec<ChangeInternal>>) {
     for row in table {
@@ -282,7 +615,10 @@ fn create_last_change_row<'src, 'target: 'src, T: Clone + PartialEq + 'target>(
     ret
 }
 
-fn hirschberg_impl<'src, 'target: 'src, T: Clone + PartialEq + 'target>(
+/// Align `target` with `source`, handing each change to `emit` in the order it
+/// has to be applied. Later parts of the sequences are visited first, so that the
+/// positions of the earlier changes are unaffected by them.
+fn hirschberg_walk<'src, 'target: 'src, T: Clone + PartialEq + 'target>(
     target: &[&'target T],
     source: &[&'src T],
     Indices {
@@ -291,7 +627,8 @@ fn hirschberg_impl<'src, 'target: 'src, T: Clone + PartialEq + 'target>(
         source_start,
         source_end,
     }: Indices,
-) -> Box<dyn DoubleEndedIterator<Item = OrderedArrayLikeChangeRef<'target, T>> + 'target> {
+    emit: &mut impl FnMut(OrderedArrayLikeChangeRef<'target, T>),
+) {
     let indices = Indices {
         target_start,
         target_end,
@@ -300,27 +637,18 @@ fn hirschberg_impl<'src, 'target: 'src, T: Clone + PartialEq + 'target>(
     };
     // base cases
     match (target_start == target_end, source_start == source_end) {
-        (true, true) => return Box::new(std::iter::empty()),
+        (true, true) => return,
         (true, false) => {
-            return Box::new(std::iter::once(OrderedArrayLikeChangeRef::Delete(
+            return emit(OrderedArrayLikeChangeRef::Delete(
                 source_start,
                 Some(source_end - 1),
-            )));
+            ));
         }
         (false, true) => {
-            let iter: Box<dyn Iterator<Item = _>> = Box::new(
-                target[target_start..target_end]
-                    .iter()
-                    .copied()
-                    .enumerate()
-                    .map(|(i, v)| {
-                        let idx = source_end + i;
-                        OrderedArrayLikeChangeRef::Insert(v, idx)
-                    })
-                    .rev(),
-            );
-
-            return Box::new(iter.collect::<Vec<_>>().into_iter());
+            for (i, v) in target[target_start..target_end].iter().copied().enumerate() {
+                emit(OrderedArrayLikeChangeRef::Insert(v, source_end + i));
+            }
+            return;
         }
         (false, false)
             if target_start
@@ -328,8 +656,8 @@ fn hirschberg_impl<'src, 'target: 'src, T: Clone + PartialEq + 'target>(
                 .min(source_start.abs_diff(source_end))
                 <= LEVENSHTEIN_CUTOFF =>
         {
-            let lev = levenshtein_impl(target, source, indices);
-            return Box::new(lev.rev());
+            levenshtein_impl(target, source, indices).for_each(emit);
+            return;
         }
         _ => (),
     }
@@ -362,27 +690,27 @@ fn hirschberg_impl<'src, 'target: 'src, T: Clone + PartialEq + 'target>(
         .map(|(idx, _)| source_start + idx)
         .unwrap();
 
-    let left = hirschberg_impl(
+    hirschberg_walk(
         target,
         source,
         Indices {
-            target_end: target_split_index,
-            source_end: source_split_index,
+            target_start: target_split_index,
+            source_start: source_split_index,
             ..indices
         },
+        emit,
     );
 
-    let right = hirschberg_impl(
+    hirschberg_walk(
         target,
         source,
         Indices {
-            target_start: target_split_index,
-            source_start: source_split_index,
+            target_end: target_split_index,
+            source_end: source_split_index,
             ..indices
         },
+        emit,
     );
-
-    Box::new(left.chain(right))
 }
 
 fn levenshtein_impl<'src, 'target: 'src, T: Clone + PartialEq + 'target>(
@@ -585,9 +913,234 @@ where
     Box::new(ret.into_iter())
 }
 
+/// Collections which an [`OrderedArrayLikeDiffOwned`] can be applied to in place.
+///
+/// A [`Rope`] applies each change directly, while other sequences are converted
+/// into a [`Rope`] for the duration of the apply.
+pub trait OrderedArrayLikeCollection<T> {
+    fn apply_ordered_changes(&mut self, changes: OrderedArrayLikeDiffOwned<T>);
+
+    /// Apply an [`OrderedArrayLikeDiffRef`], cloning each referenced item into
+    /// place rather than converting the whole diff to an owned one first.
+    fn apply_ordered_changes_ref(&mut self, changes: OrderedArrayLikeDiffRef<'_, T>)
+    where
+        T: Clone,
+    {
+        self.apply_ordered_changes(changes.into())
+    }
+}
+
+pub fn apply_in_place<T, L: OrderedArrayLikeCollection<T>>(
+    changes: impl Into<OrderedArrayLikeDiffOwned<T>>,
+    existing: &mut L,
+) {
+    existing.apply_ordered_changes(changes.into())
+}
+
+/// Apply `changes` as [`apply_in_place`] does, after charging the items
+/// they insert to `budget`
+pub fn apply_in_place_limited<T, L: OrderedArrayLikeCollection<T>>(
+    changes: impl Into<OrderedArrayLikeDiffOwned<T>>,
+    existing: &mut L,
+    budget: &mut ApplyBudget,
+) -> Result<(), LimitExceeded> {
+    let changes = changes.into();
+    let inserted = changes
+        .0
+        .iter()
+        .map(|change| match change {
+            OrderedArrayLikeChangeOwned::Insert(..) => 1,
+            OrderedArrayLikeChangeOwned::InsertMany(vals, _) => vals.len(),
+            _ => 0,
+        })
+        .fold(0, usize::saturating_add);
+    budget.grow(inserted)?;
+    existing.apply_ordered_changes(changes);
+    Ok(())
+}
+
+pub fn apply_ref_in_place<T: Clone, L: OrderedArrayLikeCollection<T>>(
+    changes: OrderedArrayLikeDiffRef<'_, T>,
+    existing: &mut L,
+) {
+    existing.apply_ordered_changes_ref(changes)
+}
+
+fn apply_ref_through_rope<T, L>(changes: OrderedArrayLikeDiffRef<'_, T>, existing: &mut L)
+where
+    T: Clone,
+    L: IntoIterator<Item = T> + FromIterator<T> + Default,
+{
+    let mut rope = std::mem::take(existing).into_iter().collect::<Rope<_>>();
+    rope.apply_ordered_changes_ref(changes);
+    *existing = rope.into_iter().collect();
+}
+
+impl<T> OrderedArrayLikeCollection<T> for Rope<T> {
+    fn apply_ordered_changes(&mut self, changes: OrderedArrayLikeDiffOwned<T>) {
+        for change in changes.0 {
+            change.apply(self);
+        }
+    }
+
+    fn apply_ordered_changes_ref(&mut self, changes: OrderedArrayLikeDiffRef<'_, T>)
+    where
+        T: Clone,
+    {
+        for change in changes.0 {
+            change.apply(self);
+        }
+    }
+}
+
+impl<T: Clone + 'static> OrderedArrayLikeCollection<T> for Vec<T> {
+    fn apply_ordered_changes(&mut self, changes: OrderedArrayLikeDiffOwned<T>) {
+        *self = apply(changes, std::mem::take(self)).collect();
+    }
+
+    fn apply_ordered_changes_ref(&mut self, changes: OrderedArrayLikeDiffRef<'_, T>) {
+        apply_ref_through_rope(changes, self)
+    }
+}
+
+impl<T: Clone + 'static> OrderedArrayLikeCollection<T> for VecDeque<T> {
+    fn apply_ordered_changes(&mut self, changes: OrderedArrayLikeDiffOwned<T>) {
+        *self = apply(changes, std::mem::take(self)).collect();
+    }
+
+    fn apply_ordered_changes_ref(&mut self, changes: OrderedArrayLikeDiffRef<'_, T>) {
+        apply_ref_through_rope(changes, self)
+    }
+}
+
+impl<T: Clone + 'static> OrderedArrayLikeCollection<T> for LinkedList<T> {
+    fn apply_ordered_changes(&mut self, changes: OrderedArrayLikeDiffOwned<T>) {
+        *self = apply(changes, std::mem::take(self)).collect();
+    }
+
+    fn apply_ordered_changes_ref(&mut self, changes: OrderedArrayLikeDiffRef<'_, T>) {
+        apply_ref_through_rope(changes, self)
+    }
+}
+
+/// Sequences which can be edited one item at a time, so that the diff of
+/// each edit can be produced without comparing the whole sequence.
+pub trait OrderedArrayLikeMut<T> {
+    fn len(&self) -> usize;
+
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Insert `value` before the item at `index`
+    fn insert_at(&mut self, index: usize, value: T);
+
+    /// Remove and return the item at `index`
+    fn remove_at(&mut self, index: usize) -> T;
+}
+
+/// Insert `value` at `index`, returning the diff of the insertion
+pub fn insert_with_diff<T: Clone, L: OrderedArrayLikeMut<T>>(
+    existing: &mut L,
+    index: usize,
+    value: T,
+) -> OrderedArrayLikeDiffOwned<T> {
+    existing.insert_at(index, value.clone());
+    OrderedArrayLikeDiffOwned(vec![OrderedArrayLikeChangeOwned::Insert(value, index)])
+}
+
+/// Append `value`, returning the diff of the insertion
+pub fn push_with_diff<T: Clone, L: OrderedArrayLikeMut<T>>(
+    existing: &mut L,
+    value: T,
+) -> OrderedArrayLikeDiffOwned<T> {
+    let index = existing.len();
+    insert_with_diff(existing, index, value)
+}
+
+/// Remove the item at `index`, returning it and the diff of the removal
+pub fn remove_with_diff<T, L: OrderedArrayLikeMut<T>>(
+    existing: &mut L,
+    index: usize,
+) -> (T, OrderedArrayLikeDiffOwned<T>) {
+    (
+        existing.remove_at(index),
+        OrderedArrayLikeDiffOwned(vec![OrderedArrayLikeChangeOwned::Delete(index, None)]),
+    )
+}
+
+impl<T> OrderedArrayLikeMut<T> for Rope<T> {
+    fn len(&self) -> usize {
+        Rope::len(self)
+    }
+
+    fn insert_at(&mut self, index: usize, value: T) {
+        self.insert(index, value)
+    }
+
+    fn remove_at(&mut self, index: usize) -> T {
+        self.remove(index)
+    }
+}
+
+impl<T> OrderedArrayLikeMut<T> for Vec<T> {
+    fn len(&self) -> usize {
+        Vec::len(self)
+    }
+
+    fn insert_at(&mut self, index: usize, value: T) {
+        self.insert(index, value)
+    }
+
+    fn remove_at(&mut self, index: usize) -> T {
+        self.remove(index)
+    }
+}
+
+impl<T> OrderedArrayLikeMut<T> for VecDeque<T> {
+    fn len(&self) -> usize {
+        VecDeque::len(self)
+    }
+
+    fn insert_at(&mut self, index: usize, value: T) {
+        self.insert(index, value)
+    }
+
+    fn remove_at(&mut self, index: usize) -> T {
+        let len = VecDeque::len(self);
+        self.remove(index)
+            .unwrap_or_else(|| panic!("removal index (is {index}) should be < len (is {len})"))
+    }
+}
+
+impl<T> OrderedArrayLikeMut<T> for LinkedList<T> {
+    fn len(&self) -> usize {
+        LinkedList::len(self)
+    }
+
+    fn insert_at(&mut self, index: usize, value: T) {
+        let mut tail = self.split_off(index);
+        self.push_back(value);
+        self.append(&mut tail);
+    }
+
+    fn remove_at(&mut self, index: usize) -> T {
+        let len = LinkedList::len(self);
+        assert!(
+            index < len,
+            "removal index (is {index}) should be < len (is {len})"
+        );
+        let mut tail = self.split_off(index);
+        let ret = tail.pop_front().unwrap();
+        self.append(&mut tail);
+        ret
+    }
+}
+
 #[cfg(feature = "nanoserde")]
 mod nanoserde_impls {
     use super::*;
+    use crate::checked::DeBinChecked;
     use nanoserde::{DeBin, SerBin};
 
     impl<T> OrderedArrayLikeChangeOwned<T> {
@@ -598,6 +1151,8 @@ mod nanoserde_impls {
                 OrderedArrayLikeChangeOwned::Insert(_, _) => 1,
                 OrderedArrayLikeChangeOwned::Delete(_, _) => 2,
                 OrderedArrayLikeChangeOwned::Swap(_, _) => 3,
+                OrderedArrayLikeChangeOwned::ReplaceRange(_, _) => 4,
+                OrderedArrayLikeChangeOwned::InsertMany(_, _) => 5,
             }
         }
     }
@@ -610,6 +1165,8 @@ mod nanoserde_impls {
                 OrderedArrayLikeChangeRef::Insert(_, _) => 1,
                 OrderedArrayLikeChangeRef::Delete(_, _) => 2,
                 OrderedArrayLikeChangeRef::Swap(_, _) => 3,
+                OrderedArrayLikeChangeRef::ReplaceRange(_, _) => 4,
+                OrderedArrayLikeChangeRef::InsertMany(_, _) => 5,
             }
         }
     }
@@ -637,6 +1194,12 @@ mod nanoserde_impls {
                     l.ser_bin(output);
                     r.ser_bin(output);
                 }
+                OrderedArrayLikeChangeOwned::ReplaceRange(vals, idx)
+                | OrderedArrayLikeChangeOwned::InsertMany(vals, idx) => {
+                    self.nanoserde_discriminant().ser_bin(output);
+                    vals.ser_bin(output);
+                    idx.ser_bin(output);
+                }
             }
         }
     }
@@ -664,20 +1227,30 @@ mod nanoserde_impls {
                     l.ser_bin(output);
                     r.ser_bin(output);
                 }
+                OrderedArrayLikeChangeRef::ReplaceRange(vals, idx)
+                | OrderedArrayLikeChangeRef::InsertMany(vals, idx) => {
+                    self.nanoserde_discriminant().ser_bin(output);
+                    // matches the encoding of `Vec<T>`
+                    vals.len().ser_bin(output);
+                    for val in vals {
+                        val.ser_bin(output);
+                    }
+                    idx.ser_bin(output);
+                }
             }
         }
     }
 
-    impl<T: DeBin> DeBin for OrderedArrayLikeChangeOwned<T> {
+    impl<T: DeBinChecked> DeBin for OrderedArrayLikeChangeOwned<T> {
         fn de_bin(offset: &mut usize, bytes: &[u8]) -> Result<Self, nanoserde::DeBinErr> {
             match <u8 as DeBin>::de_bin(offset, bytes)? {
                 0 => {
-                    let val = <T as DeBin>::de_bin(offset, bytes)?;
+                    let val = T::de_bin_checked(offset, bytes)?;
                     let idx = <usize as DeBin>::de_bin(offset, bytes)?;
                     Ok(OrderedArrayLikeChangeOwned::Replace(val, idx))
                 }
                 1 => {
-                    let val = <T as DeBin>::de_bin(offset, bytes)?;
+                    let val = T::de_bin_checked(offset, bytes)?;
                     let idx = <usize as DeBin>::de_bin(offset, bytes)?;
                     Ok(OrderedArrayLikeChangeOwned::Insert(val, idx))
                 }
@@ -691,6 +1264,16 @@ mod nanoserde_impls {
                     let r = <usize as DeBin>::de_bin(offset, bytes)?;
                     Ok(OrderedArrayLikeChangeOwned::Swap(l, r))
                 }
+                4 => {
+                    let vals = crate::checked::de_bin_vec_with(offset, bytes, T::de_bin_checked)?;
+                    let idx = <usize as DeBin>::de_bin(offset, bytes)?;
+                    Ok(OrderedArrayLikeChangeOwned::ReplaceRange(vals, idx))
+                }
+                5 => {
+                    let vals = crate::checked::de_bin_vec_with(offset, bytes, T::de_bin_checked)?;
+                    let idx = <usize as DeBin>::de_bin(offset, bytes)?;
+                    Ok(OrderedArrayLikeChangeOwned::InsertMany(vals, idx))
+                }
                 _ => Err(nanoserde::DeBinErr {
                     o: *offset - 1,
                     l: 1,
@@ -712,9 +1295,9 @@ mod nanoserde_impls {
         }
     }
 
-    impl<T: DeBin> DeBin for OrderedArrayLikeDiffOwned<T> {
+    impl<T: DeBinChecked> DeBin for OrderedArrayLikeDiffOwned<T> {
         fn de_bin(offset: &mut usize, bytes: &[u8]) -> Result<Self, nanoserde::DeBinErr> {
-            let ret = <Vec<_> as DeBin>::de_bin(offset, bytes)?;
+            let ret = crate::checked::de_bin_vec(offset, bytes)?;
             Ok(Self(ret))
         }
     }
@@ -726,8 +1309,9 @@ mod test {
 
     use crate as structdiff;
     use crate::collections::ordered_array_like::{
-        apply, OrderedArrayLikeDiffOwned, OrderedArrayLikeDiffRef,
+        apply, OrderedArrayLikeChangeRef, OrderedArrayLikeDiffOwned, OrderedArrayLikeDiffRef,
     };
+    use crate::collections::rope::Rope;
     use nanorand::{Rng, WyRand};
 
     use structdiff::{Difference, StructDiff};
@@ -785,14 +1369,115 @@ mod test {
                 return;
             };
 
+            assert_eq!(changes.0.len(), 1, "Insertions should be coalesced.");
+            let OrderedArrayLikeChangeRef::InsertMany(vals, 0) = &changes.0[0] else {
+                panic!("Expected a single InsertMany, got {:?}", changes.0);
+            };
             assert_eq!(
-                changes.0.len(),
+                vals.len(),
                 s1.len(),
-                "Should require deletions for all characters in the non-empty string."
+                "Should require insertions for all characters in the non-empty string."
             );
         }
     }
 
+    #[test]
+    fn test_coalesced_runs() {
+        let source: Vec<usize> = (0..2_000).collect();
+
+        // large insert at the front
+        let target: Vec<usize> = (2_000..3_000).chain(0..2_000).collect();
+        let changes = hirschberg(&target, &source).unwrap();
+        assert_eq!(changes.0.len(), 1, "{:?}", changes.0.first());
+        let changed = apply(changes, source.clone()).collect::<Vec<_>>();
+        assert_eq!(target, changed);
+
+        // deleted and replaced blocks in the middle
+        let mut target = source.clone();
+        target.drain(500..600);
+        target[1_000..1_050]
+            .iter_mut()
+            .for_each(|v| *v += 1_000_000);
+        let changes = hirschberg(&target, &source).unwrap();
+        assert!(changes.0.len() <= 4, "{}", changes.0.len());
+        let changed = apply(changes, source.clone()).collect::<Vec<_>>();
+        assert_eq!(target, changed);
+    }
+
+    #[test]
+    fn test_hirschberg_into() {
+        use crate::collections::CHANGES_PER_DIFF;
+
+        let mut rng = WyRand::new_seed(3);
+        let source: Vec<u8> = (0..3_000).map(|_| rng.generate_range(0..4_u8)).collect();
+        let target: Vec<u8> = (0..3_000).map(|_| rng.generate_range(0..4_u8)).collect();
+
+        let mut streamed = Vec::new();
+        super::hirschberg_into(&target, &source, &mut streamed);
+        assert!(streamed.len() > 1);
+        assert!(streamed.iter().all(|diff| diff.0.len() <= CHANGES_PER_DIFF));
+
+        // the diffs hold the same changes as a single diff would, in the same order
+        let changes = streamed
+            .iter()
+            .flat_map(|diff| diff.0.iter())
+            .map(|change| format!("{change:?}"));
+        let expected = hirschberg(&target, &source).unwrap();
+        assert!(changes.eq(expected.0.iter().map(|change| format!("{change:?}"))));
+
+        let changed = streamed
+            .into_iter()
+            .fold(source, |source, diff| apply(diff, source).collect());
+        assert_eq!(target, changed);
+
+        let mut unchanged = Vec::new();
+        super::hirschberg_into(&target, &target, &mut unchanged);
+        assert!(unchanged.is_empty());
+    }
+
+    #[test]
+    fn test_out_of_range_changes() {
+        use super::{OrderedArrayLikeChangeOwned as Owned, OrderedArrayLikeCollection};
+
+        let base: Vec<u8> = (0..4).collect();
+        let cases = [
+            // each position past the end is skipped or clamped to the end
+            (Owned::Replace(9, 4), vec![0, 1, 2, 3]),
+            (Owned::Insert(9, 7), vec![0, 1, 2, 3, 9]),
+            (Owned::ReplaceRange(vec![8, 9], 3), vec![0, 1, 2, 8]),
+            (Owned::ReplaceRange(vec![8, 9], 5), vec![0, 1, 2, 3]),
+            (Owned::InsertMany(vec![8, 9], 7), vec![0, 1, 2, 3, 8, 9]),
+            (Owned::Delete(4, None), vec![0, 1, 2, 3]),
+            (Owned::Delete(2, Some(9)), vec![0, 1]),
+            (Owned::Delete(4, Some(9)), vec![0, 1, 2, 3]),
+            // an inverted range deletes nothing
+            (Owned::Delete(3, Some(1)), vec![0, 1, 2, 3]),
+            (Owned::Swap(1, 4), vec![0, 1, 2, 3]),
+            (Owned::Swap(4, 1), vec![0, 1, 2, 3]),
+        ];
+
+        for (change, expected) in cases {
+            let owned = OrderedArrayLikeDiffOwned(vec![change.clone()]);
+            assert_eq!(apply(owned, base.clone()).collect::<Vec<_>>(), expected);
+
+            let by_ref = OrderedArrayLikeDiffRef(vec![match &change {
+                Owned::Replace(val, loc) => OrderedArrayLikeChangeRef::Replace(val, *loc),
+                Owned::Insert(val, loc) => OrderedArrayLikeChangeRef::Insert(val, *loc),
+                Owned::ReplaceRange(vals, loc) => {
+                    OrderedArrayLikeChangeRef::ReplaceRange(vals.iter().collect(), *loc)
+                }
+                Owned::InsertMany(vals, loc) => {
+                    OrderedArrayLikeChangeRef::InsertMany(vals.iter().collect(), *loc)
+                }
+                Owned::Delete(l, r) => OrderedArrayLikeChangeRef::Delete(*l, *r),
+                Owned::Swap(l, r) => OrderedArrayLikeChangeRef::Swap(*l, *r),
+            }]);
+            let mut rope = base.iter().copied().collect::<Rope<_>>();
+            rope.apply_ordered_changes_ref(by_ref);
+            assert_eq!(rope.into_iter().collect::<Vec<_>>(), expected);
+        }
+    }
+
     #[test]
     fn test_empty_strings() {
         let s1: Vec<char> = "".chars().collect();
@@ -899,16 +1584,20 @@ mod test {
             test1: Vec<i32>,
             #[difference(collection_strategy = "ordered_array_like")]
             test2: LinkedList<i32>,
+            #[difference(collection_strategy = "ordered_array_like")]
+            test3: Rope<i32>,
         }
 
         let first = TestCollection {
             test1: vec![10, 15, 20, 25, 30],
             test2: vec![10, 15, 17].into_iter().collect(),
+            test3: (0..200).collect(),
         };
 
         let second = TestCollection {
             test1: Vec::default(),
             test2: vec![10, 15, 17, 19].into_iter().collect(),
+            test3: (0..200).filter(|x| x % 50 != 0).collect(),
         };
 
         let diffs = first.diff(&second).to_owned();
@@ -927,10 +1616,17 @@ mod test {
             panic!("Collection strategy failure");
         }
 
+        if let TestCollectionFields::test3(OrderedArrayLikeDiffOwned(val)) = &diffs[2] {
+            assert_eq!(val.len(), 4);
+        } else {
+            panic!("Collection strategy failure");
+        }
+
         let diffed = first.apply(diffs);
 
         assert_eq!(diffed.test1, second.test1);
         assert_eq!(diffed.test2, second.test2);
+        assert_eq!(diffed.test3, second.test3);
     }
 
     #[test]
@@ -977,6 +1673,29 @@ mod test {
         assert_eq!(diffed.test2, second.test2);
     }
 
+    #[cfg(all(feature = "serde", feature = "nanoserde"))]
+    #[test]
+    fn test_variant_indices() {
+        use super::OrderedArrayLikeChangeOwned;
+        use nanoserde::SerBin;
+
+        // serde formats tag variants by declaration order, so new variants are
+        // appended to keep older diffs decoding as the same change
+        let changes = [
+            OrderedArrayLikeChangeOwned::Replace(1_u8, 0),
+            OrderedArrayLikeChangeOwned::Insert(1, 0),
+            OrderedArrayLikeChangeOwned::Delete(0, None),
+            OrderedArrayLikeChangeOwned::Swap(0, 1),
+            OrderedArrayLikeChangeOwned::ReplaceRange(vec![1], 0),
+            OrderedArrayLikeChangeOwned::InsertMany(vec![1], 0),
+        ];
+        for (index, change) in changes.iter().enumerate() {
+            let serde_tag = bincode::serialize(change).unwrap();
+            assert_eq!(serde_tag[..4], (index as u32).to_le_bytes());
+            assert_eq!(SerBin::serialize_bin(change)[0], index as u8);
+        }
+    }
+
     mod problem_cases {
         use super::*;
 
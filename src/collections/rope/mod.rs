@@ -1,422 +1,673 @@
 use std::{
-    cmp::Ordering::{Equal, Greater},
-    collections::VecDeque,
+    cmp::Ordering,
     fmt::Debug,
-    num::NonZeroUsize,
-    ops::{Index, IndexMut, Neg},
+    hash::{Hash, Hasher},
+    ops::{Bound, Index, IndexMut, RangeBounds},
 };
 
-/// helper type for [`NodesWithCount::slot_mut_internal`] and [`NodesWithCount::remove_internal`]
-enum RetType {
-    Further(usize),
-    This(usize),
+/// Maximum number of items held by a single leaf
+const MAX_LEAF_LEN: usize = 64;
+/// Maximum number of children held by a single branch
+const MAX_BRANCH_LEN: usize = 16;
+/// Leaves with fewer items than this are merged with a neighbour
+const MIN_LEAF_LEN: usize = MAX_LEAF_LEN / 4;
+/// Branches with fewer children than this are merged with a neighbour
+const MIN_BRANCH_LEN: usize = MAX_BRANCH_LEN / 4;
+
+/// Every leaf of the tree is kept at the same depth, so a node's height is the
+/// number of branches between it and any of its leaves.
+#[derive(Clone)]
+enum Node<T> {
+    Leaf(Vec<T>),
+    Branch { len: usize, children: Vec<Node<T>> },
 }
 
-#[derive(Clone, Default)]
-enum Node<T> {
-    #[default]
-    Empty,
-    Single(T),
-    Multiple(NodesWithCount<T>),
+impl<T> Default for Node<T> {
+    fn default() -> Self {
+        Node::Leaf(Vec::new())
+    }
 }
 
 impl<T> Node<T> {
-    fn is_occupied(&self) -> bool {
-        matches!(
-            self,
-            Node::Multiple(NodesWithCount {
-                count: Some(..),
-                ..
-            }) | Node::Single(..)
-        )
+    fn branch(children: Vec<Node<T>>) -> Self {
+        Node::Branch {
+            len: children.iter().map(Node::len).sum(),
+            children,
+        }
     }
-}
 
-#[derive(Clone)]
-struct NodesWithCount<T> {
-    count: Option<NonZeroUsize>,
-    nodes: VecDeque<Node<T>>,
-}
+    #[inline]
+    fn len(&self) -> usize {
+        match self {
+            Node::Leaf(items) => items.len(),
+            Node::Branch { len, .. } => *len,
+        }
+    }
 
-impl<T> Default for NodesWithCount<T> {
-    fn default() -> Self {
-        Self {
-            count: None,
-            nodes: VecDeque::new(),
+    fn height(&self) -> usize {
+        match self {
+            Node::Leaf(_) => 0,
+            Node::Branch { children, .. } => 1 + children[0].height(),
         }
     }
-}
 
-#[derive(Clone)]
-pub struct Rope<T>(NodesWithCount<T>);
+    #[inline]
+    fn is_overfull(&self) -> bool {
+        match self {
+            Node::Leaf(items) => items.len() > MAX_LEAF_LEN,
+            Node::Branch { children, .. } => children.len() > MAX_BRANCH_LEN,
+        }
+    }
 
-impl<T> NodesWithCount<T> {
     #[inline]
-    fn shallow_len(&self) -> usize {
-        self.count.map(NonZeroUsize::get).unwrap_or_default()
-    }
-
-    fn pop_front(&mut self) -> Option<T> {
-        let node_mut = self
-            .nodes
-            .iter_mut()
-            .find(|v| matches!(v, Node::Single(..) | Node::Multiple(..)))?;
-        match node_mut {
-            Node::Empty => unreachable!(),
-            Node::Single(_) => {
-                let Node::Single(v) = std::mem::take(node_mut) else {
-                    unreachable!()
-                };
-                self.count = match self
-                    .count
-                    .expect("we're removing a node, count should be >= 1")
-                {
-                    NonZeroUsize::MIN => None,
-                    higher => Some(NonZeroUsize::new(higher.get() - 1).unwrap()),
-                };
-                Some(v)
-            }
-            Node::Multiple(nodes) => {
-                let v = nodes
-                    .pop_front()
-                    .expect("MUST be at least one value in a Node::Multiple");
-                self.count = match self
-                    .count
-                    .expect("we're removing a node, count should be >= 1")
-                {
-                    NonZeroUsize::MIN => None,
-                    higher => Some(NonZeroUsize::new(higher.get() - 1).unwrap()),
-                };
-                if nodes.shallow_len() == 0 {
-                    std::mem::take(node_mut);
-                }
-                Some(v)
+    fn is_underfull(&self) -> bool {
+        match self {
+            Node::Leaf(items) => items.len() < MIN_LEAF_LEN,
+            Node::Branch { children, .. } => children.len() < MIN_BRANCH_LEN,
+        }
+    }
+
+    /// find the child holding `idx`, and the index of the item within that child.
+    /// An `idx` equal to the length of the node resolves to the end of the last child.
+    fn locate(children: &[Node<T>], mut idx: usize) -> (usize, usize) {
+        let last = children.len() - 1;
+        for (child_idx, child) in children.iter().enumerate() {
+            let child_len = child.len();
+            if idx < child_len || child_idx == last {
+                return (child_idx, idx);
             }
+            idx -= child_len;
         }
+        unreachable!()
     }
 
-    fn index_internal(&self, idx: usize, mut last: Option<usize>) -> Option<&T> {
-        for node in &self.nodes {
-            match (node, &mut last) {
-                (Node::Empty, _) => continue,
-                (Node::Single(v), Some(last)) if *last == idx - 1 => return Some(v),
-                (Node::Single(_), Some(last)) => *last += 1,
-                (Node::Single(v), None) if idx == 0 => return Some(v),
-                (Node::Single(_), last @ None) => *last = Some(0),
-                (Node::Multiple(nodes), last)
-                    if nodes.shallow_len() + last.unwrap_or_default() >= idx =>
-                {
-                    return nodes.index_internal(idx, *last)
-                }
-                (Node::Multiple(nodes), last) => {
-                    *last =
-                        Some(last.unwrap_or_default() + nodes.count.map_or(0, NonZeroUsize::get))
+    fn get(&self, mut idx: usize) -> Option<&T> {
+        let mut node = self;
+        loop {
+            match node {
+                Node::Leaf(items) => return items.get(idx),
+                Node::Branch { len, children } => {
+                    if idx >= *len {
+                        return None;
+                    }
+                    let (child_idx, child_offset) = Self::locate(children, idx);
+                    node = &children[child_idx];
+                    idx = child_offset;
                 }
             }
         }
-
-        None
     }
 
-    fn index_mut_internal(&mut self, idx: usize, mut last: Option<usize>) -> Option<&mut T> {
-        for node in self.nodes.iter_mut() {
+    fn get_mut(&mut self, mut idx: usize) -> Option<&mut T> {
+        let mut node = self;
+        loop {
             match node {
-                Node::Empty => continue,
-                Node::Single(v) => {
-                    let curr = last.map(|l| l + 1).unwrap_or_default();
-                    match curr == idx {
-                        true => return Some(v),
-                        false => last = Some(curr),
+                Node::Leaf(items) => return items.get_mut(idx),
+                Node::Branch { len, children } => {
+                    if idx >= *len {
+                        return None;
                     }
+                    let (child_idx, child_offset) = Self::locate(children, idx);
+                    node = &mut children[child_idx];
+                    idx = child_offset;
                 }
-                Node::Multiple(nodes) => {
-                    // relying here on the fact that a Multiple MUST have at least one element
-                    let endex = match last {
-                        Some(last) => last + nodes.shallow_len(),
-                        None => nodes.shallow_len() - 1,
-                    };
-                    match endex >= idx {
-                        true => return nodes.index_mut_internal(idx, last),
-                        false => last = Some(endex),
-                    }
+            }
+        }
+    }
+
+    /// split off the upper half of this node's entries, returning them as a new sibling
+    fn split_half(&mut self) -> Node<T> {
+        match self {
+            Node::Leaf(items) => Node::Leaf(items.split_off(items.len() / 2)),
+            Node::Branch { len, children } => {
+                let sibling = Node::branch(children.split_off(children.len() / 2));
+                *len -= sibling.len();
+                sibling
+            }
+        }
+    }
+
+    /// move all entries of a node with the same height onto the end of this one
+    fn merge(&mut self, other: Node<T>) {
+        match (self, other) {
+            (Node::Leaf(items), Node::Leaf(other)) => items.extend(other),
+            (
+                Node::Branch { len, children },
+                Node::Branch {
+                    len: other_len,
+                    children: other_children,
+                },
+            ) => {
+                *len += other_len;
+                children.extend(other_children);
+            }
+            _ => unreachable!("only nodes of the same height may be merged"),
+        }
+    }
+
+    /// split this node if it has grown too large, returning the new right-hand sibling
+    #[inline]
+    fn split_if_overfull(&mut self) -> Option<Node<T>> {
+        self.is_overfull().then(|| self.split_half())
+    }
+
+    /// merge the child at `child_idx` with a neighbour if it has become too small,
+    /// re-splitting the merged node if the result is too large
+    fn rebalance_child(children: &mut Vec<Node<T>>, child_idx: usize) {
+        if children.len() < 2 || !children[child_idx].is_underfull() {
+            return;
+        }
+
+        let left = match child_idx + 1 < children.len() {
+            true => child_idx,
+            false => child_idx - 1,
+        };
+        let right = children.remove(left + 1);
+        children[left].merge(right);
+        if let Some(sibling) = children[left].split_if_overfull() {
+            children.insert(left + 1, sibling);
+        }
+    }
+
+    fn insert(&mut self, idx: usize, element: T) -> Option<Node<T>> {
+        match self {
+            Node::Leaf(items) => items.insert(idx, element),
+            Node::Branch { len, children } => {
+                *len += 1;
+                let (child_idx, child_offset) = Self::locate(children, idx);
+                if let Some(sibling) = children[child_idx].insert(child_offset, element) {
+                    children.insert(child_idx + 1, sibling);
                 }
             }
         }
+        self.split_if_overfull()
+    }
 
-        None
+    fn remove(&mut self, idx: usize) -> T {
+        match self {
+            Node::Leaf(items) => items.remove(idx),
+            Node::Branch { len, children } => {
+                *len -= 1;
+                let (child_idx, child_offset) = Self::locate(children, idx);
+                let ret = children[child_idx].remove(child_offset);
+                Self::rebalance_child(children, child_idx);
+                ret
+            }
+        }
     }
 
-    /// returns the parent of the Node::Single which holds the item at the relevant index, the
-    /// index within the parent of that Node::Single, and whether cleanups are needed due to empty Nodes::Multiple.
-    ///
-    /// Apply an optional `adjustment` to the node count at each layer
-    fn slot_mut_internal(
-        &mut self,
-        idx: usize,
-        mut last: Option<usize>,
-        adjustment: Option<isize>,
-    ) -> Option<(&mut NodesWithCount<T>, usize)> {
-        let mut return_idx = None;
-        for (inner_idx, node) in self.nodes.iter_mut().enumerate() {
-            match node {
-                Node::Empty => continue,
-                Node::Single(_) => {
-                    let curr = last.map(|l| l + 1).unwrap_or_default();
-                    match curr == idx {
-                        true => {
-                            return_idx = Some(RetType::This(inner_idx));
-                            break;
-                        }
-                        false => last = Some(curr),
+    /// split the node at `at`, returning everything after it as a node of the same height.
+    /// `at` must be strictly between zero and the length of the node.
+    fn split_off(&mut self, at: usize) -> Node<T> {
+        match self {
+            Node::Leaf(items) => Node::Leaf(items.split_off(at)),
+            Node::Branch { len, children } => {
+                let (child_idx, child_offset) = Self::locate(children, at);
+                let right_children = match child_offset {
+                    0 => children.split_off(child_idx),
+                    offset if offset == children[child_idx].len() => {
+                        children.split_off(child_idx + 1)
                     }
-                }
-                Node::Multiple(nodes) => {
-                    // relying here on the fact that a Multiple MUST have at least one element
-                    let endex = match last {
-                        Some(last) => last + nodes.shallow_len(),
-                        None => nodes.shallow_len() - 1,
-                    };
-                    match endex >= idx {
-                        true => {
-                            return_idx = Some(RetType::Further(inner_idx));
-                            break;
-                        }
-                        false => last = Some(endex),
+                    offset => {
+                        let split = children[child_idx].split_off(offset);
+                        let mut right_children = children.split_off(child_idx + 1);
+                        right_children.insert(0, split);
+                        right_children
                     }
+                };
+                let mut right = Node::branch(right_children);
+                *len -= right.len();
+
+                let last = children.len() - 1;
+                Self::rebalance_child(children, last);
+                if let Node::Branch { children, .. } = &mut right {
+                    Self::rebalance_child(children, 0);
                 }
+                right
             }
         }
+    }
 
-        return_idx.map(|inner_idx| {
-            match adjustment {
-                Some(pos @ 1..) => {
-                    self.count = Some(self.count.unwrap().checked_add(pos as usize).unwrap())
-                }
-                Some(neg @ ..-1) => {
-                    self.count = NonZeroUsize::new(self.count.unwrap().get() - neg.neg() as usize);
+    /// attach `other` to the right edge of this node, `depth` levels down.
+    /// Returns a new right-hand sibling if this node had to be split.
+    fn append(&mut self, other: Node<T>, depth: usize) -> Option<Node<T>> {
+        match (depth, &mut *self) {
+            (0, _) => self.merge(other),
+            (_, Node::Branch { len, children }) => {
+                *len += other.len();
+                let last = children.len() - 1;
+                if let Some(sibling) = children[last].append(other, depth - 1) {
+                    children.push(sibling);
                 }
-                _ => (),
             }
-            match inner_idx {
-                RetType::Further(i) => {
-                    let Some(Node::Multiple(mult)) = self.nodes.get_mut(i) else {
-                        panic!()
-                    };
-                    mult.slot_mut_internal(idx, last, adjustment).unwrap()
-                }
-                RetType::This(i) => (self, i),
-            }
-        })
+            (_, Node::Leaf(_)) => unreachable!("leaves have no children to append to"),
+        }
+        self.split_if_overfull()
     }
 
-    fn insert_internal(&mut self, idx: usize, element: T) {
-        match (self.count, idx) {
-            (None, 0) => {
-                return {
-                    let len = self.nodes.len();
-                    match len == 0 {
-                        true => self.nodes.push_back(Node::Single(element)),
-                        false => self.nodes[len.saturating_div(2)] = Node::Single(element),
-                    }
-                    self.count = Some(NonZeroUsize::MIN);
-                }
+    /// attach `other` to the left edge of this node, `depth` levels down.
+    /// Returns a new right-hand sibling if this node had to be split.
+    fn prepend(&mut self, other: Node<T>, depth: usize) -> Option<Node<T>> {
+        match (depth, &mut *self) {
+            (0, _) => {
+                let this = std::mem::replace(self, other);
+                self.merge(this);
             }
-            (Some(len), idx) if len.get() == idx => {
-                return {
-                    // check if there's a Node::Empty that we can replace with a Node::Single(element), otherwise append to top level
-                    let last_occupied = self
-                        .nodes
-                        .iter()
-                        .enumerate()
-                        .rev()
-                        .find_map(|(idx, v)| v.is_occupied().then_some(idx))
-                        .unwrap();
-                    self.count = Some(
-                        self.count
-                            .map_or(NonZeroUsize::MIN, |v| v.checked_add(1).unwrap()),
-                    );
-                    match self.nodes.get_mut(last_occupied + 1) {
-                        None => self.nodes.push_back(Node::Single(element)),
-                        Some(empty) => *empty = Node::Single(element),
-                    };
-                };
+            (_, Node::Branch { len, children }) => {
+                *len += other.len();
+                if let Some(sibling) = children[0].prepend(other, depth - 1) {
+                    children.insert(1, sibling);
+                }
             }
-            _ => (),
+            (_, Node::Leaf(_)) => unreachable!("leaves have no children to prepend to"),
         }
+        self.split_if_overfull()
+    }
+}
 
-        // it's neither a front nor a back, find the internal slot that holds the item at the relevent index and either push it if
-        // it's at front/back of its vec, or replace the Node::Single with a Node::Multiple
-        let (nodes, slot, ..) = self.slot_mut_internal(idx, None, Some(1)).unwrap();
+/// A chunked sequence, stored as a balanced tree of small contiguous leaves.
+///
+/// Indexing, insertion and removal at arbitrary positions, as well as splitting and
+/// concatenation, are all logarithmic in the length of the sequence. This makes it
+/// a good fit for long lists which are edited in many places, like the targets of
+/// `ordered_array_like` diffs.
+///
+/// ```
+/// use structdiff::collections::rope::Rope;
+///
+/// let mut rope: Rope<i32> = (0..10).collect();
+/// rope.insert(5, 42);
+/// assert_eq!(rope.remove(0), 0);
+/// assert_eq!(rope[4], 42);
+///
+/// let tail = rope.split_off(5);
+/// assert_eq!(rope.iter().copied().collect::<Vec<_>>(), vec![1, 2, 3, 4, 42]);
+/// assert_eq!(tail.len(), 5);
+/// ```
+#[derive(Clone)]
+pub struct Rope<T> {
+    root: Node<T>,
+}
+
+impl<T> Default for Rope<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
 
-        // check for special cases
-        if slot == 0 {
-            return nodes.nodes.push_front(Node::Single(element));
+impl<T> Rope<T> {
+    /// Create an empty rope
+    pub fn new() -> Self {
+        Self {
+            root: Node::default(),
         }
-        if let Some(Node::Multiple(before)) = nodes.nodes.get_mut(slot - 1) {
-            before.count = before.count.map(|c| c.checked_add(1)).unwrap();
-            before.nodes.push_back(Node::Single(element));
+    }
+
+    /// Number of items in the rope
+    pub fn len(&self) -> usize {
+        self.root.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Get a reference to the item at `index`, if there is one
+    pub fn get(&self, index: usize) -> Option<&T> {
+        self.root.get(index)
+    }
+
+    /// Get a mutable reference to the item at `index`, if there is one
+    pub fn get_mut(&mut self, index: usize) -> Option<&mut T> {
+        self.root.get_mut(index)
+    }
+
+    pub fn first(&self) -> Option<&T> {
+        self.get(0)
+    }
+
+    pub fn last(&self) -> Option<&T> {
+        self.len().checked_sub(1).and_then(|idx| self.get(idx))
+    }
+
+    /// Insert an item at `index`, shifting all items after it to the right.
+    ///
+    /// Panics if `index > len`.
+    pub fn insert(&mut self, index: usize, element: T) {
+        assert!(
+            index <= self.len(),
+            "insertion index (is {index}) should be <= len (is {})",
+            self.len()
+        );
+        if let Some(sibling) = self.root.insert(index, element) {
+            let left = std::mem::take(&mut self.root);
+            self.root = Node::branch(vec![left, sibling]);
+        }
+    }
+
+    /// Remove and return the item at `index`, shifting all items after it to the left.
+    ///
+    /// Panics if `index >= len`.
+    pub fn remove(&mut self, index: usize) -> T {
+        assert!(
+            index < self.len(),
+            "removal index (is {index}) should be < len (is {})",
+            self.len()
+        );
+        let ret = self.root.remove(index);
+        self.collapse_root();
+        ret
+    }
+
+    /// Append an item to the back of the rope
+    pub fn push(&mut self, element: T) {
+        self.insert(self.len(), element);
+    }
+
+    /// Remove the last item from the rope and return it, if there is one
+    pub fn pop(&mut self) -> Option<T> {
+        match self.len() {
+            0 => None,
+            len => Some(self.remove(len - 1)),
+        }
+    }
+
+    /// Swap the items at indices `a` and `b`.
+    ///
+    /// Panics if either index is out of bounds.
+    pub fn swap(&mut self, a: usize, b: usize) {
+        if a == b {
             return;
         }
+        let (low, high) = (a.min(b), a.max(b));
+        let high_elem = self.remove(high);
+        let low_elem = std::mem::replace(&mut self[low], high_elem);
+        self.insert(high, low_elem);
+    }
+
+    /// Split the rope in two at `at`. `self` keeps the items in `[0, at)`,
+    /// and the items in `[at, len)` are returned.
+    ///
+    /// Panics if `at > len`.
+    pub fn split_off(&mut self, at: usize) -> Rope<T> {
+        assert!(
+            at <= self.len(),
+            "`at` split index (is {at}) should be <= len (is {})",
+            self.len()
+        );
+        if at == 0 {
+            return std::mem::take(self);
+        }
+        if at == self.len() {
+            return Rope::new();
+        }
+
+        let mut ret = Rope {
+            root: self.root.split_off(at),
+        };
+        self.collapse_root();
+        ret.collapse_root();
+        ret
+    }
+
+    /// Move all items of `other` onto the end of `self`, leaving `other` empty.
+    pub fn append(&mut self, other: &mut Rope<T>) {
+        let other = std::mem::take(other);
+        if other.is_empty() {
+            return;
+        }
+        if self.is_empty() {
+            *self = other;
+            return;
+        }
+
+        // attach the shorter tree to the edge of the taller one, at the level where
+        // their heights match
+        let (left_height, right_height) = (self.root.height(), other.root.height());
+        let (mut root, sibling) = match left_height >= right_height {
+            true => {
+                let mut root = std::mem::take(&mut self.root);
+                let sibling = root.append(other.root, left_height - right_height);
+                (root, sibling)
+            }
+            false => {
+                let mut root = other.root;
+                let left = std::mem::take(&mut self.root);
+                let sibling = root.prepend(left, right_height - left_height);
+                (root, sibling)
+            }
+        };
+
+        if let Some(sibling) = sibling {
+            root = Node::branch(vec![root, sibling]);
+        }
+        self.root = root;
+    }
+
+    /// Remove the items in `range`, returning them as an iterator.
+    /// The items are removed even if the iterator is not consumed.
+    ///
+    /// Panics if the range is out of bounds.
+    pub fn drain<R: RangeBounds<usize>>(&mut self, range: R) -> IntoIter<T> {
+        let (start, end) = self.resolve_range(range);
+        let mut tail = self.split_off(end);
+        let drained = self.split_off(start);
+        self.append(&mut tail);
+        drained.into_iter()
+    }
 
-        let node_slot = &mut nodes.nodes[slot];
-        let prev = std::mem::take(node_slot);
-        debug_assert!(matches!(prev, Node::Single(..)));
+    /// Keep only the items for which `keep` returns true, preserving their order
+    pub fn retain<F: FnMut(&T) -> bool>(&mut self, mut keep: F) {
+        *self = std::mem::take(self)
+            .into_iter()
+            .filter(|item| keep(item))
+            .collect();
+    }
 
-        *node_slot = Node::Multiple(NodesWithCount {
-            count: Some(NonZeroUsize::new(2).unwrap()),
-            nodes: vec![Node::Single(element), prev].into(),
-        });
+    /// Remove every item from the rope
+    pub fn clear(&mut self) {
+        self.root = Node::default();
     }
 
-    /// Get the item at index `idx`, patching up Node type at each level if they become empty
-    fn remove_internal(&mut self, idx: usize, mut last: Option<usize>) -> Option<T> {
-        let mut return_idx = None;
-        for (inner_idx, node) in self.nodes.iter_mut().enumerate() {
+    /// Iterate over all items of the rope, in order
+    pub fn iter(&self) -> Iter<'_, T> {
+        self.range(..)
+    }
+
+    /// Iterate over the items in `range`, in order.
+    ///
+    /// Panics if the range is out of bounds.
+    pub fn range<R: RangeBounds<usize>>(&self, range: R) -> Iter<'_, T> {
+        let (start, end) = self.resolve_range(range);
+        let mut iter = Iter {
+            stack: Vec::new(),
+            leaf: [].iter(),
+            remaining: end - start,
+        };
+
+        if iter.remaining == 0 {
+            return iter;
+        }
+
+        let mut node = &self.root;
+        let mut idx = start;
+        loop {
             match node {
-                Node::Empty => continue,
-                Node::Single(_) => {
-                    let curr = last.map(|l| l + 1).unwrap_or_default();
-                    match curr == idx {
-                        true => {
-                            return_idx = Some(RetType::This(inner_idx));
-                            break;
-                        }
-                        false => last = Some(curr),
-                    }
+                Node::Leaf(items) => {
+                    iter.leaf = items[idx..].iter();
+                    return iter;
                 }
-                Node::Multiple(nodes) => {
-                    // relying here on the fact that a Multiple MUST have at least one element
-                    let endex = match last {
-                        Some(last) => last + nodes.count.unwrap().get(),
-                        None => nodes.count.unwrap().get() - 1,
-                    };
-                    match endex >= idx {
-                        true => {
-                            return_idx = Some(RetType::Further(inner_idx));
-                            break;
-                        }
-                        false => last = Some(endex),
-                    }
+                Node::Branch { children, .. } => {
+                    let (child_idx, child_offset) = Node::locate(children, idx);
+                    iter.stack.push(children[child_idx + 1..].iter());
+                    node = &children[child_idx];
+                    idx = child_offset;
                 }
             }
         }
+    }
 
-        let inner_idx = return_idx?;
+    fn resolve_range<R: RangeBounds<usize>>(&self, range: R) -> (usize, usize) {
+        let start = match range.start_bound() {
+            Bound::Included(start) => *start,
+            Bound::Excluded(start) => start + 1,
+            Bound::Unbounded => 0,
+        };
+        let end = match range.end_bound() {
+            Bound::Included(end) => end + 1,
+            Bound::Excluded(end) => *end,
+            Bound::Unbounded => self.len(),
+        };
+        assert!(
+            start <= end,
+            "range start (is {start}) should be <= range end (is {end})"
+        );
+        assert!(
+            end <= self.len(),
+            "range end (is {end}) should be <= len (is {})",
+            self.len()
+        );
+        (start, end)
+    }
 
-        self.count = NonZeroUsize::new(self.count.unwrap().get() - 1_usize);
+    /// replace single-child branches at the root with their child
+    fn collapse_root(&mut self) {
+        while let Node::Branch { children, .. } = &mut self.root {
+            if children.len() != 1 {
+                break;
+            }
+            self.root = children.pop().unwrap();
+        }
+    }
+}
 
-        match inner_idx {
-            RetType::Further(i) => {
-                let Some(Node::Multiple(mult)) = self.nodes.get_mut(i) else {
-                    panic!()
-                };
-                let ret = mult.remove_internal(idx, last);
+impl<T> FromIterator<T> for Rope<T> {
+    fn from_iter<C: IntoIterator<Item = T>>(iter: C) -> Self {
+        let mut iter = iter.into_iter();
+        let mut nodes = Vec::new();
+        loop {
+            let leaf = iter.by_ref().take(MAX_LEAF_LEN).collect::<Vec<_>>();
+            if leaf.is_empty() {
+                break;
+            }
+            nodes.push(Node::Leaf(leaf));
+        }
 
-                if mult.count.is_none() {
-                    self.nodes[i] = Node::Empty;
+        // build the tree bottom-up, one layer of branches at a time
+        while nodes.len() > 1 {
+            let mut layer = Vec::with_capacity(nodes.len().div_ceil(MAX_BRANCH_LEN));
+            let mut nodes_iter = nodes.into_iter();
+            loop {
+                let children = nodes_iter.by_ref().take(MAX_BRANCH_LEN).collect::<Vec<_>>();
+                if children.is_empty() {
+                    break;
                 }
-
-                ret
-            }
-            RetType::This(i) => {
-                let Some(Node::Single(single)) = self.nodes.get_mut(i).map(std::mem::take) else {
-                    panic!()
-                };
-                Some(single)
+                layer.push(Node::branch(children));
             }
+            nodes = layer;
+        }
+
+        Self {
+            root: nodes.pop().unwrap_or_default(),
         }
     }
+}
 
-    fn swap_internal(&mut self, [low, high]: [usize; 2], _arg: i32) {
-        let high_elem = self.remove_internal(high, None).unwrap();
-        let (nodes, slot) = self.slot_mut_internal(low, None, None).unwrap();
+impl<T> Extend<T> for Rope<T> {
+    fn extend<I: IntoIterator<Item = T>>(&mut self, iter: I) {
+        let mut other = iter.into_iter().collect();
+        self.append(&mut other);
+    }
+}
 
-        let Node::Single(low) = std::mem::replace(&mut nodes.nodes[slot], Node::Single(high_elem))
-        else {
-            panic!();
-        };
-        self.insert_internal(high, low);
+impl<'a, T: Clone + 'a> Extend<&'a T> for Rope<T> {
+    fn extend<I: IntoIterator<Item = &'a T>>(&mut self, iter: I) {
+        self.extend(iter.into_iter().cloned());
     }
+}
 
-    fn drain_internal(&mut self, l_idx: usize, r_idx: usize, mut last: Option<usize>) {
-        let mut removed = 0;
-        for node in self.nodes.iter_mut() {
-            match node {
-                Node::Empty => continue,
-                single @ Node::Single(_) => {
-                    let new_last = last.map(|i| i + 1).unwrap_or_default();
-                    if (l_idx..=r_idx).contains(&new_last) {
-                        *single = Node::Empty;
-                        removed += 1;
+/// Borrowing iterator over a range of a [`Rope`]
+pub struct Iter<'a, T> {
+    /// the unvisited siblings at each level of the tree above the current leaf
+    stack: Vec<std::slice::Iter<'a, Node<T>>>,
+    leaf: std::slice::Iter<'a, T>,
+    remaining: usize,
+}
+
+impl<'a, T> Iterator for Iter<'a, T> {
+    type Item = &'a T;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.remaining == 0 {
+            return None;
+        }
+
+        loop {
+            if let Some(item) = self.leaf.next() {
+                self.remaining -= 1;
+                return Some(item);
+            }
+
+            // current leaf is exhausted, walk up until there's an unvisited sibling and then
+            // back down to the leftmost leaf under it
+            let mut node = loop {
+                let siblings = self.stack.last_mut()?;
+                match siblings.next() {
+                    Some(node) => break node,
+                    None => {
+                        self.stack.pop();
                     }
-                    last = Some(new_last);
                 }
-                Node::Multiple(nodes_with_count) => {
-                    let nodes_with_count_len = nodes_with_count.count.unwrap().get();
-                    // get the endex of the last element of nodes_with_count
-                    let last_after_sub = match last {
-                        Some(last) => last + nodes_with_count_len,
-                        None => nodes_with_count_len - 1,
-                    };
-
-                    // check if the endex is still lower than the lowest value we are to remove
-                    if last_after_sub < l_idx {
-                        last = Some(last_after_sub);
-                        continue;
+            };
+            loop {
+                match node {
+                    Node::Leaf(items) => {
+                        self.leaf = items.iter();
+                        break;
                     }
-
-                    // we need to remove some things; figure out if we can remove the entire subtree or need to recurse
-                    match r_idx.cmp(&last_after_sub) {
-                        // we can yeet the whole thing
-                        Equal | Greater
-                            if last.map_or_else(
-                                || l_idx == last.unwrap_or_default(),
-                                |l| l_idx <= l + 1,
-                            ) =>
-                        {
-                            let before = nodes_with_count_len;
-                            *node = Node::Empty;
-                            removed += before;
-                        }
-                        // we need to remove only part of this node
-                        _ => {
-                            let before = nodes_with_count_len;
-                            nodes_with_count.drain_internal(l_idx, r_idx, last);
-                            let after = nodes_with_count.count.unwrap().get();
-                            removed += before.abs_diff(after);
-                        }
+                    Node::Branch { children, .. } => {
+                        let mut children = children.iter();
+                        node = children.next()?;
+                        self.stack.push(children);
                     }
-                    last = Some(last_after_sub);
                 }
             }
-
-            if last.is_some_and(|l| l >= r_idx) {
-                break;
-            }
         }
+    }
 
-        self.count = self
-            .count
-            .map(|c| c.get() - removed)
-            .and_then(NonZeroUsize::new);
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.remaining, Some(self.remaining))
     }
 }
 
-impl<T> FromIterator<T> for Rope<T> {
-    fn from_iter<C: IntoIterator<Item = T>>(iter: C) -> Self {
-        let nodes = iter
-            .into_iter()
-            .map(|t| Node::Single(t))
-            .collect::<VecDeque<_>>();
-        Self(NodesWithCount {
-            count: NonZeroUsize::try_from(nodes.len()).ok(),
-            nodes,
-        })
+impl<T> ExactSizeIterator for Iter<'_, T> {}
+
+impl<T> Clone for Iter<'_, T> {
+    fn clone(&self) -> Self {
+        Self {
+            stack: self.stack.clone(),
+            leaf: self.leaf.clone(),
+            remaining: self.remaining,
+        }
     }
 }
 
+impl<'a, T> IntoIterator for &'a Rope<T> {
+    type Item = &'a T;
+
+    type IntoIter = Iter<'a, T>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+/// Owning iterator over the items of a [`Rope`]
 pub struct IntoIter<T> {
-    owned: NodesWithCount<T>,
+    /// the unvisited siblings at each level of the tree above the current leaf
+    stack: Vec<std::vec::IntoIter<Node<T>>>,
+    leaf: std::vec::IntoIter<T>,
+    remaining: usize,
 }
 
 impl<T> IntoIterator for Rope<T> {
@@ -425,7 +676,18 @@ impl<T> IntoIterator for Rope<T> {
     type IntoIter = IntoIter<T>;
 
     fn into_iter(self) -> Self::IntoIter {
-        IntoIter { owned: self.0 }
+        let remaining = self.len();
+        let (stack, leaf) = match self.root {
+            Node::Leaf(items) => (Vec::new(), items.into_iter()),
+            branch @ Node::Branch { .. } => {
+                (vec![vec![branch].into_iter()], Vec::new().into_iter())
+            }
+        };
+        IntoIter {
+            stack,
+            leaf,
+            remaining,
+        }
     }
 }
 
@@ -433,94 +695,311 @@ impl<T> Iterator for IntoIter<T> {
     type Item = T;
 
     fn next(&mut self) -> Option<Self::Item> {
-        self.owned.pop_front()
+        loop {
+            if let Some(item) = self.leaf.next() {
+                self.remaining -= 1;
+                return Some(item);
+            }
+
+            let mut node = loop {
+                let siblings = self.stack.last_mut()?;
+                match siblings.next() {
+                    Some(node) => break node,
+                    None => {
+                        self.stack.pop();
+                    }
+                }
+            };
+            loop {
+                match node {
+                    Node::Leaf(items) => {
+                        self.leaf = items.into_iter();
+                        break;
+                    }
+                    Node::Branch { children, .. } => {
+                        let mut children = children.into_iter();
+                        node = children.next()?;
+                        self.stack.push(children);
+                    }
+                }
+            }
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.remaining, Some(self.remaining))
     }
 }
 
+impl<T> ExactSizeIterator for IntoIter<T> {}
+
 impl<T> Index<usize> for Rope<T> {
     type Output = T;
 
     fn index(&self, index: usize) -> &Self::Output {
-        self.0
-            .index_internal(index, None)
-            .expect("Failed to find element")
+        self.get(index).expect("Failed to find element")
     }
 }
 
 impl<T> IndexMut<usize> for Rope<T> {
     fn index_mut(&mut self, index: usize) -> &mut Self::Output {
-        self.0
-            .index_mut_internal(index, None)
-            .expect("Failed to find element")
+        self.get_mut(index).expect("Failed to find element")
     }
 }
 
-impl<T: Debug> std::fmt::Debug for Node<T> {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        match self {
-            Self::Empty => write!(f, "Node::Empty"),
-            Self::Single(arg0) => f.debug_tuple("Node::Single").field(arg0).finish(),
-            Self::Multiple(arg0) => f.debug_tuple("Node::Multiple").field(arg0).finish(),
+impl<T: PartialEq> PartialEq for Rope<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.len() == other.len() && self.iter().eq(other.iter())
+    }
+}
+
+impl<T: Eq> Eq for Rope<T> {}
+
+impl<T: PartialOrd> PartialOrd for Rope<T> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        self.iter().partial_cmp(other.iter())
+    }
+}
+
+impl<T: Ord> Ord for Rope<T> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.iter().cmp(other.iter())
+    }
+}
+
+impl<T: Hash> Hash for Rope<T> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        state.write_usize(self.len());
+        for item in self {
+            item.hash(state);
         }
     }
 }
 
-impl<T: Debug> std::fmt::Debug for NodesWithCount<T> {
+impl<T: Debug> Debug for Rope<T> {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        f.debug_struct("NodesWithCount")
-            .field("count", &self.count)
-            .field("nodes", &self.nodes)
-            .finish()
+        f.debug_list().entries(self.iter()).finish()
     }
 }
 
-impl<T: Debug> std::fmt::Debug for Rope<T> {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        f.debug_tuple("Rope").field(&self.0).finish()
+impl<T> From<Vec<T>> for Rope<T> {
+    fn from(value: Vec<T>) -> Self {
+        value.into_iter().collect()
     }
 }
 
-impl<T> Rope<T> {
-    pub fn len(&self) -> usize {
-        self.0.count.map_or(0, usize::from)
+impl<T> From<Rope<T>> for Vec<T> {
+    fn from(value: Rope<T>) -> Self {
+        value.into_iter().collect()
     }
+}
 
-    pub fn insert(&mut self, index: usize, element: T) {
-        self.0.insert_internal(index, element);
+#[cfg(feature = "serde")]
+mod serde_impls {
+    use super::Rope;
+    use serde::{
+        de::{SeqAccess, Visitor},
+        ser::SerializeSeq,
+        Deserialize, Deserializer, Serialize, Serializer,
+    };
+
+    impl<T: Serialize> Serialize for Rope<T> {
+        fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+            let mut seq = serializer.serialize_seq(Some(self.len()))?;
+            for item in self {
+                seq.serialize_element(item)?;
+            }
+            seq.end()
+        }
     }
 
-    pub fn remove(&mut self, index: usize) {
-        self.0
-            .remove_internal(index, None)
-            .expect("No item at index");
+    impl<'de, T: Deserialize<'de>> Deserialize<'de> for Rope<T> {
+        fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+            struct RopeVisitor<T>(std::marker::PhantomData<T>);
+
+            impl<'de, T: Deserialize<'de>> Visitor<'de> for RopeVisitor<T> {
+                type Value = Rope<T>;
+
+                fn expecting(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
+                    formatter.write_str("a sequence")
+                }
+
+                fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Self::Value, A::Error> {
+                    let mut items = Vec::with_capacity(seq.size_hint().unwrap_or_default());
+                    while let Some(item) = seq.next_element()? {
+                        items.push(item);
+                    }
+                    Ok(items.into())
+                }
+            }
+
+            deserializer.deserialize_seq(RopeVisitor(std::marker::PhantomData))
+        }
     }
+}
 
-    pub fn drain<R: std::ops::RangeBounds<usize>>(&mut self, range: R) {
-        use std::ops::Bound;
+#[cfg(feature = "nanoserde")]
+mod nanoserde_impls {
+    use super::Rope;
+    use crate::checked::DeBinChecked;
+    use nanoserde::{DeBin, SerBin};
+
+    impl<T: SerBin> SerBin for Rope<T> {
+        fn ser_bin(&self, output: &mut Vec<u8>) {
+            self.len().ser_bin(output);
+            for item in self {
+                item.ser_bin(output);
+            }
+        }
+    }
 
-        let (l_idx, r_idx) = match (range.start_bound(), range.end_bound()) {
-            (Bound::Included(l_i), Bound::Excluded(r_e)) if l_i == r_e => return,
-            (Bound::Included(l_i), Bound::Included(r_i)) => (*l_i, *r_i),
-            (Bound::Included(l_i), Bound::Excluded(r_e)) => (*l_i, r_e - 1),
-            (Bound::Included(l_i), Bound::Unbounded) => (*l_i, self.len() - 1),
-            (Bound::Excluded(l_e), Bound::Included(r_i)) => (l_e + 1, *r_i),
-            (Bound::Excluded(l_e), Bound::Excluded(r_e)) => (l_e + 1, r_e - 1),
-            (Bound::Excluded(l_e), Bound::Unbounded) => (l_e + 1, self.len() - 1),
-            (Bound::Unbounded, Bound::Included(r_i)) => (0, *r_i),
-            (Bound::Unbounded, Bound::Excluded(r_e)) => (0, r_e - 1),
-            (Bound::Unbounded, Bound::Unbounded) => (0, self.len() - 1),
-        };
+    impl<T: DeBinChecked> DeBin for Rope<T> {
+        fn de_bin(offset: &mut usize, bytes: &[u8]) -> Result<Self, nanoserde::DeBinErr> {
+            let ret = crate::checked::de_bin_vec_with(offset, bytes, T::de_bin_checked)?;
+            Ok(ret.into())
+        }
+    }
 
-        if r_idx == 0 && l_idx == 0 && self.0.shallow_len() == 0 {
-            return;
+    impl<T: DeBinChecked> DeBinChecked for Rope<T> {}
+}
+
+#[cfg(test)]
+mod test {
+    use super::{Node, Rope};
+    use nanorand::{Rng, WyRand};
+
+    /// check that all leaves are at the same depth and that the cached lengths are correct
+    fn check_invariants<T>(node: &Node<T>) -> (usize, usize) {
+        match node {
+            Node::Leaf(items) => (0, items.len()),
+            Node::Branch { len, children } => {
+                assert!(!children.is_empty());
+                let (heights, lens): (Vec<_>, Vec<_>) =
+                    children.iter().map(check_invariants).unzip();
+                assert!(heights.iter().all(|h| *h == heights[0]));
+                assert_eq!(*len, lens.iter().sum::<usize>());
+                (heights[0] + 1, *len)
+            }
         }
+    }
 
-        self.0.drain_internal(l_idx, r_idx, None);
+    #[test]
+    fn test_random_ops() {
+        let mut rng = WyRand::new();
+        let mut rope: Rope<u32> = (0..500).collect();
+        let mut vec: Vec<u32> = (0..500).collect();
+
+        for _ in 0..5000 {
+            match rng.generate_range(0_u8..8) {
+                0 | 1 => {
+                    let idx = rng.generate_range(0..=vec.len());
+                    let val = rng.generate();
+                    rope.insert(idx, val);
+                    vec.insert(idx, val);
+                }
+                2 if !vec.is_empty() => {
+                    let idx = rng.generate_range(0..vec.len());
+                    assert_eq!(rope.remove(idx), vec.remove(idx));
+                }
+                3 => {
+                    let val = rng.generate();
+                    rope.push(val);
+                    vec.push(val);
+                }
+                4 => assert_eq!(rope.pop(), vec.pop()),
+                5 if !vec.is_empty() => {
+                    let (a, b) = (
+                        rng.generate_range(0..vec.len()),
+                        rng.generate_range(0..vec.len()),
+                    );
+                    rope.swap(a, b);
+                    vec.swap(a, b);
+                }
+                6 => {
+                    let start = rng.generate_range(0..=vec.len());
+                    let end = rng.generate_range(start..=vec.len());
+                    assert!(rope.drain(start..end).eq(vec.drain(start..end)));
+                }
+                _ => {
+                    let at = rng.generate_range(0..=vec.len());
+                    let mut rope_tail = rope.split_off(at);
+                    let vec_tail = vec.split_off(at);
+                    assert!(rope_tail.iter().eq(vec_tail.iter()));
+                    rope.append(&mut rope_tail);
+                    vec.extend(vec_tail);
+                    assert!(rope_tail.is_empty());
+                }
+            }
+            check_invariants(&rope.root);
+            assert_eq!(rope.len(), vec.len());
+        }
+
+        assert!(rope.iter().eq(vec.iter()));
+        assert!(rope.into_iter().eq(vec.into_iter()));
     }
 
-    pub fn swap(&mut self, a: usize, b: usize) {
-        if a != b {
-            self.0.swap_internal([a.min(b), a.max(b)], 0);
+    #[test]
+    fn test_range_and_index() {
+        let mut rope: Rope<usize> = (0..1000).collect();
+        let vec: Vec<usize> = (0..1000).collect();
+
+        for (start, end) in [(0, 0), (0, 1000), (10, 700), (999, 1000), (64, 128)] {
+            assert!(rope.range(start..end).eq(vec[start..end].iter()));
+            assert_eq!(rope.range(start..end).len(), end - start);
         }
+
+        for idx in [0, 63, 64, 500, 999] {
+            assert_eq!(rope[idx], idx);
+            rope[idx] += 1;
+            assert_eq!(rope.get(idx), Some(&(idx + 1)));
+        }
+        assert_eq!(rope.get(1000), None);
+        assert_eq!(rope.get_mut(1000), None);
+    }
+
+    #[test]
+    fn test_append_uneven_heights() {
+        let mut rng = WyRand::new();
+        for _ in 0..50 {
+            let left_len = rng.generate_range(0_usize..5000);
+            let right_len = rng.generate_range(0_usize..5000);
+            let mut left: Rope<usize> = (0..left_len).collect();
+            let mut right: Rope<usize> = (left_len..left_len + right_len).collect();
+
+            left.append(&mut right);
+            check_invariants(&left.root);
+            assert!(left.iter().copied().eq(0..left_len + right_len));
+        }
+    }
+
+    #[test]
+    fn test_retain_extend_eq_hash() {
+        use std::collections::hash_map::DefaultHasher;
+        use std::hash::{Hash, Hasher};
+
+        let mut rope: Rope<i32> = (0..300).collect();
+        rope.retain(|x| x % 3 == 0);
+        rope.extend(1000..1100);
+        rope.extend(&[1, 2, 3]);
+
+        let vec: Vec<i32> = (0..300)
+            .filter(|x| x % 3 == 0)
+            .chain(1000..1100)
+            .chain([1, 2, 3])
+            .collect();
+        let other: Rope<i32> = vec.clone().into();
+
+        assert_eq!(rope, other);
+        assert_eq!(Vec::from(rope.clone()), vec);
+
+        let hash = |rope: &Rope<i32>| {
+            let mut hasher = DefaultHasher::new();
+            rope.hash(&mut hasher);
+            hasher.finish()
+        };
+        assert_eq!(hash(&rope), hash(&other));
+
+        rope.pop();
+        assert_ne!(rope, other);
     }
 }
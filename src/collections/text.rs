@@ -0,0 +1,749 @@
+use std::{borrow::Cow, ops::Range};
+
+#[cfg(any(feature = "serde", feature = "nanoserde"))]
+use crate::borrowed::BorrowedStr;
+use crate::{
+    limits::{ApplyBudget, LimitExceeded},
+    size::EncodedSize,
+};
+
+/// Above this many token edits, the changed region is emitted as a single splice
+/// rather than searching for a minimal edit script.
+const MAX_EDIT_DISTANCE: usize = 1024;
+
+// each splice holds at least one edit, so a text diff never needs to be split
+// across several diffs when it is streamed into a sink
+const _: () = assert!(MAX_EDIT_DISTANCE <= super::CHANGES_PER_DIFF);
+
+/// The unit which text is compared in. Coarser granularities produce fewer,
+/// larger splices and are faster to diff.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum TextGranularity {
+    /// Compare individual bytes. Splices are widened to the nearest `char` boundaries.
+    Bytes,
+    #[default]
+    Chars,
+    /// Compare runs of whitespace and runs of non-whitespace
+    Words,
+    /// Compare lines, including their trailing `'\n'`
+    Lines,
+}
+
+/// Replace the bytes `start..end` of the original string with `replacement`
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub(crate) struct TextSpliceRef<'src> {
+    start: usize,
+    end: usize,
+    replacement: &'src str,
+}
+
+/// Replace the bytes `start..end` of the original string with `replacement`
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub(crate) struct TextSpliceOwned {
+    start: usize,
+    end: usize,
+    replacement: String,
+}
+
+impl<'src> From<TextSpliceRef<'src>> for TextSpliceOwned {
+    fn from(value: TextSpliceRef<'src>) -> Self {
+        Self {
+            start: value.start,
+            end: value.end,
+            replacement: value.replacement.to_owned(),
+        }
+    }
+}
+
+/// A set of non-overlapping splices, sorted by position. All ranges are byte
+/// offsets into the original string.
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct TextDiffOwned(Vec<TextSpliceOwned>);
+
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct TextDiffRef<'src>(Vec<TextSpliceRef<'src>>);
+
+impl<'src> From<TextDiffRef<'src>> for TextDiffOwned {
+    fn from(value: TextDiffRef<'src>) -> Self {
+        Self(value.0.into_iter().map(Into::into).collect())
+    }
+}
+
+impl EncodedSize for TextSpliceRef<'_> {
+    fn encoded_size(&self) -> usize {
+        self.start.encoded_size() + self.end.encoded_size() + self.replacement.encoded_size()
+    }
+}
+
+impl EncodedSize for TextSpliceOwned {
+    fn encoded_size(&self) -> usize {
+        self.start.encoded_size() + self.end.encoded_size() + self.replacement.encoded_size()
+    }
+}
+
+impl EncodedSize for TextDiffRef<'_> {
+    fn encoded_size(&self) -> usize {
+        self.0.encoded_size()
+    }
+}
+
+impl EncodedSize for TextDiffOwned {
+    fn encoded_size(&self) -> usize {
+        self.0.encoded_size()
+    }
+}
+
+impl TextDiffOwned {
+    /// Iterate over the `(range, replacement)` pairs of this diff, in order
+    pub fn splices(&self) -> impl Iterator<Item = (Range<usize>, &str)> {
+        self.0
+            .iter()
+            .map(|splice| (splice.start..splice.end, splice.replacement.as_str()))
+    }
+}
+
+impl<'src> TextDiffRef<'src> {
+    /// Iterate over the `(range, replacement)` pairs of this diff, in order
+    pub fn splices(&self) -> impl Iterator<Item = (Range<usize>, &'src str)> + '_ {
+        self.0
+            .iter()
+            .map(|splice| (splice.start..splice.end, splice.replacement))
+    }
+}
+
+/// Replace the bytes `start..end` of the original string with `replacement`
+#[cfg(any(feature = "serde", feature = "nanoserde"))]
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename = "TextSpliceOwned"))]
+pub(crate) struct TextSpliceBorrowed<'src> {
+    start: usize,
+    end: usize,
+    #[cfg_attr(feature = "serde", serde(borrow))]
+    replacement: BorrowedStr<'src>,
+}
+
+#[cfg(any(feature = "serde", feature = "nanoserde"))]
+impl<'src> From<TextSpliceBorrowed<'src>> for TextSpliceOwned {
+    fn from(value: TextSpliceBorrowed<'src>) -> Self {
+        Self {
+            start: value.start,
+            end: value.end,
+            replacement: value.replacement.into(),
+        }
+    }
+}
+
+/// A [`TextDiffOwned`] deserialized without copying its replacements out of
+/// the input buffer
+#[cfg(any(feature = "serde", feature = "nanoserde"))]
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename = "TextDiffOwned"))]
+pub struct TextDiffBorrowed<'src>(
+    #[cfg_attr(feature = "serde", serde(borrow))] Vec<TextSpliceBorrowed<'src>>,
+);
+
+#[cfg(any(feature = "serde", feature = "nanoserde"))]
+impl<'src> From<TextDiffBorrowed<'src>> for TextDiffOwned {
+    fn from(value: TextDiffBorrowed<'src>) -> Self {
+        Self(value.0.into_iter().map(Into::into).collect())
+    }
+}
+
+/// Matching byte ranges in the source and target strings
+#[derive(Clone, Debug)]
+struct Hunk {
+    source: Range<usize>,
+    target: Range<usize>,
+}
+
+/// A diff which replaces all of `source` with `target`, as a single splice
+pub fn replacement<'target>(target: &'target str, source: &str) -> TextDiffRef<'target> {
+    TextDiffRef(vec![TextSpliceRef {
+        start: 0,
+        end: source.len(),
+        replacement: target,
+    }])
+}
+
+/// Generate the splices needed to turn `source` into `target`, or `None` if they are equal
+pub fn diff_text<'target>(
+    target: &'target str,
+    source: &str,
+    granularity: TextGranularity,
+) -> Option<TextDiffRef<'target>> {
+    if target == source {
+        return None;
+    }
+
+    let source_tokens = tokenize(source, granularity);
+    let target_tokens = tokenize(target, granularity);
+
+    let mut hunks = token_hunks(&source_tokens, &target_tokens);
+    if granularity == TextGranularity::Bytes {
+        hunks = snap_to_char_boundaries(source, target, hunks);
+    }
+
+    Some(TextDiffRef(
+        hunks
+            .into_iter()
+            .map(
+                |Hunk {
+                     source,
+                     target: target_range,
+                 }| TextSpliceRef {
+                    start: source.start,
+                    end: source.end,
+                    replacement: &target[target_range],
+                },
+            )
+            .collect(),
+    ))
+}
+
+/// Split the text into tokens which concatenate back into the original string
+fn tokenize(text: &str, granularity: TextGranularity) -> Vec<&[u8]> {
+    let bytes = text.as_bytes();
+    match granularity {
+        TextGranularity::Bytes => bytes.chunks(1).collect(),
+        TextGranularity::Chars => text
+            .char_indices()
+            .map(|(idx, c)| &bytes[idx..idx + c.len_utf8()])
+            .collect(),
+        TextGranularity::Words => {
+            let mut ret = Vec::new();
+            let mut start = 0;
+            let mut in_whitespace = None;
+            for (idx, c) in text.char_indices() {
+                let whitespace = c.is_whitespace();
+                if in_whitespace.is_some_and(|prev| prev != whitespace) {
+                    ret.push(&bytes[start..idx]);
+                    start = idx;
+                }
+                in_whitespace = Some(whitespace);
+            }
+            if start < bytes.len() {
+                ret.push(&bytes[start..]);
+            }
+            ret
+        }
+        TextGranularity::Lines => text.split_inclusive('\n').map(str::as_bytes).collect(),
+    }
+}
+
+/// Find the changed regions between two token lists, as byte ranges.
+fn token_hunks(source: &[&[u8]], target: &[&[u8]]) -> Vec<Hunk> {
+    let prefix = source
+        .iter()
+        .zip(target.iter())
+        .take_while(|(l, r)| l == r)
+        .count();
+    let suffix = source[prefix..]
+        .iter()
+        .rev()
+        .zip(target[prefix..].iter().rev())
+        .take_while(|(l, r)| l == r)
+        .count();
+
+    let source_middle = &source[prefix..source.len() - suffix];
+    let target_middle = &target[prefix..target.len() - suffix];
+
+    let token_hunks = myers(source_middle, target_middle).unwrap_or_else(|| {
+        vec![Hunk {
+            source: 0..source_middle.len(),
+            target: 0..target_middle.len(),
+        }]
+    });
+
+    // convert token indices into byte offsets
+    let byte_offsets = |tokens: &[&[u8]]| {
+        std::iter::once(0)
+            .chain(tokens.iter().scan(0, |acc, token| {
+                *acc += token.len();
+                Some(*acc)
+            }))
+            .collect::<Vec<_>>()
+    };
+    let source_offsets = byte_offsets(source);
+    let target_offsets = byte_offsets(target);
+
+    token_hunks
+        .into_iter()
+        .filter(|hunk| !hunk.source.is_empty() || !hunk.target.is_empty())
+        .map(|Hunk { source, target }| Hunk {
+            source: source_offsets[prefix + source.start]..source_offsets[prefix + source.end],
+            target: target_offsets[prefix + target.start]..target_offsets[prefix + target.end],
+        })
+        .collect()
+}
+
+/// Myers' O(ND) diff, returning the changed regions as token ranges.
+/// Returns `None` if the edit distance exceeds [`MAX_EDIT_DISTANCE`].
+fn myers(source: &[&[u8]], target: &[&[u8]]) -> Option<Vec<Hunk>> {
+    let (n, m) = (source.len() as isize, target.len() as isize);
+    let max = (source.len() + target.len()).min(MAX_EDIT_DISTANCE) as isize;
+
+    // furthest reaching x on each diagonal k, stored at index k + max + 1
+    let offset = max + 1;
+    let mut v = vec![0_isize; 2 * max as usize + 3];
+    // snapshot of v[-d..=d] at the start of each round, for backtracking
+    let mut trace: Vec<Vec<isize>> = Vec::new();
+
+    let mut distance = None;
+    'rounds: for d in 0..=max {
+        trace.push(v[(offset - d) as usize..=(offset + d) as usize].to_vec());
+        for k in (-d..=d).step_by(2) {
+            let idx = (offset + k) as usize;
+            let mut x = match k == -d || (k != d && v[idx - 1] < v[idx + 1]) {
+                true => v[idx + 1],
+                false => v[idx - 1] + 1,
+            };
+            let mut y = x - k;
+            while x < n && y < m && source[x as usize] == target[y as usize] {
+                x += 1;
+                y += 1;
+            }
+            v[idx] = x;
+            if x >= n && y >= m {
+                distance = Some(d);
+                break 'rounds;
+            }
+        }
+    }
+    let distance = distance?;
+
+    // walk backwards through the trace, collecting single-token edits
+    let mut edits = Vec::with_capacity(distance as usize);
+    let (mut x, mut y) = (n, m);
+    for d in (1..=distance).rev() {
+        let snapshot = &trace[d as usize];
+        let at = |k: isize| snapshot[(k + d) as usize];
+        let k = x - y;
+        let prev_k = match k == -d || (k != d && at(k - 1) < at(k + 1)) {
+            true => k + 1,
+            false => k - 1,
+        };
+        let prev_x = at(prev_k);
+        let prev_y = prev_x - prev_k;
+        edits.push((prev_x as usize, prev_y as usize, prev_k == k + 1));
+        x = prev_x;
+        y = prev_y;
+    }
+
+    // merge adjacent edits into hunks
+    let mut ret: Vec<Hunk> = Vec::new();
+    for (x, y, insertion) in edits.into_iter().rev() {
+        let (source_len, target_len) = match insertion {
+            true => (0, 1),
+            false => (1, 0),
+        };
+        match ret.last_mut() {
+            Some(hunk) if hunk.source.end == x && hunk.target.end == y => {
+                hunk.source.end += source_len;
+                hunk.target.end += target_len;
+            }
+            _ => ret.push(Hunk {
+                source: x..x + source_len,
+                target: y..y + target_len,
+            }),
+        }
+    }
+    Some(ret)
+}
+
+/// Widen byte-level hunks so that they start and end on `char` boundaries in both
+/// strings, merging any hunks which come to overlap.
+fn snap_to_char_boundaries(source: &str, target: &str, hunks: Vec<Hunk>) -> Vec<Hunk> {
+    let is_boundary = |s: usize, t: usize| source.is_char_boundary(s) && target.is_char_boundary(t);
+
+    let mut ret: Vec<Hunk> = Vec::with_capacity(hunks.len());
+    for mut hunk in hunks {
+        // the bytes between the previous hunk and this one are identical in both strings,
+        // so both starts reach the end of the previous hunk at the same time
+        let (prev_source_end, prev_target_end) = ret
+            .last()
+            .map(|prev| (prev.source.end, prev.target.end))
+            .unwrap_or_default();
+        while hunk.source.start > prev_source_end
+            && hunk.target.start > prev_target_end
+            && !is_boundary(hunk.source.start, hunk.target.start)
+        {
+            hunk.source.start -= 1;
+            hunk.target.start -= 1;
+        }
+
+        match ret.last_mut() {
+            Some(prev) if hunk.source.start <= prev.source.end => {
+                prev.source.end = hunk.source.end;
+                prev.target.end = hunk.target.end;
+            }
+            _ => ret.push(hunk),
+        }
+
+        let last = ret.last_mut().unwrap();
+        while !is_boundary(last.source.end, last.target.end) {
+            last.source.end += 1;
+            last.target.end += 1;
+        }
+    }
+    ret
+}
+
+/// Text types which a [`TextDiffOwned`] can be applied to in place
+pub trait TextLike {
+    fn apply_text_changes(&mut self, changes: TextDiffOwned);
+
+    /// Apply a [`TextDiffRef`] without first copying its replacements into
+    /// an owned diff
+    fn apply_text_changes_ref(&mut self, changes: TextDiffRef<'_>) {
+        self.apply_text_changes(changes.into())
+    }
+}
+
+pub fn apply_in_place<S: TextLike>(changes: impl Into<TextDiffOwned>, existing: &mut S) {
+    existing.apply_text_changes(changes.into())
+}
+
+/// Apply `changes` as [`apply_in_place`] does, after charging the bytes
+/// they insert to `budget`
+pub fn apply_in_place_limited<S: TextLike>(
+    changes: impl Into<TextDiffOwned>,
+    existing: &mut S,
+    budget: &mut ApplyBudget,
+) -> Result<(), LimitExceeded> {
+    let changes = changes.into();
+    let inserted = changes
+        .0
+        .iter()
+        .map(|splice| splice.replacement.len())
+        .fold(0, usize::saturating_add);
+    budget.grow(inserted)?;
+    existing.apply_text_changes(changes);
+    Ok(())
+}
+
+pub fn apply_ref_in_place<S: TextLike>(changes: TextDiffRef<'_>, existing: &mut S) {
+    existing.apply_text_changes_ref(changes)
+}
+
+#[cfg(any(feature = "serde", feature = "nanoserde"))]
+pub fn apply_borrowed_in_place<S: TextLike>(changes: TextDiffBorrowed<'_>, existing: &mut S) {
+    let splices = changes
+        .0
+        .iter()
+        .map(|splice| TextSpliceRef {
+            start: splice.start,
+            end: splice.end,
+            replacement: splice.replacement.as_str(),
+        })
+        .collect();
+    existing.apply_text_changes_ref(TextDiffRef(splices))
+}
+
+fn apply_splices<'a>(
+    existing: &mut String,
+    mut splices: impl ExactSizeIterator<Item = (Range<usize>, &'a str)> + Clone,
+) {
+    // splices which don't fit `existing`, as when it isn't the diff's base, are skipped
+    let fits = |existing: &str, copied_until: usize, range: &Range<usize>| {
+        copied_until <= range.start
+            && range.start <= range.end
+            && existing.is_char_boundary(range.start)
+            && existing.is_char_boundary(range.end)
+    };
+
+    if splices.len() <= 1 {
+        if let Some((range, replacement)) = splices.next() {
+            if fits(existing, 0, &range) {
+                existing.replace_range(range, replacement);
+            }
+        }
+        return;
+    }
+
+    // rebuild the string in a single pass, rather than shifting the tail once per splice
+    let capacity = splices
+        .clone()
+        .fold(existing.len(), |acc, (range, replacement)| {
+            acc + replacement.len() - range.len().min(acc)
+        });
+    let mut ret = String::with_capacity(capacity);
+    let mut copied_until = 0;
+    for (range, replacement) in splices {
+        if !fits(existing, copied_until, &range) {
+            continue;
+        }
+        ret.push_str(&existing[copied_until..range.start]);
+        ret.push_str(replacement);
+        copied_until = range.end;
+    }
+    ret.push_str(&existing[copied_until..]);
+    *existing = ret;
+}
+
+impl TextLike for String {
+    fn apply_text_changes(&mut self, changes: TextDiffOwned) {
+        apply_splices(
+            self,
+            changes
+                .0
+                .iter()
+                .map(|splice| (splice.start..splice.end, splice.replacement.as_str())),
+        )
+    }
+
+    fn apply_text_changes_ref(&mut self, changes: TextDiffRef<'_>) {
+        apply_splices(
+            self,
+            changes
+                .0
+                .iter()
+                .map(|splice| (splice.start..splice.end, splice.replacement)),
+        )
+    }
+}
+
+impl TextLike for Box<str> {
+    fn apply_text_changes(&mut self, changes: TextDiffOwned) {
+        let mut owned = String::from(std::mem::take(self));
+        owned.apply_text_changes(changes);
+        *self = owned.into_boxed_str();
+    }
+
+    fn apply_text_changes_ref(&mut self, changes: TextDiffRef<'_>) {
+        let mut owned = String::from(std::mem::take(self));
+        owned.apply_text_changes_ref(changes);
+        *self = owned.into_boxed_str();
+    }
+}
+
+impl TextLike for Cow<'_, str> {
+    fn apply_text_changes(&mut self, changes: TextDiffOwned) {
+        self.to_mut().apply_text_changes(changes);
+    }
+
+    fn apply_text_changes_ref(&mut self, changes: TextDiffRef<'_>) {
+        self.to_mut().apply_text_changes_ref(changes);
+    }
+}
+
+#[cfg(feature = "nanoserde")]
+mod nanoserde_impls {
+    use super::*;
+    use crate::borrowed::DeBinBorrowed;
+    use nanoserde::{DeBin, SerBin};
+
+    impl SerBin for TextSpliceOwned {
+        fn ser_bin(&self, output: &mut Vec<u8>) {
+            self.start.ser_bin(output);
+            self.end.ser_bin(output);
+            self.replacement.ser_bin(output);
+        }
+    }
+
+    impl SerBin for TextSpliceRef<'_> {
+        fn ser_bin(&self, output: &mut Vec<u8>) {
+            self.start.ser_bin(output);
+            self.end.ser_bin(output);
+            // matches the encoding of `String`
+            self.replacement.len().ser_bin(output);
+            output.extend_from_slice(self.replacement.as_bytes());
+        }
+    }
+
+    impl DeBin for TextSpliceOwned {
+        fn de_bin(offset: &mut usize, bytes: &[u8]) -> Result<Self, nanoserde::DeBinErr> {
+            Ok(Self {
+                start: <usize as DeBin>::de_bin(offset, bytes)?,
+                end: <usize as DeBin>::de_bin(offset, bytes)?,
+                replacement: crate::checked::de_bin_string(offset, bytes)?,
+            })
+        }
+    }
+
+    impl SerBin for TextDiffRef<'_> {
+        fn ser_bin(&self, output: &mut Vec<u8>) {
+            self.0.ser_bin(output);
+        }
+    }
+
+    impl SerBin for TextDiffOwned {
+        fn ser_bin(&self, output: &mut Vec<u8>) {
+            self.0.ser_bin(output);
+        }
+    }
+
+    impl DeBin for TextDiffOwned {
+        fn de_bin(offset: &mut usize, bytes: &[u8]) -> Result<Self, nanoserde::DeBinErr> {
+            let ret = crate::checked::de_bin_vec(offset, bytes)?;
+            Ok(Self(ret))
+        }
+    }
+
+    impl<'de> DeBinBorrowed<'de> for TextSpliceBorrowed<'de> {
+        fn de_bin_borrowed(
+            offset: &mut usize,
+            bytes: &'de [u8],
+        ) -> Result<Self, nanoserde::DeBinErr> {
+            Ok(Self {
+                start: <usize as DeBin>::de_bin(offset, bytes)?,
+                end: <usize as DeBin>::de_bin(offset, bytes)?,
+                replacement: BorrowedStr::de_bin_borrowed(offset, bytes)?,
+            })
+        }
+    }
+
+    impl<'de> DeBinBorrowed<'de> for TextDiffBorrowed<'de> {
+        fn de_bin_borrowed(
+            offset: &mut usize,
+            bytes: &'de [u8],
+        ) -> Result<Self, nanoserde::DeBinErr> {
+            let ret = crate::borrowed::de_bin_vec(offset, bytes)?;
+            Ok(Self(ret))
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::borrow::Cow;
+
+    use nanorand::{Rng, WyRand};
+
+    use super::{apply_in_place, diff_text, TextDiffOwned, TextGranularity};
+    use crate as structdiff;
+    use structdiff::{Difference, StructDiff};
+
+    const GRANULARITIES: [TextGranularity; 4] = [
+        TextGranularity::Bytes,
+        TextGranularity::Chars,
+        TextGranularity::Words,
+        TextGranularity::Lines,
+    ];
+
+    fn roundtrip(source: &str, target: &str, granularity: TextGranularity) -> TextDiffOwned {
+        let diff: TextDiffOwned = diff_text(target, source, granularity)
+            .expect("Strings should differ")
+            .into();
+        let mut applied = String::from(source);
+        apply_in_place(diff.clone(), &mut applied);
+        assert_eq!(applied, target, "{granularity:?}");
+        diff
+    }
+
+    #[test]
+    fn test_identical_strings() {
+        for granularity in GRANULARITIES {
+            assert!(diff_text("same text", "same text", granularity).is_none());
+        }
+    }
+
+    #[test]
+    fn test_granularities() {
+        let source = "the quick brown fox\njumps over\nthe lazy dog\n";
+        let target = "the quick red fox\njumps over\nthe lazy dogs\n";
+
+        let chars = roundtrip(source, target, TextGranularity::Chars);
+
+        let words = roundtrip(source, target, TextGranularity::Words);
+        assert!(chars.splices().count() > words.splices().count());
+        assert_eq!(
+            words.splices().collect::<Vec<_>>(),
+            vec![(10..15, "red"), (40..43, "dogs")]
+        );
+
+        let lines = roundtrip(source, target, TextGranularity::Lines);
+        assert_eq!(
+            lines.splices().collect::<Vec<_>>(),
+            vec![(0..20, "the quick red fox\n"), (31..44, "the lazy dogs\n")]
+        );
+    }
+
+    #[test]
+    fn test_multibyte_bytes() {
+        // 'é' and 'è' share their first byte, so the byte-level diff starts mid-char
+        let diff = roundtrip("café au lait", "cafè au lait", TextGranularity::Bytes);
+        assert_eq!(diff.splices().collect::<Vec<_>>(), vec![(3..5, "è")]);
+
+        roundtrip(
+            "日本語のテキスト",
+            "日本のテキストです",
+            TextGranularity::Bytes,
+        );
+    }
+
+    #[test]
+    fn test_random_strings() {
+        let mut rng = WyRand::new();
+        let alphabet = ['a', 'b', ' ', '\n', 'é', '語'];
+        let mut random_string = |len: usize| {
+            (0..len)
+                .map(|_| alphabet[rng.generate_range(0..alphabet.len())])
+                .collect::<String>()
+        };
+
+        for _ in 0..200 {
+            let (source, target) = (random_string(40), random_string(40));
+            for granularity in GRANULARITIES {
+                if source != target {
+                    roundtrip(&source, &target, granularity);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_edit_distance_cutoff() {
+        let mut rng = WyRand::new();
+        let source = (0..5000)
+            .map(|_| char::from(rng.generate_range(b'a'..=b'z')))
+            .collect::<String>();
+        let target = (0..5000)
+            .map(|_| char::from(rng.generate_range(b'a'..=b'z')))
+            .collect::<String>();
+
+        roundtrip(&source, &target, TextGranularity::Chars);
+    }
+
+    #[test]
+    fn test_collection_strategy() {
+        #[derive(Debug, PartialEq, Clone, Difference, Default)]
+        #[difference(setters)]
+        struct Document {
+            #[difference(collection_strategy = "text")]
+            body: String,
+            #[difference(collection_strategy = "text", text_granularity = "lines")]
+            notes: Cow<'static, str>,
+            #[difference(collection_strategy = "text", text_granularity = "words")]
+            title: Box<str>,
+        }
+
+        let first = Document {
+            body: String::from("hello world"),
+            notes: Cow::Borrowed("line one\nline two\n"),
+            title: "A title".into(),
+        };
+        let second = Document {
+            body: String::from("hello, world!"),
+            notes: Cow::Borrowed("line one\nline 2\n"),
+            title: "A new title".into(),
+        };
+
+        let diffs = first.diff(&second);
+        assert_eq!(diffs.len(), 3);
+        assert_eq!(first.clone().apply(diffs), second);
+
+        let ref_diffs = first.diff_ref(&second);
+        assert_eq!(
+            first
+                .clone()
+                .apply(ref_diffs.into_iter().map(Into::into).collect()),
+            second
+        );
+    }
+}
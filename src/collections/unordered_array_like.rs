@@ -8,7 +8,16 @@ type HashMap<K, V> = std::collections::HashMap<K, V>;
 type HashMap<K, V> =
     std::collections::HashMap<K, V, std::hash::BuildHasherDefault<rustc_hash::FxHasher>>;
 
-use std::hash::Hash;
+use super::{rope::Rope, Chunks};
+use crate::{
+    limits::{ApplyBudget, LimitExceeded},
+    size::{EncodedSize, TAG},
+    DiffSink,
+};
+use std::{
+    collections::{BTreeSet, BinaryHeap, HashSet, LinkedList, VecDeque},
+    hash::{BuildHasher, Hash},
+};
 
 #[derive(Debug, Clone)]
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
@@ -91,6 +100,31 @@ impl<'a, T: Clone + 'a> From<UnorderedArrayLikeDiff<&'a T>> for UnorderedArrayLi
     }
 }
 
+impl<T: EncodedSize, S: EncodedSize> EncodedSize for UnorderedArrayLikeChangeSpec<T, S> {
+    fn encoded_size(&self) -> usize {
+        self.item.encoded_size() + self.count.encoded_size()
+    }
+}
+
+impl<T: EncodedSize> EncodedSize for UnorderedArrayLikeChange<T> {
+    fn encoded_size(&self) -> usize {
+        TAG + match self {
+            Self::InsertMany(spec) | Self::RemoveMany(spec) => spec.encoded_size(),
+            Self::InsertFew(spec) | Self::RemoveFew(spec) => spec.encoded_size(),
+            Self::InsertSingle(item) | Self::RemoveSingle(item) => item.encoded_size(),
+        }
+    }
+}
+
+impl<T: EncodedSize> EncodedSize for UnorderedArrayLikeDiff<T> {
+    fn encoded_size(&self) -> usize {
+        TAG + match &self.0 {
+            UnorderedArrayLikeDiffInternal::Replace(items) => items.encoded_size(),
+            UnorderedArrayLikeDiffInternal::Modify(changes) => changes.encoded_size(),
+        }
+    }
+}
+
 fn collect_into_map<'a, T: Hash + PartialEq + Eq + 'a, B: Iterator<Item = T>>(
     list: B,
 ) -> HashMap<T, usize> {
@@ -158,49 +192,97 @@ pub fn unordered_hashcmp<
     previous: B,
     current: B,
 ) -> Option<UnorderedArrayLikeDiff<&'a T>> {
-    let mut previous = collect_into_map(previous);
-    let current = collect_into_map(current);
+    let mut ret: Vec<UnorderedArrayLikeChange<&T>> = Vec::new();
 
-    if (current.len() as isize) < ((previous.len() as isize) - (current.len() as isize)) {
+    if let Some(replacement) = compare(previous, current, &mut |change| ret.push(change)) {
         return Some(UnorderedArrayLikeDiff(
-            UnorderedArrayLikeDiffInternal::Replace(
-                current
-                    .into_iter()
-                    .flat_map(|(k, v)| std::iter::repeat_n(k, v))
-                    .collect(),
-            ),
+            UnorderedArrayLikeDiffInternal::Replace(replacement),
         ));
     }
 
-    let mut ret: Vec<UnorderedArrayLikeChange<&T>> =
-        Vec::with_capacity((previous.len() + current.len()) >> 1);
+    ret.shrink_to_fit();
+
+    match ret.is_empty() {
+        true => None,
+        false => Some(UnorderedArrayLikeDiff(
+            UnorderedArrayLikeDiffInternal::Modify(ret),
+        )),
+    }
+}
+
+/// Generate the same changes as [`unordered_hashcmp`], pushing them to `sink` as
+/// they are found, in diffs of at most [`CHANGES_PER_DIFF`](super::CHANGES_PER_DIFF)
+/// changes. A replacement of the whole collection is pushed as a single diff.
+pub fn unordered_hashcmp_into<
+    'a,
+    #[cfg(feature = "nanoserde")] T: Hash + Clone + PartialEq + Eq + SerBin + DeBin + 'a,
+    #[cfg(not(feature = "nanoserde"))] T: Hash + Clone + PartialEq + Eq + 'a,
+    B: Iterator<Item = &'a T>,
+>(
+    previous: B,
+    current: B,
+    sink: &mut impl DiffSink<UnorderedArrayLikeDiff<&'a T>>,
+) {
+    let mut chunks = Chunks::new(|changes| {
+        sink.push(UnorderedArrayLikeDiff(
+            UnorderedArrayLikeDiffInternal::Modify(changes),
+        ))
+    });
+
+    match compare(previous, current, &mut |change| chunks.push(change)) {
+        Some(replacement) => sink.push(UnorderedArrayLikeDiff(
+            UnorderedArrayLikeDiffInternal::Replace(replacement),
+        )),
+        None => chunks.finish(),
+    }
+}
+
+/// Compare the item counts of `previous` and `current`, handing each change to
+/// `emit`. When replacing the whole collection is expected to be smaller, nothing
+/// is emitted and the replacement is returned instead.
+fn compare<'a, T: Hash + Eq + 'a, B: Iterator<Item = &'a T>>(
+    previous: B,
+    current: B,
+    emit: &mut impl FnMut(UnorderedArrayLikeChange<&'a T>),
+) -> Option<Vec<&'a T>> {
+    let mut previous = collect_into_map(previous);
+    let current = collect_into_map(current);
+
+    if (current.len() as isize) < ((previous.len() as isize) - (current.len() as isize)) {
+        return Some(
+            current
+                .into_iter()
+                .flat_map(|(k, v)| std::iter::repeat_n(k, v))
+                .collect(),
+        );
+    }
 
     for (k, current_count) in current.iter() {
         match previous.remove(k) {
             Some(prev_count) => match (*current_count as i128) - (prev_count as i128) {
-                add if add > 1 => ret.push(UnorderedArrayLikeChange::new(
+                add if add > 1 => emit(UnorderedArrayLikeChange::new(
                     k,
                     add as usize,
                     InsertOrRemove::Insert,
                 )),
-                add if add == 1 => ret.push(UnorderedArrayLikeChange::new(
+                add if add == 1 => emit(UnorderedArrayLikeChange::new(
                     k,
                     add as usize,
                     InsertOrRemove::Insert,
                 )),
-                sub if sub < 0 => ret.push(UnorderedArrayLikeChange::new(
+                sub if sub < 0 => emit(UnorderedArrayLikeChange::new(
                     k,
                     -sub as usize,
                     InsertOrRemove::Remove,
                 )),
-                sub if sub == -1 => ret.push(UnorderedArrayLikeChange::new(
+                sub if sub == -1 => emit(UnorderedArrayLikeChange::new(
                     k,
                     -sub as usize,
                     InsertOrRemove::Remove,
                 )),
                 _ => (),
             },
-            None => ret.push(UnorderedArrayLikeChange::new(
+            None => emit(UnorderedArrayLikeChange::new(
                 k,
                 *current_count,
                 InsertOrRemove::Insert,
@@ -209,17 +291,26 @@ pub fn unordered_hashcmp<
     }
 
     for (k, v) in previous.into_iter() {
-        ret.push(UnorderedArrayLikeChange::new(k, v, InsertOrRemove::Remove))
+        emit(UnorderedArrayLikeChange::new(k, v, InsertOrRemove::Remove))
     }
 
-    ret.shrink_to_fit();
+    None
+}
 
-    match ret.is_empty() {
-        true => None,
-        false => Some(UnorderedArrayLikeDiff(
-            UnorderedArrayLikeDiffInternal::Modify(ret),
-        )),
-    }
+/// A diff which replaces the whole collection with `current`
+pub fn replacement<'a, T: 'a>(
+    current: impl Iterator<Item = &'a T>,
+) -> UnorderedArrayLikeDiff<&'a T> {
+    UnorderedArrayLikeDiff(UnorderedArrayLikeDiffInternal::Replace(current.collect()))
+}
+
+/// Whether the two collections hold different items, counting duplicates,
+/// without generating a diff
+pub fn has_changes<'a, T: Hash + Eq + 'a, B: Iterator<Item = &'a T>>(
+    previous: B,
+    current: B,
+) -> bool {
+    collect_into_map(previous) != collect_into_map(current)
 }
 
 pub fn apply_unordered_hashdiffs<
@@ -298,7 +389,7 @@ where
             UnorderedArrayLikeChange::InsertMany(UnorderedArrayLikeChangeSpec { item, count }) => {
                 match list_hash.get_mut(&item) {
                     Some(val) => {
-                        *val += count;
+                        *val = val.saturating_add(count);
                     }
                     None => {
                         list_hash.insert(item, count);
@@ -308,7 +399,7 @@ where
             UnorderedArrayLikeChange::InsertFew(UnorderedArrayLikeChangeSpec { item, count }) => {
                 match list_hash.get_mut(&item) {
                     Some(val) => {
-                        *val += count as usize;
+                        *val = val.saturating_add(count as usize);
                     }
                     None => {
                         list_hash.insert(item, count as usize);
@@ -317,7 +408,7 @@ where
             }
             UnorderedArrayLikeChange::InsertSingle(item) => match list_hash.get_mut(&item) {
                 Some(val) => {
-                    *val += 1;
+                    *val = val.saturating_add(1);
                 }
                 None => {
                     list_hash.insert(item, 1);
@@ -337,12 +428,279 @@ where
     )
 }
 
+/// Collections which an [`UnorderedArrayLikeDiff`] can be applied to in place.
+///
+/// Set-like collections ignore item counts, while sequence-like collections
+/// keep multiset semantics and insert or remove the requested number of copies.
+pub trait UnorderedArrayLikeCollection<T> {
+    /// Remove every item from the collection
+    fn clear_items(&mut self);
+
+    /// Insert `count` copies of `item`
+    fn insert_items(&mut self, item: T, count: usize);
+
+    /// Remove up to `count` copies of each of the given items
+    fn remove_items(&mut self, removals: Vec<(&T, usize)>);
+}
+
+/// Split a list of changes into `(item, count)` insertions and removals
+#[allow(clippy::type_complexity)]
+fn split_changes<T>(diffs: Vec<UnorderedArrayLikeChange<T>>) -> (Vec<(T, usize)>, Vec<(T, usize)>) {
+    let mut insertions = Vec::with_capacity(diffs.len());
+    let mut removals = Vec::with_capacity(diffs.len());
+
+    for diff in diffs {
+        match diff {
+            UnorderedArrayLikeChange::InsertMany(UnorderedArrayLikeChangeSpec { item, count }) => {
+                insertions.push((item, count))
+            }
+            UnorderedArrayLikeChange::InsertFew(UnorderedArrayLikeChangeSpec { item, count }) => {
+                insertions.push((item, count as usize))
+            }
+            UnorderedArrayLikeChange::InsertSingle(item) => insertions.push((item, 1)),
+            UnorderedArrayLikeChange::RemoveMany(UnorderedArrayLikeChangeSpec { item, count }) => {
+                removals.push((item, count))
+            }
+            UnorderedArrayLikeChange::RemoveFew(UnorderedArrayLikeChangeSpec { item, count }) => {
+                removals.push((item, count as usize))
+            }
+            UnorderedArrayLikeChange::RemoveSingle(item) => removals.push((item, 1)),
+        }
+    }
+
+    (insertions, removals)
+}
+
+pub fn apply_unordered_hashdiffs_in_place<
+    #[cfg(feature = "nanoserde")] T: Hash + Clone + PartialEq + Eq + SerBin + DeBin,
+    #[cfg(not(feature = "nanoserde"))] T: Hash + Clone + PartialEq + Eq,
+    C: UnorderedArrayLikeCollection<T>,
+>(
+    list: &mut C,
+    diffs: UnorderedArrayLikeDiff<T>,
+) {
+    let diffs = match diffs {
+        UnorderedArrayLikeDiff(UnorderedArrayLikeDiffInternal::Replace(replacement)) => {
+            list.clear_items();
+            for item in replacement {
+                list.insert_items(item, 1);
+            }
+            return;
+        }
+        UnorderedArrayLikeDiff(UnorderedArrayLikeDiffInternal::Modify(diffs)) => diffs,
+    };
+
+    let (insertions, removals) = split_changes(diffs);
+
+    if !removals.is_empty() {
+        list.remove_items(
+            removals
+                .iter()
+                .map(|(item, count)| (item, *count))
+                .collect(),
+        );
+    }
+
+    for (item, count) in insertions {
+        list.insert_items(item, count);
+    }
+}
+
+/// Apply `diffs` as [`apply_unordered_hashdiffs_in_place`] does, after
+/// charging the items they insert to `budget`. Set-like collections are
+/// charged for every copy, though they keep only one.
+pub fn apply_unordered_hashdiffs_in_place_limited<
+    #[cfg(feature = "nanoserde")] T: Hash + Clone + PartialEq + Eq + SerBin + DeBin,
+    #[cfg(not(feature = "nanoserde"))] T: Hash + Clone + PartialEq + Eq,
+    C: UnorderedArrayLikeCollection<T>,
+>(
+    list: &mut C,
+    diffs: UnorderedArrayLikeDiff<T>,
+    budget: &mut ApplyBudget,
+) -> Result<(), LimitExceeded> {
+    let inserted = match &diffs.0 {
+        UnorderedArrayLikeDiffInternal::Replace(replacement) => replacement.len(),
+        UnorderedArrayLikeDiffInternal::Modify(diffs) => {
+            diffs.iter().try_fold(0_usize, |total, change| {
+                let count = match change {
+                    UnorderedArrayLikeChange::InsertMany(spec) => spec.count,
+                    UnorderedArrayLikeChange::InsertFew(spec) => spec.count as usize,
+                    UnorderedArrayLikeChange::InsertSingle(_) => 1,
+                    _ => 0,
+                };
+                budget.repeat(count)?;
+                Ok(total.saturating_add(count))
+            })?
+        }
+    };
+    budget.grow(inserted)?;
+    apply_unordered_hashdiffs_in_place(list, diffs);
+    Ok(())
+}
+
+/// Apply a diff generated with references, cloning only the inserted items
+pub fn apply_unordered_hashdiffs_in_place_ref<
+    #[cfg(feature = "nanoserde")] T: Hash + Clone + PartialEq + Eq + SerBin + DeBin,
+    #[cfg(not(feature = "nanoserde"))] T: Hash + Clone + PartialEq + Eq,
+    C: UnorderedArrayLikeCollection<T>,
+>(
+    list: &mut C,
+    diffs: UnorderedArrayLikeDiff<&T>,
+) {
+    let diffs = match diffs {
+        UnorderedArrayLikeDiff(UnorderedArrayLikeDiffInternal::Replace(replacement)) => {
+            list.clear_items();
+            for item in replacement {
+                list.insert_items(item.clone(), 1);
+            }
+            return;
+        }
+        UnorderedArrayLikeDiff(UnorderedArrayLikeDiffInternal::Modify(diffs)) => diffs,
+    };
+
+    let (insertions, removals) = split_changes(diffs);
+
+    if !removals.is_empty() {
+        list.remove_items(removals);
+    }
+
+    for (item, count) in insertions {
+        list.insert_items(item.clone(), count);
+    }
+}
+
+/// Creates a filter which rejects up to the requested number of copies of each item
+fn counted_filter<'a, T: Hash + Eq>(removals: Vec<(&'a T, usize)>) -> impl FnMut(&T) -> bool + 'a {
+    let mut to_remove: HashMap<&T, usize> = HashMap::default();
+    to_remove.reserve(removals.len());
+    for (item, count) in removals {
+        let total = to_remove.entry(item).or_default();
+        *total = total.saturating_add(count);
+    }
+
+    move |item| match to_remove.get_mut(item) {
+        Some(count) if *count > 0 => {
+            *count -= 1;
+            false
+        }
+        _ => true,
+    }
+}
+
+impl<T: Hash + Eq + Clone> UnorderedArrayLikeCollection<T> for Vec<T> {
+    fn clear_items(&mut self) {
+        self.clear();
+    }
+
+    fn insert_items(&mut self, item: T, count: usize) {
+        self.extend(std::iter::repeat_n(item, count));
+    }
+
+    fn remove_items(&mut self, removals: Vec<(&T, usize)>) {
+        self.retain(counted_filter(removals));
+    }
+}
+
+impl<T: Hash + Eq + Clone> UnorderedArrayLikeCollection<T> for VecDeque<T> {
+    fn clear_items(&mut self) {
+        self.clear();
+    }
+
+    fn insert_items(&mut self, item: T, count: usize) {
+        self.extend(std::iter::repeat_n(item, count));
+    }
+
+    fn remove_items(&mut self, removals: Vec<(&T, usize)>) {
+        self.retain(counted_filter(removals));
+    }
+}
+
+impl<T: Hash + Eq + Clone> UnorderedArrayLikeCollection<T> for LinkedList<T> {
+    fn clear_items(&mut self) {
+        self.clear();
+    }
+
+    fn insert_items(&mut self, item: T, count: usize) {
+        self.extend(std::iter::repeat_n(item, count));
+    }
+
+    fn remove_items(&mut self, removals: Vec<(&T, usize)>) {
+        // LinkedList::retain is not yet stable, so rebuild the list instead
+        let mut keep = counted_filter(removals);
+        *self = std::mem::take(self)
+            .into_iter()
+            .filter(|item| keep(item))
+            .collect();
+    }
+}
+
+impl<T: Hash + Eq + Clone> UnorderedArrayLikeCollection<T> for Rope<T> {
+    fn clear_items(&mut self) {
+        self.clear();
+    }
+
+    fn insert_items(&mut self, item: T, count: usize) {
+        self.extend(std::iter::repeat_n(item, count));
+    }
+
+    fn remove_items(&mut self, removals: Vec<(&T, usize)>) {
+        self.retain(counted_filter(removals));
+    }
+}
+
+impl<T: Hash + Eq + Ord + Clone> UnorderedArrayLikeCollection<T> for BinaryHeap<T> {
+    fn clear_items(&mut self) {
+        self.clear();
+    }
+
+    fn insert_items(&mut self, item: T, count: usize) {
+        self.extend(std::iter::repeat_n(item, count));
+    }
+
+    fn remove_items(&mut self, removals: Vec<(&T, usize)>) {
+        self.retain(counted_filter(removals));
+    }
+}
+
+impl<T: Hash + Eq, S: BuildHasher> UnorderedArrayLikeCollection<T> for HashSet<T, S> {
+    fn clear_items(&mut self) {
+        self.clear();
+    }
+
+    fn insert_items(&mut self, item: T, _count: usize) {
+        self.insert(item);
+    }
+
+    fn remove_items(&mut self, removals: Vec<(&T, usize)>) {
+        for (item, _) in removals {
+            self.remove(item);
+        }
+    }
+}
+
+impl<T: Ord> UnorderedArrayLikeCollection<T> for BTreeSet<T> {
+    fn clear_items(&mut self) {
+        self.clear();
+    }
+
+    fn insert_items(&mut self, item: T, _count: usize) {
+        self.insert(item);
+    }
+
+    fn remove_items(&mut self, removals: Vec<(&T, usize)>) {
+        for (item, _) in removals {
+            self.remove(item);
+        }
+    }
+}
+
 #[cfg(feature = "nanoserde")]
 mod nanoserde_impls {
     use super::{
         DeBin, SerBin, UnorderedArrayLikeChange, UnorderedArrayLikeChangeSpec,
         UnorderedArrayLikeDiff, UnorderedArrayLikeDiffInternal,
     };
+    use crate::checked::DeBinChecked;
 
     impl<T: SerBin + DeBin> SerBin for UnorderedArrayLikeChangeSpec<T, usize> {
         fn ser_bin(&self, output: &mut Vec<u8>) {
@@ -470,25 +828,25 @@ mod nanoserde_impls {
         }
     }
 
-    impl<T: DeBin + SerBin> DeBin for UnorderedArrayLikeChangeSpec<T, usize> {
+    impl<T: DeBinChecked + SerBin> DeBin for UnorderedArrayLikeChangeSpec<T, usize> {
         fn de_bin(offset: &mut usize, bytes: &[u8]) -> Result<Self, nanoserde::DeBinErr> {
             core::result::Result::Ok(Self {
-                item: DeBin::de_bin(offset, bytes)?,
+                item: T::de_bin_checked(offset, bytes)?,
                 count: DeBin::de_bin(offset, bytes)?,
             })
         }
     }
 
-    impl<T: DeBin + SerBin> DeBin for UnorderedArrayLikeChangeSpec<T, u8> {
+    impl<T: DeBinChecked + SerBin> DeBin for UnorderedArrayLikeChangeSpec<T, u8> {
         fn de_bin(offset: &mut usize, bytes: &[u8]) -> Result<Self, nanoserde::DeBinErr> {
             core::result::Result::Ok(Self {
-                item: DeBin::de_bin(offset, bytes)?,
+                item: T::de_bin_checked(offset, bytes)?,
                 count: DeBin::de_bin(offset, bytes)?,
             })
         }
     }
 
-    impl<T: DeBin + PartialEq + Clone + SerBin> DeBin for UnorderedArrayLikeChange<T> {
+    impl<T: DeBinChecked + PartialEq + Clone + SerBin> DeBin for UnorderedArrayLikeChange<T> {
         fn de_bin(
             offset: &mut usize,
             bytes: &[u8],
@@ -499,8 +857,8 @@ mod nanoserde_impls {
                 1_u8 => UnorderedArrayLikeChange::RemoveMany(DeBin::de_bin(offset, bytes)?),
                 2_u8 => UnorderedArrayLikeChange::InsertFew(DeBin::de_bin(offset, bytes)?),
                 3_u8 => UnorderedArrayLikeChange::RemoveFew(DeBin::de_bin(offset, bytes)?),
-                4_u8 => UnorderedArrayLikeChange::InsertSingle(DeBin::de_bin(offset, bytes)?),
-                5_u8 => UnorderedArrayLikeChange::RemoveSingle(DeBin::de_bin(offset, bytes)?),
+                4_u8 => UnorderedArrayLikeChange::InsertSingle(T::de_bin_checked(offset, bytes)?),
+                5_u8 => UnorderedArrayLikeChange::RemoveSingle(T::de_bin_checked(offset, bytes)?),
                 _ => {
                     return core::result::Result::Err(nanoserde::DeBinErr {
                         o: *offset,
@@ -512,7 +870,7 @@ mod nanoserde_impls {
         }
     }
 
-    impl<T: DeBin + PartialEq + Clone + SerBin> DeBin for UnorderedArrayLikeDiff<T> {
+    impl<T: DeBinChecked + PartialEq + Clone + SerBin> DeBin for UnorderedArrayLikeDiff<T> {
         fn de_bin(
             offset: &mut usize,
             bytes: &[u8],
@@ -520,21 +878,13 @@ mod nanoserde_impls {
             let id: u8 = DeBin::de_bin(offset, bytes)?;
             core::result::Result::Ok(match id {
                 0_u8 => {
-                    let len: usize = DeBin::de_bin(offset, bytes)?;
-                    let mut contents: Vec<T> = Vec::new();
-                    for _ in 0..len {
-                        let content = DeBin::de_bin(offset, bytes)?;
-                        contents.push(content);
-                    }
+                    let contents: Vec<T> =
+                        crate::checked::de_bin_vec_with(offset, bytes, T::de_bin_checked)?;
                     UnorderedArrayLikeDiff(UnorderedArrayLikeDiffInternal::Replace(contents))
                 }
                 1_u8 => {
-                    let len: usize = DeBin::de_bin(offset, bytes)?;
-                    let mut contents: Vec<UnorderedArrayLikeChange<T>> = Vec::new();
-                    for _ in 0..len {
-                        let content = DeBin::de_bin(offset, bytes)?;
-                        contents.push(content);
-                    }
+                    let contents: Vec<UnorderedArrayLikeChange<T>> =
+                        crate::checked::de_bin_vec(offset, bytes)?;
                     UnorderedArrayLikeDiff(UnorderedArrayLikeDiffInternal::Modify(contents))
                 }
                 _ => {
@@ -551,9 +901,12 @@ mod nanoserde_impls {
 
 #[cfg(test)]
 mod test {
-    use std::collections::{HashSet, LinkedList};
+    use std::collections::{BTreeSet, HashSet, LinkedList};
 
-    use super::{UnorderedArrayLikeDiff, UnorderedArrayLikeDiffInternal};
+    use super::{
+        apply_unordered_hashdiffs_in_place, unordered_hashcmp, UnorderedArrayLikeDiff,
+        UnorderedArrayLikeDiffInternal,
+    };
     use crate::{Difference, StructDiff};
 
     use crate as structdiff;
@@ -670,4 +1023,25 @@ mod test {
         assert_eq_unordered!(diffed.test2, second.test2);
         assert_eq_unordered!(diffed.test3, second.test3);
     }
+
+    #[test]
+    fn test_apply_in_place() {
+        let first = vec![1, 1, 1, 2, 3, 3, 4];
+        let second = vec![1, 2, 2, 3, 3, 3, 4, 5];
+
+        let diff = unordered_hashcmp(first.iter(), second.iter()).unwrap();
+        let mut diffed = first.clone();
+        apply_unordered_hashdiffs_in_place(&mut diffed, diff.into());
+
+        use assert_unordered::assert_eq_unordered;
+        assert_eq_unordered!(diffed, second);
+
+        let first: BTreeSet<i32> = [1, 2, 3, 4].into_iter().collect();
+        let second: BTreeSet<i32> = [2, 3, 4, 5].into_iter().collect();
+
+        let diff = unordered_hashcmp(first.iter(), second.iter()).unwrap();
+        let mut diffed = first.clone();
+        apply_unordered_hashdiffs_in_place(&mut diffed, diff.into());
+        assert_eq!(diffed, second);
+    }
 }
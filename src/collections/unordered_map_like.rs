@@ -8,7 +8,17 @@ type HashMap<K, V> = std::collections::HashMap<K, V>;
 type HashMap<K, V> =
     std::collections::HashMap<K, V, std::hash::BuildHasherDefault<rustc_hash::FxHasher>>;
 
-use std::hash::Hash;
+use std::{
+    collections::BTreeMap,
+    hash::{BuildHasher, Hash},
+};
+
+use super::Chunks;
+use crate::{
+    limits::{ApplyBudget, LimitExceeded},
+    size::{EncodedSize, TAG},
+    DiffSink,
+};
 
 #[derive(Debug, Clone)]
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
@@ -52,6 +62,29 @@ pub(crate) enum UnorderedMapLikeDiffInternal<K, V> {
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct UnorderedMapLikeDiff<K, V>(UnorderedMapLikeDiffInternal<K, V>);
 
+impl<K: Clone, V: PartialEq> UnorderedMapLikeDiff<K, V> {
+    /// The diff of inserting `value` at `key`, given the value the key held
+    /// before (as returned by `insert` on the map). `None` if they are equal.
+    pub fn from_insert(key: K, value: V, previous: Option<V>) -> Option<Self> {
+        let changes = match previous {
+            Some(previous) if previous == value => return None,
+            Some(_) => vec![
+                UnorderedMapLikeChange::RemoveSingle(key.clone()),
+                UnorderedMapLikeChange::InsertSingle(key, value),
+            ],
+            None => vec![UnorderedMapLikeChange::InsertSingle(key, value)],
+        };
+        Some(Self(UnorderedMapLikeDiffInternal::Modify(changes)))
+    }
+
+    /// The diff of removing the entry for `key`
+    pub fn from_remove(key: K) -> Self {
+        Self(UnorderedMapLikeDiffInternal::Modify(vec![
+            UnorderedMapLikeChange::RemoveSingle(key),
+        ]))
+    }
+}
+
 impl<'a, K: Clone, V: Clone> From<UnorderedMapLikeDiff<&'a K, &'a V>>
     for UnorderedMapLikeDiff<K, V>
 {
@@ -73,6 +106,28 @@ impl<'a, K: Clone, V: Clone> From<UnorderedMapLikeDiff<&'a K, &'a V>>
     }
 }
 
+impl<K: EncodedSize, V: EncodedSize> EncodedSize for UnorderedMapLikeChange<K, V> {
+    fn encoded_size(&self) -> usize {
+        TAG + match self {
+            Self::InsertMany(key, value, count) => {
+                key.encoded_size() + value.encoded_size() + count.encoded_size()
+            }
+            Self::RemoveMany(key, count) => key.encoded_size() + count.encoded_size(),
+            Self::InsertSingle(key, value) => key.encoded_size() + value.encoded_size(),
+            Self::RemoveSingle(key) => key.encoded_size(),
+        }
+    }
+}
+
+impl<K: EncodedSize, V: EncodedSize> EncodedSize for UnorderedMapLikeDiff<K, V> {
+    fn encoded_size(&self) -> usize {
+        TAG + match &self.0 {
+            UnorderedMapLikeDiffInternal::Replace(entries) => entries.encoded_size(),
+            UnorderedMapLikeDiffInternal::Modify(changes) => changes.encoded_size(),
+        }
+    }
+}
+
 fn collect_into_key_eq_map<
     'a,
     K: Hash + PartialEq + Eq + 'a,
@@ -151,6 +206,65 @@ pub fn unordered_hashcmp<
     current: B,
     key_only: bool,
 ) -> Option<UnorderedMapLikeDiff<&'a K, &'a V>> {
+    let mut ret: Vec<UnorderedMapLikeChange<&'a K, &'a V>> = Vec::new();
+
+    if let Some(replacement) = compare(previous, current, key_only, &mut |change| ret.push(change))
+    {
+        return Some(UnorderedMapLikeDiff(UnorderedMapLikeDiffInternal::Replace(
+            replacement,
+        )));
+    }
+
+    ret.shrink_to_fit();
+
+    match ret.is_empty() {
+        true => None,
+        false => Some(UnorderedMapLikeDiff(UnorderedMapLikeDiffInternal::Modify(
+            ret,
+        ))),
+    }
+}
+
+/// Generate the same changes as [`unordered_hashcmp`], pushing them to `sink` as
+/// they are found, in diffs of at most [`CHANGES_PER_DIFF`](super::CHANGES_PER_DIFF)
+/// changes. A replacement of the whole collection is pushed as a single diff.
+pub fn unordered_hashcmp_into<
+    'a,
+    #[cfg(feature = "nanoserde")] K: Hash + Clone + PartialEq + Eq + SerBin + DeBin + std::fmt::Debug + 'a,
+    #[cfg(not(feature = "nanoserde"))] K: Hash + Clone + PartialEq + Eq + 'a,
+    V: Clone + PartialEq + std::fmt::Debug + 'a,
+    B: Iterator<Item = (&'a K, &'a V)>,
+>(
+    previous: B,
+    current: B,
+    key_only: bool,
+    sink: &mut impl DiffSink<UnorderedMapLikeDiff<&'a K, &'a V>>,
+) {
+    let mut chunks = Chunks::new(|changes| {
+        sink.push(UnorderedMapLikeDiff(UnorderedMapLikeDiffInternal::Modify(
+            changes,
+        )))
+    });
+
+    match compare(previous, current, key_only, &mut |change| {
+        chunks.push(change)
+    }) {
+        Some(replacement) => sink.push(UnorderedMapLikeDiff(
+            UnorderedMapLikeDiffInternal::Replace(replacement),
+        )),
+        None => chunks.finish(),
+    }
+}
+
+/// Compare the entries of `previous` and `current`, handing each change to `emit`.
+/// When replacing the whole collection is expected to be smaller, nothing is
+/// emitted and the replacement is returned instead.
+fn compare<'a, K: Hash + Eq + 'a, V: PartialEq + 'a, B: Iterator<Item = (&'a K, &'a V)>>(
+    previous: B,
+    current: B,
+    key_only: bool,
+    emit: &mut impl FnMut(UnorderedMapLikeChange<&'a K, &'a V>),
+) -> Option<Vec<(&'a K, &'a V)>> {
     let (mut previous, current) = if key_only {
         (
             collect_into_key_eq_map(previous),
@@ -164,37 +278,34 @@ pub fn unordered_hashcmp<
     };
 
     if (current.len() as isize) < ((previous.len() as isize) - (current.len() as isize)) {
-        return Some(UnorderedMapLikeDiff(UnorderedMapLikeDiffInternal::Replace(
+        return Some(
             current
                 .into_iter()
                 .flat_map(|(k, (v, count))| std::iter::repeat_n((k, v), count))
                 .collect(),
-        )));
+        );
     }
 
-    let mut ret: Vec<UnorderedMapLikeChange<&'a K, &'a V>> =
-        Vec::with_capacity((previous.len() + current.len()) >> 1);
-
     for (&k, &(v, current_count)) in current.iter() {
         match previous.remove(&k) {
             Some((prev_val, prev_count)) if prev_val == v => {
                 match (current_count as i128) - (prev_count as i128) {
-                    add if add > 1 => ret.push(UnorderedMapLikeChange::new(
+                    add if add > 1 => emit(UnorderedMapLikeChange::new(
                         (k, v),
                         add as usize,
                         Operation::Insert,
                     )),
-                    add if add == 1 => ret.push(UnorderedMapLikeChange::new(
+                    add if add == 1 => emit(UnorderedMapLikeChange::new(
                         (k, v),
                         add as usize,
                         Operation::Insert,
                     )),
-                    sub if sub < 0 => ret.push(UnorderedMapLikeChange::new(
+                    sub if sub < 0 => emit(UnorderedMapLikeChange::new(
                         (k, v),
                         -sub as usize,
                         Operation::Remove,
                     )),
-                    sub if sub == -1 => ret.push(UnorderedMapLikeChange::new(
+                    sub if sub == -1 => emit(UnorderedMapLikeChange::new(
                         (k, v),
                         -sub as usize,
                         Operation::Remove,
@@ -203,19 +314,19 @@ pub fn unordered_hashcmp<
                 }
             }
             Some((prev_val, prev_count)) if prev_val != v => {
-                ret.push(UnorderedMapLikeChange::new(
+                emit(UnorderedMapLikeChange::new(
                     (k, prev_val),
                     prev_count,
                     Operation::Remove,
                 ));
-                ret.push(UnorderedMapLikeChange::new(
+                emit(UnorderedMapLikeChange::new(
                     (k, v),
                     current_count,
                     Operation::Insert,
                 ));
             }
             Some(_) => unreachable!(),
-            None => ret.push(UnorderedMapLikeChange::new(
+            None => emit(UnorderedMapLikeChange::new(
                 (k, v),
                 current_count,
                 Operation::Insert,
@@ -224,20 +335,33 @@ pub fn unordered_hashcmp<
     }
 
     for (k, (v, count)) in previous.into_iter() {
-        ret.push(UnorderedMapLikeChange::new(
+        emit(UnorderedMapLikeChange::new(
             (k, v),
             count,
             Operation::Remove,
         ))
     }
 
-    ret.shrink_to_fit();
+    None
+}
 
-    match ret.is_empty() {
-        true => None,
-        false => Some(UnorderedMapLikeDiff(UnorderedMapLikeDiffInternal::Modify(
-            ret,
-        ))),
+/// A diff which replaces the whole collection with the entries of `current`
+pub fn replacement<'a, K: 'a, V: 'a>(
+    current: impl Iterator<Item = (&'a K, &'a V)>,
+) -> UnorderedMapLikeDiff<&'a K, &'a V> {
+    UnorderedMapLikeDiff(UnorderedMapLikeDiffInternal::Replace(current.collect()))
+}
+
+/// Whether the two collections hold different entries, counting duplicate
+/// keys, without generating a diff
+pub fn has_changes<'a, K: Hash + Eq + 'a, V: PartialEq + 'a, B: Iterator<Item = (&'a K, &'a V)>>(
+    previous: B,
+    current: B,
+    key_only: bool,
+) -> bool {
+    match key_only {
+        true => collect_into_key_eq_map(previous) != collect_into_key_eq_map(current),
+        false => collect_into_key_value_eq_map(previous) != collect_into_key_value_eq_map(current),
     }
 }
 
@@ -294,7 +418,7 @@ pub fn apply_unordered_hashdiffs<
             UnorderedMapLikeChange::InsertMany(key, value, count) => {
                 match list_hash.get_mut(&key) {
                     Some(val) => {
-                        val.1 += count;
+                        val.1 = val.1.saturating_add(*count);
                     }
                     None => {
                         list_hash.insert(key, (value, *count));
@@ -303,7 +427,7 @@ pub fn apply_unordered_hashdiffs<
             }
             UnorderedMapLikeChange::InsertSingle(key, value) => match list_hash.get_mut(&key) {
                 Some(val) => {
-                    val.1 += 1;
+                    val.1 = val.1.saturating_add(1);
                 }
                 None => {
                     list_hash.insert(key, (value, 1));
@@ -325,11 +449,162 @@ pub fn apply_unordered_hashdiffs<
     )
 }
 
+/// Map-like collections which an [`UnorderedMapLikeDiff`] can be applied to in place.
+///
+/// Keys are unique, so insertion and removal counts are not used.
+pub trait UnorderedMapLikeCollection<K, V> {
+    /// Remove every entry from the collection
+    fn clear_entries(&mut self);
+
+    /// Insert an entry, replacing the value for an existing key
+    fn insert_entry(&mut self, key: K, value: V);
+
+    /// Remove the entry for `key`, if present
+    fn remove_key(&mut self, key: &K);
+}
+
+pub fn apply_unordered_hashdiffs_in_place<
+    #[cfg(feature = "nanoserde")] K: Hash + Clone + PartialEq + Eq + SerBin + DeBin,
+    #[cfg(not(feature = "nanoserde"))] K: Hash + Clone + PartialEq + Eq,
+    V: Clone,
+    C: UnorderedMapLikeCollection<K, V>,
+>(
+    map: &mut C,
+    diffs: UnorderedMapLikeDiff<K, V>,
+) {
+    let diffs = match diffs {
+        UnorderedMapLikeDiff(UnorderedMapLikeDiffInternal::Replace(replacement)) => {
+            map.clear_entries();
+            for (key, value) in replacement {
+                map.insert_entry(key, value);
+            }
+            return;
+        }
+        UnorderedMapLikeDiff(UnorderedMapLikeDiffInternal::Modify(diffs)) => diffs,
+    };
+
+    // a changed value is sent as a removal followed by an insertion for the same key,
+    // so all removals must be applied first
+    let mut insertions = Vec::with_capacity(diffs.len());
+
+    for diff in diffs {
+        match diff {
+            UnorderedMapLikeChange::InsertMany(key, value, _)
+            | UnorderedMapLikeChange::InsertSingle(key, value) => insertions.push((key, value)),
+            UnorderedMapLikeChange::RemoveMany(key, _)
+            | UnorderedMapLikeChange::RemoveSingle(key) => map.remove_key(&key),
+        }
+    }
+
+    for (key, value) in insertions {
+        map.insert_entry(key, value);
+    }
+}
+
+/// Apply `diffs` as [`apply_unordered_hashdiffs_in_place`] does, after
+/// charging the entries they insert to `budget`
+pub fn apply_unordered_hashdiffs_in_place_limited<
+    #[cfg(feature = "nanoserde")] K: Hash + Clone + PartialEq + Eq + SerBin + DeBin,
+    #[cfg(not(feature = "nanoserde"))] K: Hash + Clone + PartialEq + Eq,
+    V: Clone,
+    C: UnorderedMapLikeCollection<K, V>,
+>(
+    map: &mut C,
+    diffs: UnorderedMapLikeDiff<K, V>,
+    budget: &mut ApplyBudget,
+) -> Result<(), LimitExceeded> {
+    let inserted = match &diffs.0 {
+        UnorderedMapLikeDiffInternal::Replace(replacement) => replacement.len(),
+        UnorderedMapLikeDiffInternal::Modify(diffs) => diffs
+            .iter()
+            .filter(|diff| {
+                matches!(
+                    diff,
+                    UnorderedMapLikeChange::InsertMany(..)
+                        | UnorderedMapLikeChange::InsertSingle(..)
+                )
+            })
+            .count(),
+    };
+    budget.grow(inserted)?;
+    apply_unordered_hashdiffs_in_place(map, diffs);
+    Ok(())
+}
+
+/// Apply a diff generated with references, cloning only the inserted entries
+pub fn apply_unordered_hashdiffs_in_place_ref<
+    #[cfg(feature = "nanoserde")] K: Hash + Clone + PartialEq + Eq + SerBin + DeBin,
+    #[cfg(not(feature = "nanoserde"))] K: Hash + Clone + PartialEq + Eq,
+    V: Clone,
+    C: UnorderedMapLikeCollection<K, V>,
+>(
+    map: &mut C,
+    diffs: UnorderedMapLikeDiff<&K, &V>,
+) {
+    let diffs = match diffs {
+        UnorderedMapLikeDiff(UnorderedMapLikeDiffInternal::Replace(replacement)) => {
+            map.clear_entries();
+            for (key, value) in replacement {
+                map.insert_entry(key.clone(), value.clone());
+            }
+            return;
+        }
+        UnorderedMapLikeDiff(UnorderedMapLikeDiffInternal::Modify(diffs)) => diffs,
+    };
+
+    // see apply_unordered_hashdiffs_in_place for why removals go first
+    let mut insertions = Vec::with_capacity(diffs.len());
+
+    for diff in diffs {
+        match diff {
+            UnorderedMapLikeChange::InsertMany(key, value, _)
+            | UnorderedMapLikeChange::InsertSingle(key, value) => insertions.push((key, value)),
+            UnorderedMapLikeChange::RemoveMany(key, _)
+            | UnorderedMapLikeChange::RemoveSingle(key) => map.remove_key(key),
+        }
+    }
+
+    for (key, value) in insertions {
+        map.insert_entry(key.clone(), value.clone());
+    }
+}
+
+impl<K: Hash + Eq, V, S: BuildHasher> UnorderedMapLikeCollection<K, V>
+    for std::collections::HashMap<K, V, S>
+{
+    fn clear_entries(&mut self) {
+        self.clear();
+    }
+
+    fn insert_entry(&mut self, key: K, value: V) {
+        self.insert(key, value);
+    }
+
+    fn remove_key(&mut self, key: &K) {
+        self.remove(key);
+    }
+}
+
+impl<K: Ord, V> UnorderedMapLikeCollection<K, V> for BTreeMap<K, V> {
+    fn clear_entries(&mut self) {
+        self.clear();
+    }
+
+    fn insert_entry(&mut self, key: K, value: V) {
+        self.insert(key, value);
+    }
+
+    fn remove_key(&mut self, key: &K) {
+        self.remove(key);
+    }
+}
+
 #[cfg(feature = "nanoserde")]
 mod nanoserde_impls {
     use super::{
         DeBin, SerBin, UnorderedMapLikeChange, UnorderedMapLikeDiff, UnorderedMapLikeDiffInternal,
     };
+    use crate::checked::DeBinChecked;
 
     impl<K, V> SerBin for UnorderedMapLikeChange<K, V>
     where
@@ -447,8 +722,8 @@ mod nanoserde_impls {
 
     impl<K, V> DeBin for UnorderedMapLikeChange<K, V>
     where
-        K: SerBin + PartialEq + Clone + DeBin,
-        V: SerBin + PartialEq + Clone + DeBin,
+        K: SerBin + PartialEq + Clone + DeBinChecked,
+        V: SerBin + PartialEq + Clone + DeBinChecked,
     {
         fn de_bin(
             offset: &mut usize,
@@ -457,19 +732,19 @@ mod nanoserde_impls {
             let id: u8 = DeBin::de_bin(offset, bytes)?;
             core::result::Result::Ok(match id {
                 0_u8 => UnorderedMapLikeChange::InsertMany(
-                    DeBin::de_bin(offset, bytes)?,
-                    DeBin::de_bin(offset, bytes)?,
+                    K::de_bin_checked(offset, bytes)?,
+                    V::de_bin_checked(offset, bytes)?,
                     DeBin::de_bin(offset, bytes)?,
                 ),
                 1_u8 => UnorderedMapLikeChange::RemoveMany(
-                    DeBin::de_bin(offset, bytes)?,
+                    K::de_bin_checked(offset, bytes)?,
                     DeBin::de_bin(offset, bytes)?,
                 ),
                 2_u8 => UnorderedMapLikeChange::InsertSingle(
-                    DeBin::de_bin(offset, bytes)?,
-                    DeBin::de_bin(offset, bytes)?,
+                    K::de_bin_checked(offset, bytes)?,
+                    V::de_bin_checked(offset, bytes)?,
                 ),
-                3_u8 => UnorderedMapLikeChange::RemoveSingle(DeBin::de_bin(offset, bytes)?),
+                3_u8 => UnorderedMapLikeChange::RemoveSingle(K::de_bin_checked(offset, bytes)?),
                 _ => {
                     return core::result::Result::Err(nanoserde::DeBinErr {
                         o: *offset,
@@ -483,8 +758,8 @@ mod nanoserde_impls {
 
     impl<K, V> DeBin for UnorderedMapLikeDiff<K, V>
     where
-        K: SerBin + PartialEq + Clone + DeBin,
-        V: SerBin + PartialEq + Clone + DeBin,
+        K: SerBin + PartialEq + Clone + DeBinChecked,
+        V: SerBin + PartialEq + Clone + DeBinChecked,
     {
         fn de_bin(
             offset: &mut usize,
@@ -493,21 +768,13 @@ mod nanoserde_impls {
             let id: u8 = DeBin::de_bin(offset, bytes)?;
             core::result::Result::Ok(match id {
                 0_u8 => {
-                    let len: usize = DeBin::de_bin(offset, bytes)?;
-                    let mut contents: Vec<(K, V)> = Vec::new();
-                    for _ in 0..len {
-                        let content = DeBin::de_bin(offset, bytes)?;
-                        contents.push(content);
-                    }
+                    let contents: Vec<(K, V)> =
+                        crate::checked::de_bin_vec_with(offset, bytes, <(K, V)>::de_bin_checked)?;
                     UnorderedMapLikeDiff(UnorderedMapLikeDiffInternal::Replace(contents))
                 }
                 1_u8 => {
-                    let len: usize = DeBin::de_bin(offset, bytes)?;
-                    let mut contents: Vec<UnorderedMapLikeChange<K, V>> = Vec::new();
-                    for _ in 0..len {
-                        let content = DeBin::de_bin(offset, bytes)?;
-                        contents.push(content);
-                    }
+                    let contents: Vec<UnorderedMapLikeChange<K, V>> =
+                        crate::checked::de_bin_vec(offset, bytes)?;
                     UnorderedMapLikeDiff(UnorderedMapLikeDiffInternal::Modify(contents))
                 }
                 _ => {
@@ -524,9 +791,12 @@ mod nanoserde_impls {
 
 #[cfg(test)]
 mod test {
-    use std::collections::{BTreeMap, HashMap};
+    use std::collections::{BTreeMap, BTreeSet, HashMap};
 
-    use super::{UnorderedMapLikeDiff, UnorderedMapLikeDiffInternal};
+    use super::{
+        apply_unordered_hashdiffs_in_place, unordered_hashcmp, UnorderedMapLikeDiff,
+        UnorderedMapLikeDiffInternal,
+    };
     use crate::{Difference, StructDiff};
 
     use crate as structdiff;
@@ -635,4 +905,26 @@ mod test {
 
         assert_eq_unordered!(diffed.test1, second.test1);
     }
+
+    #[test]
+    fn test_apply_in_place() {
+        let first: BTreeMap<i32, i32> = [(10, 0), (15, 2), (20, 0), (25, 0)].into_iter().collect();
+        let second: BTreeMap<i32, i32> = [(10, 1), (15, 2), (20, 0), (30, 0)].into_iter().collect();
+
+        let diff = unordered_hashcmp(first.iter(), second.iter(), false).unwrap();
+        let mut diffed = first.clone();
+        apply_unordered_hashdiffs_in_place(&mut diffed, diff.into());
+        assert_eq!(diffed, second);
+
+        let first: HashMap<i32, i32> = first.into_iter().collect();
+        let second: HashMap<i32, i32> = [(10, 1), (30, 0)].into_iter().collect();
+
+        let diff = unordered_hashcmp(first.iter(), second.iter(), true).unwrap();
+        let mut diffed = first.clone();
+        apply_unordered_hashdiffs_in_place(&mut diffed, diff.into());
+        assert_eq!(
+            diffed.keys().collect::<BTreeSet<_>>(),
+            second.keys().collect()
+        );
+    }
 }
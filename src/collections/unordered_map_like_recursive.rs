@@ -11,9 +11,18 @@ type HashMap<K, V> = std::collections::HashMap<K, V>;
 type HashMap<K, V> =
     std::collections::HashMap<K, V, std::hash::BuildHasherDefault<rustc_hash::FxHasher>>;
 
-use std::{hash::Hash, marker::PhantomData};
+use std::{
+    collections::BTreeMap,
+    hash::{BuildHasher, Hash},
+    marker::PhantomData,
+};
 
-use crate::StructDiff;
+use super::{unordered_map_like::UnorderedMapLikeCollection, Chunks};
+use crate::{
+    limits::{ApplyBudget, LimitExceeded},
+    size::{list_size, EncodedSize, TAG},
+    DiffSink, StructDiff,
+};
 
 #[cfg_attr(feature = "debug_diffs", derive(Debug))]
 #[derive(Clone)]
@@ -67,6 +76,58 @@ pub struct UnorderedMapLikeRecursiveDiffOwned<K: Clone, V: StructDiff + Clone>(
     UnorderedMapLikeRecursiveDiffInternalOwned<K, V>,
 );
 
+impl<K: Clone + EncodedSize, V: StructDiff + Clone + EncodedSize> EncodedSize
+    for UnorderedMapLikeRecursiveDiffOwned<K, V>
+where
+    V::Diff: EncodedSize,
+{
+    fn encoded_size(&self) -> usize {
+        TAG + match &self.0 {
+            UnorderedMapLikeRecursiveDiffInternalOwned::Replace(entries) => entries.encoded_size(),
+            UnorderedMapLikeRecursiveDiffInternalOwned::Modify(changes) => {
+                list_size(changes.iter().map(|change| {
+                    TAG + match change {
+                        UnorderedMapLikeRecursiveChangeOwned::Insert(entry) => entry.encoded_size(),
+                        UnorderedMapLikeRecursiveChangeOwned::Remove(key) => key.encoded_size(),
+                        UnorderedMapLikeRecursiveChangeOwned::Change(change) => {
+                            change.encoded_size()
+                        }
+                    }
+                }))
+            }
+        }
+    }
+}
+
+impl<K: Clone, V: StructDiff + Clone> UnorderedMapLikeRecursiveDiffOwned<K, V> {
+    /// The diff of inserting `value` at `key`, given the value the key held
+    /// before (as returned by `insert` on the map). A replaced value is
+    /// diffed against its previous value, and `None` is returned if they
+    /// have no diff.
+    pub fn from_insert(key: K, value: V, previous: Option<V>) -> Option<Self> {
+        let change = match previous {
+            Some(previous) => {
+                let diffs = previous.diff(&value);
+                if diffs.is_empty() {
+                    return None;
+                }
+                UnorderedMapLikeRecursiveChangeOwned::Change((key, diffs))
+            }
+            None => UnorderedMapLikeRecursiveChangeOwned::Insert((key, value)),
+        };
+        Some(Self(UnorderedMapLikeRecursiveDiffInternalOwned::Modify(
+            vec![change],
+        )))
+    }
+
+    /// The diff of removing the entry for `key`
+    pub fn from_remove(key: K) -> Self {
+        Self(UnorderedMapLikeRecursiveDiffInternalOwned::Modify(vec![
+            UnorderedMapLikeRecursiveChangeOwned::Remove(key),
+        ]))
+    }
+}
+
 impl<'a, K: Clone, V: StructDiff + Clone> From<UnorderedMapLikeRecursiveDiffRef<'a, K, V>>
     for UnorderedMapLikeRecursiveDiffOwned<K, V>
 {
@@ -161,6 +222,71 @@ pub fn unordered_hashcmp<
     current: B,
     key_only: bool,
 ) -> Option<UnorderedMapLikeRecursiveDiffRef<'a, K, V>> {
+    let mut ret: Vec<UnorderedMapLikeRecursiveChangeRef<'a, K, V>> = Vec::new();
+
+    if let Some(replacement) = compare(previous, current, key_only, &mut |change| ret.push(change))
+    {
+        return Some(UnorderedMapLikeRecursiveDiffRef(
+            UnorderedMapLikeRecursiveDiffInternalRef::Replace(replacement),
+        ));
+    }
+
+    ret.shrink_to_fit();
+
+    match ret.is_empty() {
+        true => None,
+        false => Some(UnorderedMapLikeRecursiveDiffRef(
+            UnorderedMapLikeRecursiveDiffInternalRef::Modify(ret),
+        )),
+    }
+}
+
+/// Generate the same changes as [`unordered_hashcmp`], pushing them to `sink` as
+/// they are found, in diffs of at most [`CHANGES_PER_DIFF`](super::CHANGES_PER_DIFF)
+/// changes. A replacement of the whole map is pushed as a single diff.
+pub fn unordered_hashcmp_into<
+    'a,
+    #[cfg(feature = "nanoserde")] K: Hash + Clone + PartialEq + Eq + SerBin + DeBin + 'a,
+    #[cfg(not(feature = "nanoserde"))] K: Hash + Clone + PartialEq + Eq + 'a,
+    V: Clone + PartialEq + StructDiff + 'a,
+    B: Iterator<Item = (&'a K, &'a V)>,
+>(
+    previous: B,
+    current: B,
+    key_only: bool,
+    sink: &mut impl DiffSink<UnorderedMapLikeRecursiveDiffRef<'a, K, V>>,
+) {
+    let mut chunks = Chunks::new(|changes| {
+        sink.push(UnorderedMapLikeRecursiveDiffRef(
+            UnorderedMapLikeRecursiveDiffInternalRef::Modify(changes),
+        ))
+    });
+
+    match compare(previous, current, key_only, &mut |change| {
+        chunks.push(change)
+    }) {
+        Some(replacement) => sink.push(UnorderedMapLikeRecursiveDiffRef(
+            UnorderedMapLikeRecursiveDiffInternalRef::Replace(replacement),
+        )),
+        None => chunks.finish(),
+    }
+}
+
+/// Compare the entries of `previous` and `current`, handing each change to `emit`.
+/// When replacing the whole map is expected to be smaller, nothing is emitted and
+/// the replacement is returned instead.
+fn compare<
+    'a,
+    #[cfg(feature = "nanoserde")] K: Hash + Clone + PartialEq + Eq + SerBin + DeBin + 'a,
+    #[cfg(not(feature = "nanoserde"))] K: Hash + Clone + PartialEq + Eq + 'a,
+    V: Clone + PartialEq + StructDiff + 'a,
+    B: Iterator<Item = (&'a K, &'a V)>,
+>(
+    previous: B,
+    current: B,
+    key_only: bool,
+    emit: &mut impl FnMut(UnorderedMapLikeRecursiveChangeRef<'a, K, V>),
+) -> Option<Vec<(&'a K, &'a V)>> {
     let (previous, mut current) = (
         collect_into_key_eq_map(previous),
         collect_into_key_eq_map(current),
@@ -171,19 +297,12 @@ pub fn unordered_hashcmp<
 
     if key_only {
         if (current.len() as isize) < ((previous.len() as isize) - (current.len() as isize)) {
-            return Some(UnorderedMapLikeRecursiveDiffRef(
-                UnorderedMapLikeRecursiveDiffInternalRef::Replace(
-                    current.into_iter().collect::<Vec<(&'a K, &'a V)>>(),
-                ),
-            ));
+            return Some(current.into_iter().collect());
         }
 
-        let mut ret: Vec<UnorderedMapLikeRecursiveChangeRef<'a, K, V>> =
-            Vec::with_capacity((previous.len() + current.len()) >> 1);
-
         for prev_entry in previous.into_iter() {
             if current.remove_entry(prev_entry.0).is_none() {
-                ret.push(UnorderedMapLikeRecursiveChangeRef::new(
+                emit(UnorderedMapLikeRecursiveChangeRef::new(
                     prev_entry,
                     Operation::Remove,
                 ));
@@ -191,40 +310,26 @@ pub fn unordered_hashcmp<
         }
 
         for add_entry in current.into_iter() {
-            ret.push(UnorderedMapLikeRecursiveChangeRef::new(
+            emit(UnorderedMapLikeRecursiveChangeRef::new(
                 add_entry,
                 Operation::Insert,
             ))
         }
 
-        ret.shrink_to_fit();
-
-        match ret.is_empty() {
-            true => None,
-            false => Some(UnorderedMapLikeRecursiveDiffRef(
-                UnorderedMapLikeRecursiveDiffInternalRef::Modify(ret),
-            )),
-        }
+        None
     } else {
         if (current.len() as isize) < ((previous.len() as isize) - (current.len() as isize)) {
-            return Some(UnorderedMapLikeRecursiveDiffRef(
-                UnorderedMapLikeRecursiveDiffInternalRef::Replace(
-                    current.into_iter().collect::<Vec<(&'a K, &'a V)>>(),
-                ),
-            ));
+            return Some(current.into_iter().collect());
         }
 
-        let mut ret: Vec<UnorderedMapLikeRecursiveChangeRef<'a, K, V>> =
-            Vec::with_capacity((previous.len() + current.len()) >> 1);
-
         for prev_entry in previous.into_iter() {
             match current.remove_entry(prev_entry.0) {
-                None => ret.push(UnorderedMapLikeRecursiveChangeRef::new(
+                None => emit(UnorderedMapLikeRecursiveChangeRef::new(
                     prev_entry,
                     Operation::Remove,
                 )),
                 Some(current_entry) if prev_entry.1 != current_entry.1 => {
-                    ret.push(UnorderedMapLikeRecursiveChangeRef::new(
+                    emit(UnorderedMapLikeRecursiveChangeRef::new(
                         current_entry,
                         Operation::Change(prev_entry.1.diff_ref(current_entry.1), PhantomData),
                     ))
@@ -234,23 +339,34 @@ pub fn unordered_hashcmp<
         }
 
         for add_entry in current.into_iter() {
-            ret.push(UnorderedMapLikeRecursiveChangeRef::new(
+            emit(UnorderedMapLikeRecursiveChangeRef::new(
                 add_entry,
                 Operation::Insert,
             ))
         }
 
-        ret.shrink_to_fit();
-
-        match ret.is_empty() {
-            true => None,
-            false => Some(UnorderedMapLikeRecursiveDiffRef(
-                UnorderedMapLikeRecursiveDiffInternalRef::Modify(ret),
-            )),
-        }
+        None
     }
 }
 
+/// Whether the two maps hold different keys or, unless `key_only`, unequal
+/// values, without generating a diff
+pub fn has_changes<'a, K: Hash + Eq + 'a, V: PartialEq + 'a, B: Iterator<Item = (&'a K, &'a V)>>(
+    previous: B,
+    current: B,
+    key_only: bool,
+) -> bool {
+    let (previous, current) = (
+        collect_into_key_eq_map(previous),
+        collect_into_key_eq_map(current),
+    );
+    previous.len() != current.len()
+        || previous.iter().any(|(key, value)| match current.get(key) {
+            Some(updated) => !key_only && value != updated,
+            None => true,
+        })
+}
+
 pub fn apply_unordered_hashdiffs<
     #[cfg(feature = "nanoserde")] K: Hash + Clone + PartialEq + Eq + SerBin + DeBin + 'static,
     #[cfg(not(feature = "nanoserde"))] K: Hash + Clone + PartialEq + Eq + 'static,
@@ -307,9 +423,236 @@ pub fn apply_unordered_hashdiffs<
     Box::new(list_hash.into_iter())
 }
 
+/// Map-like collections which an [`UnorderedMapLikeRecursiveDiffOwned`] can
+/// be applied to in place, changing existing values through
+/// [`StructDiff::apply_mut`] rather than rebuilding the collection.
+pub trait UnorderedMapLikeRecursiveCollection<K, V>: UnorderedMapLikeCollection<K, V> {
+    /// The value for `key`, if present
+    fn get_entry_mut(&mut self, key: &K) -> Option<&mut V>;
+}
+
+impl<K: Hash + Eq, V, S: BuildHasher> UnorderedMapLikeRecursiveCollection<K, V>
+    for std::collections::HashMap<K, V, S>
+{
+    fn get_entry_mut(&mut self, key: &K) -> Option<&mut V> {
+        self.get_mut(key)
+    }
+}
+
+impl<K: Ord, V> UnorderedMapLikeRecursiveCollection<K, V> for BTreeMap<K, V> {
+    fn get_entry_mut(&mut self, key: &K) -> Option<&mut V> {
+        self.get_mut(key)
+    }
+}
+
+/// Apply `diffs` to `map` in place, with the same result as
+/// [`apply_unordered_hashdiffs`]
+pub fn apply_unordered_hashdiffs_in_place<
+    K: Clone,
+    V: Clone + StructDiff,
+    C: UnorderedMapLikeRecursiveCollection<K, V>,
+>(
+    map: &mut C,
+    diffs: UnorderedMapLikeRecursiveDiffOwned<K, V>,
+) {
+    let diffs = match diffs.0 {
+        UnorderedMapLikeRecursiveDiffInternalOwned::Replace(replacement) => {
+            map.clear_entries();
+            for (key, value) in replacement {
+                map.insert_entry(key, value);
+            }
+            return;
+        }
+        UnorderedMapLikeRecursiveDiffInternalOwned::Modify(diffs) => diffs,
+    };
+
+    // entries are inserted after every removal and change, as when the
+    // diff is applied by rebuilding the map
+    let mut insertions = Vec::new();
+    for diff in diffs {
+        match diff {
+            UnorderedMapLikeRecursiveChangeOwned::Insert(entry) => insertions.push(entry),
+            UnorderedMapLikeRecursiveChangeOwned::Remove(key) => map.remove_key(&key),
+            UnorderedMapLikeRecursiveChangeOwned::Change((key, diff)) => {
+                if let Some(to_change) = map.get_entry_mut(&key) {
+                    to_change.apply_mut(diff);
+                }
+            }
+        }
+    }
+
+    for (key, value) in insertions {
+        map.insert_entry(key, value);
+    }
+}
+
+/// Apply `diffs` to `map` in place, as [`apply_unordered_hashdiffs_in_place`]
+/// does, after charging the entries they insert to `budget`. Changes to
+/// existing values are applied with [`StructDiff::apply_single_limited`], one
+/// level of recursion deeper, and if one exceeds a limit, the rest of the diff
+/// is not applied.
+pub fn apply_unordered_hashdiffs_limited<
+    K: Clone,
+    V: Clone + StructDiff,
+    C: UnorderedMapLikeRecursiveCollection<K, V>,
+>(
+    map: &mut C,
+    diffs: UnorderedMapLikeRecursiveDiffOwned<K, V>,
+    budget: &mut ApplyBudget,
+) -> Result<(), LimitExceeded> {
+    let diffs = match diffs.0 {
+        UnorderedMapLikeRecursiveDiffInternalOwned::Replace(replacement) => {
+            budget.grow(replacement.len())?;
+            map.clear_entries();
+            for (key, value) in replacement {
+                map.insert_entry(key, value);
+            }
+            return Ok(());
+        }
+        UnorderedMapLikeRecursiveDiffInternalOwned::Modify(diffs) => diffs,
+    };
+
+    let inserted = diffs
+        .iter()
+        .filter(|diff| matches!(diff, UnorderedMapLikeRecursiveChangeOwned::Insert(_)))
+        .count();
+    budget.grow(inserted)?;
+
+    let mut insertions = Vec::with_capacity(inserted);
+    for diff in diffs {
+        match diff {
+            UnorderedMapLikeRecursiveChangeOwned::Insert(entry) => insertions.push(entry),
+            UnorderedMapLikeRecursiveChangeOwned::Remove(key) => map.remove_key(&key),
+            UnorderedMapLikeRecursiveChangeOwned::Change((key, diff)) => {
+                if let Some(to_change) = map.get_entry_mut(&key) {
+                    budget.apply_nested(to_change, diff)?;
+                }
+            }
+        }
+    }
+
+    for (key, value) in insertions {
+        map.insert_entry(key, value);
+    }
+    Ok(())
+}
+
+/// Apply a diff generated with references in place, applying nested changes
+/// with [`StructDiff::apply_ref_diffs`] and cloning inserted entries directly
+/// into place
+pub fn apply_unordered_hashdiffs_in_place_ref<
+    K: Clone,
+    V: Clone + StructDiff,
+    C: UnorderedMapLikeRecursiveCollection<K, V>,
+>(
+    map: &mut C,
+    diffs: UnorderedMapLikeRecursiveDiffRef<'_, K, V>,
+) {
+    let diffs = match diffs.0 {
+        UnorderedMapLikeRecursiveDiffInternalRef::Replace(replacement) => {
+            map.clear_entries();
+            for (key, value) in replacement {
+                map.insert_entry(key.clone(), value.clone());
+            }
+            return;
+        }
+        UnorderedMapLikeRecursiveDiffInternalRef::Modify(diffs) => diffs,
+    };
+
+    let mut insertions = Vec::new();
+    for diff in diffs {
+        match diff {
+            UnorderedMapLikeRecursiveChangeRef::Insert(entry) => insertions.push(entry),
+            UnorderedMapLikeRecursiveChangeRef::Remove(key) => map.remove_key(key),
+            UnorderedMapLikeRecursiveChangeRef::Change((key, diff)) => {
+                if let Some(to_change) = map.get_entry_mut(key) {
+                    to_change.apply_ref_diffs(diff);
+                }
+            }
+        }
+    }
+
+    for (key, value) in insertions {
+        match map.get_entry_mut(key) {
+            Some(existing) => existing.clone_from(value),
+            None => map.insert_entry(key.clone(), value.clone()),
+        }
+    }
+}
+
+/// Apply a diff generated with references, applying nested changes with
+/// [`StructDiff::apply_ref_diffs`] and cloning inserted entries directly into place
+pub fn apply_unordered_hashdiffs_ref<
+    'a,
+    #[cfg(feature = "nanoserde")] K: Hash + Clone + PartialEq + Eq + SerBin + DeBin + 'static,
+    #[cfg(not(feature = "nanoserde"))] K: Hash + Clone + PartialEq + Eq + 'static,
+    V: Clone + StructDiff + 'static,
+    B: IntoIterator<Item = (K, V)>,
+>(
+    list: B,
+    diffs: UnorderedMapLikeRecursiveDiffRef<'a, K, V>,
+) -> Box<dyn ExactSizeIterator<Item = (K, V)>> {
+    let diffs = match diffs {
+        UnorderedMapLikeRecursiveDiffRef(UnorderedMapLikeRecursiveDiffInternalRef::Replace(
+            replacement,
+        )) => {
+            return Box::new(
+                replacement
+                    .into_iter()
+                    .map(|(k, v)| (k.clone(), v.clone()))
+                    .collect::<Vec<_>>()
+                    .into_iter(),
+            );
+        }
+        UnorderedMapLikeRecursiveDiffRef(UnorderedMapLikeRecursiveDiffInternalRef::Modify(
+            diffs,
+        )) => diffs,
+    };
+
+    let (insertions, rem): (Vec<_>, Vec<_>) = diffs
+        .into_iter()
+        .partition(|x| matches!(&x, UnorderedMapLikeRecursiveChangeRef::Insert(_)));
+    let (removals, changes): (Vec<_>, Vec<_>) = rem
+        .into_iter()
+        .partition(|x| matches!(&x, UnorderedMapLikeRecursiveChangeRef::Remove(_)));
+
+    let mut list_hash = HashMap::<K, V>::from_iter(list);
+
+    for remove in removals {
+        let UnorderedMapLikeRecursiveChangeRef::Remove(key) = remove else {
+            continue;
+        };
+        list_hash.remove(key);
+    }
+
+    for change in changes {
+        let UnorderedMapLikeRecursiveChangeRef::Change((key, diff)) = change else {
+            continue;
+        };
+        let Some(to_change) = list_hash.get_mut(key) else {
+            continue;
+        };
+        to_change.apply_ref_diffs(diff);
+    }
+
+    for insert in insertions {
+        let UnorderedMapLikeRecursiveChangeRef::Insert((key, value)) = insert else {
+            continue;
+        };
+        match list_hash.get_mut(key) {
+            Some(existing) => existing.clone_from(value),
+            None => {
+                list_hash.insert(key.clone(), value.clone());
+            }
+        }
+    }
+
+    Box::new(list_hash.into_iter())
+}
+
 #[cfg(feature = "nanoserde")]
 mod nanoserde_impls {
-    use crate::StructDiff;
+    use crate::{checked::DeBinChecked, StructDiff};
 
     use super::{
         DeBin, SerBin, UnorderedMapLikeRecursiveChangeOwned, UnorderedMapLikeRecursiveChangeRef,
@@ -420,8 +763,8 @@ mod nanoserde_impls {
 
     impl<K, V> DeBin for UnorderedMapLikeRecursiveChangeOwned<K, V>
     where
-        K: SerBin + PartialEq + Clone + DeBin,
-        V: SerBin + PartialEq + Clone + DeBin + StructDiff,
+        K: SerBin + PartialEq + Clone + DeBinChecked,
+        V: SerBin + PartialEq + Clone + DeBinChecked + StructDiff,
     {
         fn de_bin(
             offset: &mut usize,
@@ -429,9 +772,16 @@ mod nanoserde_impls {
         ) -> Result<UnorderedMapLikeRecursiveChangeOwned<K, V>, nanoserde::DeBinErr> {
             let id: u8 = DeBin::de_bin(offset, bytes)?;
             core::result::Result::Ok(match id {
-                0_u8 => UnorderedMapLikeRecursiveChangeOwned::Insert(DeBin::de_bin(offset, bytes)?),
-                1_u8 => UnorderedMapLikeRecursiveChangeOwned::Remove(DeBin::de_bin(offset, bytes)?),
-                2_u8 => UnorderedMapLikeRecursiveChangeOwned::Change(DeBin::de_bin(offset, bytes)?),
+                0_u8 => UnorderedMapLikeRecursiveChangeOwned::Insert(<(K, V)>::de_bin_checked(
+                    offset, bytes,
+                )?),
+                1_u8 => {
+                    UnorderedMapLikeRecursiveChangeOwned::Remove(K::de_bin_checked(offset, bytes)?)
+                }
+                2_u8 => UnorderedMapLikeRecursiveChangeOwned::Change((
+                    K::de_bin_checked(offset, bytes)?,
+                    crate::checked::de_bin_vec(offset, bytes)?,
+                )),
                 _ => {
                     return core::result::Result::Err(nanoserde::DeBinErr {
                         o: *offset,
@@ -445,8 +795,8 @@ mod nanoserde_impls {
 
     impl<K, V> DeBin for UnorderedMapLikeRecursiveDiffOwned<K, V>
     where
-        K: SerBin + PartialEq + Clone + DeBin,
-        V: SerBin + PartialEq + Clone + DeBin + StructDiff,
+        K: SerBin + PartialEq + Clone + DeBinChecked,
+        V: SerBin + PartialEq + Clone + DeBinChecked + StructDiff,
     {
         fn de_bin(
             offset: &mut usize,
@@ -455,12 +805,12 @@ mod nanoserde_impls {
             let id: u8 = DeBin::de_bin(offset, bytes)?;
             core::result::Result::Ok(match id {
                 0_u8 => UnorderedMapLikeRecursiveDiffOwned(
-                    UnorderedMapLikeRecursiveDiffInternalOwned::Replace(DeBin::de_bin(
-                        offset, bytes,
-                    )?),
+                    UnorderedMapLikeRecursiveDiffInternalOwned::Replace(
+                        crate::checked::de_bin_vec_with(offset, bytes, <(K, V)>::de_bin_checked)?,
+                    ),
                 ),
                 1_u8 => UnorderedMapLikeRecursiveDiffOwned(
-                    UnorderedMapLikeRecursiveDiffInternalOwned::Modify(DeBin::de_bin(
+                    UnorderedMapLikeRecursiveDiffInternalOwned::Modify(crate::checked::de_bin_vec(
                         offset, bytes,
                     )?),
                 ),
@@ -493,6 +843,7 @@ mod test {
         #[cfg_attr(feature = "nanoserde", derive(DeBin, SerBin))]
         #[cfg_attr(feature = "serde", derive(Deserialize, Serialize))]
         #[derive(Debug, PartialEq, Clone, Difference, Default)]
+        #[difference(de_bin_checked)]
         pub struct TestRecurse {
             recurse1: i32,
             recurse2: Option<String>,
@@ -577,7 +928,7 @@ mod test {
         #[cfg_attr(feature = "nanoserde", derive(DeBin, SerBin))]
         #[cfg_attr(feature = "serde", derive(Deserialize, Serialize))]
         #[derive(Debug, PartialEq, Clone, Difference, Default)]
-        #[difference(setters)]
+        #[difference(setters, de_bin_checked)]
         pub struct TestRecurse {
             recurse1: i32,
             recurse2: Option<String>,
@@ -654,4 +1005,39 @@ mod test {
         assert_eq_unordered!(diffed.test1, second.test1);
         assert_eq_unordered!(diffed.test2, second.test2);
     }
+
+    #[test]
+    fn test_in_place() {
+        use super::{
+            apply_unordered_hashdiffs, apply_unordered_hashdiffs_in_place,
+            apply_unordered_hashdiffs_in_place_ref, apply_unordered_hashdiffs_limited,
+            unordered_hashcmp,
+        };
+        use crate::limits::{ApplyBudget, ApplyLimits};
+
+        let first: BTreeMap<u8, Option<u32>> = (0..20).map(|key| (key, Some(key as u32))).collect();
+        let mut second = first.clone();
+        second.retain(|key, _| key % 5 != 0);
+        second.insert(3, None);
+        second.insert(7, Some(70));
+        second.extend((30..35).map(|key| (key, Some(0))));
+
+        let diff = unordered_hashcmp(first.iter(), second.iter(), false).unwrap();
+        let rebuilt: BTreeMap<_, _> =
+            apply_unordered_hashdiffs(first.clone(), diff.clone().into()).collect();
+        assert_eq!(rebuilt, second);
+
+        let mut applied = first.clone();
+        apply_unordered_hashdiffs_in_place(&mut applied, diff.clone().into());
+        assert_eq!(applied, second);
+
+        let mut applied: HashMap<_, _> = first.clone().into_iter().collect();
+        apply_unordered_hashdiffs_in_place_ref(&mut applied, diff.clone());
+        assert_eq!(applied, second.clone().into_iter().collect());
+
+        let mut applied = first.clone();
+        let mut budget = ApplyBudget::new(ApplyLimits::unlimited());
+        apply_unordered_hashdiffs_limited(&mut applied, diff.into(), &mut budget).unwrap();
+        assert_eq!(applied, second);
+    }
 }
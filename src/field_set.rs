@@ -0,0 +1,99 @@
+/// The set of fields which differ between two instances of a struct, as
+/// returned by [`StructDiffFields::changed_fields`](crate::StructDiffFields::changed_fields).
+///
+/// Fields are identified by their position in
+/// [`StructDiffFields::FIELDS`](crate::StructDiffFields::FIELDS), which lists
+/// the names of every field that is not skipped. The set only allocates once
+/// a field beyond the first 64 is inserted.
+#[derive(Clone, PartialEq, Eq)]
+pub struct FieldSet {
+    names: &'static [&'static str],
+    first: u64,
+    rest: Vec<u64>,
+}
+
+impl FieldSet {
+    /// An empty set over the fields `names`
+    pub fn new(names: &'static [&'static str]) -> Self {
+        Self {
+            names,
+            first: 0,
+            rest: Vec::new(),
+        }
+    }
+
+    /// Add the field at `index`.
+    ///
+    /// # Panics
+    /// If `index` is not the position of a field in the set's names
+    pub fn insert(&mut self, index: usize) {
+        assert!(
+            index < self.names.len(),
+            "field index {index} out of range for {} fields",
+            self.names.len()
+        );
+        match index / 64 {
+            0 => self.first |= 1 << index,
+            word => {
+                if self.rest.len() < word {
+                    self.rest.resize(word, 0);
+                }
+                self.rest[word - 1] |= 1 << (index % 64);
+            }
+        }
+    }
+
+    /// Whether the field at `index` is in the set
+    pub fn contains_index(&self, index: usize) -> bool {
+        match index / 64 {
+            0 => self.first & (1 << index) != 0,
+            word => self
+                .rest
+                .get(word - 1)
+                .is_some_and(|bits| bits & (1 << (index % 64)) != 0),
+        }
+    }
+
+    /// Whether the field called `name` is in the set
+    pub fn contains(&self, name: &str) -> bool {
+        self.names
+            .iter()
+            .position(|field| *field == name)
+            .is_some_and(|index| self.contains_index(index))
+    }
+
+    /// Number of fields in the set
+    pub fn len(&self) -> usize {
+        self.first.count_ones() as usize
+            + self
+                .rest
+                .iter()
+                .map(|bits| bits.count_ones() as usize)
+                .sum::<usize>()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.first == 0 && self.rest.iter().all(|bits| *bits == 0)
+    }
+
+    /// Positions of the fields in the set, in declaration order
+    pub fn indices(&self) -> impl Iterator<Item = usize> + '_ {
+        (0..self.names.len()).filter(|index| self.contains_index(*index))
+    }
+
+    /// Names of the fields in the set, in declaration order
+    pub fn iter(&self) -> impl Iterator<Item = &'static str> + '_ {
+        self.indices().map(|index| self.names[index])
+    }
+
+    /// Names of every field the set can hold
+    pub fn names(&self) -> &'static [&'static str] {
+        self.names
+    }
+}
+
+impl std::fmt::Debug for FieldSet {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_set().entries(self.iter()).finish()
+    }
+}
@@ -0,0 +1,318 @@
+//! A log of the diffs applied to a value, from which any logged state can be
+//! reconstructed.
+
+use crate::StructDiff;
+
+/// One batch of diffs in a [`History`].
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct HistoryEntry<D> {
+    /// Position of the entry in the history, counting up from 1
+    pub seq: u64,
+    /// Caller supplied time the entry was recorded at, e.g. milliseconds
+    /// since the Unix epoch
+    pub timestamp: u64,
+    pub diffs: Vec<D>,
+}
+
+/// An initial snapshot of a value, and every batch of diffs applied to it
+/// since.
+///
+/// The state after each entry is reconstructed by applying the entries up to
+/// it. To bound the cost of that replay, a snapshot is kept every
+/// `checkpoint_interval` entries, and [`History::compact`] folds old entries
+/// into a new base snapshot.
+///
+/// With the `serde` or `nanoserde` feature, a history serializes as its base
+/// snapshot and entries. Checkpoints are rebuilt when it is deserialized.
+///
+/// ```
+/// use structdiff::{history::History, Difference};
+///
+/// #[derive(Debug, PartialEq, Clone, Difference)]
+/// struct Example {
+///     field1: f64,
+///     field2: String,
+/// }
+///
+/// let mut history = History::new(Example { field1: 0.0, field2: String::new() });
+///
+/// let first = history.record_state(100, &Example { field1: 3.14, field2: String::new() });
+/// let second = history.record_state(200, &Example { field1: 3.14, field2: String::from("pi") });
+/// assert_eq!((first, second), (Some(1), Some(2)));
+///
+/// assert_eq!(history.state_at(1).unwrap().field1, 3.14);
+/// assert_eq!(history.state_at_time(150).field2, "");
+///
+/// history.compact(1);
+/// assert_eq!(history.base_seq(), 1);
+/// assert!(history.state_at(0).is_none());
+/// assert_eq!(history.current().field2, "pi");
+/// ```
+#[derive(Clone)]
+pub struct History<T: StructDiff + Clone> {
+    base: T,
+    base_seq: u64,
+    entries: Vec<HistoryEntry<T::Diff>>,
+    /// snapshots of the state after the entry with the given sequence number
+    checkpoints: Vec<(u64, T)>,
+    checkpoint_interval: u64,
+    current: T,
+}
+
+impl<T: StructDiff + Clone> History<T> {
+    pub const DEFAULT_CHECKPOINT_INTERVAL: u64 = 64;
+
+    /// Start a history at `initial`, keeping a checkpoint every
+    /// [`History::DEFAULT_CHECKPOINT_INTERVAL`] entries
+    pub fn new(initial: T) -> Self {
+        Self::with_checkpoint_interval(initial, Self::DEFAULT_CHECKPOINT_INTERVAL)
+    }
+
+    /// Start a history at `initial`, keeping a checkpoint every
+    /// `checkpoint_interval` entries. An interval of 0 disables checkpoints.
+    pub fn with_checkpoint_interval(initial: T, checkpoint_interval: u64) -> Self {
+        Self {
+            base: initial.clone(),
+            base_seq: 0,
+            entries: Vec::new(),
+            checkpoints: Vec::new(),
+            checkpoint_interval,
+            current: initial,
+        }
+    }
+
+    /// Rebuild a history from its base snapshot and entries, or `None` if the
+    /// entries are not numbered consecutively from `base_seq + 1`
+    pub fn from_parts(
+        base: T,
+        base_seq: u64,
+        entries: Vec<HistoryEntry<T::Diff>>,
+        checkpoint_interval: u64,
+    ) -> Option<Self> {
+        let mut ret = Self::with_checkpoint_interval(base, checkpoint_interval);
+        ret.base_seq = base_seq;
+        for entry in entries {
+            if entry.seq != ret.latest_seq().checked_add(1)? {
+                return None;
+            }
+            ret.push(entry);
+        }
+        Some(ret)
+    }
+
+    /// The state after the latest entry
+    pub fn current(&self) -> &T {
+        &self.current
+    }
+
+    /// The oldest state which can be reconstructed
+    pub fn base(&self) -> &T {
+        &self.base
+    }
+
+    /// Sequence number of the base snapshot
+    pub fn base_seq(&self) -> u64 {
+        self.base_seq
+    }
+
+    /// Sequence number of the latest entry, or of the base snapshot if there
+    /// are no entries
+    pub fn latest_seq(&self) -> u64 {
+        self.base_seq + self.entries.len() as u64
+    }
+
+    pub fn entries(&self) -> &[HistoryEntry<T::Diff>] {
+        &self.entries
+    }
+
+    pub fn checkpoint_interval(&self) -> u64 {
+        self.checkpoint_interval
+    }
+
+    /// Apply `diffs` to the current state and log them, returning the
+    /// sequence number of the new entry
+    pub fn record(&mut self, timestamp: u64, diffs: Vec<T::Diff>) -> u64 {
+        let seq = self.latest_seq() + 1;
+        self.push(HistoryEntry {
+            seq,
+            timestamp,
+            diffs,
+        });
+        seq
+    }
+
+    /// Log the diffs from the current state to `state`, returning the
+    /// sequence number of the new entry, or `None` if there are none
+    pub fn record_state(&mut self, timestamp: u64, state: &T) -> Option<u64> {
+        let diffs = self.current.diff(state);
+        match diffs.is_empty() {
+            true => None,
+            false => Some(self.record(timestamp, diffs)),
+        }
+    }
+
+    fn push(&mut self, entry: HistoryEntry<T::Diff>) {
+        self.current.apply_mut(entry.diffs.clone());
+        if self.checkpoint_interval != 0 && entry.seq % self.checkpoint_interval == 0 {
+            self.checkpoints.push((entry.seq, self.current.clone()));
+        }
+        self.entries.push(entry);
+    }
+
+    /// The state after the entry `seq`, or `None` if it has been compacted
+    /// or not yet recorded
+    pub fn state_at(&self, seq: u64) -> Option<T> {
+        if seq < self.base_seq || seq > self.latest_seq() {
+            return None;
+        }
+        if seq == self.latest_seq() {
+            return Some(self.current.clone());
+        }
+
+        let (start_seq, start) = match self
+            .checkpoints
+            .partition_point(|(checkpoint, _)| *checkpoint <= seq)
+        {
+            0 => (self.base_seq, &self.base),
+            index => {
+                let (checkpoint, state) = &self.checkpoints[index - 1];
+                (*checkpoint, state)
+            }
+        };
+
+        let mut state = start.clone();
+        let replay = (start_seq - self.base_seq) as usize..(seq - self.base_seq) as usize;
+        for entry in &self.entries[replay] {
+            state.apply_mut(entry.diffs.clone());
+        }
+        Some(state)
+    }
+
+    /// Sequence number of the last entry recorded at or before `timestamp`,
+    /// or of the base snapshot if there is none. Timestamps are expected to
+    /// never decrease.
+    pub fn seq_at_time(&self, timestamp: u64) -> u64 {
+        self.base_seq
+            + self
+                .entries
+                .partition_point(|entry| entry.timestamp <= timestamp) as u64
+    }
+
+    /// The state as of `timestamp`, see [`History::seq_at_time`]
+    pub fn state_at_time(&self, timestamp: u64) -> T {
+        // always between the base and the latest entry
+        self.state_at(self.seq_at_time(timestamp)).unwrap()
+    }
+
+    /// Make the state after the entry `seq` the new base snapshot, dropping
+    /// the entries and checkpoints up to it. Does nothing if `seq` is before
+    /// the current base, and stops at the latest entry.
+    pub fn compact(&mut self, seq: u64) {
+        let seq = seq.min(self.latest_seq());
+        let Some(base) = self.state_at(seq) else {
+            return;
+        };
+        self.entries.drain(..(seq - self.base_seq) as usize);
+        self.checkpoints.retain(|(checkpoint, _)| *checkpoint > seq);
+        self.base = base;
+        self.base_seq = seq;
+    }
+}
+
+#[cfg(feature = "serde")]
+mod serde_impls {
+    use super::*;
+    use serde::{de::DeserializeOwned, Deserialize, Deserializer, Serialize, Serializer};
+
+    #[derive(Serialize)]
+    #[serde(rename = "History")]
+    struct HistoryRef<'a, T, D> {
+        base: &'a T,
+        base_seq: u64,
+        checkpoint_interval: u64,
+        entries: &'a [HistoryEntry<D>],
+    }
+
+    #[derive(Deserialize)]
+    #[serde(rename = "History")]
+    struct HistoryOwned<T, D> {
+        base: T,
+        base_seq: u64,
+        checkpoint_interval: u64,
+        entries: Vec<HistoryEntry<D>>,
+    }
+
+    impl<T: StructDiff + Clone + Serialize> Serialize for History<T> {
+        fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+            HistoryRef {
+                base: &self.base,
+                base_seq: self.base_seq,
+                checkpoint_interval: self.checkpoint_interval,
+                entries: &self.entries,
+            }
+            .serialize(serializer)
+        }
+    }
+
+    impl<'de, T: StructDiff + Clone + DeserializeOwned> Deserialize<'de> for History<T> {
+        fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+            let parts = HistoryOwned::<T, T::Diff>::deserialize(deserializer)?;
+            History::from_parts(
+                parts.base,
+                parts.base_seq,
+                parts.entries,
+                parts.checkpoint_interval,
+            )
+            .ok_or_else(|| serde::de::Error::custom("history entries are not consecutive"))
+        }
+    }
+}
+
+#[cfg(feature = "nanoserde")]
+mod nanoserde_impls {
+    use super::*;
+    use nanoserde::{DeBin, DeBinErr, SerBin};
+
+    impl<D: SerBin> SerBin for HistoryEntry<D> {
+        fn ser_bin(&self, output: &mut Vec<u8>) {
+            self.seq.ser_bin(output);
+            self.timestamp.ser_bin(output);
+            self.diffs.ser_bin(output);
+        }
+    }
+
+    impl<D: DeBin> DeBin for HistoryEntry<D> {
+        fn de_bin(offset: &mut usize, bytes: &[u8]) -> Result<Self, DeBinErr> {
+            Ok(Self {
+                seq: DeBin::de_bin(offset, bytes)?,
+                timestamp: DeBin::de_bin(offset, bytes)?,
+                diffs: crate::checked::de_bin_vec(offset, bytes)?,
+            })
+        }
+    }
+
+    impl<T: StructDiff + Clone + SerBin> SerBin for History<T> {
+        fn ser_bin(&self, output: &mut Vec<u8>) {
+            self.base.ser_bin(output);
+            self.base_seq.ser_bin(output);
+            self.checkpoint_interval.ser_bin(output);
+            self.entries.ser_bin(output);
+        }
+    }
+
+    impl<T: StructDiff + Clone + DeBin> DeBin for History<T> {
+        fn de_bin(offset: &mut usize, bytes: &[u8]) -> Result<Self, DeBinErr> {
+            let start = *offset;
+            let base = DeBin::de_bin(offset, bytes)?;
+            let base_seq = DeBin::de_bin(offset, bytes)?;
+            let checkpoint_interval = DeBin::de_bin(offset, bytes)?;
+            let entries = crate::checked::de_bin_vec(offset, bytes)?;
+            History::from_parts(base, base_seq, entries, checkpoint_interval).ok_or(DeBinErr {
+                o: start,
+                l: *offset - start,
+                s: bytes.len(),
+            })
+        }
+    }
+}
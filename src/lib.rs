@@ -6,7 +6,63 @@ use serde::{de::DeserializeOwned, Serialize};
 
 pub use structdiff_derive::Difference;
 
+#[cfg(any(feature = "serde", feature = "nanoserde"))]
+pub mod borrowed;
+#[cfg(feature = "nanoserde")]
+pub mod checked;
 pub mod collections;
+pub mod field_set;
+pub mod history;
+pub mod limits;
+#[cfg(any(feature = "serde", feature = "nanoserde"))]
+pub mod sink;
+pub mod size;
+pub mod std_impls;
+#[cfg(any(feature = "serde", feature = "nanoserde"))]
+pub mod sync;
+#[cfg(feature = "testing")]
+pub mod testing;
+pub mod tracked;
+pub mod verify;
+
+pub use field_set::FieldSet;
+pub use std_impls::{ReplaceDiff, ReplaceDiffRef};
+pub use tracked::Tracked;
+
+/// Bound required of every [`StructDiff::Diff`] type.
+///
+/// Implemented for every `Clone` type which also implements
+/// `Serialize + DeserializeOwned` with the `serde` feature, `SerBin + DeBin`
+/// with the `nanoserde` feature, and `Debug` with the `debug_diffs` feature.
+/// Bound generic diff types by this trait instead of the individual traits,
+/// so that manual implementations compile under every feature combination.
+pub use __private::StructDiffOwnedBound;
+
+/// Bound required of every [`StructDiff::DiffRef`] type.
+///
+/// The same as [`StructDiffOwnedBound`], except that only serialization
+/// (`Serialize`/`SerBin`) is required.
+pub use __private::StructDiffRefBound;
+
+/// Bound required of values carried whole by the diffs in this crate, such as
+/// the items inserted into a collection or the replacement for an `Option`.
+///
+/// The same as [`StructDiffOwnedBound`], with
+/// [`DeBinChecked`](crate::checked::DeBinChecked) also required under the
+/// `nanoserde` feature.
+pub use value_bound::DiffValueBound;
+
+#[cfg(feature = "nanoserde")]
+mod value_bound {
+    pub trait DiffValueBound: super::StructDiffOwnedBound + crate::checked::DeBinChecked {}
+    impl<T: super::StructDiffOwnedBound + crate::checked::DeBinChecked> DiffValueBound for T {}
+}
+
+#[cfg(not(feature = "nanoserde"))]
+mod value_bound {
+    pub trait DiffValueBound: super::StructDiffOwnedBound {}
+    impl<T: super::StructDiffOwnedBound> DiffValueBound for T {}
+}
 
 #[cfg(all(feature = "nanoserde", feature = "serde", feature = "debug_diffs"))]
 pub(crate) mod __private {
@@ -115,6 +171,81 @@ pub(crate) mod __private {
     impl<T: Clone> StructDiffRefBound for T {}
 }
 
+/// Receives single-field diffs one at a time from [`StructDiff::diff_into`].
+///
+/// Implemented for `Vec` and for closures taking the diff, so diffs can be
+/// forwarded to a channel as they are generated. With the `serde` or
+/// `nanoserde` feature, the `sink` module writes them straight to a
+/// serializer.
+pub trait DiffSink<D> {
+    fn push(&mut self, diff: D);
+}
+
+impl<D> DiffSink<D> for Vec<D> {
+    #[inline]
+    fn push(&mut self, diff: D) {
+        Vec::push(self, diff)
+    }
+}
+
+impl<D, F: FnMut(D)> DiffSink<D> for F {
+    #[inline]
+    fn push(&mut self, diff: D) {
+        self(diff)
+    }
+}
+
+/// Generate and apply diffs between two instances of a type.
+///
+/// Usually implemented with `#[derive(Difference)]`. Implementations are
+/// also provided for primitives and standard library types (see
+/// [`std_impls`]), and [`impl_replace_diff!`] implements it for any other
+/// type by replacing the whole value when it changes.
+///
+/// # Manual implementations
+///
+/// Manual implementations must uphold the following, which the derive and
+/// the provided implementations rely on when recursing:
+/// - `a.apply(a.diff(&b)) == b`, and the output of `diff_ref` converted
+///   with `Into` applies identically to the output of `diff`
+/// - `diff` and `diff_ref` return an empty `Vec` when nothing has changed
+/// - a `DiffRef` serializes to the same bytes as the `Diff` it converts
+///   into, so the receiver can always deserialize a `Diff`
+/// - `apply_single` does not panic when given a diff generated against a
+///   different base value
+///
+/// Generic diff types should be bounded by [`StructDiffOwnedBound`] and
+/// [`StructDiffRefBound`], rather than by the serialization traits directly.
+///
+/// ```
+/// use structdiff::{ReplaceDiff, StructDiff};
+///
+/// #[derive(Debug, Clone, Copy, PartialEq)]
+/// struct Celsius(f64);
+///
+/// impl StructDiff for Celsius {
+///     type Diff = ReplaceDiff<f64>;
+///     type DiffRef<'target> = ReplaceDiff<f64>;
+///
+///     fn diff(&self, updated: &Self) -> Vec<Self::Diff> {
+///         self.diff_ref(updated)
+///     }
+///
+///     fn diff_ref<'target>(&'target self, updated: &'target Self) -> Vec<Self::DiffRef<'target>> {
+///         match self != updated {
+///             true => vec![ReplaceDiff::new(updated.0)],
+///             false => Vec::new(),
+///         }
+///     }
+///
+///     fn apply_single(&mut self, diff: Self::Diff) {
+///         self.0 = diff.into_inner();
+///     }
+/// }
+///
+/// let diffs = Celsius(20.0).diff(&Celsius(21.5));
+/// assert_eq!(Celsius(20.0).apply(diffs), Celsius(21.5));
+/// ```
 pub trait StructDiff {
     /// A generated type used to represent the difference
     /// between two instances of a struct which implements
@@ -182,6 +313,75 @@ pub trait StructDiff {
     /// ```
     fn diff_ref<'target>(&'target self, updated: &'target Self) -> Vec<Self::DiffRef<'target>>;
 
+    /// Generate a diff between two instances of a struct, pushing each
+    /// changed field to `sink` as it is found instead of collecting them.
+    ///
+    /// Collection fields push their changes as they are generated, in diffs of
+    /// at most [`collections::CHANGES_PER_DIFF`] changes, so one field may
+    /// produce several diffs which apply in order. `diff` and `diff_ref` still
+    /// produce a single diff per changed field.
+    ///
+    /// ```
+    /// use structdiff::{Difference, StructDiff};
+    ///
+    /// #[derive(Debug, PartialEq, Clone, Difference)]
+    /// struct Example {
+    ///     field1: f64,
+    ///     field2: String,
+    /// }
+    ///
+    /// let first = Example {
+    ///     field1: 0.0,
+    ///     field2: String::from("first"),
+    /// };
+    ///
+    /// let second = Example {
+    ///     field1: 3.14,
+    ///     field2: String::from("second"),
+    /// };
+    ///
+    /// let mut diffed = first.clone();
+    /// first.diff_into(&second, &mut |diff: <Example as StructDiff>::DiffRef<'_>| {
+    ///     diffed.apply_single(diff.into())
+    /// });
+    /// assert_eq!(diffed, second);
+    /// ```
+    fn diff_into<'target>(
+        &'target self,
+        updated: &'target Self,
+        sink: &mut impl DiffSink<Self::DiffRef<'target>>,
+    ) {
+        for diff in self.diff_ref(updated) {
+            sink.push(diff);
+        }
+    }
+
+    /// Whether any field differs between two instances of a struct, without
+    /// generating a diff.
+    ///
+    /// The derived implementation stops at the first changed field. Skipped
+    /// fields are ignored, so this can serve as an equality which only
+    /// considers the fields that are diffed.
+    ///
+    /// ```
+    /// use structdiff::{Difference, StructDiff};
+    ///
+    /// #[derive(Debug, PartialEq, Clone, Difference)]
+    /// struct Example {
+    ///     field1: f64,
+    ///     #[difference(skip)]
+    ///     cached: u64,
+    /// }
+    ///
+    /// let first = Example { field1: 0.0, cached: 1 };
+    ///
+    /// assert!(first.has_diff(&Example { field1: 3.14, cached: 1 }));
+    /// assert!(!first.has_diff(&Example { field1: 0.0, cached: 2 }));
+    /// ```
+    fn has_diff(&self, updated: &Self) -> bool {
+        !self.diff_ref(updated).is_empty()
+    }
+
     /// Apply a single-field diff to a mutable self ref
     fn apply_single(&mut self, diff: Self::Diff);
 
@@ -211,4 +411,407 @@ pub trait StructDiff {
             self.apply_single(diff);
         }
     }
+
+    /// Apply a single-field diff generated by [`StructDiff::diff_ref`] to a
+    /// mutable self ref.
+    ///
+    /// The derived implementation clones each referenced value directly into
+    /// place, so no owned diff is built along the way.
+    fn apply_single_ref<'target>(&mut self, diff: Self::DiffRef<'target>)
+    where
+        Self: 'target,
+    {
+        self.apply_single(diff.into())
+    }
+
+    /// Apply a full diff generated by [`StructDiff::diff_ref`] to a mutable
+    /// self ref, without converting it into an owned diff first.
+    ///
+    /// ```
+    /// use structdiff::{Difference, StructDiff};
+    ///
+    /// #[derive(Debug, PartialEq, Clone, Difference)]
+    /// struct Example {
+    ///     field1: String,
+    ///     #[difference(collection_strategy = "ordered_array_like")]
+    ///     field2: Vec<u32>,
+    /// }
+    ///
+    /// let first = Example {
+    ///     field1: "first".into(),
+    ///     field2: vec![1, 2, 3],
+    /// };
+    ///
+    /// let second = Example {
+    ///     field1: "second".into(),
+    ///     field2: vec![1, 3, 4],
+    /// };
+    ///
+    /// let mut diffed = first.clone();
+    /// diffed.apply_ref_diffs(first.diff_ref(&second));
+    /// assert_eq!(diffed, second);
+    /// ```
+    fn apply_ref_diffs<'target>(&mut self, diffs: Vec<Self::DiffRef<'target>>)
+    where
+        Self: Clone + 'target,
+    {
+        for diff in diffs {
+            self.apply_single_ref(diff);
+        }
+    }
+
+    /// Apply a single-field diff to a mutable self ref, charging the work it
+    /// does to `budget` first.
+    ///
+    /// The derived implementation and those for `std` collections charge
+    /// the elements each collection diff inserts, and apply the diffs of
+    /// recursed fields one level deeper. The default implementation charges
+    /// nothing.
+    fn apply_single_limited(
+        &mut self,
+        diff: Self::Diff,
+        budget: &mut limits::ApplyBudget,
+    ) -> Result<(), limits::LimitExceeded> {
+        let _ = budget;
+        self.apply_single(diff);
+        Ok(())
+    }
+
+    /// Apply a full diff to an owned instance, failing instead of doing
+    /// more work than `limits` allow.
+    ///
+    /// Diffs are applied in order until one would exceed a limit, so an
+    /// instance is only returned when all of them were applied. See
+    /// [`limits`] for an example.
+    fn apply_limited(
+        mut self,
+        diffs: Vec<Self::Diff>,
+        limits: limits::ApplyLimits,
+    ) -> Result<Self, limits::LimitExceeded>
+    where
+        Self: Sized,
+    {
+        self.apply_mut_limited(diffs, limits)?;
+        Ok(self)
+    }
+
+    /// Apply a full diff to a mutable self ref, failing instead of doing
+    /// more work than `limits` allow.
+    ///
+    /// On error, `self` may be left with only some of the diffs applied,
+    /// and should be discarded.
+    fn apply_mut_limited(
+        &mut self,
+        diffs: Vec<Self::Diff>,
+        limits: limits::ApplyLimits,
+    ) -> Result<(), limits::LimitExceeded> {
+        let mut budget = limits::ApplyBudget::new(limits);
+        diffs
+            .into_iter()
+            .try_for_each(|diff| self.apply_single_limited(diff, &mut budget))
+    }
+}
+
+/// Find which fields differ between two instances of a struct, without
+/// generating a diff.
+///
+/// Implemented by `#[derive(Difference)]` on structs. Each field is compared
+/// the same way it is diffed: with `eq_with` or `float_epsilon` if given, as
+/// a collection for fields with a `collection_strategy`, and with
+/// `PartialEq` for recursed fields.
+///
+/// ```
+/// use structdiff::{Difference, StructDiffFields};
+///
+/// #[derive(Debug, PartialEq, Clone, Difference)]
+/// struct Example {
+///     field1: f64,
+///     #[difference(collection_strategy = "unordered_array_like")]
+///     field2: Vec<u32>,
+///     field3: String,
+/// }
+///
+/// let first = Example {
+///     field1: 0.0,
+///     field2: vec![1, 2, 3],
+///     field3: String::from("first"),
+/// };
+///
+/// let second = Example {
+///     field1: 3.14,
+///     field2: vec![3, 2, 1],
+///     field3: String::from("first"),
+/// };
+///
+/// let changed = first.changed_fields(&second);
+/// assert_eq!(changed.len(), 1);
+/// assert!(changed.contains("field1"));
+/// assert_eq!(Example::FIELDS, ["field1", "field2", "field3"]);
+/// ```
+pub trait StructDiffFields: StructDiff {
+    /// Names of the fields which are diffed, in declaration order
+    const FIELDS: &'static [&'static str];
+
+    /// The set of fields which differ between `self` and `updated`
+    fn changed_fields(&self, updated: &Self) -> FieldSet;
+}
+
+/// Check that diffs are applied to the state they were generated against.
+///
+/// Implemented by `#[derive(Difference)]` on structs marked
+/// `#[difference(verify_base)]`, which requires every field that is not
+/// skipped to implement `Hash`. Diffs carry a [`StableHasher`](verify::StableHasher)
+/// fingerprint of the fields they modify, so diffs to other fields do not
+/// conflict with them.
+///
+/// ```
+/// use structdiff::{verify::BaseMismatch, Difference, StructDiffVerified};
+///
+/// #[derive(Debug, PartialEq, Clone, Difference)]
+/// #[difference(verify_base)]
+/// struct Example {
+///     field1: u32,
+///     #[difference(collection_strategy = "ordered_array_like")]
+///     field2: Vec<u32>,
+/// }
+///
+/// let first = Example { field1: 0, field2: vec![1, 2, 3] };
+/// let second = Example { field1: 0, field2: vec![1, 3] };
+/// let diff = first.diff_verified(&second);
+///
+/// // a change to another field does not invalidate the diff
+/// let other = Example { field1: 1, ..first.clone() };
+/// assert_eq!(other.try_apply(diff.clone()).unwrap().field2, [1, 3]);
+///
+/// let stale = Example { field1: 0, field2: vec![2, 3] };
+/// assert!(matches!(stale.try_apply(diff), Err(BaseMismatch { .. })));
+/// ```
+pub trait StructDiffVerified: StructDiff {
+    /// Fingerprint of the fields of `self` which `diffs` modify
+    fn base_fingerprint(&self, diffs: &[Self::Diff]) -> u64;
+
+    /// Generate the diffs to `updated`, with the fingerprint of `self`
+    fn diff_verified(&self, updated: &Self) -> verify::VerifiedDiff<Self::Diff> {
+        let diffs = self.diff(updated);
+        verify::VerifiedDiff {
+            base: self.base_fingerprint(&diffs),
+            diffs,
+        }
+    }
+
+    /// Apply `diff` if `self` matches its fingerprint
+    fn try_apply(
+        mut self,
+        diff: verify::VerifiedDiff<Self::Diff>,
+    ) -> Result<Self, verify::BaseMismatch>
+    where
+        Self: Sized,
+    {
+        self.try_apply_mut(diff)?;
+        Ok(self)
+    }
+
+    /// Apply `diff` if `self` matches its fingerprint, leaving `self`
+    /// unchanged otherwise
+    fn try_apply_mut(
+        &mut self,
+        diff: verify::VerifiedDiff<Self::Diff>,
+    ) -> Result<(), verify::BaseMismatch> {
+        let found = self.base_fingerprint(&diff.diffs);
+        if found != diff.base {
+            return Err(verify::BaseMismatch {
+                expected: diff.base,
+                found,
+            });
+        }
+        self.apply_mut(diff.diffs);
+        Ok(())
+    }
+}
+
+/// Apply diffs deserialized without copying their string and byte payloads
+/// out of the input buffer.
+///
+/// Implemented by `#[derive(Difference)]` on structs when the `serde` or
+/// `nanoserde` feature is enabled. [`StructDiffBorrowed::DiffBorrowed`] reads
+/// the same encoding as [`StructDiff::Diff`], so senders are unchanged. See
+/// the [`borrowed`] module for which fields are borrowed.
+///
+/// ```
+/// # #[cfg(feature = "serde")] {
+/// use structdiff::{Difference, StructDiff, StructDiffBorrowed};
+///
+/// #[derive(Debug, PartialEq, Clone, Difference)]
+/// struct Example {
+///     field1: String,
+///     field2: Vec<u8>,
+/// }
+///
+/// let first = Example {
+///     field1: "first".into(),
+///     field2: vec![1, 2, 3],
+/// };
+///
+/// let second = Example {
+///     field1: "second".into(),
+///     field2: vec![4, 5, 6],
+/// };
+///
+/// let buffer = bincode::serialize(&first.diff_ref(&second)).unwrap();
+/// let diffs: Vec<<Example as StructDiffBorrowed>::DiffBorrowed<'_>> =
+///     bincode::deserialize(&buffer).unwrap();
+///
+/// let mut diffed = first.clone();
+/// diffed.apply_borrowed(diffs);
+/// assert_eq!(diffed, second);
+/// # }
+/// ```
+#[cfg(any(feature = "serde", feature = "nanoserde"))]
+pub trait StructDiffBorrowed: StructDiff {
+    /// A generated type with the same encoding as [`StructDiff::Diff`], which
+    /// borrows from the buffer it is deserialized from.
+    type DiffBorrowed<'de>: borrowed::DiffBorrowedBound<'de> + Into<Self::Diff>;
+
+    /// Apply a single-field borrowed diff to a mutable self ref
+    fn apply_single_borrowed(&mut self, diff: Self::DiffBorrowed<'_>);
+
+    /// Apply a full borrowed diff to a mutable self ref
+    fn apply_borrowed(&mut self, diffs: Vec<Self::DiffBorrowed<'_>>) {
+        for diff in diffs {
+            self.apply_single_borrowed(diff);
+        }
+    }
+}
+
+/// Generate and apply diffs for a type from another crate, through a
+/// mirror definition of it.
+///
+/// The orphan rule prevents implementing [`StructDiff`] for a type from
+/// another crate, so instead `#[derive(Difference)]` on a mirror struct
+/// annotated with `#[difference(remote = "other_crate::Type")]` implements
+/// this trait for the mirror. Fields of the remote type are diffed through
+/// the mirror with `#[difference(remote = "Mirror")]`. Private fields are
+/// read with `#[difference(getter = "path::to::fn")]`, taking `&Remote` and
+/// returning a reference to the field, and written with a matching
+/// `getter_mut` function.
+///
+/// ```
+/// use structdiff::{Difference, StructDiff, StructDiffRemote};
+///
+/// mod other_crate {
+///     #[derive(Debug, Clone, PartialEq)]
+///     pub struct Config {
+///         pub name: String,
+///         retries: u32,
+///     }
+///
+///     impl Config {
+///         pub fn new(name: String, retries: u32) -> Self {
+///             Self { name, retries }
+///         }
+///
+///         pub fn retries(&self) -> &u32 {
+///             &self.retries
+///         }
+///
+///         pub fn retries_mut(&mut self) -> &mut u32 {
+///             &mut self.retries
+///         }
+///     }
+/// }
+///
+/// #[derive(Difference)]
+/// #[difference(remote = "other_crate::Config")]
+/// struct ConfigDef {
+///     name: String,
+///     #[difference(getter = "other_crate::Config::retries")]
+///     #[difference(getter_mut = "other_crate::Config::retries_mut")]
+///     retries: u32,
+/// }
+///
+/// #[derive(Debug, Clone, PartialEq, Difference)]
+/// struct Service {
+///     #[difference(remote = "ConfigDef")]
+///     config: other_crate::Config,
+/// }
+///
+/// let first = other_crate::Config::new(String::from("a"), 1);
+/// let second = other_crate::Config::new(String::from("a"), 3);
+///
+/// let mut updated = first.clone();
+/// ConfigDef::apply_mut(&mut updated, ConfigDef::diff(&first, &second));
+/// assert_eq!(updated, second);
+///
+/// let service = Service { config: first };
+/// let diffs = service.diff(&Service { config: second.clone() });
+/// assert_eq!(service.apply(diffs).config, second);
+/// ```
+pub trait StructDiffRemote<Remote> {
+    /// A generated type used to represent the difference
+    /// between two instances of the remote type.
+    type Diff: __private::StructDiffOwnedBound;
+
+    /// A generated type used to represent the difference
+    /// between two instances of the remote type (using references).
+    type DiffRef<'target>: __private::StructDiffRefBound + Into<Self::Diff>
+    where
+        Remote: 'target;
+
+    /// Generate a diff between two instances of the remote type
+    fn diff(remote: &Remote, updated: &Remote) -> Vec<Self::Diff>;
+
+    /// Generate a diff between two instances of the remote type,
+    /// borrowing from the updated instance
+    fn diff_ref<'target>(
+        remote: &'target Remote,
+        updated: &'target Remote,
+    ) -> Vec<Self::DiffRef<'target>>;
+
+    /// Generate a diff between two instances of the remote type, pushing
+    /// each changed field to `sink` as it is found
+    fn diff_into<'target>(
+        remote: &'target Remote,
+        updated: &'target Remote,
+        sink: &mut impl DiffSink<Self::DiffRef<'target>>,
+    ) {
+        for diff in Self::diff_ref(remote, updated) {
+            sink.push(diff);
+        }
+    }
+
+    /// Whether any field differs between two instances of the remote type,
+    /// without generating a diff
+    fn has_diff(remote: &Remote, updated: &Remote) -> bool {
+        !Self::diff_ref(remote, updated).is_empty()
+    }
+
+    /// Apply a single-field diff to the remote type
+    fn apply_single(remote: &mut Remote, diff: Self::Diff);
+
+    /// Apply a full diff to the remote type
+    fn apply_mut(remote: &mut Remote, diffs: Vec<Self::Diff>) {
+        for diff in diffs {
+            Self::apply_single(remote, diff);
+        }
+    }
+
+    /// Apply a single-field diff generated by [`StructDiffRemote::diff_ref`]
+    /// to the remote type
+    fn apply_single_ref<'target>(remote: &mut Remote, diff: Self::DiffRef<'target>)
+    where
+        Remote: 'target,
+    {
+        Self::apply_single(remote, diff.into())
+    }
+
+    /// Apply a full diff generated by [`StructDiffRemote::diff_ref`] to the
+    /// remote type, without converting it into an owned diff first
+    fn apply_ref_diffs<'target>(remote: &mut Remote, diffs: Vec<Self::DiffRef<'target>>)
+    where
+        Remote: Clone + 'target,
+    {
+        for diff in diffs {
+            Self::apply_single_ref(remote, diff);
+        }
+    }
 }
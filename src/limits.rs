@@ -0,0 +1,222 @@
+//! Limits on the work done applying diffs from untrusted peers.
+//!
+//! Even a well-formed diff can be abusive: an `unordered_array_like` change
+//! may insert an item `usize::MAX` times, a `binary_delta` may copy the
+//! whole target over and over, and nested diffs recurse as deeply as the
+//! types allow. [`StructDiff::apply_limited`] charges the work of each diff
+//! to an [`ApplyBudget`] before doing it, and stops with a [`LimitExceeded`]
+//! error once that would exceed its [`ApplyLimits`].
+//!
+//! ```
+//! use structdiff::{limits::{ApplyLimits, Limit}, Difference, StructDiff};
+//!
+//! #[derive(Debug, PartialEq, Clone, Difference)]
+//! struct Example {
+//!     #[difference(collection_strategy = "unordered_array_like")]
+//!     values: Vec<u32>,
+//! }
+//!
+//! let first = Example { values: vec![] };
+//! let second = Example { values: vec![7; 1000] };
+//! let diffs = first.diff(&second);
+//!
+//! let limits = ApplyLimits::new().max_repeat(100);
+//! let err = first.clone().apply_limited(diffs.clone(), limits).unwrap_err();
+//! assert_eq!(err.limit, Limit::Repeat);
+//!
+//! let limits = ApplyLimits::new().max_repeat(1000);
+//! assert_eq!(first.apply_limited(diffs, limits).unwrap(), second);
+//! ```
+
+use crate::StructDiff;
+
+/// The limits enforced by [`StructDiff::apply_limited`].
+///
+/// Elements are counted in items for most collections, and in bytes for
+/// `text` and `binary_delta` fields. Fields replaced whole are not counted,
+/// since their size is bounded by the diff itself.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ApplyLimits {
+    max_growth: usize,
+    max_repeat: usize,
+    max_depth: usize,
+    max_inserted: usize,
+}
+
+impl Default for ApplyLimits {
+    fn default() -> Self {
+        Self {
+            max_growth: 1 << 20,
+            max_repeat: 1 << 16,
+            max_depth: 64,
+            max_inserted: 1 << 24,
+        }
+    }
+}
+
+impl ApplyLimits {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// No limits, so that applying behaves as [`StructDiff::apply`] does
+    pub fn unlimited() -> Self {
+        Self {
+            max_growth: usize::MAX,
+            max_repeat: usize::MAX,
+            max_depth: usize::MAX,
+            max_inserted: usize::MAX,
+        }
+    }
+
+    /// The most elements a single diff may insert into one collection,
+    /// 2^20 by default
+    pub fn max_growth(mut self, max_growth: usize) -> Self {
+        self.max_growth = max_growth;
+        self
+    }
+
+    /// The most copies of one item a single change to a multiset may
+    /// insert, 2^16 by default
+    pub fn max_repeat(mut self, max_repeat: usize) -> Self {
+        self.max_repeat = max_repeat;
+        self
+    }
+
+    /// How deeply the diffs of recursed fields may be nested, 64 by default
+    pub fn max_depth(mut self, max_depth: usize) -> Self {
+        self.max_depth = max_depth;
+        self
+    }
+
+    /// The most elements all of the diffs together may insert, 2^24 by
+    /// default
+    pub fn max_inserted(mut self, max_inserted: usize) -> Self {
+        self.max_inserted = max_inserted;
+        self
+    }
+}
+
+/// One of the [`ApplyLimits`]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Limit {
+    Growth,
+    Repeat,
+    Depth,
+    Inserted,
+}
+
+impl core::fmt::Display for Limit {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.write_str(match self {
+            Limit::Growth => "collection growth",
+            Limit::Repeat => "repeat count",
+            Limit::Depth => "recursion depth",
+            Limit::Inserted => "total inserted elements",
+        })
+    }
+}
+
+/// Returned when applying a diff would exceed one of the [`ApplyLimits`]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct LimitExceeded {
+    /// The limit which would be exceeded
+    pub limit: Limit,
+    /// Its configured maximum
+    pub max: usize,
+    /// The amount the diff required
+    pub requested: usize,
+}
+
+impl core::fmt::Display for LimitExceeded {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write!(
+            f,
+            "applying the diff requires a {} of {}, but the limit is {}",
+            self.limit, self.requested, self.max
+        )
+    }
+}
+
+impl std::error::Error for LimitExceeded {}
+
+/// Tracks the work done so far by one call to [`StructDiff::apply_limited`],
+/// and is passed to [`StructDiff::apply_single_limited`] to be charged
+/// before any more is done.
+#[derive(Clone, Debug)]
+pub struct ApplyBudget {
+    limits: ApplyLimits,
+    depth: usize,
+    inserted: usize,
+}
+
+impl ApplyBudget {
+    pub fn new(limits: ApplyLimits) -> Self {
+        Self {
+            limits,
+            depth: 0,
+            inserted: 0,
+        }
+    }
+
+    pub fn limits(&self) -> &ApplyLimits {
+        &self.limits
+    }
+
+    /// The number of elements charged so far
+    pub fn inserted(&self) -> usize {
+        self.inserted
+    }
+
+    /// Charge a diff which inserts `count` elements into one collection
+    pub fn grow(&mut self, count: usize) -> Result<(), LimitExceeded> {
+        check(Limit::Growth, self.limits.max_growth, count)?;
+        let inserted = self.inserted.saturating_add(count);
+        check(Limit::Inserted, self.limits.max_inserted, inserted)?;
+        self.inserted = inserted;
+        Ok(())
+    }
+
+    /// Check a change which inserts `count` copies of one item. The copies
+    /// must still be charged with [`ApplyBudget::grow`].
+    pub fn repeat(&self, count: usize) -> Result<(), LimitExceeded> {
+        check(Limit::Repeat, self.limits.max_repeat, count)
+    }
+
+    /// Run `apply` one level of recursion deeper
+    pub fn nested<R>(
+        &mut self,
+        apply: impl FnOnce(&mut Self) -> Result<R, LimitExceeded>,
+    ) -> Result<R, LimitExceeded> {
+        let depth = self.depth.saturating_add(1);
+        check(Limit::Depth, self.limits.max_depth, depth)?;
+        self.depth = depth;
+        let ret = apply(self);
+        self.depth -= 1;
+        ret
+    }
+
+    /// Apply the diffs of a recursed value, one level of recursion deeper
+    pub fn apply_nested<T: StructDiff>(
+        &mut self,
+        target: &mut T,
+        diffs: Vec<T::Diff>,
+    ) -> Result<(), LimitExceeded> {
+        self.nested(|budget| {
+            diffs
+                .into_iter()
+                .try_for_each(|diff| target.apply_single_limited(diff, budget))
+        })
+    }
+}
+
+fn check(limit: Limit, max: usize, requested: usize) -> Result<(), LimitExceeded> {
+    match requested > max {
+        true => Err(LimitExceeded {
+            limit,
+            max,
+            requested,
+        }),
+        false => Ok(()),
+    }
+}
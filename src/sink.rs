@@ -0,0 +1,129 @@
+//! [`DiffSink`] implementations which serialize each diff as it is generated,
+//! so that a `Vec` of diffs is never built.
+
+use crate::DiffSink;
+
+/// Writes diffs to a buffer in the encoding of `SerBin` on a `Vec` of diffs.
+///
+/// The length prefix is kept up to date after every push, so `output` can be
+/// read back with `DeBin` at any point.
+///
+/// ```
+/// use nanoserde::{DeBin, SerBin};
+/// use structdiff::{sink::SerBinSink, Difference, StructDiff};
+///
+/// #[derive(Debug, PartialEq, Clone, Difference)]
+/// struct Example {
+///     field1: f64,
+///     field2: String,
+/// }
+///
+/// let first = Example { field1: 0.0, field2: String::from("first") };
+/// let second = Example { field1: 3.14, field2: String::from("second") };
+///
+/// let mut output = Vec::new();
+/// first.diff_into(&second, &mut SerBinSink::new(&mut output));
+/// assert_eq!(output, first.diff_ref(&second).serialize_bin());
+///
+/// let diffs: Vec<<Example as StructDiff>::Diff> = DeBin::deserialize_bin(&output).unwrap();
+/// assert_eq!(first.apply(diffs), second);
+/// ```
+#[cfg(feature = "nanoserde")]
+pub struct SerBinSink<'out> {
+    output: &'out mut Vec<u8>,
+    len_offset: usize,
+    len: usize,
+}
+
+#[cfg(feature = "nanoserde")]
+impl<'out> SerBinSink<'out> {
+    /// Start a list of diffs at the end of `output`
+    pub fn new(output: &'out mut Vec<u8>) -> Self {
+        let len_offset = output.len();
+        nanoserde::SerBin::ser_bin(&0usize, output);
+        Self {
+            output,
+            len_offset,
+            len: 0,
+        }
+    }
+
+    /// Number of diffs written so far
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+}
+
+#[cfg(feature = "nanoserde")]
+impl<D: nanoserde::SerBin> DiffSink<D> for SerBinSink<'_> {
+    fn push(&mut self, diff: D) {
+        diff.ser_bin(self.output);
+        self.len += 1;
+        // matches the encoding of `usize`
+        self.output[self.len_offset..self.len_offset + 8]
+            .copy_from_slice(&(self.len as u64).to_le_bytes());
+    }
+}
+
+/// Writes diffs as the elements of a serde sequence.
+///
+/// The sequence is started with an unknown length, which not every format
+/// supports (`bincode` requires the length up front, while `serde_json`
+/// does not). Serialization stops at the first error, which is returned by
+/// [`SerializeSeqSink::finish`].
+///
+/// ```
+/// use serde::Serializer;
+/// use structdiff::{sink::SerializeSeqSink, Difference, StructDiff};
+///
+/// #[derive(Debug, PartialEq, Clone, Difference)]
+/// struct Example {
+///     field1: f64,
+///     field2: String,
+/// }
+///
+/// let first = Example { field1: 0.0, field2: String::from("first") };
+/// let second = Example { field1: 3.14, field2: String::from("second") };
+///
+/// let mut output = Vec::new();
+/// let mut serializer = serde_json::Serializer::new(&mut output);
+/// let mut sink = SerializeSeqSink::new(serializer.serialize_seq(None).unwrap());
+/// first.diff_into(&second, &mut sink);
+/// sink.finish().unwrap();
+///
+/// let diffs: Vec<<Example as StructDiff>::Diff> = serde_json::from_slice(&output).unwrap();
+/// assert_eq!(first.apply(diffs), second);
+/// ```
+#[cfg(feature = "serde")]
+pub struct SerializeSeqSink<S: serde::ser::SerializeSeq> {
+    seq: S,
+    error: Option<S::Error>,
+}
+
+#[cfg(feature = "serde")]
+impl<S: serde::ser::SerializeSeq> SerializeSeqSink<S> {
+    pub fn new(seq: S) -> Self {
+        Self { seq, error: None }
+    }
+
+    /// End the sequence, or return the first error raised while writing it
+    pub fn finish(self) -> Result<S::Ok, S::Error> {
+        match self.error {
+            Some(error) => Err(error),
+            None => self.seq.end(),
+        }
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<S: serde::ser::SerializeSeq, D: serde::Serialize> DiffSink<D> for SerializeSeqSink<S> {
+    fn push(&mut self, diff: D) {
+        if self.error.is_none() {
+            self.error = self.seq.serialize_element(&diff).err();
+        }
+    }
+}
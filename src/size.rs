@@ -0,0 +1,257 @@
+//! Estimates of how large values and diffs are once encoded, used to decide
+//! whether a diff is worth sending instead of the value it produces.
+//!
+//! Sizes follow the `nanoserde` binary encoding: integers take their fixed
+//! width, lengths and `usize`s take 8 bytes, the variants of the diffs of
+//! collections and standard library types are tagged with a `u8` ([`TAG`]),
+//! and the variants of the diff types generated by `#[derive(Difference)]`
+//! with a `u16` ([`DIFF_TAG`]). Other compact formats are close enough for the
+//! estimates to rank a diff against a replacement the same way.
+//!
+//! Fields with `#[difference(collection_strategy = "auto")]` use
+//! [`smaller`] to send whichever of their incremental diff and a full
+//! replacement is estimated to be smaller. Their items must implement
+//! [`EncodedSize`], which user types do by summing the sizes of their fields.
+//!
+//! ```
+//! use structdiff::collections::ordered_array_like::replacement;
+//! use structdiff::size::{estimate, EncodedSize, DIFF_TAG, LEN};
+//! use structdiff::{Difference, StructDiff};
+//!
+//! #[derive(Debug, PartialEq, Clone, Difference)]
+//! #[difference(encoded_size)]
+//! struct Example {
+//!     #[difference(collection_strategy = "auto")]
+//!     values: Vec<u32>,
+//! }
+//!
+//! let first = Example { values: (0..100).collect() };
+//!
+//! // a single insertion is sent as such
+//! let mut values = first.values.clone();
+//! values.insert(50, 7);
+//! let second = Example { values };
+//! assert!(first.diff(&second).encoded_size() < second.encoded_size());
+//!
+//! // and a list changed in every other item is sent whole, as that is
+//! // smaller than replacing each item
+//! let third = Example {
+//!     values: (0..100).map(|v| if v % 2 == 0 { v } else { v + 100 }).collect(),
+//! };
+//! let whole = replacement(&third.values, &first.values);
+//! assert_eq!(
+//!     first.diff(&third).encoded_size(),
+//!     LEN + DIFF_TAG + whole.encoded_size()
+//! );
+//!
+//! // estimate compares any diff with a replacement of the whole value
+//! let estimate = estimate(&first, &second);
+//! assert!(estimate.diff < estimate.replace);
+//! ```
+
+use std::{
+    collections::{BTreeMap, BTreeSet, HashMap, HashSet, LinkedList, VecDeque},
+    rc::Rc,
+    sync::Arc,
+};
+
+use crate::StructDiff;
+
+/// The encoded size of a length prefix
+pub const LEN: usize = core::mem::size_of::<u64>();
+
+/// The encoded size of the variant tag of a collection's or standard library
+/// type's diff
+pub const TAG: usize = core::mem::size_of::<u8>();
+
+/// The encoded size of the variant tag of a diff type generated by
+/// `#[derive(Difference)]`
+pub const DIFF_TAG: usize = core::mem::size_of::<u16>();
+
+/// The estimated number of bytes a value takes once encoded
+pub trait EncodedSize {
+    fn encoded_size(&self) -> usize;
+}
+
+/// The estimated encoded sizes of a diff and of the replacement it could be
+/// sent as instead
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct SizeEstimate {
+    /// The size of the diffs, including the length of the list holding them
+    pub diff: usize,
+    /// The size of the updated value
+    pub replace: usize,
+}
+
+impl SizeEstimate {
+    /// Whether sending the updated value is estimated to be smaller
+    pub fn prefer_replace(&self) -> bool {
+        self.replace < self.diff
+    }
+}
+
+/// Estimate the size of the diff from `previous` to `updated`, and of
+/// `updated` itself
+pub fn estimate<T>(previous: &T, updated: &T) -> SizeEstimate
+where
+    T: StructDiff + EncodedSize,
+    T::Diff: EncodedSize,
+{
+    SizeEstimate {
+        diff: previous.diff(updated).encoded_size(),
+        replace: updated.encoded_size(),
+    }
+}
+
+/// Whichever of `diff` and the diff built by `replacement` is estimated to
+/// be smaller, or `None` when there is no diff at all. `replacement` is only
+/// called when there are changes.
+pub fn smaller<D: EncodedSize>(diff: Option<D>, replacement: impl FnOnce() -> D) -> Option<D> {
+    let diff = diff?;
+    let replacement = replacement();
+    match replacement.encoded_size() < diff.encoded_size() {
+        true => Some(replacement),
+        false => Some(diff),
+    }
+}
+
+macro_rules! fixed_size_impls {
+    ($($ty:ty => $size:expr),* $(,)?) => {$(
+        impl EncodedSize for $ty {
+            #[inline]
+            fn encoded_size(&self) -> usize {
+                $size
+            }
+        }
+    )*};
+}
+
+fixed_size_impls!(
+    () => 0,
+    bool => 1,
+    u8 => 1,
+    i8 => 1,
+    u16 => 2,
+    i16 => 2,
+    u32 => 4,
+    i32 => 4,
+    f32 => 4,
+    char => 4,
+    u64 => 8,
+    i64 => 8,
+    f64 => 8,
+    usize => 8,
+    isize => 8,
+    u128 => 16,
+    i128 => 16,
+);
+
+impl EncodedSize for str {
+    fn encoded_size(&self) -> usize {
+        LEN + self.len()
+    }
+}
+
+impl EncodedSize for String {
+    fn encoded_size(&self) -> usize {
+        self.as_str().encoded_size()
+    }
+}
+
+impl<T: EncodedSize + ?Sized> EncodedSize for &T {
+    fn encoded_size(&self) -> usize {
+        (**self).encoded_size()
+    }
+}
+
+impl<T: EncodedSize + ?Sized> EncodedSize for Box<T> {
+    fn encoded_size(&self) -> usize {
+        (**self).encoded_size()
+    }
+}
+
+impl<T: EncodedSize + ?Sized> EncodedSize for Rc<T> {
+    fn encoded_size(&self) -> usize {
+        (**self).encoded_size()
+    }
+}
+
+impl<T: EncodedSize + ?Sized> EncodedSize for Arc<T> {
+    fn encoded_size(&self) -> usize {
+        (**self).encoded_size()
+    }
+}
+
+impl<T: EncodedSize> EncodedSize for Option<T> {
+    fn encoded_size(&self) -> usize {
+        TAG + self.as_ref().map_or(0, EncodedSize::encoded_size)
+    }
+}
+
+impl<T: EncodedSize, const N: usize> EncodedSize for [T; N] {
+    fn encoded_size(&self) -> usize {
+        self.iter().map(EncodedSize::encoded_size).sum()
+    }
+}
+
+/// The size of a length-prefixed list of `items`
+pub fn list_size<T: EncodedSize>(items: impl IntoIterator<Item = T>) -> usize {
+    items
+        .into_iter()
+        .fold(LEN, |sum, item| sum.saturating_add(item.encoded_size()))
+}
+
+impl<T: EncodedSize> EncodedSize for [T] {
+    fn encoded_size(&self) -> usize {
+        list_size(self)
+    }
+}
+
+macro_rules! list_impls {
+    ($($list:ident),* $(,)?) => {$(
+        impl<T: EncodedSize> EncodedSize for $list<T> {
+            fn encoded_size(&self) -> usize {
+                list_size(self)
+            }
+        }
+    )*};
+}
+
+list_impls!(Vec, VecDeque, LinkedList, BTreeSet);
+
+impl<T: EncodedSize, S> EncodedSize for HashSet<T, S> {
+    fn encoded_size(&self) -> usize {
+        list_size(self)
+    }
+}
+
+impl<K: EncodedSize, V: EncodedSize, S> EncodedSize for HashMap<K, V, S> {
+    fn encoded_size(&self) -> usize {
+        list_size(self)
+    }
+}
+
+impl<K: EncodedSize, V: EncodedSize> EncodedSize for BTreeMap<K, V> {
+    fn encoded_size(&self) -> usize {
+        list_size(self)
+    }
+}
+
+macro_rules! tuple_impls {
+    ($(($($name:ident, $idx:tt),+)),* $(,)?) => {$(
+        impl<$($name: EncodedSize),+> EncodedSize for ($($name,)+) {
+            fn encoded_size(&self) -> usize {
+                0 $(+ self.$idx.encoded_size())+
+            }
+        }
+    )*};
+}
+
+tuple_impls!(
+    (A, 0),
+    (A, 0, B, 1),
+    (A, 0, B, 1, C, 2),
+    (A, 0, B, 1, C, 2, D, 3),
+    (A, 0, B, 1, C, 2, D, 3, E, 4),
+    (A, 0, B, 1, C, 2, D, 3, E, 4, F, 5),
+);
@@ -0,0 +1,1266 @@
+#[cfg(feature = "nanoserde")]
+use nanoserde::{DeBin, SerBin};
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+use std::{
+    collections::{BTreeMap, BTreeSet, HashMap, HashSet, LinkedList, VecDeque},
+    hash::{BuildHasher, Hash},
+    rc::Rc,
+    sync::Arc,
+};
+
+use crate::{
+    collections::{
+        ordered_array_like::{self, OrderedArrayLikeDiffOwned, OrderedArrayLikeDiffRef},
+        rope::Rope,
+        unordered_array_like::{self, UnorderedArrayLikeDiff},
+        unordered_map_like_recursive::{
+            self, UnorderedMapLikeRecursiveDiffOwned, UnorderedMapLikeRecursiveDiffRef,
+        },
+    },
+    limits::{ApplyBudget, LimitExceeded},
+    size::{EncodedSize, TAG},
+    DiffSink, DiffValueBound, StructDiff,
+};
+
+/// Diff which replaces the whole value, used for primitives and `String`,
+/// and by [`impl_replace_diff!`](crate::impl_replace_diff)
+#[repr(transparent)]
+#[derive(Clone)]
+#[cfg_attr(feature = "debug_diffs", derive(Debug))]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct ReplaceDiff<T>(T);
+
+/// Borrowed version of [`ReplaceDiff`], which serializes identically
+#[repr(transparent)]
+#[cfg_attr(feature = "debug_diffs", derive(Debug))]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+pub struct ReplaceDiffRef<'a, T>(&'a T);
+
+impl<T> ReplaceDiff<T> {
+    /// Create a diff which replaces the target with `value`
+    pub fn new(value: T) -> Self {
+        Self(value)
+    }
+
+    /// The value which the target is replaced with
+    pub fn get(&self) -> &T {
+        &self.0
+    }
+
+    pub fn into_inner(self) -> T {
+        self.0
+    }
+}
+
+impl<'a, T> ReplaceDiffRef<'a, T> {
+    /// Create a diff which replaces the target with a clone of `value`
+    pub fn new(value: &'a T) -> Self {
+        Self(value)
+    }
+
+    /// The value which the target is replaced with
+    pub fn get(&self) -> &'a T {
+        self.0
+    }
+}
+
+impl<T> Clone for ReplaceDiffRef<'_, T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<T> Copy for ReplaceDiffRef<'_, T> {}
+
+impl<T: Clone> From<ReplaceDiffRef<'_, T>> for ReplaceDiff<T> {
+    fn from(value: ReplaceDiffRef<'_, T>) -> Self {
+        ReplaceDiff(value.0.clone())
+    }
+}
+
+impl<T: EncodedSize> EncodedSize for ReplaceDiff<T> {
+    fn encoded_size(&self) -> usize {
+        self.0.encoded_size()
+    }
+}
+
+/// Implement [`StructDiff`] for one or more types by replacing the whole
+/// value whenever it changes, using [`ReplaceDiff`] and [`ReplaceDiffRef`].
+///
+/// The types must implement `Clone` and `PartialEq`, as well as the traits
+/// required by [`StructDiffOwnedBound`](crate::StructDiffOwnedBound) for the
+/// enabled features. With the `nanoserde` feature, the macro also implements
+/// [`DeBinChecked`](crate::checked::DeBinChecked) for them with its default
+/// method, which reads them with their own `DeBin`.
+///
+/// ```
+/// #[cfg(feature = "nanoserde")]
+/// use nanoserde::{DeBin, SerBin};
+/// use structdiff::StructDiff;
+///
+/// #[derive(Debug, Clone, PartialEq)]
+/// #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+/// #[cfg_attr(feature = "nanoserde", derive(SerBin, DeBin))]
+/// enum Mode {
+///     Fast,
+///     Slow,
+/// }
+///
+/// structdiff::impl_replace_diff!(Mode);
+///
+/// let diffs = Mode::Fast.diff(&Mode::Slow);
+/// assert_eq!(Mode::Fast.apply(diffs), Mode::Slow);
+/// ```
+#[macro_export]
+macro_rules! impl_replace_diff {
+    (@struct_diff $($ty:ty),+) => {$(
+        impl $crate::StructDiff for $ty {
+            type Diff = $crate::ReplaceDiff<$ty>;
+            type DiffRef<'target> = $crate::ReplaceDiffRef<'target, $ty>;
+
+            fn diff(&self, updated: &Self) -> Vec<Self::Diff> {
+                self.diff_ref(updated).into_iter().map(Into::into).collect()
+            }
+
+            fn diff_ref<'target>(&'target self, updated: &'target Self) -> Vec<Self::DiffRef<'target>> {
+                match self != updated {
+                    true => vec![$crate::ReplaceDiffRef::new(updated)],
+                    false => Vec::new(),
+                }
+            }
+
+            fn has_diff(&self, updated: &Self) -> bool {
+                self != updated
+            }
+
+            fn apply_single(&mut self, diff: Self::Diff) {
+                *self = diff.into_inner();
+            }
+
+            fn apply_single_ref<'target>(&mut self, diff: Self::DiffRef<'target>)
+            where
+                Self: 'target,
+            {
+                self.clone_from(diff.get());
+            }
+        }
+    )+};
+    ($($ty:ty),+ $(,)?) => {
+        $crate::impl_replace_diff!(@struct_diff $($ty),+);
+        $($crate::__impl_de_bin_checked!($ty);)+
+    };
+}
+
+/// Implement [`DeBinChecked`](crate::checked::DeBinChecked) with its default
+/// method for the types given to [`impl_replace_diff!`](crate::impl_replace_diff)
+#[cfg(feature = "nanoserde")]
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __impl_de_bin_checked {
+    ($ty:ty) => {
+        impl $crate::checked::DeBinChecked for $ty {}
+    };
+}
+
+#[cfg(not(feature = "nanoserde"))]
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __impl_de_bin_checked {
+    ($ty:ty) => {};
+}
+
+// `checked` implements `DeBinChecked` for these itself
+crate::impl_replace_diff!(
+    @struct_diff bool, u8, u16, u32, u64, u128, usize, i8, i16, i32, i64, i128, f32, f64, String
+);
+
+// nanoserde has no binary encoding for `char` or `isize`, so their
+// diffs carry the `u32` scalar value and an `i64` instead
+
+impl StructDiff for char {
+    type Diff = ReplaceDiff<u32>;
+    type DiffRef<'target> = ReplaceDiff<u32>;
+
+    fn diff(&self, updated: &Self) -> Vec<Self::Diff> {
+        self.diff_ref(updated)
+    }
+
+    fn diff_ref<'target>(&'target self, updated: &'target Self) -> Vec<Self::DiffRef<'target>> {
+        match self != updated {
+            true => vec![ReplaceDiff(u32::from(*updated))],
+            false => Vec::new(),
+        }
+    }
+
+    fn has_diff(&self, updated: &Self) -> bool {
+        self != updated
+    }
+
+    fn apply_single(&mut self, diff: Self::Diff) {
+        if let Some(updated) = char::from_u32(diff.0) {
+            *self = updated;
+        }
+    }
+}
+
+impl StructDiff for isize {
+    type Diff = ReplaceDiff<i64>;
+    type DiffRef<'target> = ReplaceDiff<i64>;
+
+    fn diff(&self, updated: &Self) -> Vec<Self::Diff> {
+        self.diff_ref(updated)
+    }
+
+    fn diff_ref<'target>(&'target self, updated: &'target Self) -> Vec<Self::DiffRef<'target>> {
+        match self != updated {
+            true => vec![ReplaceDiff(*updated as i64)],
+            false => Vec::new(),
+        }
+    }
+
+    fn has_diff(&self, updated: &Self) -> bool {
+        self != updated
+    }
+
+    fn apply_single(&mut self, diff: Self::Diff) {
+        *self = diff.0 as isize;
+    }
+}
+
+macro_rules! sequence_impls {
+    ($($seq:ident),* $(,)?) => {$(
+        impl<T: Clone + PartialEq + DiffValueBound + 'static> StructDiff for $seq<T> {
+            type Diff = OrderedArrayLikeDiffOwned<T>;
+            type DiffRef<'target> = OrderedArrayLikeDiffRef<'target, T>;
+
+            fn diff(&self, updated: &Self) -> Vec<Self::Diff> {
+                self.diff_ref(updated).into_iter().map(Into::into).collect()
+            }
+
+            fn diff_ref<'target>(&'target self, updated: &'target Self) -> Vec<Self::DiffRef<'target>> {
+                ordered_array_like::hirschberg(updated, self).into_iter().collect()
+            }
+
+            fn has_diff(&self, updated: &Self) -> bool {
+                ordered_array_like::has_changes(updated, self)
+            }
+
+            fn apply_single(&mut self, diff: Self::Diff) {
+                ordered_array_like::apply_in_place(diff, self)
+            }
+
+            fn apply_single_limited(
+                &mut self,
+                diff: Self::Diff,
+                budget: &mut ApplyBudget,
+            ) -> Result<(), LimitExceeded> {
+                ordered_array_like::apply_in_place_limited(diff, self, budget)
+            }
+
+            fn apply_single_ref<'target>(&mut self, diff: Self::DiffRef<'target>)
+            where
+                Self: 'target,
+            {
+                ordered_array_like::apply_ref_in_place(diff, self)
+            }
+        }
+    )*};
+}
+
+sequence_impls!(Vec, VecDeque, LinkedList, Rope);
+
+/// Borrowed diff for set-like collections, which converts into an
+/// [`UnorderedArrayLikeDiff`]
+#[repr(transparent)]
+#[derive(Clone)]
+#[cfg_attr(feature = "debug_diffs", derive(Debug))]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+pub struct SetDiffRef<'a, T>(UnorderedArrayLikeDiff<&'a T>);
+
+impl<'a, T: Clone + 'a> From<SetDiffRef<'a, T>> for UnorderedArrayLikeDiff<T> {
+    fn from(value: SetDiffRef<'a, T>) -> Self {
+        value.0.into()
+    }
+}
+
+impl<T, S> StructDiff for HashSet<T, S>
+where
+    T: Hash + Eq + Clone + DiffValueBound,
+    S: BuildHasher,
+{
+    type Diff = UnorderedArrayLikeDiff<T>;
+    type DiffRef<'target>
+        = SetDiffRef<'target, T>
+    where
+        Self: 'target;
+
+    fn diff(&self, updated: &Self) -> Vec<Self::Diff> {
+        self.diff_ref(updated).into_iter().map(Into::into).collect()
+    }
+
+    fn diff_ref<'target>(&'target self, updated: &'target Self) -> Vec<Self::DiffRef<'target>> {
+        unordered_array_like::unordered_hashcmp(self.iter(), updated.iter())
+            .map(SetDiffRef)
+            .into_iter()
+            .collect()
+    }
+
+    fn has_diff(&self, updated: &Self) -> bool {
+        self != updated
+    }
+
+    fn apply_single(&mut self, diff: Self::Diff) {
+        unordered_array_like::apply_unordered_hashdiffs_in_place(self, diff)
+    }
+
+    fn apply_single_limited(
+        &mut self,
+        diff: Self::Diff,
+        budget: &mut ApplyBudget,
+    ) -> Result<(), LimitExceeded> {
+        unordered_array_like::apply_unordered_hashdiffs_in_place_limited(self, diff, budget)
+    }
+
+    fn apply_single_ref<'target>(&mut self, diff: Self::DiffRef<'target>)
+    where
+        Self: 'target,
+    {
+        unordered_array_like::apply_unordered_hashdiffs_in_place_ref(self, diff.0)
+    }
+}
+
+impl<T> StructDiff for BTreeSet<T>
+where
+    T: Hash + Ord + Clone + DiffValueBound,
+{
+    type Diff = UnorderedArrayLikeDiff<T>;
+    type DiffRef<'target>
+        = SetDiffRef<'target, T>
+    where
+        Self: 'target;
+
+    fn diff(&self, updated: &Self) -> Vec<Self::Diff> {
+        self.diff_ref(updated).into_iter().map(Into::into).collect()
+    }
+
+    fn diff_ref<'target>(&'target self, updated: &'target Self) -> Vec<Self::DiffRef<'target>> {
+        unordered_array_like::unordered_hashcmp(self.iter(), updated.iter())
+            .map(SetDiffRef)
+            .into_iter()
+            .collect()
+    }
+
+    fn has_diff(&self, updated: &Self) -> bool {
+        self != updated
+    }
+
+    fn apply_single(&mut self, diff: Self::Diff) {
+        unordered_array_like::apply_unordered_hashdiffs_in_place(self, diff)
+    }
+
+    fn apply_single_limited(
+        &mut self,
+        diff: Self::Diff,
+        budget: &mut ApplyBudget,
+    ) -> Result<(), LimitExceeded> {
+        unordered_array_like::apply_unordered_hashdiffs_in_place_limited(self, diff, budget)
+    }
+
+    fn apply_single_ref<'target>(&mut self, diff: Self::DiffRef<'target>)
+    where
+        Self: 'target,
+    {
+        unordered_array_like::apply_unordered_hashdiffs_in_place_ref(self, diff.0)
+    }
+}
+
+impl<K, V, S> StructDiff for HashMap<K, V, S>
+where
+    K: Hash + Eq + Clone + DiffValueBound + 'static,
+    V: StructDiff + PartialEq + Clone + DiffValueBound + 'static,
+    S: BuildHasher + Default,
+{
+    type Diff = UnorderedMapLikeRecursiveDiffOwned<K, V>;
+    type DiffRef<'target>
+        = UnorderedMapLikeRecursiveDiffRef<'target, K, V>
+    where
+        Self: 'target;
+
+    fn diff(&self, updated: &Self) -> Vec<Self::Diff> {
+        self.diff_ref(updated).into_iter().map(Into::into).collect()
+    }
+
+    fn diff_ref<'target>(&'target self, updated: &'target Self) -> Vec<Self::DiffRef<'target>> {
+        unordered_map_like_recursive::unordered_hashcmp(self.iter(), updated.iter(), false)
+            .into_iter()
+            .collect()
+    }
+
+    fn has_diff(&self, updated: &Self) -> bool {
+        unordered_map_like_recursive::has_changes(self.iter(), updated.iter(), false)
+    }
+
+    fn apply_single(&mut self, diff: Self::Diff) {
+        unordered_map_like_recursive::apply_unordered_hashdiffs_in_place(self, diff)
+    }
+
+    fn apply_single_limited(
+        &mut self,
+        diff: Self::Diff,
+        budget: &mut ApplyBudget,
+    ) -> Result<(), LimitExceeded> {
+        unordered_map_like_recursive::apply_unordered_hashdiffs_limited(self, diff, budget)
+    }
+
+    fn apply_single_ref<'target>(&mut self, diff: Self::DiffRef<'target>)
+    where
+        Self: 'target,
+    {
+        unordered_map_like_recursive::apply_unordered_hashdiffs_in_place_ref(self, diff)
+    }
+}
+
+impl<K, V> StructDiff for BTreeMap<K, V>
+where
+    K: Hash + Ord + Clone + DiffValueBound + 'static,
+    V: StructDiff + PartialEq + Clone + DiffValueBound + 'static,
+{
+    type Diff = UnorderedMapLikeRecursiveDiffOwned<K, V>;
+    type DiffRef<'target>
+        = UnorderedMapLikeRecursiveDiffRef<'target, K, V>
+    where
+        Self: 'target;
+
+    fn diff(&self, updated: &Self) -> Vec<Self::Diff> {
+        self.diff_ref(updated).into_iter().map(Into::into).collect()
+    }
+
+    fn diff_ref<'target>(&'target self, updated: &'target Self) -> Vec<Self::DiffRef<'target>> {
+        unordered_map_like_recursive::unordered_hashcmp(self.iter(), updated.iter(), false)
+            .into_iter()
+            .collect()
+    }
+
+    fn has_diff(&self, updated: &Self) -> bool {
+        unordered_map_like_recursive::has_changes(self.iter(), updated.iter(), false)
+    }
+
+    fn apply_single(&mut self, diff: Self::Diff) {
+        unordered_map_like_recursive::apply_unordered_hashdiffs_in_place(self, diff)
+    }
+
+    fn apply_single_limited(
+        &mut self,
+        diff: Self::Diff,
+        budget: &mut ApplyBudget,
+    ) -> Result<(), LimitExceeded> {
+        unordered_map_like_recursive::apply_unordered_hashdiffs_limited(self, diff, budget)
+    }
+
+    fn apply_single_ref<'target>(&mut self, diff: Self::DiffRef<'target>)
+    where
+        Self: 'target,
+    {
+        unordered_map_like_recursive::apply_unordered_hashdiffs_in_place_ref(self, diff)
+    }
+}
+
+/// Diff for an [`Option`], which either recurses into the contained value
+/// or replaces the whole option
+#[derive(Clone)]
+#[cfg_attr(feature = "debug_diffs", derive(Debug))]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum OptionDiffOwned<T: StructDiff> {
+    Inner(Vec<T::Diff>),
+    Full(T),
+    None,
+}
+
+/// Borrowed version of [`OptionDiffOwned`]
+#[derive(Clone)]
+#[cfg_attr(feature = "debug_diffs", derive(Debug))]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+pub enum OptionDiffRef<'a, T: StructDiff> {
+    Inner(Vec<T::DiffRef<'a>>),
+    Full(&'a T),
+    None,
+}
+
+impl<'a, T: StructDiff + Clone> From<OptionDiffRef<'a, T>> for OptionDiffOwned<T> {
+    fn from(value: OptionDiffRef<'a, T>) -> Self {
+        match value {
+            OptionDiffRef::Inner(diffs) => {
+                OptionDiffOwned::Inner(diffs.into_iter().map(Into::into).collect())
+            }
+            OptionDiffRef::Full(value) => OptionDiffOwned::Full(value.clone()),
+            OptionDiffRef::None => OptionDiffOwned::None,
+        }
+    }
+}
+
+impl<T: StructDiff + EncodedSize> EncodedSize for OptionDiffOwned<T>
+where
+    T::Diff: EncodedSize,
+{
+    fn encoded_size(&self) -> usize {
+        TAG + match self {
+            OptionDiffOwned::Inner(diffs) => diffs.encoded_size(),
+            OptionDiffOwned::Full(value) => value.encoded_size(),
+            OptionDiffOwned::None => 0,
+        }
+    }
+}
+
+impl<T: StructDiff + DiffValueBound> StructDiff for Option<T> {
+    type Diff = OptionDiffOwned<T>;
+    type DiffRef<'target>
+        = OptionDiffRef<'target, T>
+    where
+        Self: 'target;
+
+    fn diff(&self, updated: &Self) -> Vec<Self::Diff> {
+        self.diff_ref(updated).into_iter().map(Into::into).collect()
+    }
+
+    fn diff_ref<'target>(&'target self, updated: &'target Self) -> Vec<Self::DiffRef<'target>> {
+        match (self, updated) {
+            (Some(previous), Some(updated)) => {
+                let diffs = previous.diff_ref(updated);
+                match diffs.is_empty() {
+                    true => Vec::new(),
+                    false => vec![OptionDiffRef::Inner(diffs)],
+                }
+            }
+            (None, Some(updated)) => vec![OptionDiffRef::Full(updated)],
+            (Some(_), None) => vec![OptionDiffRef::None],
+            (None, None) => Vec::new(),
+        }
+    }
+
+    fn has_diff(&self, updated: &Self) -> bool {
+        match (self, updated) {
+            (Some(previous), Some(updated)) => previous.has_diff(updated),
+            (None, None) => false,
+            _ => true,
+        }
+    }
+
+    fn apply_single(&mut self, diff: Self::Diff) {
+        match diff {
+            OptionDiffOwned::Inner(diffs) => {
+                if let Some(inner) = self {
+                    inner.apply_mut(diffs);
+                }
+            }
+            OptionDiffOwned::Full(value) => *self = Some(value),
+            OptionDiffOwned::None => *self = None,
+        }
+    }
+
+    fn apply_single_limited(
+        &mut self,
+        diff: Self::Diff,
+        budget: &mut ApplyBudget,
+    ) -> Result<(), LimitExceeded> {
+        match (diff, self.as_mut()) {
+            (OptionDiffOwned::Inner(diffs), Some(inner)) => budget.apply_nested(inner, diffs),
+            (diff, _) => {
+                self.apply_single(diff);
+                Ok(())
+            }
+        }
+    }
+
+    fn apply_single_ref<'target>(&mut self, diff: Self::DiffRef<'target>)
+    where
+        Self: 'target,
+    {
+        match diff {
+            OptionDiffRef::Inner(diffs) => {
+                if let Some(inner) = self {
+                    for diff in diffs {
+                        inner.apply_single_ref(diff);
+                    }
+                }
+            }
+            OptionDiffRef::Full(value) => match self {
+                Some(existing) => existing.clone_from(value),
+                None => *self = Some(value.clone()),
+            },
+            OptionDiffRef::None => *self = None,
+        }
+    }
+}
+
+impl<T: StructDiff> StructDiff for Box<T> {
+    type Diff = T::Diff;
+    type DiffRef<'target>
+        = T::DiffRef<'target>
+    where
+        Self: 'target;
+
+    fn diff(&self, updated: &Self) -> Vec<Self::Diff> {
+        (**self).diff(updated)
+    }
+
+    fn diff_ref<'target>(&'target self, updated: &'target Self) -> Vec<Self::DiffRef<'target>> {
+        (**self).diff_ref(updated)
+    }
+
+    fn diff_into<'target>(
+        &'target self,
+        updated: &'target Self,
+        sink: &mut impl DiffSink<Self::DiffRef<'target>>,
+    ) {
+        (**self).diff_into(updated, sink)
+    }
+
+    fn has_diff(&self, updated: &Self) -> bool {
+        (**self).has_diff(updated)
+    }
+
+    fn apply_single(&mut self, diff: Self::Diff) {
+        (**self).apply_single(diff)
+    }
+
+    fn apply_single_limited(
+        &mut self,
+        diff: Self::Diff,
+        budget: &mut ApplyBudget,
+    ) -> Result<(), LimitExceeded> {
+        (**self).apply_single_limited(diff, budget)
+    }
+
+    fn apply_single_ref<'target>(&mut self, diff: Self::DiffRef<'target>)
+    where
+        Self: 'target,
+    {
+        (**self).apply_single_ref(diff)
+    }
+}
+
+macro_rules! shared_pointer_impls {
+    ($($ptr:ident),* $(,)?) => {$(
+        impl<T: StructDiff + Clone> StructDiff for $ptr<T> {
+            type Diff = T::Diff;
+            type DiffRef<'target>
+                = T::DiffRef<'target>
+            where
+                Self: 'target;
+
+            fn diff(&self, updated: &Self) -> Vec<Self::Diff> {
+                match $ptr::ptr_eq(self, updated) {
+                    true => Vec::new(),
+                    false => (**self).diff(updated),
+                }
+            }
+
+            fn diff_ref<'target>(&'target self, updated: &'target Self) -> Vec<Self::DiffRef<'target>> {
+                match $ptr::ptr_eq(self, updated) {
+                    true => Vec::new(),
+                    false => (**self).diff_ref(updated),
+                }
+            }
+
+            fn diff_into<'target>(
+                &'target self,
+                updated: &'target Self,
+                sink: &mut impl DiffSink<Self::DiffRef<'target>>,
+            ) {
+                if !$ptr::ptr_eq(self, updated) {
+                    (**self).diff_into(updated, sink)
+                }
+            }
+
+            fn has_diff(&self, updated: &Self) -> bool {
+                !$ptr::ptr_eq(self, updated) && (**self).has_diff(updated)
+            }
+
+            /// Clones the inner value first if it is shared with another pointer
+            fn apply_single(&mut self, diff: Self::Diff) {
+                $ptr::make_mut(self).apply_single(diff)
+            }
+
+            fn apply_single_limited(
+                &mut self,
+                diff: Self::Diff,
+                budget: &mut ApplyBudget,
+            ) -> Result<(), LimitExceeded> {
+                $ptr::make_mut(self).apply_single_limited(diff, budget)
+            }
+
+            fn apply_single_ref<'target>(&mut self, diff: Self::DiffRef<'target>)
+            where
+                Self: 'target,
+            {
+                $ptr::make_mut(self).apply_single_ref(diff)
+            }
+        }
+    )*};
+}
+
+shared_pointer_impls!(Rc, Arc);
+
+/// Diff for a fixed size array, holding the changes to the element at `index`
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct ArrayDiffOwned<T: StructDiff> {
+    index: usize,
+    diffs: Vec<T::Diff>,
+}
+
+/// Borrowed version of [`ArrayDiffOwned`]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+pub struct ArrayDiffRef<'a, T: StructDiff + 'a> {
+    index: usize,
+    diffs: Vec<T::DiffRef<'a>>,
+}
+
+impl<T: StructDiff> Clone for ArrayDiffOwned<T> {
+    fn clone(&self) -> Self {
+        Self {
+            index: self.index,
+            diffs: self.diffs.clone(),
+        }
+    }
+}
+
+impl<T: StructDiff> Clone for ArrayDiffRef<'_, T> {
+    fn clone(&self) -> Self {
+        Self {
+            index: self.index,
+            diffs: self.diffs.clone(),
+        }
+    }
+}
+
+#[cfg(feature = "debug_diffs")]
+impl<T: StructDiff> std::fmt::Debug for ArrayDiffOwned<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ArrayDiffOwned")
+            .field("index", &self.index)
+            .field("diffs", &self.diffs)
+            .finish()
+    }
+}
+
+#[cfg(feature = "debug_diffs")]
+impl<T: StructDiff> std::fmt::Debug for ArrayDiffRef<'_, T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ArrayDiffRef")
+            .field("index", &self.index)
+            .field("diffs", &self.diffs)
+            .finish()
+    }
+}
+
+impl<'a, T: StructDiff> From<ArrayDiffRef<'a, T>> for ArrayDiffOwned<T> {
+    fn from(value: ArrayDiffRef<'a, T>) -> Self {
+        Self {
+            index: value.index,
+            diffs: value.diffs.into_iter().map(Into::into).collect(),
+        }
+    }
+}
+
+impl<T: StructDiff> EncodedSize for ArrayDiffOwned<T>
+where
+    T::Diff: EncodedSize,
+{
+    fn encoded_size(&self) -> usize {
+        self.index.encoded_size() + self.diffs.encoded_size()
+    }
+}
+
+impl<T: StructDiff, const N: usize> StructDiff for [T; N] {
+    type Diff = ArrayDiffOwned<T>;
+    type DiffRef<'target>
+        = ArrayDiffRef<'target, T>
+    where
+        Self: 'target;
+
+    fn diff(&self, updated: &Self) -> Vec<Self::Diff> {
+        self.iter()
+            .zip(updated.iter())
+            .enumerate()
+            .filter_map(|(index, (previous, updated))| {
+                let diffs = previous.diff(updated);
+                (!diffs.is_empty()).then_some(ArrayDiffOwned { index, diffs })
+            })
+            .collect()
+    }
+
+    fn diff_ref<'target>(&'target self, updated: &'target Self) -> Vec<Self::DiffRef<'target>> {
+        let mut diffs = Vec::new();
+        self.diff_into(updated, &mut diffs);
+        diffs
+    }
+
+    fn diff_into<'target>(
+        &'target self,
+        updated: &'target Self,
+        sink: &mut impl DiffSink<Self::DiffRef<'target>>,
+    ) {
+        for (index, (previous, updated)) in self.iter().zip(updated.iter()).enumerate() {
+            let diffs = previous.diff_ref(updated);
+            if !diffs.is_empty() {
+                sink.push(ArrayDiffRef { index, diffs });
+            }
+        }
+    }
+
+    fn has_diff(&self, updated: &Self) -> bool {
+        self.iter()
+            .zip(updated.iter())
+            .any(|(previous, updated)| previous.has_diff(updated))
+    }
+
+    fn apply_single(&mut self, diff: Self::Diff) {
+        if let Some(item) = self.get_mut(diff.index) {
+            item.apply_mut(diff.diffs);
+        }
+    }
+
+    fn apply_single_limited(
+        &mut self,
+        diff: Self::Diff,
+        budget: &mut ApplyBudget,
+    ) -> Result<(), LimitExceeded> {
+        match self.get_mut(diff.index) {
+            Some(item) => budget.apply_nested(item, diff.diffs),
+            None => Ok(()),
+        }
+    }
+
+    fn apply_single_ref<'target>(&mut self, diff: Self::DiffRef<'target>)
+    where
+        Self: 'target,
+    {
+        if let Some(item) = self.get_mut(diff.index) {
+            for diff in diff.diffs {
+                item.apply_single_ref(diff);
+            }
+        }
+    }
+}
+
+macro_rules! tuple_impls {
+    ($owned:ident, $borrowed:ident; $(($name:ident, $variant:ident, $idx:tt)),+) => {
+        /// Diff for a tuple, holding the changes to a single element
+        #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+        pub enum $owned<$($name: StructDiff),+> {
+            $($variant(Vec<$name::Diff>)),+
+        }
+
+        /// Borrowed version of the tuple diff with the same arity
+        #[cfg_attr(feature = "serde", derive(Serialize))]
+        pub enum $borrowed<'a, $($name: StructDiff + 'a),+> {
+            $($variant(Vec<$name::DiffRef<'a>>)),+
+        }
+
+        impl<$($name: StructDiff),+> Clone for $owned<$($name),+> {
+            fn clone(&self) -> Self {
+                match self {
+                    $(Self::$variant(diffs) => Self::$variant(diffs.clone())),+
+                }
+            }
+        }
+
+        impl<$($name: StructDiff),+> Clone for $borrowed<'_, $($name),+> {
+            fn clone(&self) -> Self {
+                match self {
+                    $(Self::$variant(diffs) => Self::$variant(diffs.clone())),+
+                }
+            }
+        }
+
+        #[cfg(feature = "debug_diffs")]
+        impl<$($name: StructDiff),+> std::fmt::Debug for $owned<$($name),+> {
+            fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+                match self {
+                    $(Self::$variant(diffs) => f.debug_tuple(stringify!($variant)).field(diffs).finish()),+
+                }
+            }
+        }
+
+        #[cfg(feature = "debug_diffs")]
+        impl<$($name: StructDiff),+> std::fmt::Debug for $borrowed<'_, $($name),+> {
+            fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+                match self {
+                    $(Self::$variant(diffs) => f.debug_tuple(stringify!($variant)).field(diffs).finish()),+
+                }
+            }
+        }
+
+        impl<'a, $($name: StructDiff),+> From<$borrowed<'a, $($name),+>> for $owned<$($name),+> {
+            fn from(value: $borrowed<'a, $($name),+>) -> Self {
+                match value {
+                    $($borrowed::$variant(diffs) => Self::$variant(diffs.into_iter().map(Into::into).collect())),+
+                }
+            }
+        }
+
+        impl<$($name: StructDiff),+> EncodedSize for $owned<$($name),+>
+        where
+            $($name::Diff: EncodedSize),+
+        {
+            fn encoded_size(&self) -> usize {
+                TAG + match self {
+                    $(Self::$variant(diffs) => diffs.encoded_size()),+
+                }
+            }
+        }
+
+        #[cfg(feature = "nanoserde")]
+        impl<$($name: StructDiff),+> SerBin for $owned<$($name),+> {
+            fn ser_bin(&self, output: &mut Vec<u8>) {
+                match self {
+                    $(Self::$variant(diffs) => {
+                        ($idx as u8).ser_bin(output);
+                        diffs.ser_bin(output);
+                    }),+
+                }
+            }
+        }
+
+        #[cfg(feature = "nanoserde")]
+        impl<$($name: StructDiff),+> SerBin for $borrowed<'_, $($name),+> {
+            fn ser_bin(&self, output: &mut Vec<u8>) {
+                match self {
+                    $(Self::$variant(diffs) => {
+                        ($idx as u8).ser_bin(output);
+                        diffs.ser_bin(output);
+                    }),+
+                }
+            }
+        }
+
+        #[cfg(feature = "nanoserde")]
+        impl<$($name: StructDiff),+> DeBin for $owned<$($name),+> {
+            fn de_bin(offset: &mut usize, bytes: &[u8]) -> Result<Self, nanoserde::DeBinErr> {
+                let id: u8 = DeBin::de_bin(offset, bytes)?;
+                match id {
+                    $($idx => Ok(Self::$variant(crate::checked::de_bin_vec(offset, bytes)?)),)+
+                    _ => Err(nanoserde::DeBinErr {
+                        o: *offset - 1,
+                        l: 1,
+                        s: 1,
+                    }),
+                }
+            }
+        }
+
+        impl<$($name: StructDiff),+> StructDiff for ($($name,)+) {
+            type Diff = $owned<$($name),+>;
+            type DiffRef<'target>
+                = $borrowed<'target, $($name),+>
+            where
+                Self: 'target;
+
+            fn diff(&self, updated: &Self) -> Vec<Self::Diff> {
+                let mut diffs = Vec::new();
+                $(
+                    let inner = self.$idx.diff(&updated.$idx);
+                    if !inner.is_empty() {
+                        diffs.push($owned::$variant(inner));
+                    }
+                )+
+                diffs
+            }
+
+            fn diff_ref<'target>(&'target self, updated: &'target Self) -> Vec<Self::DiffRef<'target>> {
+                let mut diffs = Vec::new();
+                self.diff_into(updated, &mut diffs);
+                diffs
+            }
+
+            fn diff_into<'target>(
+                &'target self,
+                updated: &'target Self,
+                sink: &mut impl DiffSink<Self::DiffRef<'target>>,
+            ) {
+                $(
+                    let inner = self.$idx.diff_ref(&updated.$idx);
+                    if !inner.is_empty() {
+                        sink.push($borrowed::$variant(inner));
+                    }
+                )+
+            }
+
+            fn has_diff(&self, updated: &Self) -> bool {
+                $(self.$idx.has_diff(&updated.$idx))||+
+            }
+
+            fn apply_single(&mut self, diff: Self::Diff) {
+                match diff {
+                    $($owned::$variant(diffs) => self.$idx.apply_mut(diffs)),+
+                }
+            }
+
+            fn apply_single_limited(
+                &mut self,
+                diff: Self::Diff,
+                budget: &mut ApplyBudget,
+            ) -> Result<(), LimitExceeded> {
+                match diff {
+                    $($owned::$variant(diffs) => budget.apply_nested(&mut self.$idx, diffs)),+
+                }
+            }
+
+            fn apply_single_ref<'target>(&mut self, diff: Self::DiffRef<'target>)
+            where
+                Self: 'target,
+            {
+                match diff {
+                    $($borrowed::$variant(diffs) => {
+                        for diff in diffs {
+                            self.$idx.apply_single_ref(diff);
+                        }
+                    }),+
+                }
+            }
+        }
+    };
+}
+
+tuple_impls!(Tuple1DiffOwned, Tuple1DiffRef; (A, Item0, 0));
+tuple_impls!(Tuple2DiffOwned, Tuple2DiffRef; (A, Item0, 0), (B, Item1, 1));
+tuple_impls!(Tuple3DiffOwned, Tuple3DiffRef; (A, Item0, 0), (B, Item1, 1), (C, Item2, 2));
+tuple_impls!(
+    Tuple4DiffOwned, Tuple4DiffRef;
+    (A, Item0, 0), (B, Item1, 1), (C, Item2, 2), (D, Item3, 3)
+);
+tuple_impls!(
+    Tuple5DiffOwned, Tuple5DiffRef;
+    (A, Item0, 0), (B, Item1, 1), (C, Item2, 2), (D, Item3, 3), (E, Item4, 4)
+);
+tuple_impls!(
+    Tuple6DiffOwned, Tuple6DiffRef;
+    (A, Item0, 0), (B, Item1, 1), (C, Item2, 2), (D, Item3, 3), (E, Item4, 4), (F, Item5, 5)
+);
+
+#[cfg(feature = "nanoserde")]
+mod nanoserde_impls {
+    use super::{
+        ArrayDiffOwned, ArrayDiffRef, DeBin, OptionDiffOwned, OptionDiffRef, ReplaceDiff,
+        ReplaceDiffRef, SerBin, SetDiffRef,
+    };
+    use crate::{checked::DeBinChecked, StructDiff};
+
+    impl<T: SerBin> SerBin for ReplaceDiff<T> {
+        fn ser_bin(&self, output: &mut Vec<u8>) {
+            self.0.ser_bin(output)
+        }
+    }
+
+    impl<T: SerBin> SerBin for ReplaceDiffRef<'_, T> {
+        fn ser_bin(&self, output: &mut Vec<u8>) {
+            self.0.ser_bin(output)
+        }
+    }
+
+    impl<T: DeBinChecked> DeBin for ReplaceDiff<T> {
+        fn de_bin(offset: &mut usize, bytes: &[u8]) -> Result<Self, nanoserde::DeBinErr> {
+            Ok(ReplaceDiff(T::de_bin_checked(offset, bytes)?))
+        }
+    }
+
+    impl<T: SerBin + DeBin + PartialEq + Clone> SerBin for SetDiffRef<'_, T> {
+        fn ser_bin(&self, output: &mut Vec<u8>) {
+            (&self.0).ser_bin(output)
+        }
+    }
+
+    impl<T: StructDiff + SerBin> SerBin for OptionDiffOwned<T> {
+        fn ser_bin(&self, output: &mut Vec<u8>) {
+            match self {
+                Self::Inner(diffs) => {
+                    0_u8.ser_bin(output);
+                    diffs.ser_bin(output);
+                }
+                Self::Full(value) => {
+                    1_u8.ser_bin(output);
+                    value.ser_bin(output);
+                }
+                Self::None => 2_u8.ser_bin(output),
+            }
+        }
+    }
+
+    impl<T: StructDiff + SerBin> SerBin for OptionDiffRef<'_, T> {
+        fn ser_bin(&self, output: &mut Vec<u8>) {
+            match self {
+                Self::Inner(diffs) => {
+                    0_u8.ser_bin(output);
+                    diffs.ser_bin(output);
+                }
+                Self::Full(value) => {
+                    1_u8.ser_bin(output);
+                    value.ser_bin(output);
+                }
+                Self::None => 2_u8.ser_bin(output),
+            }
+        }
+    }
+
+    impl<T: StructDiff + DeBinChecked> DeBin for OptionDiffOwned<T> {
+        fn de_bin(offset: &mut usize, bytes: &[u8]) -> Result<Self, nanoserde::DeBinErr> {
+            let id: u8 = DeBin::de_bin(offset, bytes)?;
+            match id {
+                0 => Ok(Self::Inner(crate::checked::de_bin_vec(offset, bytes)?)),
+                1 => Ok(Self::Full(T::de_bin_checked(offset, bytes)?)),
+                2 => Ok(Self::None),
+                _ => Err(nanoserde::DeBinErr {
+                    o: *offset - 1,
+                    l: 1,
+                    s: 1,
+                }),
+            }
+        }
+    }
+
+    impl<T: StructDiff> SerBin for ArrayDiffOwned<T> {
+        fn ser_bin(&self, output: &mut Vec<u8>) {
+            self.index.ser_bin(output);
+            self.diffs.ser_bin(output);
+        }
+    }
+
+    impl<T: StructDiff> SerBin for ArrayDiffRef<'_, T> {
+        fn ser_bin(&self, output: &mut Vec<u8>) {
+            self.index.ser_bin(output);
+            self.diffs.ser_bin(output);
+        }
+    }
+
+    impl<T: StructDiff> DeBin for ArrayDiffOwned<T> {
+        fn de_bin(offset: &mut usize, bytes: &[u8]) -> Result<Self, nanoserde::DeBinErr> {
+            Ok(Self {
+                index: DeBin::de_bin(offset, bytes)?,
+                diffs: crate::checked::de_bin_vec(offset, bytes)?,
+            })
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::{
+        collections::{HashMap, HashSet, VecDeque},
+        rc::Rc,
+    };
+
+    use nanorand::{Rng, WyRand};
+
+    use super::OptionDiffOwned;
+    use crate::StructDiff;
+
+    fn roundtrip<T: StructDiff + Clone + PartialEq + std::fmt::Debug>(
+        first: &T,
+        second: &T,
+    ) -> usize {
+        let diffs = first.diff(second);
+        assert_eq!(first.apply_ref(diffs.clone()), *second);
+
+        let ref_diffs = first.diff_ref(second);
+        #[cfg(feature = "nanoserde")]
+        let ref_encoded = nanoserde::SerBin::serialize_bin(&ref_diffs);
+        let ref_diffs: Vec<T::Diff> = ref_diffs.into_iter().map(Into::into).collect();
+
+        // hashed collections may emit changes in a different order on each
+        // call, so compare the encoding of a single diff in both forms
+        #[cfg(feature = "nanoserde")]
+        assert_eq!(ref_encoded, nanoserde::SerBin::serialize_bin(&ref_diffs));
+
+        assert_eq!(first.apply_ref(ref_diffs), *second);
+
+        diffs.len()
+    }
+
+    #[test]
+    fn test_primitives() {
+        assert_eq!(roundtrip(&1_u8, &1), 0);
+        assert_eq!(roundtrip(&1_u8, &2), 1);
+        assert_eq!(roundtrip(&-1_i128, &i128::MAX), 1);
+        assert_eq!(roundtrip(&-1_isize, &isize::MIN), 1);
+        assert_eq!(roundtrip(&'a', &'ß'), 1);
+        assert_eq!(roundtrip(&0.5_f64, &-0.25), 1);
+        assert_eq!(roundtrip(&true, &false), 1);
+        assert_eq!(roundtrip(&String::from("a"), &String::from("b")), 1);
+    }
+
+    #[test]
+    fn test_option() {
+        let full = Some(String::from("full"));
+        assert_eq!(roundtrip(&None, &full), 1);
+        assert_eq!(roundtrip(&full, &None), 1);
+        assert_eq!(roundtrip(&None::<String>, &None), 0);
+
+        let diffs = Some(1_u32).diff(&Some(2));
+        assert!(matches!(diffs[..], [OptionDiffOwned::Inner(_)]));
+    }
+
+    #[test]
+    fn test_containers() {
+        let mut rng = WyRand::new();
+        for _ in 0..10 {
+            let first: Vec<u16> = (0..rng.generate_range(0..50_usize))
+                .map(|_| rng.generate_range(0..10))
+                .collect();
+            let second: Vec<u16> = (0..rng.generate_range(0..50_usize))
+                .map(|_| rng.generate_range(0..10))
+                .collect();
+
+            roundtrip(&first, &second);
+            roundtrip(
+                &first.iter().copied().collect::<VecDeque<_>>(),
+                &second.iter().copied().collect::<VecDeque<_>>(),
+            );
+            roundtrip(
+                &first.iter().copied().collect::<HashSet<_>>(),
+                &second.iter().copied().collect::<HashSet<_>>(),
+            );
+            roundtrip(
+                &first
+                    .iter()
+                    .map(|x| (*x, first.clone()))
+                    .collect::<HashMap<_, _>>(),
+                &second
+                    .iter()
+                    .map(|x| (*x, second.clone()))
+                    .collect::<HashMap<_, _>>(),
+            );
+        }
+    }
+
+    #[test]
+    fn test_tuples_and_arrays() {
+        assert_eq!(roundtrip(&(1_u8, 'a', 2_i32), &(1, 'b', 3)), 2);
+        assert_eq!(
+            roundtrip(
+                &[[0_u8; 4]; 4],
+                &[[0, 1, 0, 0], [0; 4], [0; 4], [0, 0, 2, 0]]
+            ),
+            2
+        );
+        assert_eq!(
+            roundtrip(&(Some(vec![1_u64]), [true]), &(Some(vec![1, 2]), [true])),
+            1
+        );
+    }
+
+    #[test]
+    fn test_shared_pointers() {
+        let first = Rc::new(vec![1_u32, 2, 3]);
+        let second = Rc::new(vec![1_u32, 3]);
+        assert_eq!(roundtrip(&first, &second), 1);
+        assert!(first.diff(&Rc::clone(&first)).is_empty());
+
+        // applying to a shared pointer must not modify the other owners
+        let mut applied = Rc::clone(&first);
+        applied.apply_mut(first.diff(&second));
+        assert_eq!(applied, second);
+        assert_eq!(*first, vec![1, 2, 3]);
+    }
+}
@@ -0,0 +1,445 @@
+//! Keeps copies of a value on other machines up to date, over any transport
+//! which delivers byte messages in order.
+//!
+//! A [`Replicator`] owns the authoritative value and a [`History`] of its
+//! versions. For each client it produces a message holding either the diffs
+//! from the last version sent to that client, or a full snapshot when that
+//! encodes to fewer bytes. A [`Replica`] applies the messages, and reports a
+//! [`SyncError::Gap`] when a message does not follow from its version, after
+//! which the replicator can be told the replica's version with
+//! [`Replicator::resync`].
+//!
+//! Messages are encoded by a [`SyncCodec`]. [`NanoserdeCodec`] is provided
+//! with the `nanoserde` feature, and with the `serde` feature the message
+//! types can be encoded in any serde format.
+
+use std::collections::BTreeMap;
+
+use crate::{history::History, StructDiff};
+
+/// A message sent from a [`Replicator`] to a [`Replica`], borrowing the
+/// replicator's state. Encodes the same as [`SyncMessage`].
+#[derive(Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+#[cfg_attr(
+    feature = "serde",
+    serde(rename = "SyncMessage", bound(serialize = "T: serde::Serialize"))
+)]
+pub enum SyncMessageRef<'a, T: StructDiff + 'a> {
+    /// The whole state at `version`
+    Snapshot { version: u64, state: &'a T },
+    /// The diffs taking the state at version `from` to version `to`
+    Diff {
+        from: u64,
+        to: u64,
+        diffs: Vec<T::DiffRef<'a>>,
+    },
+}
+
+/// A message received by a [`Replica`]
+#[derive(Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(
+    feature = "serde",
+    serde(bound(
+        serialize = "T: serde::Serialize",
+        deserialize = "T: serde::de::DeserializeOwned"
+    ))
+)]
+pub enum SyncMessage<T: StructDiff> {
+    /// The whole state at `version`
+    Snapshot { version: u64, state: T },
+    /// The diffs taking the state at version `from` to version `to`
+    Diff {
+        from: u64,
+        to: u64,
+        diffs: Vec<T::Diff>,
+    },
+}
+
+impl<'a, T: StructDiff + Clone> From<SyncMessageRef<'a, T>> for SyncMessage<T> {
+    fn from(value: SyncMessageRef<'a, T>) -> Self {
+        match value {
+            SyncMessageRef::Snapshot { version, state } => SyncMessage::Snapshot {
+                version,
+                state: state.clone(),
+            },
+            SyncMessageRef::Diff { from, to, diffs } => SyncMessage::Diff {
+                from,
+                to,
+                diffs: diffs.into_iter().map(Into::into).collect(),
+            },
+        }
+    }
+}
+
+/// Converts sync messages to and from bytes
+pub trait SyncCodec<T: StructDiff> {
+    type Error;
+
+    /// Append the encoded `message` to `output`
+    fn encode(&self, message: &SyncMessageRef<'_, T>, output: &mut Vec<u8>);
+
+    fn decode(&self, bytes: &[u8]) -> Result<SyncMessage<T>, Self::Error>;
+}
+
+/// Encodes sync messages with `nanoserde`'s binary format
+///
+/// ```
+/// use nanoserde::{DeBin, SerBin};
+/// use structdiff::{
+///     sync::{NanoserdeCodec, Replica, Replicator},
+///     Difference,
+/// };
+///
+/// #[derive(Debug, PartialEq, Clone, Difference, SerBin, DeBin)]
+/// struct Example {
+///     field1: f64,
+///     field2: String,
+/// }
+///
+/// let mut server = Replicator::new(Example { field1: 0.0, field2: String::new() }, NanoserdeCodec);
+/// let client = server.add_client();
+/// let mut replica = Replica::new(NanoserdeCodec);
+///
+/// // the first message is a snapshot, later ones are diffs
+/// let message = server.message_for(client).unwrap();
+/// assert_eq!(replica.receive(&message).unwrap(), 0);
+///
+/// server.update(&Example { field1: 3.14, field2: String::new() });
+/// let message = server.message_for(client).unwrap();
+/// let version = replica.receive(&message).unwrap();
+/// server.acknowledge(client, version);
+///
+/// assert_eq!(replica.state(), Some(server.current()));
+/// assert!(server.message_for(client).is_none());
+/// ```
+#[cfg(feature = "nanoserde")]
+#[derive(Clone, Copy, Debug, Default)]
+pub struct NanoserdeCodec;
+
+#[cfg(feature = "nanoserde")]
+impl<T: StructDiff + nanoserde::SerBin + nanoserde::DeBin> SyncCodec<T> for NanoserdeCodec {
+    type Error = nanoserde::DeBinErr;
+
+    fn encode(&self, message: &SyncMessageRef<'_, T>, output: &mut Vec<u8>) {
+        nanoserde::SerBin::ser_bin(message, output)
+    }
+
+    fn decode(&self, bytes: &[u8]) -> Result<SyncMessage<T>, Self::Error> {
+        nanoserde::DeBin::deserialize_bin(bytes)
+    }
+}
+
+/// Why a [`Replica`] could not apply a message
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SyncError<E> {
+    /// The message could not be decoded
+    Decode(E),
+    /// The message holds diffs from a version other than the replica's,
+    /// so some messages were lost or reordered
+    Gap { version: Option<u64>, from: u64 },
+}
+
+impl<E: std::fmt::Display> std::fmt::Display for SyncError<E> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SyncError::Decode(error) => write!(f, "failed to decode sync message: {error}"),
+            SyncError::Gap {
+                version: Some(version),
+                from,
+            } => write!(f, "received diffs from version {from} at version {version}"),
+            SyncError::Gap {
+                version: None,
+                from,
+            } => write!(f, "received diffs from version {from} before a snapshot"),
+        }
+    }
+}
+
+impl<E: std::fmt::Debug + std::fmt::Display> std::error::Error for SyncError<E> {}
+
+/// Identifies a client of a [`Replicator`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct ClientId(u64);
+
+#[derive(Debug, Clone, Copy, Default)]
+struct ClientState {
+    /// version of the last message produced for the client
+    sent: Option<u64>,
+    /// latest version the client reported having
+    acked: Option<u64>,
+}
+
+/// The authoritative copy of a value, which produces messages bringing each
+/// client's [`Replica`] up to date.
+pub struct Replicator<T: StructDiff + Clone, C: SyncCodec<T>> {
+    history: History<T>,
+    codec: C,
+    clients: BTreeMap<ClientId, ClientState>,
+    next_client: u64,
+    /// the encoded snapshot of the latest version, shared between clients
+    snapshot: Option<(u64, Vec<u8>)>,
+}
+
+impl<T: StructDiff + Clone, C: SyncCodec<T>> Replicator<T, C> {
+    pub fn new(initial: T, codec: C) -> Self {
+        Self::with_history(History::new(initial), codec)
+    }
+
+    /// Replicate the latest state of `history`. Clients behind the history's
+    /// base version are sent snapshots.
+    pub fn with_history(history: History<T>, codec: C) -> Self {
+        Self {
+            history,
+            codec,
+            clients: BTreeMap::new(),
+            next_client: 0,
+            snapshot: None,
+        }
+    }
+
+    pub fn current(&self) -> &T {
+        self.history.current()
+    }
+
+    /// The latest version
+    pub fn version(&self) -> u64 {
+        self.history.latest_seq()
+    }
+
+    pub fn history(&self) -> &History<T> {
+        &self.history
+    }
+
+    /// Make `state` the latest version, returning its version number, or
+    /// `None` if it has no diff from the current state
+    pub fn update(&mut self, state: &T) -> Option<u64> {
+        let version = self.version() + 1;
+        self.history.record_state(version, state)
+    }
+
+    /// Apply `diffs` to the current state as a new version
+    pub fn apply(&mut self, diffs: Vec<T::Diff>) -> u64 {
+        let version = self.version() + 1;
+        self.history.record(version, diffs)
+    }
+
+    /// Drop the history before the oldest version acknowledged by every
+    /// client, which bounds the memory used by the replicator
+    pub fn compact(&mut self) {
+        let oldest = self
+            .clients
+            .values()
+            .map(|client| client.acked.unwrap_or(0))
+            .min()
+            .unwrap_or(self.version());
+        self.history.compact(oldest);
+    }
+
+    /// Register a new client, which will first be sent a snapshot
+    pub fn add_client(&mut self) -> ClientId {
+        let id = ClientId(self.next_client);
+        self.next_client += 1;
+        self.clients.insert(id, ClientState::default());
+        id
+    }
+
+    pub fn remove_client(&mut self, client: ClientId) {
+        self.clients.remove(&client);
+    }
+
+    /// Record that `client` has applied the messages up to `version`
+    pub fn acknowledge(&mut self, client: ClientId, version: u64) {
+        if let Some(state) = self.clients.get_mut(&client) {
+            state.acked = state.acked.max(Some(version));
+        }
+    }
+
+    /// The latest version `client` has acknowledged
+    pub fn acknowledged(&self, client: ClientId) -> Option<u64> {
+        self.clients.get(&client).and_then(|state| state.acked)
+    }
+
+    /// Base the next message for `client` on `version`, such as after the
+    /// client's replica reported a [`SyncError::Gap`] or reconnected.
+    /// `None` sends a snapshot.
+    pub fn resync(&mut self, client: ClientId, version: Option<u64>) {
+        if let Some(state) = self.clients.get_mut(&client) {
+            state.sent = version;
+            state.acked = version;
+        }
+    }
+
+    /// Encode the message bringing `client` up to the latest version, or
+    /// `None` if it has already been sent the latest version.
+    ///
+    /// Messages are assumed to be delivered in order, so the next message
+    /// follows from this one rather than from the last acknowledged version.
+    pub fn message_for(&mut self, client: ClientId) -> Option<Vec<u8>> {
+        let version = self.version();
+        let sent = self.clients.get(&client)?.sent;
+        if sent == Some(version) {
+            return None;
+        }
+
+        if !matches!(&self.snapshot, Some((snapshot_version, _)) if *snapshot_version == version) {
+            let mut encoded = Vec::new();
+            self.codec.encode(
+                &SyncMessageRef::Snapshot {
+                    version,
+                    state: self.history.current(),
+                },
+                &mut encoded,
+            );
+            self.snapshot = Some((version, encoded));
+        }
+        let snapshot = &self.snapshot.as_ref().unwrap().1;
+
+        let diff = sent
+            .and_then(|from| self.history.state_at(from).map(|base| (from, base)))
+            .map(|(from, base)| {
+                let mut encoded = Vec::new();
+                self.codec.encode(
+                    &SyncMessageRef::Diff {
+                        from,
+                        to: version,
+                        diffs: base.diff_ref(self.history.current()),
+                    },
+                    &mut encoded,
+                );
+                encoded
+            });
+
+        let message = match diff {
+            Some(diff) if diff.len() < snapshot.len() => diff,
+            _ => snapshot.clone(),
+        };
+        self.clients.get_mut(&client).unwrap().sent = Some(version);
+        Some(message)
+    }
+}
+
+/// A copy of a value kept up to date by the messages of a [`Replicator`]
+pub struct Replica<T: StructDiff, C: SyncCodec<T>> {
+    state: Option<T>,
+    version: Option<u64>,
+    codec: C,
+}
+
+impl<T: StructDiff, C: SyncCodec<T>> Replica<T, C> {
+    /// A replica which has not yet received a snapshot
+    pub fn new(codec: C) -> Self {
+        Self {
+            state: None,
+            version: None,
+            codec,
+        }
+    }
+
+    /// The replicated state, once a snapshot has been received
+    pub fn state(&self) -> Option<&T> {
+        self.state.as_ref()
+    }
+
+    /// The version of the replicated state
+    pub fn version(&self) -> Option<u64> {
+        self.version
+    }
+
+    /// Decode and apply a message, returning the replica's version after it.
+    /// Messages for versions the replica already has are ignored.
+    pub fn receive(&mut self, bytes: &[u8]) -> Result<u64, SyncError<C::Error>> {
+        let message = self.codec.decode(bytes).map_err(SyncError::Decode)?;
+        self.apply(message)
+    }
+
+    /// Apply a decoded message, see [`Replica::receive`]
+    pub fn apply(&mut self, message: SyncMessage<T>) -> Result<u64, SyncError<C::Error>> {
+        match message {
+            SyncMessage::Snapshot { version, .. } if self.version > Some(version) => {}
+            SyncMessage::Snapshot { version, state } => {
+                self.state = Some(state);
+                self.version = Some(version);
+            }
+            SyncMessage::Diff { to, .. } if self.version >= Some(to) => {}
+            SyncMessage::Diff { from, to, diffs } => match &mut self.state {
+                Some(state) if self.version == Some(from) => {
+                    state.apply_mut(diffs);
+                    self.version = Some(to);
+                }
+                _ => {
+                    return Err(SyncError::Gap {
+                        version: self.version,
+                        from,
+                    })
+                }
+            },
+        }
+        Ok(self.version.unwrap())
+    }
+}
+
+#[cfg(feature = "nanoserde")]
+mod nanoserde_impls {
+    use super::*;
+    use nanoserde::{DeBin, DeBinErr, SerBin};
+
+    impl<T: StructDiff + SerBin> SerBin for SyncMessageRef<'_, T> {
+        fn ser_bin(&self, output: &mut Vec<u8>) {
+            match self {
+                SyncMessageRef::Snapshot { version, state } => {
+                    0u16.ser_bin(output);
+                    version.ser_bin(output);
+                    state.ser_bin(output);
+                }
+                SyncMessageRef::Diff { from, to, diffs } => {
+                    1u16.ser_bin(output);
+                    from.ser_bin(output);
+                    to.ser_bin(output);
+                    diffs.ser_bin(output);
+                }
+            }
+        }
+    }
+
+    impl<T: StructDiff + SerBin> SerBin for SyncMessage<T> {
+        fn ser_bin(&self, output: &mut Vec<u8>) {
+            match self {
+                SyncMessage::Snapshot { version, state } => {
+                    0u16.ser_bin(output);
+                    version.ser_bin(output);
+                    state.ser_bin(output);
+                }
+                SyncMessage::Diff { from, to, diffs } => {
+                    1u16.ser_bin(output);
+                    from.ser_bin(output);
+                    to.ser_bin(output);
+                    diffs.ser_bin(output);
+                }
+            }
+        }
+    }
+
+    impl<T: StructDiff + DeBin> DeBin for SyncMessage<T> {
+        fn de_bin(offset: &mut usize, bytes: &[u8]) -> Result<Self, DeBinErr> {
+            let id: u16 = DeBin::de_bin(offset, bytes)?;
+            match id {
+                0 => Ok(SyncMessage::Snapshot {
+                    version: DeBin::de_bin(offset, bytes)?,
+                    state: DeBin::de_bin(offset, bytes)?,
+                }),
+                1 => Ok(SyncMessage::Diff {
+                    from: DeBin::de_bin(offset, bytes)?,
+                    to: DeBin::de_bin(offset, bytes)?,
+                    diffs: crate::checked::de_bin_vec(offset, bytes)?,
+                }),
+                _ => Err(DeBinErr {
+                    o: *offset - 2,
+                    l: 2,
+                    s: bytes.len(),
+                }),
+            }
+        }
+    }
+}
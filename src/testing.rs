@@ -0,0 +1,304 @@
+//! Randomized checks that a [`StructDiff`] implementation upholds the
+//! guarantees documented on the trait. Requires the `testing` feature.
+//!
+//! Values are generated with the [`arbitrary`] crate, which is re-exported
+//! here, so any type implementing [`Arbitrary`] can be checked.
+//! `#[derive(Difference)]` implements it for structs marked
+//! `#[difference(arbitrary)]`, setting skipped fields to their `Default`.
+//! [`check_laws`] then diffs random pairs of values, which are often partly
+//! equal, and panics with the pair if any law is broken.
+//!
+//! ```
+//! use structdiff::Difference;
+//!
+//! #[derive(Debug, PartialEq, Clone, Difference)]
+//! #[difference(arbitrary)]
+//! struct Example {
+//!     field1: f64,
+//!     #[difference(collection_strategy = "ordered_array_like")]
+//!     field2: Vec<u32>,
+//! }
+//!
+//! structdiff::testing::check_laws::<Example>();
+//! ```
+//!
+//! The laws do not hold for the merging diffs of the types in
+//! [`collections::crdt`](crate::collections::crdt).
+
+use std::fmt::Debug;
+
+pub use arbitrary::{self, Arbitrary, Unstructured};
+
+use crate::{collections::rope::Rope, StructDiff};
+
+/// Bytes of input from which the values of each case are generated
+const CASE_LEN: usize = 1024;
+
+/// A small, seeded pseudo-random number generator (wyrand), so that failing
+/// cases can be reproduced
+#[derive(Clone, Debug)]
+struct Rng {
+    state: u64,
+}
+
+impl Rng {
+    fn new(seed: u64) -> Self {
+        Self { state: seed }
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0xa076_1d64_78bd_642f);
+        let product = (self.state as u128) * ((self.state ^ 0xe703_7ed1_a0b4_28db) as u128);
+        ((product >> 64) ^ product) as u64
+    }
+
+    /// Input for [`Unstructured`], where half of the bytes are below 4 so
+    /// that generated numbers are often small and collections short, making
+    /// two values often partly equal, which exercises the unchanged paths of
+    /// a diff
+    fn bytes(&mut self, len: usize) -> Vec<u8> {
+        (0..len)
+            .map(|_| match self.next_u64() {
+                random if random & 1 == 1 => (random >> 8) as u8 & 3,
+                random => (random >> 8) as u8,
+            })
+            .collect()
+    }
+}
+
+impl<'a, T: Arbitrary<'a>> Arbitrary<'a> for Rope<T> {
+    fn arbitrary(u: &mut Unstructured<'a>) -> arbitrary::Result<Self> {
+        u.arbitrary_iter()?.collect()
+    }
+}
+
+/// A pair of values to diff: equal, unrelated, or the first with part of
+/// the diff to an unrelated value applied
+pub fn arbitrary_pair<'a, T: StructDiff + Arbitrary<'a> + Clone>(
+    u: &mut Unstructured<'a>,
+) -> arbitrary::Result<(T, T)> {
+    let first = T::arbitrary(u)?;
+    let second = match u.int_in_range(0..=3_u8)? {
+        0 => first.clone(),
+        1 => T::arbitrary(u)?,
+        _ => {
+            let mut diffs = Vec::new();
+            for diff in first.diff(&T::arbitrary(u)?) {
+                if u.arbitrary()? {
+                    diffs.push(diff);
+                }
+            }
+            first.clone().apply(diffs)
+        }
+    };
+    Ok((first, second))
+}
+
+/// A serde data format for [`Laws::check_serde`] to round-trip diffs
+/// through, since this crate doesn't depend on one
+#[cfg(feature = "serde")]
+pub trait SerdeFormat {
+    fn serialize<S: serde::Serialize>(&self, value: &S) -> Vec<u8>;
+
+    fn deserialize<D: serde::de::DeserializeOwned>(&self, bytes: &[u8]) -> D;
+}
+
+/// Configures the laws checked by [`check_laws`].
+///
+/// For every pair of values `a` and `b` from [`arbitrary_pair`], checks
+/// that:
+/// - `a.apply(a.diff(&b)) == b`, as do `apply_ref` and `apply_mut`
+/// - the diffs from `diff_ref` and `diff_into` apply the same as `diff`
+/// - `has_diff` is true exactly when `diff` is not empty
+/// - `a.diff(&a)` is empty, and applying an empty diff changes nothing
+/// - applying the diff between two other values to `a` does not panic
+/// - with the `nanoserde` feature, owned and borrowed diffs serialize to
+///   owned diffs which apply the same
+#[derive(Clone, Debug)]
+pub struct Laws {
+    cases: usize,
+    seed: u64,
+}
+
+impl Default for Laws {
+    fn default() -> Self {
+        Self {
+            cases: 256,
+            seed: 0x5eed,
+        }
+    }
+}
+
+impl Laws {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Check this many pairs of values, 256 by default
+    pub fn cases(mut self, cases: usize) -> Self {
+        self.cases = cases;
+        self
+    }
+
+    /// Seed the generator, to check different values or reproduce a failure
+    pub fn seed(mut self, seed: u64) -> Self {
+        self.seed = seed;
+        self
+    }
+
+    /// Check the laws for `T`, panicking with the first pair of values
+    /// which breaks one
+    pub fn check<T>(&self)
+    where
+        T: StructDiff + for<'a> Arbitrary<'a> + Clone + PartialEq + Debug,
+    {
+        self.run::<T>(|_, _| Ok(()))
+    }
+
+    /// Check the laws for `T`, and that owned and borrowed diffs serialized
+    /// with `format` deserialize to owned diffs which apply the same
+    #[cfg(feature = "serde")]
+    pub fn check_serde<T>(&self, format: &impl SerdeFormat)
+    where
+        T: StructDiff + for<'a> Arbitrary<'a> + Clone + PartialEq + Debug,
+    {
+        self.run::<T>(|first, second| {
+            let diffs: Vec<T::Diff> = format.deserialize(&format.serialize(&first.diff(second)));
+            if &first.clone().apply(diffs) != second {
+                return Err("serde round trip of `diff` changes how it applies");
+            }
+            let diffs: Vec<T::Diff> =
+                format.deserialize(&format.serialize(&first.diff_ref(second)));
+            if &first.clone().apply(diffs) != second {
+                return Err("serde round trip of `diff_ref` changes how it applies");
+            }
+            Ok(())
+        })
+    }
+
+    fn run<T>(&self, mut extra: impl FnMut(&T, &T) -> Result<(), &'static str>)
+    where
+        T: StructDiff + for<'a> Arbitrary<'a> + Clone + PartialEq + Debug,
+    {
+        let mut rng = Rng::new(self.seed);
+        for case in 0..self.cases {
+            let bytes = rng.bytes(CASE_LEN);
+            let result = check_unstructured::<T>(&mut Unstructured::new(&bytes), &mut extra);
+            if let Err(failure) = result {
+                panic!(
+                    "{failure} (case {case} with seed {seed:#x})",
+                    seed = self.seed
+                );
+            }
+        }
+    }
+}
+
+/// Check the laws described on [`Laws`] for one pair of values generated
+/// from `u`, as in a fuzz target, along with the checks in `extra`. Returns
+/// an error describing the pair if a law is broken.
+///
+/// Pairs which `u` is malformed for, and values which are not equal to
+/// themselves, such as floats holding NaN, are skipped.
+pub fn check_unstructured<'a, T>(
+    u: &mut Unstructured<'a>,
+    mut extra: impl FnMut(&T, &T) -> Result<(), &'static str>,
+) -> Result<(), String>
+where
+    T: StructDiff + Arbitrary<'a> + Clone + PartialEq + Debug,
+{
+    let Ok((first, second)) = arbitrary_pair::<T>(u) else {
+        return Ok(());
+    };
+    let unrelated = match (T::arbitrary(u), T::arbitrary(u)) {
+        (Ok(third), Ok(fourth)) => third.diff(&fourth),
+        _ => Vec::new(),
+    };
+    #[allow(clippy::eq_op)]
+    if first != first || second != second {
+        return Ok(());
+    }
+    check_pair(&first, &second, unrelated)
+        .and_then(|()| extra(&first, &second))
+        .map_err(|law| format!("{law}\n first: {first:?}\nsecond: {second:?}"))
+}
+
+fn check_pair<T>(first: &T, second: &T, unrelated: Vec<T::Diff>) -> Result<(), &'static str>
+where
+    T: StructDiff + Clone + PartialEq,
+{
+    let diffs = first.diff(second);
+    if &first.clone().apply(diffs.clone()) != second {
+        return Err("applying `diff` does not produce the updated value");
+    }
+    if &first.apply_ref(diffs.clone()) != second {
+        return Err("`apply_ref` differs from `apply`");
+    }
+    let mut applied = first.clone();
+    applied.apply_mut(diffs.clone());
+    if &applied != second {
+        return Err("`apply_mut` differs from `apply`");
+    }
+    if first.has_diff(second) == diffs.is_empty() {
+        return Err("`has_diff` disagrees with whether `diff` is empty");
+    }
+
+    let diff_refs = first.diff_ref(second);
+    if diff_refs.len() != diffs.len() {
+        return Err("`diff_ref` returns a different number of diffs than `diff`");
+    }
+    let mut applied = first.clone();
+    applied.apply_ref_diffs(diff_refs.clone());
+    if &applied != second {
+        return Err("applying `diff_ref` does not produce the updated value");
+    }
+    let owned = diff_refs.clone().into_iter().map(Into::into).collect();
+    if &first.clone().apply(owned) != second {
+        return Err("`diff_ref` converted to owned diffs does not produce the updated value");
+    }
+    let mut sunk = Vec::new();
+    first.diff_into(second, &mut sunk);
+    let mut applied = first.clone();
+    applied.apply_ref_diffs(sunk);
+    if &applied != second {
+        return Err("applying `diff_into` does not produce the updated value");
+    }
+
+    if !first.diff(first).is_empty() || !first.diff_ref(first).is_empty() {
+        return Err("diffing a value with itself is not empty");
+    }
+    if first.has_diff(first) {
+        return Err("`has_diff` of a value with itself is true");
+    }
+    if &first.clone().apply(Vec::new()) != first {
+        return Err("applying no diffs changes the value");
+    }
+    // diffs generated against a different base may be applied, but must not
+    // panic
+    let _ = first.clone().apply(unrelated);
+
+    #[cfg(feature = "nanoserde")]
+    {
+        use nanoserde::{DeBin, SerBin};
+
+        let round_trip = |bytes: Vec<u8>| -> Result<Vec<T::Diff>, &'static str> {
+            DeBin::deserialize_bin(&bytes).map_err(|_| "nanoserde diffs fail to deserialize")
+        };
+        if &first.clone().apply(round_trip(diffs.serialize_bin())?) != second {
+            return Err("nanoserde round trip of `diff` changes how it applies");
+        }
+        if &first.clone().apply(round_trip(diff_refs.serialize_bin())?) != second {
+            return Err("nanoserde round trip of `diff_ref` changes how it applies");
+        }
+    }
+
+    Ok(())
+}
+
+/// Check the laws described on [`Laws`] for 256 pairs of values
+pub fn check_laws<T>()
+where
+    T: StructDiff + for<'a> Arbitrary<'a> + Clone + PartialEq + Debug,
+{
+    Laws::new().check::<T>()
+}
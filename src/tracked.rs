@@ -0,0 +1,143 @@
+//! A wrapper which keeps a baseline copy of a value, so that changes made
+//! through any mutable access can be collected as diffs later.
+
+use core::ops::{Deref, DerefMut};
+
+use crate::StructDiff;
+
+/// A value and the baseline it was last committed at.
+///
+/// Mutable access, through [`Tracked::modify`] or [`DerefMut`], marks the
+/// value as touched. The pending diffs are computed against the baseline when
+/// they are requested, so changes to nested fields and through `&mut`
+/// references are never missed.
+///
+/// ```
+/// use structdiff::{Difference, StructDiff, Tracked};
+///
+/// #[derive(Debug, PartialEq, Clone, Difference)]
+/// struct Example {
+///     field1: f64,
+///     field2: Vec<u32>,
+/// }
+///
+/// let mut tracked = Tracked::new(Example { field1: 0.0, field2: vec![] });
+///
+/// tracked.modify(|example| example.field1 = 3.14);
+/// tracked.field2.push(1);
+///
+/// let mut replica = tracked.baseline().clone();
+/// replica.apply_mut(tracked.take_diffs());
+/// assert_eq!(&replica, &*tracked);
+/// assert!(!tracked.is_dirty());
+///
+/// tracked.field2.clear();
+/// tracked.rollback();
+/// assert_eq!(tracked.field2, [1]);
+/// ```
+#[derive(Clone, Debug)]
+pub struct Tracked<T: StructDiff + Clone> {
+    baseline: T,
+    current: T,
+    touched: bool,
+}
+
+impl<T: StructDiff + Clone> Tracked<T> {
+    /// Start tracking `value`, with itself as the baseline
+    pub fn new(value: T) -> Self {
+        Self {
+            baseline: value.clone(),
+            current: value,
+            touched: false,
+        }
+    }
+
+    /// The value as of the last commit
+    pub fn baseline(&self) -> &T {
+        &self.baseline
+    }
+
+    /// Mutate the value
+    pub fn modify<R>(&mut self, f: impl FnOnce(&mut T) -> R) -> R {
+        self.touched = true;
+        f(&mut self.current)
+    }
+
+    /// Whether the value differs from the baseline. Only compares the two
+    /// if the value has been mutably accessed since the last commit.
+    pub fn is_dirty(&self) -> bool {
+        self.touched && self.baseline.has_diff(&self.current)
+    }
+
+    /// Diffs which take the baseline to the current value, without
+    /// committing them
+    pub fn pending_diffs(&self) -> Vec<T::Diff> {
+        match self.touched {
+            true => self.baseline.diff(&self.current),
+            false => Vec::new(),
+        }
+    }
+
+    /// Diffs which take the baseline to the current value, as references
+    pub fn pending_diffs_ref(&self) -> Vec<T::DiffRef<'_>> {
+        match self.touched {
+            true => self.baseline.diff_ref(&self.current),
+            false => Vec::new(),
+        }
+    }
+
+    /// Return the pending diffs and commit the current value
+    pub fn take_diffs(&mut self) -> Vec<T::Diff> {
+        let diffs = self.pending_diffs();
+        self.commit();
+        diffs
+    }
+
+    /// Make the current value the new baseline, discarding its diffs
+    pub fn commit(&mut self) {
+        if self.touched {
+            self.baseline.clone_from(&self.current);
+            self.touched = false;
+        }
+    }
+
+    /// Restore the value to the baseline, discarding its diffs
+    pub fn rollback(&mut self) {
+        if self.touched {
+            self.current.clone_from(&self.baseline);
+            self.touched = false;
+        }
+    }
+
+    /// Stop tracking, returning the current value
+    pub fn into_inner(self) -> T {
+        self.current
+    }
+}
+
+impl<T: StructDiff + Clone> Deref for Tracked<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.current
+    }
+}
+
+impl<T: StructDiff + Clone> DerefMut for Tracked<T> {
+    fn deref_mut(&mut self) -> &mut T {
+        self.touched = true;
+        &mut self.current
+    }
+}
+
+impl<T: StructDiff + Clone + Default> Default for Tracked<T> {
+    fn default() -> Self {
+        Self::new(T::default())
+    }
+}
+
+impl<T: StructDiff + Clone> From<T> for Tracked<T> {
+    fn from(value: T) -> Self {
+        Self::new(value)
+    }
+}
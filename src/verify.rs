@@ -0,0 +1,148 @@
+//! Fingerprints of the state a diff was generated against, so that applying
+//! it to a different state is detected instead of silently corrupting it.
+//!
+//! See [`StructDiffVerified`](crate::StructDiffVerified).
+
+use core::hash::{Hash, Hasher};
+
+/// A 64 bit FNV-1a hasher.
+///
+/// Unlike the `std` hashers it is not randomly seeded, integers are always
+/// hashed as little endian bytes and `usize`/`isize` as 8 of them, so a
+/// fingerprint is the same in every process and on every platform. Values must still hash the same way in
+/// both places, which holds for the `std` `Hash` impls of integers, strings,
+/// and ordered collections, but not for `HashMap` or `HashSet`, which are
+/// not `Hash` at all.
+#[derive(Clone, Copy, Debug)]
+pub struct StableHasher(u64);
+
+impl StableHasher {
+    const OFFSET_BASIS: u64 = 0xcbf2_9ce4_8422_2325;
+    const PRIME: u64 = 0x0000_0100_0000_01b3;
+}
+
+impl Default for StableHasher {
+    fn default() -> Self {
+        Self(Self::OFFSET_BASIS)
+    }
+}
+
+impl Hasher for StableHasher {
+    fn finish(&self) -> u64 {
+        self.0
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        for byte in bytes {
+            self.0 = (self.0 ^ *byte as u64).wrapping_mul(Self::PRIME);
+        }
+    }
+
+    fn write_u8(&mut self, i: u8) {
+        self.write(&[i])
+    }
+
+    fn write_u16(&mut self, i: u16) {
+        self.write(&i.to_le_bytes())
+    }
+
+    fn write_u32(&mut self, i: u32) {
+        self.write(&i.to_le_bytes())
+    }
+
+    fn write_u64(&mut self, i: u64) {
+        self.write(&i.to_le_bytes())
+    }
+
+    fn write_u128(&mut self, i: u128) {
+        self.write(&i.to_le_bytes())
+    }
+
+    fn write_usize(&mut self, i: usize) {
+        self.write_u64(i as u64)
+    }
+
+    fn write_i8(&mut self, i: i8) {
+        self.write(&i.to_le_bytes())
+    }
+
+    fn write_i16(&mut self, i: i16) {
+        self.write(&i.to_le_bytes())
+    }
+
+    fn write_i32(&mut self, i: i32) {
+        self.write(&i.to_le_bytes())
+    }
+
+    fn write_i64(&mut self, i: i64) {
+        self.write(&i.to_le_bytes())
+    }
+
+    fn write_i128(&mut self, i: i128) {
+        self.write(&i.to_le_bytes())
+    }
+
+    fn write_isize(&mut self, i: isize) {
+        self.write_i64(i as i64)
+    }
+}
+
+/// Hash `value` with a [`StableHasher`]
+pub fn fingerprint<T: Hash + ?Sized>(value: &T) -> u64 {
+    let mut hasher = StableHasher::default();
+    value.hash(&mut hasher);
+    hasher.finish()
+}
+
+/// Diffs along with a fingerprint of the fields they modify, taken from the
+/// state they were generated against
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct VerifiedDiff<D> {
+    pub base: u64,
+    pub diffs: Vec<D>,
+}
+
+/// Returned when verified diffs are applied to a state other than the one
+/// they were generated against
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct BaseMismatch {
+    /// The fingerprint carried by the diffs
+    pub expected: u64,
+    /// The fingerprint of the state they were applied to
+    pub found: u64,
+}
+
+impl core::fmt::Display for BaseMismatch {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write!(
+            f,
+            "diffs were generated against base {:016x}, but were applied to {:016x}",
+            self.expected, self.found
+        )
+    }
+}
+
+impl std::error::Error for BaseMismatch {}
+
+#[cfg(feature = "nanoserde")]
+mod nanoserde_impls {
+    use super::*;
+    use nanoserde::{DeBin, DeBinErr, SerBin};
+
+    impl<D: SerBin> SerBin for VerifiedDiff<D> {
+        fn ser_bin(&self, output: &mut Vec<u8>) {
+            self.base.ser_bin(output);
+            self.diffs.ser_bin(output);
+        }
+    }
+
+    impl<D: DeBin> DeBin for VerifiedDiff<D> {
+        fn de_bin(offset: &mut usize, bytes: &[u8]) -> Result<Self, DeBinErr> {
+            Ok(Self {
+                base: DeBin::de_bin(offset, bytes)?,
+                diffs: crate::checked::de_bin_vec(offset, bytes)?,
+            })
+        }
+    }
+}
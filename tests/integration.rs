@@ -406,115 +406,1762 @@ fn test_recurse() {
     nanoserde_ref_test!(first, second);
 }
 
+#[test]
+fn test_recurse_std_types() {
+    #[derive(Debug, PartialEq, Clone, Difference)]
+    struct TestStd {
+        #[difference(recurse)]
+        test1: HashMap<String, Vec<Option<u32>>>,
+        #[difference(recurse)]
+        test2: (Box<Test>, [i64; 3], char),
+        #[difference(recurse)]
+        test3: BTreeMap<u8, (String, BTreeSet<i16>)>,
+    }
+
+    let first = TestStd {
+        test1: HashMap::from([
+            (String::from("a"), vec![Some(1), None, Some(3)]),
+            (String::from("b"), vec![]),
+        ]),
+        test2: (Box::default(), [1, 2, 3], 'x'),
+        test3: BTreeMap::from([(1, (String::from("one"), BTreeSet::from([1, 2])))]),
+    };
+
+    let second = TestStd {
+        test1: HashMap::from([
+            (String::from("a"), vec![Some(1), Some(2), Some(3), None]),
+            (String::from("c"), vec![Some(7)]),
+        ]),
+        test2: (
+            Box::new(Test {
+                test1: 4,
+                ..Default::default()
+            }),
+            [1, 5, 3],
+            'y',
+        ),
+        test3: BTreeMap::from([
+            (1, (String::from("uno"), BTreeSet::from([2, 3]))),
+            (2, (String::new(), BTreeSet::new())),
+        ]),
+    };
+
+    let diffs = first.diff(&second);
+    assert_eq!(diffs.len(), 3);
+
+    #[cfg(feature = "serde")]
+    {
+        let ser_diff = bincode::serialize(&diffs).unwrap();
+        let deser_diff = bincode::deserialize(&ser_diff).unwrap();
+        let diffed_serde = first.clone().apply(deser_diff);
+
+        assert_eq!(diffed_serde, second);
+    }
+
+    #[cfg(feature = "nanoserde")]
+    {
+        let ser = SerBin::serialize_bin(&diffs);
+        let diffed_nserde = first.clone().apply(DeBin::deserialize_bin(&ser).unwrap());
+
+        assert_eq!(diffed_nserde, second);
+    }
+
+    let diffed = first.clone().apply(diffs);
+    assert_eq!(diffed, second);
+
+    // std types can also be diffed directly, without a wrapping struct
+    let diffed = first.test1.apply_ref(first.test1.diff(&second.test1));
+    assert_eq!(diffed, second.test1);
+}
+
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "nanoserde", derive(SerBin, DeBin))]
+enum TestMode {
+    Fast,
+    Slow,
+}
+
+structdiff::impl_replace_diff!(TestMode);
+
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "nanoserde", derive(SerBin, DeBin))]
+struct TestOpaque<T>(T);
+
+impl<T: structdiff::DiffValueBound + PartialEq> StructDiff for TestOpaque<T> {
+    type Diff = structdiff::ReplaceDiff<T>;
+    type DiffRef<'target>
+        = structdiff::ReplaceDiffRef<'target, T>
+    where
+        Self: 'target;
+
+    fn diff(&self, updated: &Self) -> Vec<Self::Diff> {
+        self.diff_ref(updated).into_iter().map(Into::into).collect()
+    }
+
+    fn diff_ref<'target>(&'target self, updated: &'target Self) -> Vec<Self::DiffRef<'target>> {
+        match self.0 != updated.0 {
+            true => vec![structdiff::ReplaceDiffRef::new(&updated.0)],
+            false => Vec::new(),
+        }
+    }
+
+    fn apply_single(&mut self, diff: Self::Diff) {
+        self.0 = diff.into_inner();
+    }
+}
+
+#[cfg(feature = "nanoserde")]
+impl<T: structdiff::checked::DeBinChecked> structdiff::checked::DeBinChecked for TestOpaque<T> {
+    fn de_bin_checked(offset: &mut usize, bytes: &[u8]) -> Result<Self, nanoserde::DeBinErr> {
+        T::de_bin_checked(offset, bytes).map(TestOpaque)
+    }
+}
+
+#[test]
+fn test_manual_impls() {
+    #[derive(Debug, PartialEq, Clone, Difference)]
+    struct TestManual {
+        #[difference(recurse)]
+        test1: TestMode,
+        #[difference(recurse)]
+        test2: BTreeMap<u8, TestOpaque<Vec<String>>>,
+    }
+
+    let first = TestManual {
+        test1: TestMode::Fast,
+        test2: BTreeMap::from([(1, TestOpaque(vec![String::from("a")]))]),
+    };
+
+    let second = TestManual {
+        test1: TestMode::Slow,
+        test2: BTreeMap::from([(1, TestOpaque(vec![String::from("b")]))]),
+    };
+
+    let diffs = first.diff(&second);
+    assert_eq!(diffs.len(), 2);
+
+    #[cfg(feature = "serde")]
+    {
+        let ser_diff = bincode::serialize(&diffs).unwrap();
+        let deser_diff = bincode::deserialize(&ser_diff).unwrap();
+        let diffed_serde = first.clone().apply(deser_diff);
+
+        assert_eq!(diffed_serde, second);
+    }
+
+    #[cfg(feature = "nanoserde")]
+    {
+        let ser = SerBin::serialize_bin(&diffs);
+        let diffed_nserde = first.clone().apply(DeBin::deserialize_bin(&ser).unwrap());
+
+        assert_eq!(diffed_nserde, second);
+    }
+
+    let diffed = first.clone().apply(diffs);
+    assert_eq!(diffed, second);
+
+    nanoserde_ref_test!(first, second);
+}
+
+mod remote_crate {
+    #[derive(Debug, Clone, PartialEq, Default)]
+    pub struct Config {
+        pub name: String,
+        pub tags: Vec<String>,
+        pub limits: Option<(u32, u64)>,
+        retries: u32,
+    }
+
+    impl Config {
+        pub fn retries(&self) -> &u32 {
+            &self.retries
+        }
+
+        pub fn retries_mut(&mut self) -> &mut u32 {
+            &mut self.retries
+        }
+    }
+
+    #[derive(Debug, Clone, PartialEq, Default)]
+    pub struct Pair {
+        pub a: u32,
+        ab: u32,
+    }
+
+    impl Pair {
+        pub fn ab(&self) -> &u32 {
+            &self.ab
+        }
+
+        pub fn ab_mut(&mut self) -> &mut u32 {
+            &mut self.ab
+        }
+    }
+}
+
+#[derive(Difference)]
+#[difference(remote = "remote_crate::Config")]
+struct ConfigDef {
+    name: String,
+    #[difference(collection_strategy = "ordered_array_like")]
+    tags: Vec<String>,
+    #[difference(recurse)]
+    limits: Option<(u32, u64)>,
+    #[difference(getter = "remote_crate::Config::retries")]
+    #[difference(getter_mut = "remote_crate::Config::retries_mut")]
+    retries: u32,
+}
+
+// a field whose name is a prefix of a field with a getter
+#[derive(Difference)]
+#[difference(remote = "remote_crate::Pair")]
+struct PairDef {
+    a: u32,
+    #[difference(getter = "remote_crate::Pair::ab")]
+    #[difference(getter_mut = "remote_crate::Pair::ab_mut")]
+    ab: u32,
+}
+
+#[test]
+fn test_remote() {
+    use structdiff::StructDiffRemote;
+
+    #[derive(Debug, PartialEq, Clone, Difference)]
+    struct TestRemote {
+        test1: i32,
+        #[difference(remote = "ConfigDef")]
+        test2: remote_crate::Config,
+    }
+
+    let first = TestRemote {
+        test1: 0,
+        test2: remote_crate::Config::default(),
+    };
+
+    let mut second = TestRemote {
+        test1: 0,
+        test2: remote_crate::Config::default(),
+    };
+    second.test2.name = String::from("second");
+    second.test2.tags = vec![String::from("a"), String::from("b")];
+    second.test2.limits = Some((1, 2));
+    *second.test2.retries_mut() = 5;
+
+    assert_eq!(ConfigDef::diff(&first.test2, &second.test2).len(), 4);
+    assert!(ConfigDef::diff(&second.test2, &second.test2).is_empty());
+
+    let diffs = first.diff(&second);
+    assert_eq!(diffs.len(), 1);
+
+    #[cfg(feature = "serde")]
+    {
+        let ser_diff = bincode::serialize(&diffs).unwrap();
+        let deser_diff = bincode::deserialize(&ser_diff).unwrap();
+        let diffed_serde = first.clone().apply(deser_diff);
+
+        assert_eq!(diffed_serde, second);
+    }
+
+    #[cfg(feature = "nanoserde")]
+    {
+        let ser = SerBin::serialize_bin(&diffs);
+        let diffed_nserde = first.clone().apply(DeBin::deserialize_bin(&ser).unwrap());
+
+        assert_eq!(diffed_nserde, second);
+    }
+
+    let diffed = first.clone().apply(diffs);
+    assert_eq!(diffed, second);
+
+    let mut diffed_ref = first.clone();
+    diffed_ref.apply_ref_diffs(first.diff_ref(&second));
+    assert_eq!(diffed_ref, second);
+
+    let mut reverted = second.test2.clone();
+    ConfigDef::apply_ref_diffs(
+        &mut reverted,
+        ConfigDef::diff_ref(&second.test2, &first.test2),
+    );
+    assert_eq!(reverted, first.test2);
+
+    let mut streamed = first.test2.clone();
+    ConfigDef::diff_into(&first.test2, &second.test2, &mut |diff| {
+        ConfigDef::apply_single_ref(&mut streamed, diff)
+    });
+    assert_eq!(streamed, second.test2);
+
+    nanoserde_ref_test!(first, second);
+
+    let mut pair = remote_crate::Pair::default();
+    let mut updated = pair.clone();
+    updated.a = 1;
+    *updated.ab_mut() = 2;
+    let diffs = PairDef::diff(&pair, &updated);
+    assert_eq!(diffs.len(), 2);
+    PairDef::apply_mut(&mut pair, diffs);
+    assert_eq!((pair.a, *pair.ab()), (1, 2));
+}
+
+fn eq_ignore_case(a: &str, b: &str) -> bool {
+    a.eq_ignore_ascii_case(b)
+}
+
+fn same_second(a: &Option<u64>, b: &Option<u64>) -> bool {
+    a.map(|millis| millis / 1000) == b.map(|millis| millis / 1000)
+}
+
+/// Diffs a counter as the change in its value instead of the new value
+mod counter_delta {
+    #[cfg(feature = "nanoserde")]
+    use nanoserde::{DeBin, SerBin};
+
+    #[derive(Debug, Clone, PartialEq)]
+    #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+    #[cfg_attr(feature = "nanoserde", derive(SerBin, DeBin))]
+    pub struct Delta(pub i64);
+
+    pub type Diff = Delta;
+    pub type DiffRef<'a> = Delta;
+
+    pub fn diff(current: &i64, updated: &i64) -> Option<Diff> {
+        (current != updated).then(|| Delta(updated.wrapping_sub(*current)))
+    }
+
+    pub fn diff_ref<'a>(current: &'a i64, updated: &'a i64) -> Option<DiffRef<'a>> {
+        diff(current, updated)
+    }
+
+    pub fn apply(target: &mut i64, diff: Diff) {
+        *target = target.wrapping_add(diff.0);
+    }
+}
+
+#[test]
+fn test_custom_comparison() {
+    #[derive(Debug, PartialEq, Clone, Difference)]
+    #[difference(setters)]
+    struct TestCustom {
+        #[difference(float_epsilon = 1e-6)]
+        test1: f64,
+        #[difference(float_epsilon = 0.01)]
+        test2: f32,
+        #[difference(eq_with = "eq_ignore_case")]
+        test3: String,
+        #[difference(eq_with = "same_second")]
+        test4: Option<u64>,
+        #[difference(with = "counter_delta")]
+        test5: i64,
+    }
+
+    let first = TestCustom {
+        test1: 1.0,
+        test2: 1.0,
+        test3: String::from("Hello"),
+        test4: Some(1000),
+        test5: 10,
+    };
+
+    let close = TestCustom {
+        test1: 1.0 + 1e-7,
+        test2: 1.005,
+        test3: String::from("HELLO"),
+        test4: Some(1999),
+        test5: 10,
+    };
+    assert!(first.diff(&close).is_empty());
+    assert!(first.diff_ref(&close).is_empty());
+
+    let second = TestCustom {
+        test1: 1.1,
+        test2: f32::NAN,
+        test3: String::from("World"),
+        test4: None,
+        test5: -3,
+    };
+
+    let diffs = first.diff(&second);
+    assert_eq!(diffs.len(), 5);
+
+    let check = |diffed: TestCustom| {
+        assert_eq!(diffed.test1, second.test1);
+        assert!(diffed.test2.is_nan());
+        assert_eq!(diffed.test3, second.test3);
+        assert_eq!(diffed.test4, second.test4);
+        assert_eq!(diffed.test5, second.test5);
+    };
+
+    #[cfg(feature = "serde")]
+    {
+        let ser_diff = bincode::serialize(&diffs).unwrap();
+        let deser_diff = bincode::deserialize(&ser_diff).unwrap();
+        check(first.clone().apply(deser_diff));
+    }
+
+    #[cfg(feature = "nanoserde")]
+    {
+        let ser = SerBin::serialize_bin(&diffs);
+        check(first.clone().apply(DeBin::deserialize_bin(&ser).unwrap()));
+    }
+
+    check(first.clone().apply(diffs));
+    let ref_diffs: Vec<_> = first
+        .diff_ref(&second)
+        .into_iter()
+        .map(Into::into)
+        .collect();
+    check(first.clone().apply(ref_diffs));
+    nanoserde_ref_test!(first, second);
+
+    #[cfg(feature = "generated_setters")]
+    {
+        let mut set = first.clone();
+        assert!(set.set_test1_with_diff(close.test1).is_none());
+        assert!(set.set_test3_with_diff(close.test3.clone()).is_none());
+        assert!(set.set_test5_with_diff(close.test5).is_none());
+
+        let mut partial_diffs = vec![];
+        partial_diffs.extend(set.set_test1_with_diff(second.test1));
+        partial_diffs.extend(set.set_test2_with_diff(second.test2));
+        partial_diffs.extend(set.set_test3_with_diff(second.test3.clone()));
+        partial_diffs.extend(set.set_test4_with_diff(second.test4));
+        partial_diffs.extend(set.set_test5_with_diff(second.test5));
+        assert_eq!(partial_diffs.len(), 5);
+        check(first.clone().apply(partial_diffs));
+    }
+}
+
+/// Stand-in for a type which can't be serialized, snapshotted as its contents
+#[derive(Debug, Clone, Default)]
+struct Shared(std::sync::Arc<std::sync::Mutex<u32>>);
+
+impl PartialEq for Shared {
+    fn eq(&self, other: &Self) -> bool {
+        std::sync::Arc::ptr_eq(&self.0, &other.0)
+            || *self.0.lock().unwrap() == *other.0.lock().unwrap()
+    }
+}
+
+impl From<Shared> for u32 {
+    fn from(value: Shared) -> Self {
+        *value.0.lock().unwrap()
+    }
+}
+
+impl From<u32> for Shared {
+    fn from(value: u32) -> Self {
+        Shared(std::sync::Arc::new(std::sync::Mutex::new(value)))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "nanoserde", derive(SerBin, DeBin))]
+struct Millis(u64);
+
+#[cfg(feature = "nanoserde")]
+impl structdiff::checked::DeBinChecked for Millis {}
+
+impl From<std::time::Duration> for Millis {
+    fn from(value: std::time::Duration) -> Self {
+        Millis(value.as_millis() as u64)
+    }
+}
+
+impl From<Millis> for std::time::Duration {
+    fn from(value: Millis) -> Self {
+        std::time::Duration::from_millis(value.0)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "nanoserde", derive(SerBin, DeBin))]
+struct PathString(String);
+
+#[cfg(feature = "nanoserde")]
+impl structdiff::checked::DeBinChecked for PathString {
+    fn de_bin_checked(offset: &mut usize, bytes: &[u8]) -> Result<Self, nanoserde::DeBinErr> {
+        structdiff::checked::de_bin_string(offset, bytes).map(PathString)
+    }
+}
+
+impl From<std::path::PathBuf> for PathString {
+    fn from(value: std::path::PathBuf) -> Self {
+        PathString(value.to_string_lossy().into_owned())
+    }
+}
+
+impl From<PathString> for std::path::PathBuf {
+    fn from(value: PathString) -> Self {
+        value.0.into()
+    }
+}
+
+#[test]
+fn test_diff_as() {
+    use std::path::PathBuf;
+    use std::time::Duration;
+
+    #[derive(Debug, PartialEq, Clone, Difference)]
+    #[difference(setters)]
+    struct TestDiffAs {
+        #[difference(diff_as = "String")]
+        test1: Box<str>,
+        #[difference(diff_as = "Millis")]
+        test2: Duration,
+        #[difference(diff_as = "u32")]
+        test3: Shared,
+        #[difference(diff_as = "String")]
+        test4: Option<Box<str>>,
+        #[difference(diff_as = "PathString")]
+        test5: PathBuf,
+        test6: u8,
+    }
+
+    let first = TestDiffAs {
+        test1: "first".into(),
+        test2: Duration::from_millis(10),
+        test3: Shared::from(1),
+        test4: None,
+        test5: PathBuf::from("first"),
+        test6: 0,
+    };
+
+    let second = TestDiffAs {
+        test1: "second".into(),
+        test2: Duration::from_secs(3),
+        test3: Shared::from(2),
+        test4: Some("second".into()),
+        test5: PathBuf::from("second"),
+        test6: 1,
+    };
+
+    assert!(first.diff(&first.clone()).is_empty());
+    let diffs = first.diff(&second);
+    assert_eq!(diffs.len(), 6);
+
+    #[cfg(feature = "serde")]
+    {
+        let ser_diff = bincode::serialize(&diffs).unwrap();
+        let deser_diff = bincode::deserialize(&ser_diff).unwrap();
+        assert_eq!(first.clone().apply(deser_diff), second);
+    }
+
+    #[cfg(feature = "nanoserde")]
+    {
+        let ser = SerBin::serialize_bin(&diffs);
+        assert_eq!(
+            first.clone().apply(DeBin::deserialize_bin(&ser).unwrap()),
+            second
+        );
+    }
+
+    assert_eq!(first.clone().apply(diffs), second);
+    nanoserde_ref_test!(first, second);
+
+    #[cfg(feature = "generated_setters")]
+    {
+        let mut set = first.clone();
+        assert!(set.set_test2_with_diff(first.test2).is_none());
+
+        let mut partial_diffs = vec![];
+        partial_diffs.extend(set.set_test1_with_diff(second.test1.clone()));
+        partial_diffs.extend(set.set_test2_with_diff(second.test2));
+        partial_diffs.extend(set.set_test3_with_diff(second.test3.clone()));
+        partial_diffs.extend(set.set_test4_with_diff(second.test4.clone()));
+        partial_diffs.extend(set.set_test5_with_diff(second.test5.clone()));
+        partial_diffs.extend(set.set_test6_with_diff(second.test6));
+        assert_eq!(set, second);
+        assert_eq!(first.clone().apply(partial_diffs), second);
+    }
+}
+
+#[test]
+fn test_diff_as_only() {
+    #[derive(Debug, PartialEq, Clone, Difference)]
+    struct TestDiffAsOnly {
+        #[difference(diff_as = "u32")]
+        test1: Shared,
+    }
+
+    let first = TestDiffAsOnly {
+        test1: Shared::from(1),
+    };
+    let second = TestDiffAsOnly {
+        test1: Shared::from(2),
+    };
+
+    let diffs = first.diff(&second);
+    assert_eq!(diffs.len(), 1);
+    assert_eq!(first.clone().apply(diffs), second);
+    nanoserde_ref_test!(first, second);
+}
+
+#[cfg(feature = "serde")]
+fn ser_doubled<V: std::borrow::Borrow<u32>, S: serde::Serializer>(
+    value: &V,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    serializer.serialize_u32(value.borrow() * 2)
+}
+
+#[cfg(feature = "serde")]
+fn de_halved<'de, D: serde::Deserializer<'de>>(deserializer: D) -> Result<u32, D::Error> {
+    u32::deserialize(deserializer).map(|value| value / 2)
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn test_serde_attrs() {
+    #[derive(Debug, PartialEq, Clone, Difference)]
+    #[difference(serde(rename_all = "camelCase", tag = "field", content = "value"))]
+    struct TestSerdeAttrs {
+        field_one: u32,
+        #[difference(serde(rename = "renamed"))]
+        field_two: String,
+        #[difference(serde(serialize_with = "ser_doubled", deserialize_with = "de_halved"))]
+        field_three: u32,
+        #[difference(recurse, serde(rename = "maybe"))]
+        field_four: Option<u32>,
+    }
+
+    let first = TestSerdeAttrs {
+        field_one: 0,
+        field_two: String::new(),
+        field_three: 0,
+        field_four: None,
+    };
+    let second = TestSerdeAttrs {
+        field_one: 1,
+        field_two: String::from("two"),
+        field_three: 3,
+        field_four: Some(4),
+    };
+
+    let diffs = first.diff(&second);
+    let json = serde_json::to_string(&diffs).unwrap();
+    assert_eq!(
+        json,
+        concat!(
+            r#"[{"field":"fieldOne","value":1},"#,
+            r#"{"field":"renamed","value":"two"},"#,
+            r#"{"field":"fieldThree","value":6},"#,
+            r#"{"field":"maybe_full","value":4}]"#
+        )
+    );
+    assert_eq!(
+        serde_json::to_string(&first.diff_ref(&second)).unwrap(),
+        json
+    );
+
+    let deser_diff = serde_json::from_str(&json).unwrap();
+    assert_eq!(first.clone().apply(deser_diff), second);
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn test_serde_attrs_brackets() {
+    #[derive(Debug, PartialEq, Clone, Difference)]
+    struct TestSerdeBrackets {
+        #[difference(serde(rename = "a]b"))]
+        field_one: String,
+        #[difference(recurse, serde(rename = "(c"))]
+        field_two: Option<u32>,
+    }
+
+    let first = TestSerdeBrackets {
+        field_one: String::new(),
+        field_two: None,
+    };
+    let second = TestSerdeBrackets {
+        field_one: String::from("one"),
+        field_two: Some(2),
+    };
+
+    let json = serde_json::to_string(&first.diff(&second)).unwrap();
+    assert_eq!(json, r#"[{"a]b":"one"},{"(c_full":2}]"#);
+    let deser_diff = serde_json::from_str(&json).unwrap();
+    assert_eq!(first.clone().apply(deser_diff), second);
+
+    #[cfg(feature = "nanoserde")]
+    {
+        use structdiff::borrowed::deserialize_bin_borrowed;
+        use structdiff::StructDiffBorrowed;
+
+        let bytes = first.diff(&second).serialize_bin();
+        let deser_diff = structdiff::checked::deserialize_bin(&bytes).unwrap();
+        assert_eq!(first.clone().apply(deser_diff), second);
+        let mut borrowed = first.clone();
+        borrowed.apply_borrowed(deserialize_bin_borrowed(&bytes).unwrap());
+        assert_eq!(borrowed, second);
+    }
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn test_serde_attrs_enum() {
+    #[derive(Debug, PartialEq, Clone, Difference, Serialize, Deserialize)]
+    #[cfg_attr(feature = "nanoserde", derive(SerBin, DeBin))]
+    #[difference(serde(rename = "Diff", tag = "op"))]
+    enum TestSerdeAttrsEnum {
+        A { value: u32 },
+        B,
+    }
+
+    let first = TestSerdeAttrsEnum::B;
+    let second = TestSerdeAttrsEnum::A { value: 1 };
+
+    let json = serde_json::to_string(&first.diff(&second)).unwrap();
+    assert_eq!(json, r#"[{"op":"Replace","A":{"value":1}}]"#);
+    assert_eq!(
+        serde_json::to_string(&first.diff_ref(&second)).unwrap(),
+        json
+    );
+
+    let deser_diff = serde_json::from_str(&json).unwrap();
+    assert_eq!(first.apply(deser_diff), second);
+}
+
 #[test]
 fn test_collection_strategies() {
     #[derive(Debug, PartialEq, Clone, Difference, Default)]
-    struct TestCollection {
-        #[difference(collection_strategy = "unordered_array_like")]
-        test1: Vec<i32>,
-        #[difference(collection_strategy = "unordered_array_like")]
-        test2: HashSet<i32>,
+    struct TestCollection {
+        #[difference(collection_strategy = "unordered_array_like")]
+        test1: Vec<i32>,
+        #[difference(collection_strategy = "unordered_array_like")]
+        test2: HashSet<i32>,
+        #[difference(collection_strategy = "unordered_array_like")]
+        test3: LinkedList<i32>,
+    }
+
+    let first = TestCollection {
+        test1: vec![10, 15, 20, 25, 30],
+        test3: vec![10, 15, 17].into_iter().collect(),
+        ..Default::default()
+    };
+
+    let second = TestCollection {
+        test1: Vec::default(),
+        test2: vec![10].into_iter().collect(),
+        test3: vec![10, 15, 17, 19].into_iter().collect(),
+    };
+
+    let diffs = first.diff(&second);
+
+    #[cfg(feature = "serde")]
+    {
+        let ser_diff = bincode::serialize(&diffs).unwrap();
+        let deser_diff = bincode::deserialize(&ser_diff).unwrap();
+        let diffed_serde = first.clone().apply(deser_diff);
+
+        use assert_unordered::assert_eq_unordered;
+        assert_eq_unordered!(&diffed_serde.test1, &second.test1);
+        assert_eq_unordered!(&diffed_serde.test2, &second.test2);
+        assert_eq_unordered!(&diffed_serde.test3, &second.test3);
+    }
+
+    #[cfg(feature = "nanoserde")]
+    {
+        let ser = SerBin::serialize_bin(&diffs);
+        let diffed_nserde = first.clone().apply(DeBin::deserialize_bin(&ser).unwrap());
+
+        use assert_unordered::assert_eq_unordered;
+        assert_eq_unordered!(&diffed_nserde.test1, &second.test1);
+        assert_eq_unordered!(&diffed_nserde.test2, &second.test2);
+        assert_eq_unordered!(&diffed_nserde.test3, &second.test3);
+    }
+
+    let diffed = first.clone().apply(diffs);
+
+    use assert_unordered::assert_eq_unordered;
+    assert_eq_unordered!(&diffed.test1, &second.test1);
+    assert_eq_unordered!(&diffed.test2, &second.test2);
+    assert_eq_unordered!(&diffed.test3, &second.test3);
+
+    nanoserde_ref_test!(first, second);
+}
+
+#[test]
+fn test_text_strategy() {
+    #[derive(Debug, PartialEq, Clone, Difference, Default)]
+    struct TestText {
+        #[difference(collection_strategy = "text")]
+        test1: String,
+        #[difference(collection_strategy = "text", text_granularity = "lines")]
+        test2: String,
+        #[difference(collection_strategy = "text", text_granularity = "bytes")]
+        test3: std::borrow::Cow<'static, str>,
+    }
+
+    let first = TestText {
+        test1: String::from("kitten"),
+        test2: String::from("first line\nsecond line\nthird line\n"),
+        test3: "naïve café".into(),
+    };
+
+    let second = TestText {
+        test1: String::from("sitting"),
+        test2: String::from("first line\n2nd line\nthird line\n"),
+        test3: "naive cafè".into(),
+    };
+
+    let diffs = first.diff(&second);
+
+    #[cfg(feature = "serde")]
+    {
+        let ser_diff = bincode::serialize(&diffs).unwrap();
+        let deser_diff = bincode::deserialize(&ser_diff).unwrap();
+        let diffed_serde = first.clone().apply(deser_diff);
+
+        assert_eq!(diffed_serde, second);
+    }
+
+    #[cfg(feature = "nanoserde")]
+    {
+        let ser = SerBin::serialize_bin(&diffs);
+        let diffed_nserde = first.clone().apply(DeBin::deserialize_bin(&ser).unwrap());
+
+        assert_eq!(diffed_nserde, second);
+    }
+
+    let diffed = first.clone().apply(diffs);
+    assert_eq!(diffed, second);
+
+    nanoserde_ref_test!(first, second);
+}
+
+#[test]
+fn test_binary_delta_strategy() {
+    #[derive(Debug, PartialEq, Clone, Difference, Default)]
+    struct TestBlob {
+        #[difference(collection_strategy = "binary_delta")]
+        test1: Vec<u8>,
+        #[difference(collection_strategy = "binary_delta")]
+        test2: Box<[u8]>,
+    }
+
+    let first = TestBlob {
+        test1: (0..=u8::MAX).cycle().take(8192).collect(),
+        test2: vec![1, 2, 3].into_boxed_slice(),
+    };
+
+    let mut second = first.clone();
+    second.test1.splice(4000..4010, [7; 32]);
+    second.test2 = vec![3, 2, 1].into_boxed_slice();
+
+    let diffs = first.diff(&second);
+
+    #[cfg(feature = "serde")]
+    {
+        let ser_diff = bincode::serialize(&diffs).unwrap();
+        let deser_diff = bincode::deserialize(&ser_diff).unwrap();
+        let diffed_serde = first.clone().apply(deser_diff);
+
+        assert_eq!(diffed_serde, second);
+    }
+
+    #[cfg(feature = "nanoserde")]
+    {
+        let ser = SerBin::serialize_bin(&diffs);
+        let diffed_nserde = first.clone().apply(DeBin::deserialize_bin(&ser).unwrap());
+
+        assert_eq!(diffed_nserde, second);
+    }
+
+    let diffed = first.clone().apply(diffs);
+    assert_eq!(diffed, second);
+
+    nanoserde_ref_test!(first, second);
+}
+
+#[test]
+fn test_key_value() {
+    #[derive(Debug, PartialEq, Clone, Difference, Default)]
+    struct TestCollection {
+        #[difference(
+            collection_strategy = "unordered_map_like",
+            map_equality = "key_and_value"
+        )]
+        test1: HashMap<i32, i32>,
+    }
+
+    let first = TestCollection {
+        test1: vec![(10, 0), (15, 2), (20, 0), (25, 0), (30, 15)]
+            .into_iter()
+            .collect(),
+    };
+
+    let second = TestCollection {
+        test1: vec![(10, 21), (15, 2), (20, 0), (25, 0), (30, 15)]
+            .into_iter()
+            .collect(),
+    };
+
+    let diffs = first.diff(&second);
+
+    #[cfg(feature = "serde")]
+    {
+        let ser_diff = bincode::serialize(&diffs).unwrap();
+        let deser_diff = bincode::deserialize(&ser_diff).unwrap();
+        let diffed_serde = first.clone().apply(deser_diff);
+
+        use assert_unordered::assert_eq_unordered;
+        assert_eq_unordered!(&diffed_serde.test1, &second.test1);
+    }
+
+    #[cfg(feature = "nanoserde")]
+    {
+        let ser = SerBin::serialize_bin(&diffs);
+        let diffed_serde = first.clone().apply(DeBin::deserialize_bin(&ser).unwrap());
+
+        use assert_unordered::assert_eq_unordered;
+        assert_eq_unordered!(&diffed_serde.test1, &second.test1);
+    }
+
+    let diffed = first.clone().apply(diffs);
+
+    use assert_unordered::assert_eq_unordered;
+    assert_eq_unordered!(&diffed.test1, &second.test1);
+
+    nanoserde_ref_test!(first, second);
+}
+
+#[test]
+fn test_apply_ref_diffs() {
+    #[derive(Debug, PartialEq, Clone, Difference, Default)]
+    struct TestApplyRef {
+        test1: String,
+        test2: Option<u32>,
+        #[difference(collection_strategy = "ordered_array_like")]
+        test3: Vec<u32>,
+        #[difference(collection_strategy = "ordered_array_like")]
+        test4: LinkedList<String>,
+        #[difference(collection_strategy = "text", text_granularity = "words")]
+        test5: String,
+        #[difference(collection_strategy = "binary_delta")]
+        test6: Vec<u8>,
+        #[difference(collection_strategy = "unordered_array_like")]
+        test7: HashSet<i32>,
+        #[difference(collection_strategy = "unordered_map_like")]
+        test8: BTreeMap<u8, String>,
+        #[difference(recurse)]
+        test9: Vec<i32>,
+        #[difference(recurse)]
+        test10: Option<Test>,
+        #[difference(with = "counter_delta")]
+        test11: i64,
+        #[difference(diff_as = "String")]
+        test12: Box<str>,
+    }
+
+    let first = TestApplyRef {
+        test1: String::from("first"),
+        test3: vec![1, 2, 3, 4],
+        test4: ["a", "b", "c"].into_iter().map(String::from).collect(),
+        test5: String::from("the quick brown fox"),
+        test6: (0..=u8::MAX).cycle().take(4096).collect(),
+        test7: [1, 2, 3].into_iter().collect(),
+        test8: [(1, String::from("one")), (2, String::from("two"))]
+            .into_iter()
+            .collect(),
+        test9: vec![5, 6],
+        test10: Some(Test::next()),
+        test11: 10,
+        ..Default::default()
+    };
+
+    let second = TestApplyRef {
+        test1: String::from("second"),
+        test2: Some(7),
+        test3: vec![2, 3, 5],
+        test4: ["a", "z", "c", "d"].into_iter().map(String::from).collect(),
+        test5: String::from("the slow brown dog"),
+        test6: (0..=u8::MAX).cycle().skip(3).take(4200).collect(),
+        test7: [2, 3, 4].into_iter().collect(),
+        test8: [(2, String::from("deux")), (3, String::from("three"))]
+            .into_iter()
+            .collect(),
+        test9: vec![6, 7, 8],
+        test10: Some(Test::next()),
+        test11: -3,
+        test12: "proxied".into(),
+    };
+
+    let mut diffed = first.clone();
+    diffed.apply_ref_diffs(first.diff_ref(&second));
+    assert_eq!(diffed, second);
+
+    let mut reverted = second.clone();
+    reverted.apply_ref_diffs(second.diff_ref(&first));
+    assert_eq!(reverted, first);
+
+    // replacing a None and removing a Some
+    let third = TestApplyRef {
+        test10: None,
+        ..second.clone()
+    };
+    let mut diffed = first.clone();
+    diffed.test10 = None;
+    diffed.apply_ref_diffs(diffed.clone().diff_ref(&second));
+    assert_eq!(diffed, second);
+    diffed.apply_ref_diffs(second.diff_ref(&third));
+    assert_eq!(diffed, third);
+
+    use types::TestSetters;
+    for _ in 0..20 {
+        let (start, end) = (TestSetters::next(), TestSetters::next());
+        let mut applied_ref = start.clone();
+        applied_ref.apply_ref_diffs(start.diff_ref(&end));
+        let applied = start.clone().apply(start.diff(&end));
+        assert_eq!(applied_ref.f0, applied.f0);
+        assert_eq!(applied_ref.f1, applied.f1);
+        assert_eq!(applied_ref.f2, applied.f2);
+        assert_eq!(applied_ref.f3, applied.f3);
+        assert_eq_unordered_sort!(applied_ref.f4, end.f4);
+        assert_eq!(applied_ref.f5, end.f5);
+        assert_eq!(applied_ref.f6, end.f6);
+    }
+}
+
+#[cfg(any(feature = "serde", feature = "nanoserde"))]
+#[test]
+fn test_apply_borrowed_diffs() {
+    use structdiff::StructDiffBorrowed;
+
+    #[derive(Debug, PartialEq, Clone, Difference, Default)]
+    #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+    struct TestBorrowed {
+        test1: String,
+        test2: Vec<u8>,
+        test3: Option<u32>,
+        #[difference(collection_strategy = "text", text_granularity = "words")]
+        test4: String,
+        #[difference(collection_strategy = "binary_delta")]
+        test5: Vec<u8>,
+        #[difference(recurse)]
+        test6: Option<Test>,
+        #[difference(collection_strategy = "ordered_array_like")]
+        test7: Vec<String>,
+    }
+
+    type DiffBorrowed<'de> = <TestBorrowed as StructDiffBorrowed>::DiffBorrowed<'de>;
+
+    let first = TestBorrowed {
+        test1: String::from("first"),
+        test2: vec![1, 2, 3],
+        test4: String::from("the quick brown fox"),
+        test5: (0..=u8::MAX).cycle().take(4096).collect(),
+        test6: Some(Test::next()),
+        test7: vec![String::from("a"), String::from("b")],
+        ..Default::default()
+    };
+
+    let second = TestBorrowed {
+        test1: String::from("second \"quoted\""),
+        test2: vec![4, 5, 6, 7],
+        test3: Some(3),
+        test4: String::from("the slow brown dog"),
+        test5: (0..=u8::MAX).cycle().skip(3).take(4200).collect(),
+        test6: None,
+        test7: vec![String::from("b"), String::from("c")],
+    };
+
+    #[cfg(feature = "serde")]
+    {
+        let buffer = bincode::serialize(&first.diff_ref(&second)).unwrap();
+        let diffs: Vec<DiffBorrowed<'_>> = bincode::deserialize(&buffer).unwrap();
+        for diff in diffs.iter() {
+            match diff {
+                DiffBorrowed::test1(value) => assert!(value.is_borrowed()),
+                DiffBorrowed::test2(value) => assert!(value.is_borrowed()),
+                _ => (),
+            }
+        }
+        let mut diffed = first.clone();
+        diffed.apply_borrowed(diffs);
+        assert_eq!(diffed, second);
+
+        // self-describing formats fall back to owned values where they must
+        let buffer = serde_json::to_string(&second.diff(&first)).unwrap();
+        let diffs: Vec<DiffBorrowed<'_>> = serde_json::from_str(&buffer).unwrap();
+        let mut reverted = second.clone();
+        reverted.apply_borrowed(diffs);
+        assert_eq!(reverted, first);
+    }
+
+    #[cfg(feature = "nanoserde")]
+    {
+        let buffer = SerBin::serialize_bin(&first.diff_ref(&second));
+        let diffs: Vec<DiffBorrowed<'_>> =
+            structdiff::borrowed::deserialize_bin_borrowed(&buffer).unwrap();
+        let mut diffed = first.clone();
+        diffed.apply_borrowed(diffs);
+        assert_eq!(diffed, second);
+
+        let buffer = SerBin::serialize_bin(&second.diff(&first));
+        let diffs: Vec<DiffBorrowed<'_>> =
+            structdiff::borrowed::deserialize_bin_borrowed(&buffer).unwrap();
+        let mut reverted = second.clone();
+        reverted.apply_borrowed(diffs);
+        assert_eq!(reverted, first);
+
+        let truncated = &buffer[..buffer.len() - 1];
+        assert!(
+            structdiff::borrowed::deserialize_bin_borrowed::<DiffBorrowed<'_>>(truncated).is_err()
+        );
+    }
+
+    // borrowed diffs convert into the same owned diffs
+    for _ in 0..20 {
+        let (start, end) = (Test::next(), Test::next());
+        #[cfg(feature = "serde")]
+        let applied = {
+            let buffer = bincode::serialize(&start.diff_ref(&end)).unwrap();
+            let diffs: Vec<<Test as StructDiffBorrowed>::DiffBorrowed<'_>> =
+                bincode::deserialize(&buffer).unwrap();
+            start
+                .clone()
+                .apply(diffs.into_iter().map(Into::into).collect())
+        };
+        #[cfg(not(feature = "serde"))]
+        let applied = {
+            let buffer = SerBin::serialize_bin(&start.diff_ref(&end));
+            let diffs: Vec<<Test as StructDiffBorrowed>::DiffBorrowed<'_>> =
+                structdiff::borrowed::deserialize_bin_borrowed(&buffer).unwrap();
+            start
+                .clone()
+                .apply(diffs.into_iter().map(Into::into).collect())
+        };
+        assert_eq!(applied, end);
+    }
+}
+
+#[test]
+fn test_diff_into() {
+    #[derive(Debug, PartialEq, Clone, Difference, Default)]
+    struct TestStream {
+        test1: String,
+        test2: Option<u32>,
+        #[difference(collection_strategy = "ordered_array_like")]
+        test3: Vec<u32>,
+        #[difference(collection_strategy = "text")]
+        test4: String,
+        #[difference(recurse)]
+        test5: Option<Test>,
+        #[difference(recurse)]
+        test6: (u8, String, [i32; 3]),
+        #[difference(recurse)]
+        test7: Box<Test>,
+    }
+
+    for _ in 0..20 {
+        let first = TestStream {
+            test1: String::from("first"),
+            test3: vec![1, 2, 3],
+            test4: String::from("some text"),
+            test5: Some(Test::next()),
+            test6: (1, String::from("a"), [1, 2, 3]),
+            test7: Box::new(Test::next()),
+            ..Default::default()
+        };
+        let second = TestStream {
+            test1: String::from("second"),
+            test2: Some(2),
+            test3: vec![3, 2, 1],
+            test4: String::from("other text"),
+            test5: None,
+            test6: (1, String::from("b"), [1, 5, 3]),
+            test7: Box::new(Test::next()),
+        };
+
+        let mut streamed = first.clone();
+        let mut count = 0;
+        first.diff_into(&second, &mut |diff| {
+            count += 1;
+            streamed.apply_single_ref(diff);
+        });
+        assert_eq!(streamed, second);
+        assert_eq!(count, first.diff(&second).len());
+
+        let mut collected = Vec::new();
+        second.diff_into(&first, &mut collected);
+        let mut reverted = second.clone();
+        reverted.apply_ref_diffs(collected);
+        assert_eq!(reverted, first);
+
+        let mut unchanged = Vec::new();
+        first.diff_into(&first, &mut unchanged);
+        assert!(unchanged.is_empty());
+
+        #[cfg(feature = "nanoserde")]
+        {
+            let mut output = Vec::new();
+            first.diff_into(&second, &mut structdiff::sink::SerBinSink::new(&mut output));
+            assert_eq!(output, SerBin::serialize_bin(&first.diff(&second)));
+        }
+
+        #[cfg(feature = "serde")]
+        {
+            use serde::Serializer;
+
+            let mut output = Vec::new();
+            let mut serializer = serde_json::Serializer::new(&mut output);
+            let mut sink =
+                structdiff::sink::SerializeSeqSink::new(serializer.serialize_seq(None).unwrap());
+            first.diff_into(&second, &mut sink);
+            sink.finish().unwrap();
+            assert_eq!(output, serde_json::to_vec(&first.diff(&second)).unwrap());
+        }
+    }
+
+    // the standard library impls stream their elements' diffs as well
+    let (first, second) = ([1u8, 2, 3], [1u8, 4, 5]);
+    let mut diffs = Vec::new();
+    first.diff_into(&second, &mut diffs);
+    assert_eq!(diffs.len(), 2);
+    assert_eq!(
+        first.apply(diffs.into_iter().map(Into::into).collect()),
+        second
+    );
+}
+
+#[test]
+fn test_diff_into_chunks() {
+    use structdiff::{collections::CHANGES_PER_DIFF, StructDiffFields};
+
+    #[derive(Debug, PartialEq, Clone, Difference, Default)]
+    struct TestChunks {
+        #[difference(collection_strategy = "ordered_array_like")]
+        test1: Vec<u32>,
+        #[difference(collection_strategy = "unordered_array_like")]
+        test2: HashSet<u32>,
+        #[difference(
+            collection_strategy = "unordered_map_like",
+            map_equality = "key_and_value"
+        )]
+        test3: HashMap<u32, u32>,
+        #[difference(collection_strategy = "text")]
+        test4: String,
+        #[difference(collection_strategy = "ordered_array_like")]
+        test5: Vec<u32>,
+    }
+
+    let len = 3 * CHANGES_PER_DIFF as u32;
+    let first = TestChunks {
+        test1: (0..len).collect(),
+        test2: (0..len).collect(),
+        test3: (0..len).map(|x| (x, x)).collect(),
+        test4: "a".repeat(len as usize),
+        test5: vec![1, 2, 3],
+    };
+    // every other item changes, so that no changes can be merged
+    let second = TestChunks {
+        test1: (0..len).map(|x| x + (x % 2) * len).collect(),
+        test2: (0..len).map(|x| x + (x % 2) * len).collect(),
+        test3: (0..len).map(|x| (x, x + (x % 2) * len)).collect(),
+        test4: (0..len)
+            .map(|x| if x % 2 == 0 { 'a' } else { 'b' })
+            .collect(),
+        test5: vec![3, 2, 1],
+    };
+
+    let mut streamed = first.clone();
+    let mut fields = Vec::new();
+    first.diff_into(&second, &mut |diff| {
+        let before = streamed.clone();
+        streamed.apply_single_ref(diff);
+        fields.extend(before.changed_fields(&streamed).iter());
+    });
+    assert_eq!(streamed, second);
+    // diff and diff_ref are not chunked
+    assert_eq!(first.diff(&second).len(), 5);
+    assert_eq!(first.diff_ref(&second).len(), 5);
+    // each collection's changes are split across several diffs, while small ones still
+    // produce one. Text diffs are limited by their edit distance search.
+    for (field, expected) in [
+        ("test1", 2),
+        ("test2", 3),
+        ("test3", 3),
+        ("test4", 1),
+        ("test5", 1),
+    ] {
+        assert_eq!(
+            fields.iter().filter(|name| **name == field).count(),
+            expected
+        );
+    }
+
+    assert_eq!(first.clone().apply(first.diff(&second)), second);
+    assert_eq!(second.clone().apply(second.diff(&first)), first);
+}
+
+#[test]
+fn test_has_diff() {
+    use structdiff::StructDiffFields;
+
+    #[derive(Debug, PartialEq, Clone, Difference, Default)]
+    struct TestChanges {
+        test1: String,
+        #[difference(skip)]
+        test2: u32,
+        #[difference(float_epsilon = 0.01)]
+        test3: f64,
         #[difference(collection_strategy = "unordered_array_like")]
-        test3: LinkedList<i32>,
+        test4: Vec<u32>,
+        #[difference(collection_strategy = "unordered_map_like", map_equality = "key_only")]
+        test5: HashMap<u8, u32>,
+        #[difference(recurse)]
+        test6: Option<Test>,
+        #[difference(collection_strategy = "text")]
+        test7: String,
     }
 
-    let first = TestCollection {
-        test1: vec![10, 15, 20, 25, 30],
-        test3: vec![10, 15, 17].into_iter().collect(),
+    assert_eq!(
+        TestChanges::FIELDS,
+        ["test1", "test3", "test4", "test5", "test6", "test7"]
+    );
+
+    let first = TestChanges {
+        test1: String::from("first"),
+        test4: vec![1, 2, 3],
+        test5: HashMap::from([(1, 1), (2, 2)]),
+        test6: Some(Test::next()),
         ..Default::default()
     };
 
-    let second = TestCollection {
-        test1: Vec::default(),
-        test2: vec![10].into_iter().collect(),
-        test3: vec![10, 15, 17, 19].into_iter().collect(),
+    // only skipped fields and changes the strategies ignore
+    let second = TestChanges {
+        test2: 5,
+        test3: 0.001,
+        test4: vec![3, 2, 1],
+        ..first.clone()
     };
+    assert_ne!(first, second);
+    assert!(!first.has_diff(&second));
+    assert!(first.changed_fields(&second).is_empty());
+    assert!(first.diff(&second).is_empty());
+
+    let third = TestChanges {
+        test1: String::from("third"),
+        test4: vec![1, 2],
+        test5: HashMap::from([(1, 10), (2, 20)]),
+        test6: None,
+        test7: String::from("text"),
+        ..second.clone()
+    };
+    assert!(first.has_diff(&third));
+    let changed = first.changed_fields(&third);
+    assert_eq!(changed.len(), 5);
+    assert_eq!(
+        changed.iter().collect::<Vec<_>>(),
+        ["test1", "test4", "test5", "test6", "test7"]
+    );
+    assert!(changed.contains("test4"));
+    assert!(!changed.contains("test2"));
+    assert_eq!(changed.len(), first.diff(&third).len());
 
-    let diffs = first.diff(&second);
+    for _ in 0..100 {
+        let (first, second) = (Test::next(), Test::next());
+        assert_eq!(first.has_diff(&second), !first.diff(&second).is_empty());
+        assert!(!first.has_diff(&first.clone()));
+        assert_eq!(
+            first.changed_fields(&second).len(),
+            first.diff(&second).len()
+        );
+    }
+}
+
+#[test]
+fn test_tracked() {
+    use structdiff::Tracked;
+
+    for _ in 0..20 {
+        let start = Test::next();
+        let mut tracked = Tracked::new(start.clone());
+        let mut replica = start.clone();
+        assert!(!tracked.is_dirty());
+        assert!(tracked.take_diffs().is_empty());
+
+        let end = Test::next();
+        tracked.modify(|value| value.test1 = end.test1);
+        tracked.test2.clone_from(&end.test2);
+        *tracked = end.clone();
+        assert_eq!(tracked.is_dirty(), start != end);
+        assert_eq!(tracked.pending_diffs_ref().len(), start.diff(&end).len());
+
+        replica.apply_mut(tracked.take_diffs());
+        assert_eq!(replica, end);
+        assert_eq!(tracked.baseline(), &end);
+        assert!(!tracked.is_dirty());
+
+        tracked.modify(|value| *value = start.clone());
+        tracked.rollback();
+        assert_eq!(&*tracked, &end);
+        assert!(tracked.pending_diffs().is_empty());
+
+        *tracked = start.clone();
+        tracked.commit();
+        assert!(tracked.take_diffs().is_empty());
+        assert_eq!(tracked.into_inner(), start);
+    }
+}
+
+#[test]
+fn test_history() {
+    use structdiff::history::History;
+
+    let mut states = vec![Test::next()];
+    let mut history = History::with_checkpoint_interval(states[0].clone(), 4);
+    for timestamp in 1..=30 {
+        let next = Test::next();
+        match history.record_state(timestamp * 10, &next) {
+            Some(seq) => {
+                assert_eq!(seq, states.len() as u64);
+                states.push(next);
+            }
+            None => assert!(!states.last().unwrap().has_diff(&next)),
+        }
+    }
+    history.record(310, vec![]);
+    states.push(history.current().clone());
+
+    let latest = history.latest_seq();
+    assert_eq!(latest as usize, states.len() - 1);
+    for (seq, state) in states.iter().enumerate() {
+        assert_eq!(history.state_at(seq as u64).as_ref(), Some(state));
+    }
+    assert!(history.state_at(latest + 1).is_none());
+    assert_eq!(history.seq_at_time(0), 0);
+    assert_eq!(history.seq_at_time(15), 1);
+    assert_eq!(&history.state_at_time(u64::MAX), history.current());
 
     #[cfg(feature = "serde")]
     {
-        let ser_diff = bincode::serialize(&diffs).unwrap();
-        let deser_diff = bincode::deserialize(&ser_diff).unwrap();
-        let diffed_serde = first.clone().apply(deser_diff);
-
-        use assert_unordered::assert_eq_unordered;
-        assert_eq_unordered!(&diffed_serde.test1, &second.test1);
-        assert_eq_unordered!(&diffed_serde.test2, &second.test2);
-        assert_eq_unordered!(&diffed_serde.test3, &second.test3);
+        let serialized = serde_json::to_string(&history).unwrap();
+        let deserialized: History<Test> = serde_json::from_str(&serialized).unwrap();
+        for (seq, state) in states.iter().enumerate() {
+            assert_eq!(deserialized.state_at(seq as u64).as_ref(), Some(state));
+        }
     }
 
     #[cfg(feature = "nanoserde")]
     {
-        let ser = SerBin::serialize_bin(&diffs);
-        let diffed_nserde = first.clone().apply(DeBin::deserialize_bin(&ser).unwrap());
+        let deserialized: History<Test> =
+            DeBin::deserialize_bin(&SerBin::serialize_bin(&history)).unwrap();
+        assert_eq!(deserialized.latest_seq(), latest);
+        assert_eq!(deserialized.current(), history.current());
+    }
 
-        use assert_unordered::assert_eq_unordered;
-        assert_eq_unordered!(&diffed_nserde.test1, &second.test1);
-        assert_eq_unordered!(&diffed_nserde.test2, &second.test2);
-        assert_eq_unordered!(&diffed_nserde.test3, &second.test3);
+    history.compact(10);
+    assert_eq!(history.base_seq(), 10);
+    assert_eq!(history.base(), &states[10]);
+    assert_eq!(history.entries().len() as u64, latest - 10);
+    assert!(history.state_at(9).is_none());
+    for (seq, state) in states.iter().enumerate().skip(10) {
+        assert_eq!(history.state_at(seq as u64).as_ref(), Some(state));
     }
 
-    let diffed = first.clone().apply(diffs);
+    // entries numbered out of order are rejected
+    let mut entries = history.entries().to_vec();
+    entries.swap(0, 1);
+    assert!(History::from_parts(history.base().clone(), 10, entries, 4).is_none());
 
-    use assert_unordered::assert_eq_unordered;
-    assert_eq_unordered!(&diffed.test1, &second.test1);
-    assert_eq_unordered!(&diffed.test2, &second.test2);
-    assert_eq_unordered!(&diffed.test3, &second.test3);
+    history.compact(u64::MAX);
+    assert_eq!(history.base_seq(), latest);
+    assert_eq!(history.base(), history.current());
+}
 
-    nanoserde_ref_test!(first, second);
+#[derive(Debug, PartialEq, Clone, Default, Difference)]
+#[difference(verify_base)]
+struct TestVerified<A> {
+    id: A,
+    #[difference(collection_strategy = "ordered_array_like")]
+    items: Vec<u32>,
+    #[difference(collection_strategy = "unordered_map_like")]
+    tags: BTreeMap<String, u32>,
+    #[difference(recurse)]
+    child: Option<TestVerifiedChild>,
+    #[difference(skip)]
+    scratch: f64,
 }
 
-#[test]
-fn test_key_value() {
-    #[derive(Debug, PartialEq, Clone, Difference, Default)]
-    struct TestCollection {
-        #[difference(
-            collection_strategy = "unordered_map_like",
-            map_equality = "key_and_value"
-        )]
-        test1: HashMap<i32, i32>,
-    }
+#[derive(Debug, PartialEq, Clone, Default, Hash, Difference)]
+#[difference(de_bin_checked)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "nanoserde", derive(SerBin, DeBin))]
+struct TestVerifiedChild {
+    name: String,
+}
 
-    let first = TestCollection {
-        test1: vec![(10, 0), (15, 2), (20, 0), (25, 0), (30, 15)]
-            .into_iter()
-            .collect(),
+#[test]
+fn test_verify_base() {
+    use structdiff::{
+        verify::{fingerprint, BaseMismatch, StableHasher, VerifiedDiff},
+        StructDiffVerified,
     };
 
-    let second = TestCollection {
-        test1: vec![(10, 21), (15, 2), (20, 0), (25, 0), (30, 15)]
-            .into_iter()
-            .collect(),
+    // fingerprints do not depend on the process or platform
+    assert_eq!(fingerprint(&0u64), fingerprint(&0usize));
+    assert_eq!(
+        std::hash::Hasher::finish(&StableHasher::default()),
+        0xcbf2_9ce4_8422_2325
+    );
+    assert_eq!(fingerprint(&0x0102_0304u32), 0xb345_225e_3644_edb5);
+    assert_eq!(fingerprint(&-2i16), 0x0a9d_c907_b6fa_303a);
+    assert_eq!(fingerprint(&(1u128 << 100)), 0x8875_5f33_fda3_9b55);
+    assert_eq!(fingerprint("abc"), 0xfc18_2483_ee08_06dc);
+    assert_eq!(fingerprint(&vec![1u16, 2]), 0x9eed_4012_7c71_5314);
+
+    let base = TestVerified {
+        id: 1u8,
+        items: vec![1, 2, 3, 4],
+        tags: [(String::from("a"), 1)].into_iter().collect(),
+        child: None,
+        scratch: 0.0,
     };
-
-    let diffs = first.diff(&second);
+    let mut updated = base.clone();
+    updated.items.remove(1);
+    updated.child = Some(TestVerifiedChild {
+        name: String::from("child"),
+    });
+
+    let diff = base.diff_verified(&updated);
+    assert_eq!(diff.diffs.len(), 2);
+    assert_eq!(diff.base, base.base_fingerprint(&diff.diffs));
+
+    // fields the diffs don't modify, and skipped fields, may differ
+    let mut unrelated = base.clone();
+    unrelated.id = 2;
+    unrelated.tags.insert(String::from("b"), 2);
+    unrelated.scratch = 1.0;
+    let applied = unrelated.try_apply(diff.clone()).unwrap();
+    assert_eq!(applied.items, updated.items);
+    assert_eq!(applied.child, updated.child);
+    assert_eq!(applied.tags.len(), 2);
+
+    // a modified field is rejected, and left unchanged
+    let mut stale = base.clone();
+    stale.items.insert(0, 0);
+    let before = stale.clone();
+    let Err(BaseMismatch { expected, found }) = stale.try_apply_mut(diff.clone()) else {
+        panic!("diff applied to the wrong base");
+    };
+    assert_eq!(expected, diff.base);
+    assert_ne!(found, expected);
+    assert_eq!(stale, before);
+
+    // a diff against the recursed field's `Option` wrapper is still covered
+    let mut child_changed = updated.clone();
+    child_changed.child.as_mut().unwrap().name.push('!');
+    let diff_child = updated.diff_verified(&child_changed);
+    assert!(base.clone().try_apply(diff_child.clone()).is_err());
+    assert_eq!(
+        updated.clone().try_apply(diff_child).unwrap(),
+        child_changed
+    );
+
+    let empty = base.diff_verified(&base);
+    assert_eq!(empty.base, base.base_fingerprint(&[]));
+    assert_eq!(updated.clone().try_apply(empty).unwrap(), updated);
 
     #[cfg(feature = "serde")]
     {
-        let ser_diff = bincode::serialize(&diffs).unwrap();
-        let deser_diff = bincode::deserialize(&ser_diff).unwrap();
-        let diffed_serde = first.clone().apply(deser_diff);
-
-        use assert_unordered::assert_eq_unordered;
-        assert_eq_unordered!(&diffed_serde.test1, &second.test1);
+        let deserialized: VerifiedDiff<_> =
+            bincode::deserialize(&bincode::serialize(&diff).unwrap()).unwrap();
+        assert_eq!(base.clone().try_apply(deserialized).unwrap(), updated);
     }
 
     #[cfg(feature = "nanoserde")]
     {
-        let ser = SerBin::serialize_bin(&diffs);
-        let diffed_serde = first.clone().apply(DeBin::deserialize_bin(&ser).unwrap());
+        let deserialized: VerifiedDiff<_> =
+            DeBin::deserialize_bin(&SerBin::serialize_bin(&diff)).unwrap();
+        assert_eq!(base.clone().try_apply(deserialized).unwrap(), updated);
+    }
+}
 
-        use assert_unordered::assert_eq_unordered;
-        assert_eq_unordered!(&diffed_serde.test1, &second.test1);
+#[derive(Debug, PartialEq, Clone, Default, Difference)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "nanoserde", derive(SerBin, DeBin))]
+#[difference(merge)]
+struct TestCrdt {
+    #[difference(collection_strategy = "crdt")]
+    title: structdiff::collections::crdt::LwwRegister<String>,
+    #[difference(collection_strategy = "crdt")]
+    tags: structdiff::collections::crdt::OrSet<String>,
+    #[difference(collection_strategy = "crdt")]
+    scores: structdiff::collections::crdt::LwwMap<String, u32>,
+    #[difference(collection_strategy = "crdt")]
+    body: structdiff::collections::crdt::Rga<u8>,
+    #[difference(skip)]
+    local_only: u32,
+}
+
+#[test]
+fn test_crdt_fields() {
+    use structdiff::collections::crdt::Merge;
+
+    let mut base = TestCrdt::default();
+    base.title.set(String::from("draft"), 0);
+    base.tags.insert(String::from("todo"), 0);
+    for byte in "hello".bytes() {
+        base.body.push(byte, 0);
     }
 
-    let diffed = first.clone().apply(diffs);
+    let mut first = base.clone();
+    first.title.set(String::from("first"), 1);
+    first.tags.remove(&String::from("todo"));
+    first.scores.insert(String::from("alice"), 1, 1);
+    first.body.insert(5, b'!', 1);
+    first.local_only = 1;
 
-    use assert_unordered::assert_eq_unordered;
-    assert_eq_unordered!(&diffed.test1, &second.test1);
+    let mut second = base.clone();
+    second.title.set(String::from("second"), 2);
+    second.tags.insert(String::from("urgent"), 2);
+    second.scores.insert(String::from("bob"), 2, 2);
+    second.body.insert(0, b'>', 2);
+    second.local_only = 2;
 
-    nanoserde_ref_test!(first, second);
+    let first_diffs = base.diff(&first);
+    let second_diffs = base.diff(&second);
+
+    #[cfg(feature = "serde")]
+    let (first_diffs, second_diffs): (
+        Vec<<TestCrdt as StructDiff>::Diff>,
+        Vec<<TestCrdt as StructDiff>::Diff>,
+    ) = (
+        bincode::deserialize(&bincode::serialize(&first_diffs).unwrap()).unwrap(),
+        bincode::deserialize(&bincode::serialize(&second_diffs).unwrap()).unwrap(),
+    );
+
+    #[cfg(feature = "nanoserde")]
+    let (first_diffs, second_diffs): (
+        Vec<<TestCrdt as StructDiff>::Diff>,
+        Vec<<TestCrdt as StructDiff>::Diff>,
+    ) = (
+        DeBin::deserialize_bin(&SerBin::serialize_bin(&first_diffs)).unwrap(),
+        DeBin::deserialize_bin(&SerBin::serialize_bin(&second_diffs)).unwrap(),
+    );
+
+    // each replica applies the other's diffs, and both converge
+    let mut on_first = first.clone();
+    on_first.apply_mut(second_diffs.clone());
+    let mut on_second = second.clone();
+    on_second.apply_mut(first_diffs.clone());
+    // applying diffs again has no effect
+    on_second.apply_mut(first_diffs);
+    on_second.apply_mut(second_diffs);
+
+    for merged in [&on_first, &on_second] {
+        assert_eq!(merged.title.get(), "second");
+        assert_eq!(merged.tags.iter().collect::<Vec<_>>(), ["urgent"]);
+        assert_eq!(merged.scores.get(&String::from("alice")), Some(&1));
+        assert_eq!(merged.scores.get(&String::from("bob")), Some(&2));
+        assert_eq!(merged.body.iter().copied().collect::<Vec<_>>(), b">hello!");
+    }
+    assert!(on_first.diff(&on_second).is_empty());
+
+    // merging whole states gives the same result, and keeps skipped fields
+    let mut merged = first.clone();
+    merged.merge(second.clone());
+    assert_eq!(merged.title, on_first.title);
+    assert_eq!(merged.tags, on_first.tags);
+    assert_eq!(merged.scores, on_first.scores);
+    assert_eq!(merged.body, on_first.body);
+    assert_eq!(merged.local_only, 1);
+}
+
+#[cfg(any(feature = "serde", feature = "nanoserde"))]
+fn check_sync<C: structdiff::sync::SyncCodec<Test> + Clone>(codec: C)
+where
+    C::Error: std::fmt::Debug,
+{
+    use std::sync::mpsc::channel;
+    use structdiff::sync::{Replica, Replicator, SyncError};
+
+    let mut server = Replicator::new(Test::next(), codec.clone());
+    let fast = server.add_client();
+    let slow = server.add_client();
+    let (to_fast, from_server) = channel();
+    let mut fast_replica = Replica::new(codec.clone());
+    let mut slow_replica = Replica::new(codec);
+
+    for round in 0..20 {
+        server.update(&Test::next());
+        if let Some(message) = server.message_for(fast) {
+            to_fast.send(message).unwrap();
+        }
+        for message in from_server.try_iter() {
+            let version = fast_replica.receive(&message).unwrap();
+            server.acknowledge(fast, version);
+        }
+        assert_eq!(fast_replica.state(), Some(server.current()));
+        assert_eq!(server.acknowledged(fast), Some(server.version()));
+        assert!(server.message_for(fast).is_none());
+
+        // the slow client only receives every fifth version
+        if round % 5 == 0 {
+            let message = server.message_for(slow).unwrap();
+            let version = slow_replica.receive(&message).unwrap();
+            assert_eq!(version, server.version());
+            assert_eq!(slow_replica.state(), Some(server.current()));
+        }
+    }
+
+    // a lost message leaves a gap, which is repaired by resyncing
+    server.update(&Test::next());
+    server.message_for(slow).unwrap();
+    server.update(&Test::next());
+    let message = server.message_for(slow).unwrap();
+    let before = slow_replica.version();
+    match slow_replica.receive(&message) {
+        Err(SyncError::Gap { version, .. }) => assert_eq!(version, before),
+        // the snapshot was smaller than the diff
+        Ok(version) => assert_eq!(version, server.version()),
+        Err(SyncError::Decode(error)) => panic!("{error:?}"),
+    }
+    server.resync(slow, slow_replica.version());
+    if let Some(message) = server.message_for(slow) {
+        slow_replica.receive(&message).unwrap();
+    }
+    assert_eq!(slow_replica.state(), Some(server.current()));
+
+    // replayed messages are ignored
+    assert_eq!(slow_replica.receive(&message).unwrap(), server.version());
+
+    server.remove_client(slow);
+    server.compact();
+    assert_eq!(Some(server.history().base_seq()), server.acknowledged(fast));
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn test_sync_serde() {
+    use structdiff::sync::{SyncCodec, SyncMessage, SyncMessageRef};
+
+    #[derive(Clone)]
+    struct BincodeCodec;
+
+    impl SyncCodec<Test> for BincodeCodec {
+        type Error = bincode::Error;
+
+        fn encode(&self, message: &SyncMessageRef<'_, Test>, output: &mut Vec<u8>) {
+            bincode::serialize_into(output, message).unwrap()
+        }
+
+        fn decode(&self, bytes: &[u8]) -> Result<SyncMessage<Test>, Self::Error> {
+            bincode::deserialize(bytes)
+        }
+    }
+
+    check_sync(BincodeCodec);
+}
+
+#[cfg(feature = "nanoserde")]
+#[test]
+fn test_sync_nanoserde() {
+    check_sync(structdiff::sync::NanoserdeCodec);
 }
 
 #[cfg(feature = "generated_setters")]
@@ -564,3 +2211,556 @@ fn test_setters() {
     assert_eq_unordered!(modified.f5, end.f5);
     assert_eq_unordered!(modified.f6, end.f6);
 }
+
+#[cfg(feature = "generated_setters")]
+#[test]
+fn test_collection_mutators() {
+    use std::collections::VecDeque;
+
+    #[derive(Difference, Default, PartialEq, Debug, Clone)]
+    #[difference(setters)]
+    struct TestMutators {
+        #[difference(collection_strategy = "ordered_array_like")]
+        f0: Vec<i32>,
+        #[difference(collection_strategy = "ordered_array_like")]
+        f1: VecDeque<String>,
+        #[difference(collection_strategy = "ordered_array_like")]
+        f2: LinkedList<u8>,
+        #[difference(collection_strategy = "unordered_map_like")]
+        f3: HashMap<i32, String>,
+        #[difference(recurse, collection_strategy = "unordered_map_like")]
+        f4: HashMap<i32, Test>,
+        #[difference(recurse)]
+        f5: Test,
+        #[difference(recurse)]
+        f6: Option<Test>,
+    }
+
+    let mut base = TestMutators::default();
+    let mut diffs = vec![];
+    let start = base.clone();
+
+    for i in 0..20 {
+        diffs.push(base.push_f0_with_diff(i));
+        diffs.push(base.insert_f0_with_diff(0, -i));
+        diffs.push(base.insert_f1_with_diff(base.f1.len() / 2, i.to_string()));
+        diffs.push(base.push_f2_with_diff(i as u8));
+        diffs.extend(base.insert_f3_with_diff(i % 5, i.to_string()));
+        diffs.extend(base.insert_f4_with_diff(i % 5, Test::next()));
+        diffs.extend(base.modify_f5_with_diff(|inner| inner.test3.push(i)));
+        diffs.extend(base.modify_f6_with_diff(|inner| inner.test1 = i));
+    }
+    assert_eq!(base.f0.len(), 40);
+    assert_eq!(base.f3.len(), 5);
+    assert!(base.f6.is_none());
+
+    let (removed, diff) = base.remove_f0_with_diff(3);
+    assert_eq!(removed, -16);
+    diffs.push(diff);
+    diffs.push(base.remove_f1_with_diff(0).1);
+    diffs.push(base.remove_f2_with_diff(5).1);
+    let (removed, diff) = base.remove_f3_with_diff(&2).unwrap();
+    assert_eq!(removed, "17");
+    diffs.push(diff);
+    diffs.extend(base.remove_f4_with_diff(&4).map(|(_, diff)| diff));
+    assert!(base.remove_f3_with_diff(&2).is_none());
+    assert!(base.insert_f3_with_diff(1, String::from("16")).is_none());
+    assert!(base.modify_f5_with_diff(|_| ()).is_none());
+
+    base.f6 = Some(Test::next());
+    let mut with_option = base.clone();
+    let diff = base.modify_f6_with_diff(|inner| inner.test2.push('!'));
+    with_option.apply_mut(diff.into_iter().collect());
+    assert_eq!(with_option, base);
+
+    base.f6 = None;
+    assert_eq!(start.apply(diffs), base);
+}
+
+#[cfg(feature = "testing")]
+#[derive(Debug, PartialEq, Clone, Difference)]
+#[difference(arbitrary, de_bin_checked)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "nanoserde", derive(SerBin, DeBin))]
+struct TestLawsInner {
+    label: String,
+    #[difference(collection_strategy = "unordered_array_like")]
+    tags: HashSet<u16>,
+}
+
+#[cfg(feature = "testing")]
+#[derive(Debug, PartialEq, Clone, Difference)]
+#[difference(arbitrary)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "nanoserde", derive(SerBin, DeBin))]
+struct TestLaws<A> {
+    scalar: f64,
+    generic: A,
+    optional: Option<u32>,
+    #[difference(collection_strategy = "ordered_array_like")]
+    ordered: Vec<u32>,
+    #[difference(
+        collection_strategy = "unordered_map_like",
+        map_equality = "key_and_value"
+    )]
+    map: HashMap<u8, String>,
+    #[difference(recurse)]
+    inner: TestLawsInner,
+    #[difference(recurse)]
+    maybe_inner: Option<TestLawsInner>,
+    #[difference(skip)]
+    local_only: u64,
+}
+
+#[cfg(feature = "testing")]
+#[test]
+fn test_check_laws() {
+    use structdiff::testing::{check_laws, Laws};
+
+    check_laws::<TestLaws<i32>>();
+    check_laws::<TestLaws<Vec<u8>>>();
+    Laws::new()
+        .cases(64)
+        .seed(0xdecaf)
+        .check::<TestLaws<String>>();
+
+    #[cfg(feature = "serde")]
+    {
+        struct Bincode;
+
+        impl structdiff::testing::SerdeFormat for Bincode {
+            fn serialize<S: serde::Serialize>(&self, value: &S) -> Vec<u8> {
+                bincode::serialize(value).unwrap()
+            }
+
+            fn deserialize<D: serde::de::DeserializeOwned>(&self, bytes: &[u8]) -> D {
+                bincode::deserialize(bytes).unwrap()
+            }
+        }
+
+        Laws::new().check_serde::<TestLaws<i32>>(&Bincode);
+    }
+}
+
+#[cfg(feature = "testing")]
+#[test]
+#[should_panic(expected = "applying `diff` does not produce the updated value")]
+fn test_check_laws_broken() {
+    use structdiff::testing::{arbitrary, Arbitrary, Unstructured};
+    use structdiff::ReplaceDiff;
+
+    // drops the lowest bit when applied
+    #[derive(Debug, Clone, Copy, PartialEq)]
+    struct Even(u32);
+
+    impl StructDiff for Even {
+        type Diff = ReplaceDiff<u32>;
+        type DiffRef<'target> = ReplaceDiff<u32>;
+
+        fn diff(&self, updated: &Self) -> Vec<Self::Diff> {
+            self.diff_ref(updated)
+        }
+
+        fn diff_ref<'target>(&'target self, updated: &'target Self) -> Vec<Self::DiffRef<'target>> {
+            match self != updated {
+                true => vec![ReplaceDiff::new(updated.0)],
+                false => Vec::new(),
+            }
+        }
+
+        fn apply_single(&mut self, diff: Self::Diff) {
+            self.0 = diff.into_inner() & !1;
+        }
+    }
+
+    impl<'a> Arbitrary<'a> for Even {
+        fn arbitrary(u: &mut Unstructured<'a>) -> arbitrary::Result<Self> {
+            Ok(Self(u.arbitrary()?))
+        }
+    }
+
+    structdiff::testing::check_laws::<Even>();
+}
+
+#[cfg(feature = "nanoserde")]
+#[test]
+fn test_malformed_nanoserde() {
+    use nanoserde::SerBin;
+    use structdiff::borrowed::deserialize_bin_borrowed;
+    use structdiff::checked::deserialize_bin;
+    use structdiff::StructDiffBorrowed;
+
+    #[derive(Debug, PartialEq, Clone, Default, Difference)]
+    struct Malformed {
+        name: String,
+        values: Vec<u32>,
+        #[difference(collection_strategy = "ordered_array_like")]
+        ordered: Vec<u32>,
+        #[difference(collection_strategy = "text")]
+        text: String,
+        #[difference(collection_strategy = "binary_delta")]
+        bytes: Vec<u8>,
+        #[difference(collection_strategy = "unordered_map_like")]
+        tags: HashMap<String, u32>,
+        #[difference(collection_strategy = "unordered_array_like")]
+        items: Vec<String>,
+        mode: MalformedMode,
+    }
+
+    #[derive(Debug, PartialEq, Clone, Default, Difference, SerBin, DeBin)]
+    #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+    enum MalformedMode {
+        #[default]
+        Off,
+        Named(String),
+    }
+    type Diff = <Malformed as StructDiff>::Diff;
+    type DiffBorrowed<'de> = <Malformed as StructDiffBorrowed>::DiffBorrowed<'de>;
+
+    // a single diff of the field with variant index `variant`
+    fn single(variant: u16, payload: &[u8]) -> Vec<u8> {
+        let mut ret = 1_usize.serialize_bin();
+        variant.ser_bin(&mut ret);
+        ret.extend_from_slice(payload);
+        ret
+    }
+
+    let hostile = [
+        // more diffs than the buffer could hold
+        vec![0xff; 8],
+        // a string longer than the buffer
+        single(0, &usize::MAX.serialize_bin()),
+        // a string which is not UTF-8
+        single(0, &[2, 0, 0, 0, 0, 0, 0, 0, 0xff, 0xfe]),
+        // a list longer than the buffer
+        single(1, &(usize::MAX / 4).serialize_bin()),
+    ];
+    for bytes in &hostile {
+        assert!(deserialize_bin::<Diff>(bytes).is_err());
+        assert!(deserialize_bin_borrowed::<DiffBorrowed<'_>>(bytes).is_err());
+    }
+
+    // a map key, an inserted item or an enum payload which is not UTF-8
+    let corrupted = [
+        Malformed {
+            tags: HashMap::from([(String::from("key"), 1)]),
+            ..Default::default()
+        },
+        Malformed {
+            items: vec![String::from("key")],
+            ..Default::default()
+        },
+        Malformed {
+            mode: MalformedMode::Named(String::from("key")),
+            ..Default::default()
+        },
+    ];
+    for updated in &corrupted {
+        let mut bytes = Malformed::default().diff(updated).serialize_bin();
+        let at = bytes.windows(3).position(|w| w == b"key").unwrap();
+        bytes[at..at + 2].copy_from_slice(&[0xff, 0xfe]);
+        assert!(deserialize_bin::<Diff>(&bytes).is_err());
+        assert!(deserialize_bin_borrowed::<DiffBorrowed<'_>>(&bytes).is_err());
+    }
+
+    // diffs generated against a different base are applied without panicking
+    let base = Malformed {
+        name: String::from("base"),
+        values: vec![1, 2, 3],
+        ordered: (0..50).collect(),
+        text: String::from("a much longer base text\nspanning ä few lines\n"),
+        bytes: (0..=255).collect(),
+        tags: HashMap::from([(String::from("ä"), 1), (String::from("b"), 2)]),
+        items: vec![String::from("x"), String::from("y")],
+        mode: MalformedMode::Off,
+    };
+    let updated = Malformed {
+        name: String::from("updated"),
+        values: vec![],
+        ordered: (25..40).rev().collect(),
+        text: String::from("a much shorter text\n"),
+        bytes: (0..=255).rev().collect(),
+        tags: HashMap::from([(String::from("b"), 3), (String::from("c"), 4)]),
+        items: vec![String::from("y"), String::from("z")],
+        mode: MalformedMode::Named(String::from("ö")),
+    };
+    let bytes = base.diff(&updated).serialize_bin();
+    assert_eq!(
+        base.clone().apply(deserialize_bin(&bytes).unwrap()),
+        updated
+    );
+    let _ = Malformed::default().apply(deserialize_bin(&bytes).unwrap());
+    let mut target = Malformed::default();
+    target.apply_borrowed(deserialize_bin_borrowed(&bytes).unwrap());
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn test_malformed_serde() {
+    use structdiff::limits::ApplyLimits;
+
+    #[derive(Debug, PartialEq, Clone, Difference)]
+    struct Malformed {
+        #[difference(collection_strategy = "ordered_array_like")]
+        values: Vec<u32>,
+    }
+    type Diff = <Malformed as StructDiff>::Diff;
+
+    let base = Malformed {
+        values: (0..32).collect(),
+    };
+    // an inverted range of deleted positions
+    let json = r#"[{"values":[{"Delete":[5,2]}]}]"#;
+    let diffs: Vec<Diff> = serde_json::from_str(json).unwrap();
+    assert_eq!(base.clone().apply(diffs.clone()), base);
+    assert_eq!(
+        base.clone()
+            .apply_limited(diffs, ApplyLimits::unlimited())
+            .unwrap(),
+        base
+    );
+}
+
+#[test]
+fn test_apply_limits() {
+    use structdiff::limits::{ApplyLimits, Limit};
+
+    #[derive(Debug, PartialEq, Clone, Default, Difference)]
+    struct Limited {
+        #[difference(collection_strategy = "unordered_array_like")]
+        multiset: Vec<u16>,
+        #[difference(collection_strategy = "ordered_array_like")]
+        ordered: Vec<u16>,
+        #[difference(collection_strategy = "text")]
+        text: String,
+        #[difference(recurse)]
+        map: HashMap<u8, Vec<u16>>,
+        #[difference(recurse)]
+        nested: Option<Option<Option<u32>>>,
+    }
+
+    let first = Limited {
+        nested: Some(Some(Some(1))),
+        ..Default::default()
+    };
+
+    // a single item inserted many times
+    let second = Limited {
+        multiset: vec![7; 1000],
+        ..first.clone()
+    };
+    let diffs = first.diff(&second);
+    let err = first
+        .clone()
+        .apply_limited(diffs.clone(), ApplyLimits::new().max_repeat(999))
+        .unwrap_err();
+    assert_eq!(
+        (err.limit, err.max, err.requested),
+        (Limit::Repeat, 999, 1000)
+    );
+    assert_eq!(
+        first
+            .clone()
+            .apply_limited(diffs, ApplyLimits::new().max_repeat(1000))
+            .unwrap(),
+        second
+    );
+
+    // many items inserted into one collection
+    let second = Limited {
+        ordered: (0..1000).collect(),
+        text: "x".repeat(1000),
+        ..first.clone()
+    };
+    let limits = ApplyLimits::new().max_growth(999);
+    for diff in first.diff(&second) {
+        let err = first.clone().apply_limited(vec![diff], limits).unwrap_err();
+        assert_eq!(err.limit, Limit::Growth);
+    }
+
+    // collections which each fit, but not together
+    let second = Limited {
+        ordered: (0..600).collect(),
+        map: (0..=255).map(|key| (key, vec![key as u16])).collect(),
+        ..first.clone()
+    };
+    let diffs = first.diff(&second);
+    let limits = ApplyLimits::new().max_inserted(855);
+    let err = first
+        .clone()
+        .apply_limited(diffs.clone(), limits)
+        .unwrap_err();
+    assert_eq!((err.limit, err.requested), (Limit::Inserted, 856));
+    let limits = ApplyLimits::new().max_inserted(856);
+    assert_eq!(first.clone().apply_limited(diffs, limits).unwrap(), second);
+
+    // the map's values and the nested options are applied one level deeper
+    // per recursed value
+    let third = Limited {
+        map: (0..=255).map(|key| (key, vec![key as u16, 0])).collect(),
+        nested: Some(Some(Some(2))),
+        ..second.clone()
+    };
+    let diffs = second.diff(&third);
+    let err = second
+        .clone()
+        .apply_limited(diffs.clone(), ApplyLimits::new().max_depth(2))
+        .unwrap_err();
+    assert_eq!((err.limit, err.requested), (Limit::Depth, 3));
+    let mut limited = second.clone();
+    limited
+        .apply_mut_limited(diffs.clone(), ApplyLimits::new().max_depth(3))
+        .unwrap();
+    assert_eq!(limited, third);
+    assert_eq!(
+        second
+            .apply_limited(diffs, ApplyLimits::unlimited())
+            .unwrap(),
+        third
+    );
+}
+
+#[test]
+fn test_auto_strategy() {
+    use structdiff::size::{estimate, EncodedSize};
+
+    #[derive(Debug, PartialEq, Clone, Default, Difference)]
+    #[difference(encoded_size)]
+    struct Auto {
+        #[difference(collection_strategy = "auto")]
+        list: Vec<u32>,
+        #[difference(collection_strategy = "auto")]
+        set: HashSet<u16>,
+        #[difference(collection_strategy = "auto")]
+        map: HashMap<u8, u64>,
+        #[difference(collection_strategy = "auto")]
+        text: String,
+        #[difference(collection_strategy = "auto")]
+        bytes: Vec<u8>,
+        #[difference(skip)]
+        skipped: u8,
+    }
+
+    let first = Auto {
+        list: (0..200).collect(),
+        set: (0..200).collect(),
+        map: (0..200).map(|key| (key, key as u64)).collect(),
+        text: "lorem ipsum dolor sit amet ".repeat(20),
+        bytes: (0..=255).cycle().take(2048).collect(),
+        skipped: 0,
+    };
+
+    // small edits are sent as incremental diffs
+    let mut second = first.clone();
+    second.list[100] = 7;
+    second.set.remove(&3);
+    second.map.insert(3, 4);
+    second.text.push('!');
+    second.bytes[1024] = 1;
+    let diffs = first.diff(&second);
+    assert_eq!(diffs.len(), 5);
+    for diff in &diffs {
+        assert!(diff.encoded_size() < 100, "{}", diff.encoded_size());
+    }
+    // the estimates use the tag widths of the nanoserde encoding
+    #[cfg(feature = "nanoserde")]
+    assert_eq!(
+        diffs.encoded_size(),
+        nanoserde::SerBin::serialize_bin(&diffs).len()
+    );
+    assert_eq!(first.clone().apply(diffs), second);
+    nanoserde_ref_test!(first, second);
+
+    let estimate = estimate(&first, &second);
+    assert!(!estimate.prefer_replace());
+    assert!(estimate.diff < estimate.replace);
+
+    // rewrites are sent as replacements of about the field's size
+    let third = Auto {
+        list: (1000..1200).rev().collect(),
+        set: (1000..1200).collect(),
+        map: (0..200).map(|key| (key, !(key as u64))).collect(),
+        text: "consectetur adipiscing elit ".repeat(20),
+        bytes: (0..2048).map(|i| (i * 7 % 251) as u8).collect(),
+        skipped: 0,
+    };
+    let diffs = first.diff(&third);
+    assert_eq!(diffs.len(), 5);
+    #[cfg(feature = "nanoserde")]
+    assert_eq!(
+        diffs.encoded_size(),
+        nanoserde::SerBin::serialize_bin(&diffs).len()
+    );
+    assert!(
+        diffs.encoded_size() <= third.encoded_size() + 5 * 32,
+        "{} {}",
+        diffs.encoded_size(),
+        third.encoded_size()
+    );
+    assert_eq!(first.clone().apply(diffs), third);
+    nanoserde_ref_test!(first, third);
+
+    // unchanged fields produce no diff
+    assert!(first.diff(&first).is_empty());
+    assert_eq!(
+        first.encoded_size(),
+        first.list.encoded_size()
+            + first.set.encoded_size()
+            + first.map.encoded_size()
+            + first.text.encoded_size()
+            + first.bytes.encoded_size()
+            + 1
+    );
+}
+
+#[test]
+fn test_custom_unordered_collection() {
+    use structdiff::collections::unordered_array_like::UnorderedArrayLikeCollection;
+
+    #[derive(Debug, PartialEq, Clone, Default)]
+    struct Bag<T>(Vec<T>);
+
+    impl<T> Bag<T> {
+        fn iter(&self) -> std::slice::Iter<'_, T> {
+            self.0.iter()
+        }
+    }
+
+    impl<T: PartialEq + Clone> UnorderedArrayLikeCollection<T> for Bag<T> {
+        fn clear_items(&mut self) {
+            self.0.clear()
+        }
+
+        fn insert_items(&mut self, item: T, count: usize) {
+            self.0.extend(std::iter::repeat_n(item, count))
+        }
+
+        fn remove_items(&mut self, removals: Vec<(&T, usize)>) {
+            for (item, count) in removals {
+                for _ in 0..count {
+                    match self.0.iter().position(|other| other == item) {
+                        Some(index) => drop(self.0.swap_remove(index)),
+                        None => break,
+                    }
+                }
+            }
+        }
+    }
+
+    #[derive(Debug, PartialEq, Clone, Default, Difference)]
+    struct Custom {
+        #[difference(collection_strategy = "unordered_array_like")]
+        bag: Bag<u32>,
+    }
+
+    let first = Custom {
+        bag: Bag(vec![1, 2, 2, 3, 3, 3]),
+    };
+    let second = Custom {
+        bag: Bag(vec![3, 1, 4, 2, 4]),
+    };
+
+    let mut diffed = first.clone().apply(first.diff(&second));
+    diffed.bag.0.sort();
+    assert_eq!(diffed.bag.0, vec![1, 2, 3, 4, 4]);
+}
@@ -24,7 +24,7 @@ where
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 #[cfg_attr(feature = "nanoserde", derive(SerBin, DeBin))]
 #[derive(Debug, PartialEq, Clone, Difference, Default)]
-#[difference(setters)]
+#[difference(setters, de_bin_checked)]
 pub struct Test {
     pub test1: i32,
     pub test2: String,